@@ -0,0 +1,164 @@
+//! Release channels for updating the app itself.
+//!
+//! Every app release has the updater manifest `latest.json` attached by the
+//! release workflow, prereleases included. Stable users check the manifest
+//! of GitHub's latest release, which is never a prerelease. Beta users
+//! check the newest release of any kind, found through the GitHub API.
+
+use serde::Serialize;
+
+use crate::releases::{GitHubAsset, GitHubRelease, ReleaseChannel};
+
+/// App releases, newest first.
+pub const APP_RELEASES_API_URL: &str =
+    "https://api.github.com/repos/BlueBuzzah/BlueBuzzah-Updater/releases";
+
+/// Manifest of the latest stable release; also the endpoint in
+/// `tauri.conf.json`.
+pub const STABLE_MANIFEST_URL: &str =
+    "https://github.com/BlueBuzzah/BlueBuzzah-Updater/releases/latest/download/latest.json";
+
+/// Releases looked at when searching for the newest on a channel.
+pub const APP_RELEASES_PER_PAGE: usize = 20;
+
+/// Name of the updater manifest attached to each release.
+const MANIFEST_ASSET: &str = "latest.json";
+
+/// An app update available on the chosen channel.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppUpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub release_notes: String,
+    /// RFC 3339 release time, when the manifest has one.
+    pub release_date: Option<String>,
+    /// Size of the update package in bytes, when GitHub reported it.
+    pub download_size: Option<u64>,
+    pub channel: ReleaseChannel,
+}
+
+/// Progress of `install_app_update`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppUpdateProgress {
+    /// "downloading", "waiting" while a firmware flash finishes, then
+    /// "ready" once installed.
+    pub stage: &'static str,
+    pub downloaded: u64,
+    pub total: u64,
+    pub percent: u32,
+}
+
+impl AppUpdateProgress {
+    pub fn new(stage: &'static str, downloaded: u64, total: u64) -> Self {
+        let percent = (downloaded.min(total) * 100)
+            .checked_div(total)
+            .unwrap_or(0) as u32;
+        Self {
+            stage,
+            downloaded,
+            total,
+            percent,
+        }
+    }
+}
+
+/// The newest release on `channel` with an updater manifest, and that
+/// manifest. Releases still being built don't have one yet and are skipped.
+pub fn newest_update_release(
+    releases: &[GitHubRelease],
+    channel: ReleaseChannel,
+) -> Option<(&GitHubRelease, &GitHubAsset)> {
+    releases
+        .iter()
+        .filter(|release| channel.includes(ReleaseChannel::of(release.prerelease)))
+        .find_map(|release| {
+            release
+                .assets
+                .iter()
+                .find(|asset| asset.name == MANIFEST_ASSET)
+                .map(|manifest| (release, manifest))
+        })
+}
+
+/// Size of the asset of `release` served from `download_url`.
+pub fn download_size(release: &GitHubRelease, download_url: &str) -> Option<u64> {
+    release
+        .assets
+        .iter()
+        .find(|asset| asset.browser_download_url == download_url)
+        .map(|asset| asset.size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool, assets: &[&str]) -> GitHubRelease {
+        GitHubRelease {
+            tag_name: tag.to_string(),
+            name: None,
+            body: None,
+            published_at: None,
+            prerelease,
+            assets: assets
+                .iter()
+                .map(|name| GitHubAsset {
+                    name: name.to_string(),
+                    browser_download_url: format!("https://example.com/{}/{}", tag, name),
+                    size: 1234,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_newest_update_release_per_channel() {
+        let releases = vec![
+            release("v1.3.0-beta.1", true, &["latest.json"]),
+            release("v1.2.0", false, &["latest.json"]),
+        ];
+
+        let (stable, manifest) = newest_update_release(&releases, ReleaseChannel::Stable).unwrap();
+        assert_eq!(stable.tag_name, "v1.2.0");
+        assert_eq!(
+            manifest.browser_download_url,
+            "https://example.com/v1.2.0/latest.json"
+        );
+
+        let (beta, _) = newest_update_release(&releases, ReleaseChannel::Beta).unwrap();
+        assert_eq!(beta.tag_name, "v1.3.0-beta.1");
+    }
+
+    #[test]
+    fn test_newest_update_release_skips_releases_without_manifest() {
+        let releases = vec![
+            release("v1.3.0", false, &["BlueBuzzah_Updater.app.tar.gz"]),
+            release("v1.2.0", false, &["latest.json"]),
+        ];
+
+        let (release, _) = newest_update_release(&releases, ReleaseChannel::Beta).unwrap();
+        assert_eq!(release.tag_name, "v1.2.0");
+        assert!(newest_update_release(&[], ReleaseChannel::Stable).is_none());
+    }
+
+    #[test]
+    fn test_download_size_matches_asset_url() {
+        let release = release("v1.2.0", false, &["latest.json", "app.nsis.zip"]);
+        assert_eq!(
+            download_size(&release, "https://example.com/v1.2.0/app.nsis.zip"),
+            Some(1234)
+        );
+        assert_eq!(
+            download_size(&release, "https://example.com/other.zip"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_progress_percent() {
+        assert_eq!(AppUpdateProgress::new("downloading", 50, 200).percent, 25);
+        assert_eq!(AppUpdateProgress::new("downloading", 50, 0).percent, 0);
+        assert_eq!(AppUpdateProgress::new("ready", 300, 200).percent, 100);
+    }
+}
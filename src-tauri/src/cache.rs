@@ -1,9 +1,15 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::time::{Duration, SystemTime};
+
+use crate::releases::ReleaseChannel;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedFirmwareMetadata {
@@ -15,18 +21,489 @@ pub struct CachedFirmwareMetadata {
     pub file_size: u64,
     pub published_at: String,
     pub release_notes: String,
+    /// True when the download matched a checksum published with the release;
+    /// false when the hash was only computed locally.
+    #[serde(default)]
+    pub checksum_verified: bool,
+    /// True if the zip was imported from a local file rather than downloaded.
+    #[serde(default)]
+    pub locally_imported: bool,
+    /// Pinned versions are kept by cache cleanup and "clear cache".
+    #[serde(default)]
+    pub pinned: bool,
+    /// When the version was last resolved from the cache or flashed (RFC 3339).
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    /// Release channel the version came from, so cached betas stay labeled.
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    /// What the zip contains. Entries from older indexes start as `Unknown`
+    /// and are reclassified by `verify_and_clean_cache`.
+    #[serde(default)]
+    pub asset_kind: AssetKind,
+}
+
+/// Kind of release asset a cached zip holds.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    /// Nordic DFU package (manifest.json + init packet) for the nRF52.
+    DfuPackage,
+    /// CircuitPython file bundle copied onto the CIRCUITPY drive.
+    #[serde(rename = "circuitpy_bundle")]
+    CircuitPyBundle,
+    /// Not inspected yet, unreadable, or neither of the above.
+    #[default]
+    Unknown,
+}
+
+impl AssetKind {
+    /// Classify a zip from its entry names.
+    pub fn from_entry_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut has_manifest = false;
+        let mut has_init_packet = false;
+        let mut has_python_entry = false;
+
+        for name in names {
+            let file_name = name.rsplit('/').next().unwrap_or(name);
+            // DFU packages keep their files at the archive root
+            if name == "manifest.json" {
+                has_manifest = true;
+            } else if !name.contains('/') && file_name.ends_with(".dat") {
+                has_init_packet = true;
+            }
+            if file_name == "code.py" || file_name == "boot.py" {
+                has_python_entry = true;
+            }
+        }
+
+        if has_manifest && has_init_packet {
+            AssetKind::DfuPackage
+        } else if has_python_entry {
+            AssetKind::CircuitPyBundle
+        } else {
+            AssetKind::Unknown
+        }
+    }
+
+    /// Human-readable name for messages.
+    pub fn label(self) -> &'static str {
+        match self {
+            AssetKind::DfuPackage => "a DFU package",
+            AssetKind::CircuitPyBundle => "a CircuitPython bundle",
+            AssetKind::Unknown => "an unrecognized archive",
+        }
+    }
+
+    /// Classify the zip at `path`. Unreadable archives are `Unknown`.
+    pub fn detect(path: &Path) -> Self {
+        let archive = fs::File::open(path)
+            .ok()
+            .and_then(|file| zip::ZipArchive::new(file).ok());
+        match archive {
+            Some(archive) => Self::from_entry_names(archive.file_names()),
+            None => AssetKind::Unknown,
+        }
+    }
+}
+
+impl CachedFirmwareMetadata {
+    /// Most recent of the download and last-use times, if either parses.
+    pub fn last_activity(&self) -> Option<DateTime<Utc>> {
+        [Some(&self.downloaded_at), self.last_used_at.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .max()
+    }
+
+    /// Re-hash the zip and sanity-check its contents before it is flashed.
+    ///
+    /// Fails with the reason if the zip is missing, doesn't match the
+    /// recorded SHA256, can't be read as an archive, is empty, or no longer
+    /// contains the files its recorded asset kind requires.
+    pub fn check_integrity(&self) -> Result<(), String> {
+        let zip_path = Path::new(&self.zip_path);
+        let actual_hash = CacheManager::calculate_sha256(zip_path)?;
+        if !actual_hash.eq_ignore_ascii_case(&self.sha256_hash) {
+            return Err(format!(
+                "hash mismatch: expected {}, got {}",
+                self.sha256_hash, actual_hash
+            ));
+        }
+
+        let file = fs::File::open(zip_path).map_err(|e| format!("Failed to open zip: {}", e))?;
+        let archive =
+            zip::ZipArchive::new(file).map_err(|e| format!("unreadable zip archive: {}", e))?;
+        if archive.is_empty() {
+            return Err("zip archive is empty".to_string());
+        }
+        if self.asset_kind != AssetKind::Unknown
+            && AssetKind::from_entry_names(archive.file_names()) != self.asset_kind
+        {
+            return Err(format!(
+                "zip no longer looks like {}",
+                self.asset_kind.label()
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 pub type FirmwareCacheIndex = HashMap<String, CachedFirmwareMetadata>;
 
+/// Version key split into numeric release components and prerelease identifiers.
+struct ParsedVersion<'a> {
+    release: Vec<u64>,
+    prerelease: Vec<&'a str>,
+}
+
+/// Parse `v1.2.3-beta.1+build` style versions. Returns `None` for tags
+/// that don't start with a numeric release like `1`, `1.2` or `1.2.3`.
+fn parse_version(version: &str) -> Option<ParsedVersion<'_>> {
+    let version = version.trim();
+    let version = version
+        .strip_prefix('v')
+        .or_else(|| version.strip_prefix('V'))
+        .unwrap_or(version);
+    // Build metadata never affects ordering
+    let version = version.split('+').next().unwrap_or(version);
+    let (release, prerelease) = match version.split_once('-') {
+        Some((release, prerelease)) => (release, prerelease.split('.').collect()),
+        None => (version, Vec::new()),
+    };
+
+    let release = release
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some(ParsedVersion {
+        release,
+        prerelease,
+    })
+}
+
+/// Compare prerelease identifiers per semver: numeric ones numerically and
+/// below alphanumeric ones, and a shorter list first when one is a prefix.
+fn compare_prerelease(a: &[&str], b: &[&str]) -> Ordering {
+    for (a, b) in a.iter().zip(b) {
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+fn compare_parsed(a: &ParsedVersion, b: &ParsedVersion) -> Ordering {
+    let len = a.release.len().max(b.release.len());
+    for i in 0..len {
+        let a_part = a.release.get(i).copied().unwrap_or(0);
+        let b_part = b.release.get(i).copied().unwrap_or(0);
+        if a_part != b_part {
+            return a_part.cmp(&b_part);
+        }
+    }
+
+    match (a.prerelease.is_empty(), b.prerelease.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => compare_prerelease(&a.prerelease, &b.prerelease),
+    }
+}
+
+/// Order version keys semver-style, so `v1.10.0` sorts after `v1.2.0`.
+///
+/// A `v` prefix is ignored and missing components count as 0 (`1.2` ==
+/// `1.2.0`). A prerelease sorts before its release. Keys that aren't
+/// versions sort below all versions, and every tie falls back to comparing
+/// the raw strings so the order is deterministic.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let ordering = match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => compare_parsed(&a, &b),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    };
+    ordering.then_with(|| a.cmp(b))
+}
+
+/// Whether `version` is `minimum` or newer, by the ordering of
+/// [`compare_versions`]. `None` when either isn't a version.
+pub fn version_at_least(version: &str, minimum: &str) -> Option<bool> {
+    let version = parse_version(version)?;
+    let minimum = parse_version(minimum)?;
+    Some(compare_parsed(&version, &minimum) != Ordering::Less)
+}
+
+/// Disk usage for a single cached firmware version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedVersionStats {
+    pub version: String,
+    pub downloaded_at: String,
+    pub zip_exists: bool,
+    pub zip_size: u64,
+    /// Size of the extracted directory (`firmware/<version>/`), 0 if none.
+    pub extracted_size: u64,
+}
+
+/// Summary of the firmware cache for the cache management screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub version_count: usize,
+    pub total_size: u64,
+    pub oldest_downloaded_at: Option<String>,
+    pub versions: Vec<CachedVersionStats>,
+    /// Human-readable descriptions of index/disk mismatches.
+    pub inconsistencies: Vec<String>,
+}
+
+/// Versions removed by an age-based cache cleanup.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheCleanupResult {
+    pub removed: Vec<String>,
+    pub bytes_freed: u64,
+}
+
+/// What "clear cache" removed and left behind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheClearResult {
+    pub versions_removed: Vec<String>,
+    pub bytes_freed: u64,
+    /// Files in the firmware directory that aren't ours, left in place.
+    pub skipped_unknown_files: Vec<String>,
+    /// Pinned versions that were kept.
+    pub pinned_kept: usize,
+    /// Files that couldn't be deleted, with the reason.
+    pub failed: Vec<String>,
+}
+
+/// Progress event sent while un-indexed zips are hashed into the index.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheMigrationProgressEvent {
+    /// Zip being hashed.
+    pub file_name: String,
+    /// Position of this zip among those being migrated (1-based).
+    pub current: usize,
+    /// Number of zips being migrated.
+    pub total: usize,
+    /// Human-readable message.
+    pub message: String,
+}
+
+/// Options for the combined `verify_and_clean_cache` command.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheMaintenanceOptions {
+    /// Skip hashing un-indexed zips into the index, e.g. once the frontend
+    /// has already run `migrate_firmware_cache` this session.
+    #[serde(default)]
+    pub skip_migration: bool,
+    /// Also remove orphaned files from the firmware directory.
+    #[serde(default)]
+    pub collect_garbage: bool,
+}
+
+/// What a garbage collection pass found in the firmware directory.
+///
+/// Every entry lands in exactly one of `indexed`, `migratable` or `orphans`;
+/// `removed` lists the orphans old enough to be deleted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheGarbageReport {
+    /// Entries backing an index entry: cached zips and extracted directories.
+    pub indexed: Vec<String>,
+    /// Un-indexed zips that migration will add to the index.
+    pub migratable: Vec<String>,
+    /// Everything else: partial or staged downloads, stray directories and files.
+    pub orphans: Vec<String>,
+    /// Orphans deleted because they were older than the grace period.
+    pub removed: Vec<String>,
+    pub bytes_freed: u64,
+}
+
+/// Suffixes of files the cache writes into the firmware directory:
+/// partial downloads, staged imports and cached zips.
+const CACHE_FILE_SUFFIXES: [&str; 3] = [".zip.partial", ".zip.tmp", ".zip"];
+
+/// Version a firmware directory entry belongs to, if its name follows the
+/// cache's naming: `<version>.zip` (and partial/staged variants) for files,
+/// `<version>` for directories.
+fn cache_file_version(name: &str, is_dir: bool) -> Option<&str> {
+    let version = if is_dir {
+        name
+    } else {
+        CACHE_FILE_SUFFIXES
+            .iter()
+            .find_map(|suffix| name.strip_suffix(suffix))?
+    };
+    parse_version(version).map(|_| version)
+}
+
+/// Total size of all files under `path`, without following symlinks.
+fn dir_size(path: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let metadata = match fs::symlink_metadata(entry.path()) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if metadata.is_file() {
+                total += metadata.len();
+            }
+        }
+    }
+
+    total
+}
+
+/// Most recent modification time of `path` or, for a directory, of anything
+/// under it, so a directory still being extracted into counts as fresh.
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    let mut newest = fs::symlink_metadata(path).and_then(|m| m.modified()).ok();
+    let mut pending = vec![path.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let metadata = match fs::symlink_metadata(entry.path()) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            }
+            if let Ok(modified) = metadata.modified() {
+                newest = newest.max(Some(modified));
+            }
+        }
+    }
+
+    newest
+}
+
+/// Whether migration would add `path` to the index: a `.zip` file whose
+/// stem isn't already an indexed version.
+fn is_migratable_zip(path: &Path, index: &FirmwareCacheIndex) -> bool {
+    path.is_file()
+        && path.extension().and_then(|s| s.to_str()) == Some("zip")
+        && path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .is_some_and(|version| !index.contains_key(version))
+}
+
+/// Delete a version's zip and extracted directory, returning the bytes freed.
+///
+/// The extracted directory is only deleted when it is a direct child of
+/// `firmware_dir`, so a malformed version can never reach outside it.
+fn remove_version_files(
+    firmware_dir: &Path,
+    version: &str,
+    zip_path: &Path,
+) -> Result<u64, String> {
+    let mut bytes_freed = 0;
+
+    if let Ok(zip_metadata) = fs::metadata(zip_path) {
+        fs::remove_file(zip_path).map_err(|e| format!("Failed to delete zip file: {}", e))?;
+        bytes_freed += zip_metadata.len();
+    }
+
+    let extracted_dir = firmware_dir.join(version);
+    let in_firmware_dir = extracted_dir.parent() == Some(firmware_dir)
+        && extracted_dir.file_name() == Some(version.as_ref());
+    if in_firmware_dir && extracted_dir.is_dir() {
+        let extracted_size = dir_size(&extracted_dir);
+        fs::remove_dir_all(&extracted_dir)
+            .map_err(|e| format!("Failed to delete extracted firmware: {}", e))?;
+        bytes_freed += extracted_size;
+    }
+
+    Ok(bytes_freed)
+}
+
+/// Firmware cache index, held in Tauri managed state.
+///
+/// The index is read from disk once and then served from memory; every
+/// mutation is saved to disk before it becomes visible. Clones share the
+/// same in-memory index, so blocking tasks can take their own handle.
+#[derive(Clone)]
 pub struct CacheManager {
     cache_file_path: PathBuf,
+    /// In-memory index; `None` until first loaded from disk.
+    index: Arc<RwLock<Option<FirmwareCacheIndex>>>,
+}
+
+/// Write access to the index for one load-modify-save cycle.
+///
+/// Commands run concurrently (e.g. verify_and_clean_cache during a
+/// download); holding the write lock across the cycle keeps interleaved
+/// updates from losing entries.
+struct IndexLock<'a> {
+    manager: &'a CacheManager,
+    guard: RwLockWriteGuard<'a, Option<FirmwareCacheIndex>>,
+}
+
+impl IndexLock<'_> {
+    /// Copy of the current index to modify.
+    fn load(&mut self) -> FirmwareCacheIndex {
+        let manager = self.manager;
+        self.guard
+            .get_or_insert_with(|| manager.read_index_file())
+            .clone()
+    }
+
+    /// Persist `index` and make it the in-memory index.
+    fn save(&mut self, index: FirmwareCacheIndex) -> Result<(), String> {
+        self.manager.write_index(&index)?;
+        *self.guard = Some(index);
+        Ok(())
+    }
 }
 
 impl CacheManager {
     pub fn new(app_data_dir: &Path) -> Result<Self, String> {
         let cache_file_path = app_data_dir.join("firmware_cache.json");
-        Ok(Self { cache_file_path })
+        Ok(Self {
+            cache_file_path,
+            index: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    /// Take the index write lock. A panic while holding it can't leave the
+    /// index half-written (saves are atomic and the in-memory copy is only
+    /// replaced after a save), so poisoning is ignored.
+    fn lock_index(&self) -> IndexLock<'_> {
+        IndexLock {
+            manager: self,
+            guard: self
+                .index
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        }
     }
 
     /// Calculate SHA256 hash of a file
@@ -53,39 +530,71 @@ impl CacheManager {
         Ok(format!("{:x}", hash))
     }
 
-    /// Load the cache index from disk.
-    ///
-    /// Returns an empty index on read or parse errors (graceful recovery).
+    /// Path of the backup copy written after each successful save.
+    fn backup_path(&self) -> PathBuf {
+        self.cache_file_path.with_extension("json.bak")
+    }
+
+    /// Snapshot of the cache index, read from disk on first use.
     pub fn load_index(&self) -> Result<FirmwareCacheIndex, String> {
+        {
+            let guard = self
+                .index
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(index) = guard.as_ref() {
+                return Ok(index.clone());
+            }
+        }
+        Ok(self.lock_index().load())
+    }
+
+    /// Read the cache index from disk.
+    ///
+    /// Falls back to the `.bak` copy if the main file can't be read or parsed,
+    /// and returns an empty index if that fails too (graceful recovery).
+    fn read_index_file(&self) -> FirmwareCacheIndex {
         if !self.cache_file_path.exists() {
-            return Ok(HashMap::new());
+            return HashMap::new();
         }
 
         let contents = match fs::read_to_string(&self.cache_file_path) {
             Ok(c) => c,
             Err(e) => {
-                eprintln!(
-                    "[Cache] Warning: Failed to read cache index, returning empty: {}",
-                    e
-                );
-                return Ok(HashMap::new());
+                log::warn!("Failed to read cache index, trying backup: {}", e);
+                return self.load_backup();
             }
         };
 
         match serde_json::from_str(&contents) {
-            Ok(index) => Ok(index),
+            Ok(index) => index,
             Err(e) => {
-                eprintln!(
-                    "[Cache] Warning: Cache index corrupted, returning empty: {}",
-                    e
-                );
-                Ok(HashMap::new())
+                log::warn!("Cache index corrupted, trying backup: {}", e);
+                self.load_backup()
+            }
+        }
+    }
+
+    /// Load the backup index, or an empty index if it is missing or unreadable.
+    fn load_backup(&self) -> FirmwareCacheIndex {
+        let parsed = fs::read_to_string(self.backup_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+
+        match parsed {
+            Some(index) => {
+                log::warn!("Recovered cache index from backup");
+                index
+            }
+            None => {
+                log::warn!("No usable cache index backup, returning empty");
+                HashMap::new()
             }
         }
     }
 
-    /// Save the cache index to disk using atomic write (write-to-tmp then rename).
-    pub fn save_index(&self, index: &FirmwareCacheIndex) -> Result<(), String> {
+    /// Write the index to disk. Callers go through [`IndexLock::save`].
+    fn write_index(&self, index: &FirmwareCacheIndex) -> Result<(), String> {
         let contents = serde_json::to_string_pretty(index)
             .map_err(|e| format!("Failed to serialize cache index: {}", e))?;
 
@@ -107,22 +616,59 @@ impl CacheManager {
             format!("Failed to finalize cache index: {}", e)
         })?;
 
+        // Keep a last-known-good copy for recovery; the save itself already succeeded
+        let backup_path = self.backup_path();
+        let backup_tmp = self.cache_file_path.with_extension("json.bak.tmp");
+        if let Err(e) =
+            fs::write(&backup_tmp, &contents).and_then(|_| fs::rename(&backup_tmp, &backup_path))
+        {
+            let _ = fs::remove_file(&backup_tmp);
+            log::warn!("Failed to write cache index backup: {}", e);
+        }
+
         Ok(())
     }
 
     /// Add or update a firmware entry in the cache index
     pub fn update_entry(&self, metadata: CachedFirmwareMetadata) -> Result<(), String> {
-        let mut index = self.load_index()?;
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
         index.insert(metadata.version.clone(), metadata);
-        self.save_index(&index)?;
+        lock.save(index)?;
         Ok(())
     }
 
+    /// Add or update several entries in one index write, so a set of
+    /// related downloads appears in the index together or not at all.
+    ///
+    /// An entry replacing one of the same version keeps its pin and last
+    /// use, so downloading a pinned version again leaves it pinned. Returns
+    /// the entries as saved.
+    pub fn update_entries(
+        &self,
+        entries: Vec<CachedFirmwareMetadata>,
+    ) -> Result<Vec<CachedFirmwareMetadata>, String> {
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
+        let mut saved = Vec::with_capacity(entries.len());
+        for mut metadata in entries {
+            if let Some(existing) = index.get(&metadata.version) {
+                metadata.pinned = existing.pinned;
+                metadata.last_used_at = existing.last_used_at.clone();
+            }
+            index.insert(metadata.version.clone(), metadata.clone());
+            saved.push(metadata);
+        }
+        lock.save(index)?;
+        Ok(saved)
+    }
+
     /// Remove a firmware entry from the cache index
     pub fn remove_entry(&self, version: &str) -> Result<(), String> {
-        let mut index = self.load_index()?;
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
         index.remove(version);
-        self.save_index(&index)?;
+        lock.save(index)?;
         Ok(())
     }
 
@@ -132,13 +678,317 @@ impl CacheManager {
         Ok(index.get(version).cloned())
     }
 
-    /// Clear all entries from the cache index
-    pub fn clear_index(&self) -> Result<(), String> {
-        let empty_index: FirmwareCacheIndex = HashMap::new();
-        self.save_index(&empty_index)?;
+    /// Resolve a cached version for flashing.
+    ///
+    /// `version` may be the index key or its release tag (`v2.3.1`). Fails
+    /// if the version isn't cached, its zip fails the integrity check, or it
+    /// isn't a DFU package.
+    pub fn resolve_dfu_package(&self, version: &str) -> Result<CachedFirmwareMetadata, String> {
+        let index = self.load_index()?;
+        let bare = version
+            .strip_prefix('v')
+            .or_else(|| version.strip_prefix('V'))
+            .unwrap_or(version);
+        let metadata = index
+            .get(version)
+            .or_else(|| index.get(bare))
+            .or_else(|| index.values().find(|m| m.tag_name == version))
+            .cloned()
+            .ok_or_else(|| format!("Firmware version {} is not cached", version))?;
+
+        metadata.check_integrity().map_err(|reason| {
+            format!(
+                "Cached firmware {} failed integrity check: {}",
+                metadata.version, reason
+            )
+        })?;
+
+        // Entries from older indexes haven't been classified yet
+        let kind = match metadata.asset_kind {
+            AssetKind::Unknown => AssetKind::detect(Path::new(&metadata.zip_path)),
+            known => known,
+        };
+        if kind != AssetKind::DfuPackage {
+            return Err(format!(
+                "Cached firmware {} is {}, not {}",
+                metadata.version,
+                kind.label(),
+                AssetKind::DfuPackage.label()
+            ));
+        }
+
+        Ok(metadata)
+    }
+
+    /// Find the cached entry whose zip is at `zip_path`, if any.
+    pub fn entry_by_path(&self, zip_path: &Path) -> Result<Option<CachedFirmwareMetadata>, String> {
+        let index = self.load_index()?;
+        Ok(index
+            .values()
+            .find(|metadata| Path::new(&metadata.zip_path) == zip_path)
+            .cloned())
+    }
+
+    /// Newest cached version whose zip is on disk, by [`compare_versions`].
+    ///
+    /// `kind` restricts the asset kind; `channel` restricts to versions that
+    /// channel offers (beta includes stable).
+    pub fn latest_entry(
+        &self,
+        kind: Option<AssetKind>,
+        channel: Option<ReleaseChannel>,
+    ) -> Result<Option<CachedFirmwareMetadata>, String> {
+        let index = self.load_index()?;
+        Ok(index
+            .into_values()
+            .filter(|metadata| kind.is_none_or(|kind| metadata.asset_kind == kind))
+            .filter(|metadata| channel.is_none_or(|channel| channel.includes(metadata.channel)))
+            .filter(|metadata| Path::new(&metadata.zip_path).exists())
+            .max_by(|a, b| compare_versions(&a.version, &b.version)))
+    }
+
+    /// Inspect entries whose asset kind is still `Unknown` and record what
+    /// they contain. Returns the versions that were reclassified.
+    pub fn reclassify_unknown(&self) -> Result<Vec<String>, String> {
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
+        let mut reclassified = Vec::new();
+
+        for (version, metadata) in index.iter_mut() {
+            if metadata.asset_kind != AssetKind::Unknown {
+                continue;
+            }
+            let kind = AssetKind::detect(Path::new(&metadata.zip_path));
+            if kind != AssetKind::Unknown {
+                metadata.asset_kind = kind;
+                reclassified.push(version.clone());
+            }
+        }
+
+        if !reclassified.is_empty() {
+            lock.save(index)?;
+        }
+        Ok(reclassified)
+    }
+
+    /// Pin or unpin a cached version.
+    pub fn set_pinned(&self, version: &str, pinned: bool) -> Result<(), String> {
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
+        let entry = index
+            .get_mut(version)
+            .ok_or_else(|| format!("Firmware version {} is not cached", version))?;
+        entry.pinned = pinned;
+        lock.save(index)?;
+        Ok(())
+    }
+
+    /// Record that a cached version was just used. Unknown versions are ignored.
+    pub fn mark_used(&self, version: &str) -> Result<(), String> {
+        self.mark_used_where(|metadata| metadata.version == version)
+    }
+
+    /// Record that the cached zip at `zip_path` was just used (e.g. flashed).
+    pub fn mark_used_by_path(&self, zip_path: &Path) -> Result<(), String> {
+        self.mark_used_where(|metadata| Path::new(&metadata.zip_path) == zip_path)
+    }
+
+    fn mark_used_where(
+        &self,
+        matches: impl Fn(&CachedFirmwareMetadata) -> bool,
+    ) -> Result<(), String> {
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
+        let now = Utc::now().to_rfc3339();
+        let mut changed = false;
+
+        for metadata in index.values_mut().filter(|metadata| matches(metadata)) {
+            metadata.last_used_at = Some(now.clone());
+            changed = true;
+        }
+
+        if changed {
+            lock.save(index)?;
+        }
         Ok(())
     }
 
+    /// Delete a cached version's files and index entry, pinned or not.
+    ///
+    /// Uses the zip path recorded in the index, which may lie outside
+    /// `firmware_dir` (migrated or relocated entries); the default
+    /// `<version>.zip` is only assumed when there is no entry, as for legacy
+    /// zips downloaded before the index. Returns the number of bytes freed.
+    pub fn delete_version(&self, firmware_dir: &Path, version: &str) -> Result<u64, String> {
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
+        let zip_path = match index.remove(version) {
+            Some(metadata) => PathBuf::from(metadata.zip_path),
+            None => {
+                let zip_path = firmware_dir.join(format!("{}.zip", version));
+                // A malformed version must not name a file outside firmware_dir
+                if zip_path.parent() != Some(firmware_dir) {
+                    return Err(format!("Firmware {} is not cached", version));
+                }
+                zip_path
+            }
+        };
+
+        let bytes_freed = remove_version_files(firmware_dir, version, &zip_path)?;
+        lock.save(index)?;
+        Ok(bytes_freed)
+    }
+
+    /// Remove unpinned versions neither downloaded nor used within `max_age` of `now`.
+    ///
+    /// Deletes each version's zip and extracted directory along with its
+    /// index entry. Entries with no parseable timestamp are kept.
+    pub fn cleanup_older_than(
+        &self,
+        firmware_dir: &Path,
+        max_age: chrono::Duration,
+        now: DateTime<Utc>,
+    ) -> Result<CacheCleanupResult, String> {
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
+        let cutoff = now - max_age;
+
+        let mut expired: Vec<String> = index
+            .values()
+            .filter(|metadata| !metadata.pinned)
+            .filter(|metadata| match metadata.last_activity() {
+                Some(last_activity) => last_activity < cutoff,
+                None => {
+                    log::warn!(
+                        "No valid timestamp for {}; skipping age cleanup",
+                        metadata.version
+                    );
+                    false
+                }
+            })
+            .map(|metadata| metadata.version.clone())
+            .collect();
+        expired.sort();
+
+        let mut result = CacheCleanupResult::default();
+        for version in expired {
+            let Some(metadata) = index.remove(&version) else {
+                continue;
+            };
+
+            result.bytes_freed +=
+                remove_version_files(firmware_dir, &version, Path::new(&metadata.zip_path))?;
+            result.removed.push(version);
+        }
+
+        if !result.removed.is_empty() {
+            lock.save(index)?;
+        }
+        Ok(result)
+    }
+
+    /// Remove every unpinned version from disk and the index.
+    ///
+    /// Deletes the files recorded for each unpinned entry, then any orphaned
+    /// cache files in `firmware_dir` that follow our naming (version zips,
+    /// partial or staged downloads, version directories). Pinned versions,
+    /// files we don't recognize and the `downloading` partial files of
+    /// downloads still running are left alone. A file that can't be deleted
+    /// is reported in `failed` without stopping the rest, and the index is
+    /// saved either way.
+    pub fn clear_unpinned(
+        &self,
+        firmware_dir: &Path,
+        downloading: &[PathBuf],
+    ) -> Result<CacheClearResult, String> {
+        let mut lock = self.lock_index();
+        let mut index = lock.load();
+        let mut result = CacheClearResult::default();
+
+        let mut unpinned: Vec<String> = index
+            .values()
+            .filter(|metadata| !metadata.pinned)
+            .map(|metadata| metadata.version.clone())
+            .collect();
+        unpinned.sort();
+
+        for version in unpinned {
+            let Some(metadata) = index.get(&version) else {
+                continue;
+            };
+            let zip_path = PathBuf::from(&metadata.zip_path);
+            match remove_version_files(firmware_dir, &version, &zip_path) {
+                Ok(bytes_freed) => {
+                    index.remove(&version);
+                    result.bytes_freed += bytes_freed;
+                    result.versions_removed.push(version);
+                }
+                Err(e) => {
+                    // Only the extracted directory is left once the zip is
+                    // gone; the sweep below retries it as an orphan
+                    if !zip_path.exists() {
+                        index.remove(&version);
+                    }
+                    result.failed.push(format!("{}: {}", version, e));
+                }
+            }
+        }
+        result.pinned_kept = index.values().filter(|metadata| metadata.pinned).count();
+
+        let entries = match fs::read_dir(firmware_dir) {
+            Ok(entries) => Some(entries),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                result
+                    .failed
+                    .push(format!("Failed to read firmware directory: {}", e));
+                None
+            }
+        };
+        if let Some(entries) = entries {
+            let keep_zips: Vec<PathBuf> = index
+                .values()
+                .map(|metadata| PathBuf::from(&metadata.zip_path))
+                .collect();
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                let is_dir = path.is_dir();
+
+                let Some(version) = cache_file_version(&name, is_dir) else {
+                    result.skipped_unknown_files.push(name);
+                    continue;
+                };
+                if keep_zips.contains(&path)
+                    || (is_dir && index.contains_key(version))
+                    || downloading.contains(&path)
+                {
+                    continue;
+                }
+
+                let (size, removed) = if is_dir {
+                    (dir_size(&path), fs::remove_dir_all(&path))
+                } else {
+                    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    (size, fs::remove_file(&path))
+                };
+                match removed {
+                    Ok(()) => result.bytes_freed += size,
+                    Err(e) => {
+                        result
+                            .failed
+                            .push(format!("Failed to delete {}: {}", path.display(), e))
+                    }
+                }
+            }
+        }
+        result.skipped_unknown_files.sort();
+
+        lock.save(index)?;
+        Ok(result)
+    }
+
     /// Verify that cached files still exist on disk
     pub fn verify_cache_integrity(&self) -> Result<Vec<String>, String> {
         let index = self.load_index()?;
@@ -173,80 +1023,279 @@ impl CacheManager {
         }
     }
 
+    /// Collect disk usage and consistency information for the cache.
+    ///
+    /// Walks the index, checks each zip exists, sums zip and extracted
+    /// directory sizes, and reports zips on disk that aren't indexed.
+    pub fn stats(&self, firmware_dir: &Path) -> Result<CacheStats, String> {
+        let index = self.load_index()?;
+        let mut versions = Vec::new();
+        let mut inconsistencies = Vec::new();
+
+        for (version, metadata) in index.iter() {
+            let zip_size = fs::metadata(&metadata.zip_path).map(|m| m.len()).ok();
+
+            match zip_size {
+                None => inconsistencies.push(format!(
+                    "{}: zip file missing at {}",
+                    version, metadata.zip_path
+                )),
+                Some(size) if size != metadata.file_size => inconsistencies.push(format!(
+                    "{}: zip is {} bytes on disk but {} bytes in index",
+                    version, size, metadata.file_size
+                )),
+                Some(_) => {}
+            }
+
+            let extracted_dir = firmware_dir.join(version);
+            let extracted_size = if extracted_dir.is_dir() {
+                dir_size(&extracted_dir)
+            } else {
+                0
+            };
+
+            versions.push(CachedVersionStats {
+                version: version.clone(),
+                downloaded_at: metadata.downloaded_at.clone(),
+                zip_exists: zip_size.is_some(),
+                zip_size: zip_size.unwrap_or(0),
+                extracted_size,
+            });
+        }
+
+        // Zips on disk that the index doesn't know about
+        if let Ok(entries) = fs::read_dir(firmware_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("zip") {
+                    if let Some(version) = path.file_stem().and_then(|s| s.to_str()) {
+                        if !index.contains_key(version) {
+                            inconsistencies
+                                .push(format!("{}: zip file not in cache index", version));
+                        }
+                    }
+                }
+            }
+        }
+
+        versions.sort_by(|a, b| a.version.cmp(&b.version));
+        inconsistencies.sort();
+
+        let total_size = versions.iter().map(|v| v.zip_size + v.extracted_size).sum();
+        let oldest_downloaded_at = versions
+            .iter()
+            .map(|v| v.downloaded_at.clone())
+            .filter(|d| !d.is_empty())
+            .min();
+
+        Ok(CacheStats {
+            version_count: versions.len(),
+            total_size,
+            oldest_downloaded_at,
+            versions,
+            inconsistencies,
+        })
+    }
+
     /// Migrate existing cached firmware to the index
-    /// Scans firmware directory for existing zip files and adds them to cache index
-    pub fn migrate_existing_cache(&self, firmware_dir: &Path) -> Result<Vec<String>, String> {
+    /// Scans firmware directory for existing zip files and adds them to cache index,
+    /// calling `on_progress` before each un-indexed zip is hashed.
+    ///
+    /// Hashing runs without holding the index lock, so other commands aren't
+    /// blocked behind a slow migration.
+    pub fn migrate_existing_cache_with_progress(
+        &self,
+        firmware_dir: &Path,
+        mut on_progress: impl FnMut(CacheMigrationProgressEvent),
+    ) -> Result<Vec<String>, String> {
         if !firmware_dir.exists() {
             return Ok(Vec::new());
         }
 
-        let mut migrated_versions = Vec::new();
-        let mut index = self.load_index()?;
+        let mut migrated = Vec::new();
+        let index = self.load_index()?;
 
         // Read firmware directory entries
         let entries = fs::read_dir(firmware_dir)
             .map_err(|e| format!("Failed to read firmware directory: {}", e))?;
 
+        // Collect un-indexed .zip files first so progress can report a total
+        let mut pending = Vec::new();
         for entry in entries {
             let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
             let path = entry.path();
-
-            // Look for .zip files
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("zip") {
+            if is_migratable_zip(&path, &index) {
                 if let Some(version) = path.file_stem().and_then(|s| s.to_str()) {
-                    // Skip if already in index
-                    if index.contains_key(version) {
-                        continue;
+                    pending.push((version.to_string(), path.clone()));
+                }
+            }
+        }
+        pending.sort();
+
+        let total = pending.len();
+        for (i, (version, path)) in pending.into_iter().enumerate() {
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            on_progress(CacheMigrationProgressEvent {
+                message: format!(
+                    "Checking cached firmware {} ({}/{})...",
+                    file_name,
+                    i + 1,
+                    total
+                ),
+                file_name,
+                current: i + 1,
+                total,
+            });
+
+            // Calculate hash
+            let sha256_hash = match Self::calculate_sha256(&path) {
+                Ok(hash) => hash,
+                Err(_) => continue,
+            };
+
+            // Get file size
+            let file_size = match fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => continue,
+            };
+
+            // Get file modified time as fallback for download date
+            let downloaded_at = match fs::metadata(&path) {
+                Ok(metadata) => match metadata.modified() {
+                    Ok(time) => {
+                        let datetime: chrono::DateTime<chrono::Utc> = time.into();
+                        datetime.to_rfc3339()
                     }
+                    Err(_) => chrono::Utc::now().to_rfc3339(),
+                },
+                Err(_) => chrono::Utc::now().to_rfc3339(),
+            };
+
+            // Create metadata entry
+            let metadata = CachedFirmwareMetadata {
+                version: version.clone(),
+                tag_name: version.clone(),
+                sha256_hash,
+                zip_path: path.to_string_lossy().to_string(),
+                downloaded_at,
+                file_size,
+                published_at: "".to_string(), // Unknown for migrated cache
+                release_notes: "Migrated from existing cache".to_string(),
+                checksum_verified: false,
+                locally_imported: false,
+                pinned: false,
+                last_used_at: None,
+                channel: ReleaseChannel::Stable,
+                asset_kind: AssetKind::detect(&path),
+            };
+
+            migrated.push(metadata);
+        }
 
-                    // Calculate hash
-                    let sha256_hash = match Self::calculate_sha256(&path) {
-                        Ok(hash) => hash,
-                        Err(_) => continue,
-                    };
-
-                    // Get file size
-                    let file_size = match fs::metadata(&path) {
-                        Ok(metadata) => metadata.len(),
-                        Err(_) => continue,
-                    };
-
-                    // Get file modified time as fallback for download date
-                    let downloaded_at = match fs::metadata(&path) {
-                        Ok(metadata) => match metadata.modified() {
-                            Ok(time) => {
-                                let datetime: chrono::DateTime<chrono::Utc> = time.into();
-                                datetime.to_rfc3339()
-                            }
-                            Err(_) => chrono::Utc::now().to_rfc3339(),
-                        },
-                        Err(_) => chrono::Utc::now().to_rfc3339(),
-                    };
-
-                    // Create metadata entry
-                    let metadata = CachedFirmwareMetadata {
-                        version: version.to_string(),
-                        tag_name: version.to_string(),
-                        sha256_hash,
-                        zip_path: path.to_string_lossy().to_string(),
-                        downloaded_at,
-                        file_size,
-                        published_at: "".to_string(), // Unknown for migrated cache
-                        release_notes: "Migrated from existing cache".to_string(),
-                    };
-
-                    index.insert(version.to_string(), metadata);
-                    migrated_versions.push(version.to_string());
+        // Save updated index if we migrated anything, without overwriting
+        // entries another command added while we were hashing
+        let mut migrated_versions = Vec::new();
+        if !migrated.is_empty() {
+            let mut lock = self.lock_index();
+            let mut index = lock.load();
+            for metadata in migrated {
+                if !index.contains_key(&metadata.version) {
+                    migrated_versions.push(metadata.version.clone());
+                    index.insert(metadata.version.clone(), metadata);
                 }
             }
+            lock.save(index)?;
+        }
+
+        Ok(migrated_versions)
+    }
+
+    /// Classify every entry in `firmware_dir` and delete orphans that haven't
+    /// been modified within `grace_period` of `now`.
+    ///
+    /// An entry is indexed if an index entry's zip is, or lives under, that
+    /// path (so relocated zips are safe) or if it is the extracted directory
+    /// of an indexed version. Un-indexed zips are left for migration. The
+    /// grace period keeps resumable partial downloads and in-progress
+    /// extractions alive. Failed deletions are logged and skipped.
+    pub fn collect_cache_garbage(
+        &self,
+        firmware_dir: &Path,
+        grace_period: Duration,
+        now: SystemTime,
+    ) -> Result<CacheGarbageReport, String> {
+        let mut report = CacheGarbageReport::default();
+        if !firmware_dir.exists() {
+            return Ok(report);
         }
 
-        // Save updated index if we migrated anything
-        if !migrated_versions.is_empty() {
-            self.save_index(&index)?;
+        // Hold the lock so nothing is indexed or deleted while we decide
+        let mut lock = self.lock_index();
+        let index = lock.load();
+        let referenced: Vec<PathBuf> = index
+            .values()
+            .flat_map(|metadata| {
+                let path = PathBuf::from(&metadata.zip_path);
+                let canonical = fs::canonicalize(&path).ok();
+                std::iter::once(path).chain(canonical)
+            })
+            .collect();
+
+        let entries = fs::read_dir(firmware_dir)
+            .map_err(|e| format!("Failed to read firmware directory: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            let is_dir = path.is_dir();
+
+            let is_referenced = referenced
+                .iter()
+                .any(|zip| zip.starts_with(&path) || zip.starts_with(&canonical));
+            if is_referenced || (is_dir && index.contains_key(&name)) {
+                report.indexed.push(name);
+                continue;
+            }
+            if is_migratable_zip(&path, &index) {
+                report.migratable.push(name);
+                continue;
+            }
+            report.orphans.push(name.clone());
+
+            let age = last_modified(&path).and_then(|modified| now.duration_since(modified).ok());
+            if age.is_none_or(|age| age <= grace_period) {
+                continue;
+            }
+
+            let (size, removed) = if is_dir {
+                (dir_size(&path), fs::remove_dir_all(&path))
+            } else {
+                let size = fs::symlink_metadata(&path).map(|m| m.len()).unwrap_or(0);
+                (size, fs::remove_file(&path))
+            };
+            match removed {
+                Ok(()) => {
+                    report.removed.push(name);
+                    report.bytes_freed += size;
+                }
+                Err(e) => log::warn!(
+                    "Failed to remove orphaned cache entry {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
         }
 
-        Ok(migrated_versions)
+        report.indexed.sort();
+        report.migratable.sort();
+        report.orphans.sort();
+        report.removed.sort();
+        Ok(report)
     }
 }
 
@@ -265,22 +1314,313 @@ mod tests {
             file_size: 1024,
             published_at: "2024-01-01T00:00:00Z".to_string(),
             release_notes: "Test release".to_string(),
+            checksum_verified: false,
+            locally_imported: false,
+            pinned: false,
+            last_used_at: None,
+            channel: ReleaseChannel::Stable,
+            asset_kind: AssetKind::DfuPackage,
         }
     }
 
     #[test]
-    fn test_calculate_sha256_valid_file() {
+    fn test_load_index_without_checksum_verified_field() {
         let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("test.txt");
-        fs::write(&file_path, "hello world").unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
 
-        let hash = CacheManager::calculate_sha256(&file_path).unwrap();
-        // SHA256 of "hello world"
-        assert_eq!(
-            hash,
-            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
-        );
-    }
+        // Index written before checksum verification existed
+        let legacy = r#"{
+            "1.0.0": {
+                "version": "1.0.0",
+                "tag_name": "v1.0.0",
+                "sha256_hash": "abc123",
+                "zip_path": "/path/to/zip",
+                "downloaded_at": "2024-01-01T00:00:00Z",
+                "file_size": 1024,
+                "published_at": "2024-01-01T00:00:00Z",
+                "release_notes": "Old entry"
+            }
+        }"#;
+        fs::write(temp_dir.path().join("firmware_cache.json"), legacy).unwrap();
+
+        let index = cache_manager.load_index().unwrap();
+        assert!(!index.get("1.0.0").unwrap().checksum_verified);
+        assert!(!index.get("1.0.0").unwrap().pinned);
+        assert_eq!(index.get("1.0.0").unwrap().channel, ReleaseChannel::Stable);
+        assert_eq!(index.get("1.0.0").unwrap().asset_kind, AssetKind::Unknown);
+    }
+
+    #[test]
+    fn test_set_pinned() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        cache_manager.update_entry(create_test_metadata("1.0.0")).unwrap();
+
+        cache_manager.set_pinned("1.0.0", true).unwrap();
+        assert!(cache_manager.get_entry("1.0.0").unwrap().unwrap().pinned);
+
+        cache_manager.set_pinned("1.0.0", false).unwrap();
+        assert!(!cache_manager.get_entry("1.0.0").unwrap().unwrap().pinned);
+    }
+
+    #[test]
+    fn test_set_pinned_uncached_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+
+        let result = cache_manager.set_pinned("9.9.9", true);
+        assert!(result.unwrap_err().contains("not cached"));
+    }
+
+    #[test]
+    fn test_clear_unpinned_preserves_pinned() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(firmware_dir.join("1.0.0")).unwrap();
+
+        for version in ["1.0.0", "2.0.0"] {
+            let zip_path = firmware_dir.join(format!("{}.zip", version));
+            fs::write(&zip_path, "zip").unwrap();
+            let mut metadata = create_test_metadata(version);
+            metadata.zip_path = zip_path.to_string_lossy().to_string();
+            cache_manager.update_entry(metadata).unwrap();
+        }
+        fs::write(firmware_dir.join("3.0.0.zip.partial"), "partial").unwrap();
+        cache_manager.set_pinned("2.0.0", true).unwrap();
+
+        let result = cache_manager.clear_unpinned(&firmware_dir, &[]).unwrap();
+
+        assert_eq!(result.pinned_kept, 1);
+        assert_eq!(result.versions_removed, vec!["1.0.0"]);
+        // 1.0.0.zip and the 3.0.0 partial
+        assert_eq!(result.bytes_freed, 3 + 7);
+        assert!(firmware_dir.join("2.0.0.zip").exists());
+        assert!(!firmware_dir.join("1.0.0.zip").exists());
+        assert!(!firmware_dir.join("1.0.0").exists());
+        assert!(!firmware_dir.join("3.0.0.zip.partial").exists());
+
+        let index = cache_manager.load_index().unwrap();
+        assert_eq!(index.len(), 1);
+        assert!(index.contains_key("2.0.0"));
+    }
+
+    #[test]
+    fn test_clear_unpinned_keeps_unknown_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(firmware_dir.join("Backups")).unwrap();
+        fs::write(firmware_dir.join("notes.txt"), "mine").unwrap();
+        fs::write(firmware_dir.join("photos.zip"), "mine").unwrap();
+        // Orphans that follow our naming are removed
+        fs::write(firmware_dir.join("0.9.0.zip"), "orphan").unwrap();
+        fs::create_dir_all(firmware_dir.join("v0.8.0")).unwrap();
+
+        let result = cache_manager.clear_unpinned(&firmware_dir, &[]).unwrap();
+
+        assert_eq!(
+            result.skipped_unknown_files,
+            vec!["Backups", "notes.txt", "photos.zip"]
+        );
+        assert!(firmware_dir.join("notes.txt").exists());
+        assert!(firmware_dir.join("photos.zip").exists());
+        assert!(firmware_dir.join("Backups").exists());
+        assert!(!firmware_dir.join("0.9.0.zip").exists());
+        assert!(!firmware_dir.join("v0.8.0").exists());
+    }
+
+    #[test]
+    fn test_clear_unpinned_saves_index_despite_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        // A directory where the zip should be can't be deleted as a file
+        let stuck = firmware_dir.join("1.0.0.zip");
+        fs::create_dir_all(&stuck).unwrap();
+        let removable = firmware_dir.join("2.0.0.zip");
+        fs::write(&removable, "zip").unwrap();
+        for (version, zip_path) in [("1.0.0", &stuck), ("2.0.0", &removable)] {
+            let mut metadata = create_test_metadata(version);
+            metadata.zip_path = zip_path.to_string_lossy().to_string();
+            cache_manager.update_entry(metadata).unwrap();
+        }
+        let downloading = firmware_dir.join("3.0.0.zip.partial");
+        fs::write(&downloading, "partial").unwrap();
+
+        let result = cache_manager
+            .clear_unpinned(&firmware_dir, std::slice::from_ref(&downloading))
+            .unwrap();
+
+        assert_eq!(result.versions_removed, vec!["2.0.0"]);
+        assert_eq!(result.failed.len(), 1);
+        assert!(result.failed[0].starts_with("1.0.0: "));
+        assert!(downloading.exists());
+
+        let index = CacheManager::new(temp_dir.path())
+            .unwrap()
+            .load_index()
+            .unwrap();
+        assert_eq!(index.len(), 1);
+        assert!(index.contains_key("1.0.0"));
+    }
+
+    fn create_aged_entry(
+        cache_manager: &CacheManager,
+        firmware_dir: &Path,
+        version: &str,
+        downloaded_days_ago: i64,
+        used_days_ago: Option<i64>,
+    ) {
+        let now = Utc::now();
+        let zip_path = firmware_dir.join(format!("{}.zip", version));
+        fs::create_dir_all(firmware_dir).unwrap();
+        fs::write(&zip_path, "0123456789").unwrap();
+
+        let mut metadata = create_test_metadata(version);
+        metadata.zip_path = zip_path.to_string_lossy().to_string();
+        metadata.downloaded_at = (now - chrono::Duration::days(downloaded_days_ago)).to_rfc3339();
+        metadata.last_used_at =
+            used_days_ago.map(|days| (now - chrono::Duration::days(days)).to_rfc3339());
+        cache_manager.update_entry(metadata).unwrap();
+    }
+
+    #[test]
+    fn test_last_activity_prefers_latest_timestamp() {
+        let mut metadata = create_test_metadata("1.0.0");
+        metadata.downloaded_at = "2024-01-01T00:00:00Z".to_string();
+        assert_eq!(
+            metadata.last_activity().unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+
+        metadata.last_used_at = Some("2024-06-01T00:00:00Z".to_string());
+        assert_eq!(
+            metadata.last_activity().unwrap().to_rfc3339(),
+            "2024-06-01T00:00:00+00:00"
+        );
+
+        metadata.downloaded_at = "not a date".to_string();
+        metadata.last_used_at = None;
+        assert!(metadata.last_activity().is_none());
+    }
+
+    #[test]
+    fn test_mark_used() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        cache_manager.update_entry(create_test_metadata("1.0.0")).unwrap();
+
+        cache_manager.mark_used("1.0.0").unwrap();
+        cache_manager.mark_used("9.9.9").unwrap();
+
+        let entry = cache_manager.get_entry("1.0.0").unwrap().unwrap();
+        assert!(entry.last_used_at.is_some());
+        assert!(cache_manager.get_entry("9.9.9").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_mark_used_by_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        for version in ["1.0.0", "2.0.0"] {
+            let mut metadata = create_test_metadata(version);
+            metadata.zip_path = format!("/path/to/{}.zip", version);
+            cache_manager.update_entry(metadata).unwrap();
+        }
+
+        cache_manager
+            .mark_used_by_path(Path::new("/path/to/2.0.0.zip"))
+            .unwrap();
+
+        let index = cache_manager.load_index().unwrap();
+        assert!(index.get("1.0.0").unwrap().last_used_at.is_none());
+        assert!(index.get("2.0.0").unwrap().last_used_at.is_some());
+    }
+
+    #[test]
+    fn test_cleanup_older_than() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+
+        create_aged_entry(&cache_manager, &firmware_dir, "1.0.0", 200, None);
+        create_aged_entry(&cache_manager, &firmware_dir, "1.1.0", 200, Some(120));
+        create_aged_entry(&cache_manager, &firmware_dir, "2.0.0", 200, Some(5));
+        create_aged_entry(&cache_manager, &firmware_dir, "3.0.0", 10, None);
+        create_aged_entry(&cache_manager, &firmware_dir, "0.9.0", 365, None);
+        cache_manager.set_pinned("0.9.0", true).unwrap();
+        fs::create_dir_all(firmware_dir.join("1.0.0")).unwrap();
+        fs::write(firmware_dir.join("1.0.0").join("code.py"), "12345").unwrap();
+
+        let result = cache_manager
+            .cleanup_older_than(&firmware_dir, chrono::Duration::days(90), Utc::now())
+            .unwrap();
+
+        // Old and unused (or used long ago) versions go
+        assert_eq!(result.removed, vec!["1.0.0", "1.1.0"]);
+        assert_eq!(result.bytes_freed, 10 + 5 + 10);
+        assert!(!firmware_dir.join("1.0.0.zip").exists());
+        assert!(!firmware_dir.join("1.0.0").exists());
+        assert!(!firmware_dir.join("1.1.0.zip").exists());
+
+        // Old but recently used, recently downloaded, and pinned versions stay
+        let index = cache_manager.load_index().unwrap();
+        assert_eq!(index.len(), 3);
+        for version in ["2.0.0", "3.0.0", "0.9.0"] {
+            assert!(index.contains_key(version));
+            assert!(firmware_dir.join(format!("{}.zip", version)).exists());
+        }
+    }
+
+    #[test]
+    fn test_cleanup_older_than_keeps_unparseable_timestamps() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let mut metadata = create_test_metadata("1.0.0");
+        metadata.downloaded_at = "unknown".to_string();
+        cache_manager.update_entry(metadata).unwrap();
+
+        let result = cache_manager
+            .cleanup_older_than(
+                &temp_dir.path().join("firmware"),
+                chrono::Duration::days(1),
+                Utc::now(),
+            )
+            .unwrap();
+
+        assert!(result.removed.is_empty());
+        assert!(cache_manager.get_entry("1.0.0").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_clear_unpinned_without_firmware_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        cache_manager.update_entry(create_test_metadata("1.0.0")).unwrap();
+
+        let result = cache_manager
+            .clear_unpinned(&temp_dir.path().join("firmware"), &[])
+            .unwrap();
+
+        assert_eq!(result.pinned_kept, 0);
+        assert_eq!(result.versions_removed, vec!["1.0.0"]);
+        assert!(cache_manager.load_index().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_calculate_sha256_valid_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, "hello world").unwrap();
+
+        let hash = CacheManager::calculate_sha256(&file_path).unwrap();
+        // SHA256 of "hello world"
+        assert_eq!(
+            hash,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
 
     #[test]
     fn test_calculate_sha256_empty_file() {
@@ -320,7 +1660,7 @@ mod tests {
         let mut index: FirmwareCacheIndex = HashMap::new();
         index.insert("v1.0.0".to_string(), create_test_metadata("1.0.0"));
 
-        cache_manager.save_index(&index).unwrap();
+        cache_manager.lock_index().save(index).unwrap();
         let loaded = cache_manager.load_index().unwrap();
 
         assert_eq!(loaded.len(), 1);
@@ -416,26 +1756,6 @@ mod tests {
         assert!(entry.is_none());
     }
 
-    #[test]
-    fn test_clear_index() {
-        let temp_dir = TempDir::new().unwrap();
-        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
-
-        // Add entries
-        cache_manager
-            .update_entry(create_test_metadata("1.0.0"))
-            .unwrap();
-        cache_manager
-            .update_entry(create_test_metadata("2.0.0"))
-            .unwrap();
-
-        // Clear
-        cache_manager.clear_index().unwrap();
-
-        let index = cache_manager.load_index().unwrap();
-        assert!(index.is_empty());
-    }
-
     #[test]
     fn test_verify_cache_integrity_missing_files() {
         let temp_dir = TempDir::new().unwrap();
@@ -545,7 +1865,9 @@ mod tests {
         fs::write(&zip_path, "fake zip content").unwrap();
 
         let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
-        let migrated = cache_manager.migrate_existing_cache(&firmware_dir).unwrap();
+        let migrated = cache_manager
+            .migrate_existing_cache_with_progress(&firmware_dir, |_| {})
+            .unwrap();
 
         assert_eq!(migrated.len(), 1);
         assert!(migrated.contains(&"v1.0.0".to_string()));
@@ -571,10 +1893,168 @@ mod tests {
         cache_manager.update_entry(create_test_metadata("v1.0.0")).unwrap();
 
         // Migrate should skip already indexed
-        let migrated = cache_manager.migrate_existing_cache(&firmware_dir).unwrap();
+        let migrated = cache_manager
+            .migrate_existing_cache_with_progress(&firmware_dir, |_| {})
+            .unwrap();
         assert!(migrated.is_empty());
     }
 
+    #[test]
+    fn test_migrate_existing_cache_reports_progress() {
+        let temp_dir = TempDir::new().unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(&firmware_dir).unwrap();
+        for version in ["v1.0.0", "v1.1.0", "v2.0.0"] {
+            fs::write(firmware_dir.join(format!("{}.zip", version)), version).unwrap();
+        }
+
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        cache_manager
+            .update_entry(create_test_metadata("v1.1.0"))
+            .unwrap();
+
+        let mut events = Vec::new();
+        let migrated = cache_manager
+            .migrate_existing_cache_with_progress(&firmware_dir, |event| events.push(event))
+            .unwrap();
+
+        // Only un-indexed zips are hashed and reported
+        assert_eq!(migrated, vec!["v1.0.0", "v2.0.0"]);
+        let reported: Vec<_> = events
+            .iter()
+            .map(|e| (e.file_name.as_str(), e.current, e.total))
+            .collect();
+        assert_eq!(reported, vec![("v1.0.0.zip", 1, 2), ("v2.0.0.zip", 2, 2)]);
+    }
+
+    #[test]
+    fn test_reads_are_served_from_memory() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        cache_manager
+            .update_entry(create_test_metadata("1.0.0"))
+            .unwrap();
+
+        // Once loaded, the index isn't re-read from disk
+        fs::remove_file(temp_dir.path().join("firmware_cache.json")).unwrap();
+        assert!(cache_manager.get_entry("1.0.0").unwrap().is_some());
+
+        // Clones share the in-memory index, and mutations still persist
+        let clone = cache_manager.clone();
+        clone.update_entry(create_test_metadata("2.0.0")).unwrap();
+        assert!(cache_manager.get_entry("2.0.0").unwrap().is_some());
+
+        let reloaded = CacheManager::new(temp_dir.path()).unwrap();
+        assert_eq!(reloaded.load_index().unwrap().len(), 2);
+    }
+
+    /// Firmware directory with one of everything garbage collection has to
+    /// tell apart. Returns the manager and the firmware directory.
+    fn garbage_fixture(temp_dir: &TempDir) -> (CacheManager, PathBuf) {
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(firmware_dir.join("1.0.0")).unwrap();
+        fs::create_dir_all(firmware_dir.join("custom")).unwrap();
+        fs::create_dir_all(firmware_dir.join("6.0.0").join("nested")).unwrap();
+
+        // Indexed: default zip, its extracted directory, and a relocated zip
+        fs::write(firmware_dir.join("1.0.0.zip"), b"indexed").unwrap();
+        fs::write(firmware_dir.join("1.0.0").join("app.bin"), b"extracted").unwrap();
+        let relocated = firmware_dir.join("custom").join("relocated.zip");
+        fs::write(&relocated, b"relocated").unwrap();
+        cache_manager
+            .update_entries(vec![
+                CachedFirmwareMetadata {
+                    zip_path: firmware_dir.join("1.0.0.zip").to_string_lossy().to_string(),
+                    ..create_test_metadata("1.0.0")
+                },
+                CachedFirmwareMetadata {
+                    zip_path: relocated.to_string_lossy().to_string(),
+                    ..create_test_metadata("2.0.0")
+                },
+            ])
+            .unwrap();
+
+        // Migratable: a zip nobody indexed yet
+        fs::write(firmware_dir.join("3.0.0.zip"), b"unindexed").unwrap();
+
+        // Orphans: partial and staged downloads, a half-extracted directory, a stray file
+        fs::write(firmware_dir.join("4.0.0.zip.partial"), b"part").unwrap();
+        fs::write(firmware_dir.join("5.0.0.zip.tmp"), b"staged").unwrap();
+        fs::write(firmware_dir.join("6.0.0").join("nested").join("app.dat"), b"half").unwrap();
+        fs::write(firmware_dir.join("notes.txt"), b"notes").unwrap();
+
+        (cache_manager, firmware_dir)
+    }
+
+    const GRACE: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+    #[test]
+    fn test_collect_cache_garbage_removes_old_orphans() {
+        let temp_dir = TempDir::new().unwrap();
+        let (cache_manager, firmware_dir) = garbage_fixture(&temp_dir);
+        let later = SystemTime::now() + Duration::from_secs(30 * 24 * 60 * 60);
+
+        let report = cache_manager
+            .collect_cache_garbage(&firmware_dir, GRACE, later)
+            .unwrap();
+
+        assert_eq!(report.indexed, vec!["1.0.0", "1.0.0.zip", "custom"]);
+        assert_eq!(report.migratable, vec!["3.0.0.zip"]);
+        let orphans = vec!["4.0.0.zip.partial", "5.0.0.zip.tmp", "6.0.0", "notes.txt"];
+        assert_eq!(report.orphans, orphans);
+        assert_eq!(report.removed, orphans);
+        assert_eq!(report.bytes_freed, (4 + 6 + 4 + 5) as u64);
+
+        // Everything the index references (and the migratable zip) survives
+        assert!(firmware_dir.join("1.0.0.zip").exists());
+        assert!(firmware_dir.join("1.0.0").join("app.bin").exists());
+        assert!(firmware_dir.join("custom").join("relocated.zip").exists());
+        assert!(firmware_dir.join("3.0.0.zip").exists());
+        for orphan in orphans {
+            assert!(!firmware_dir.join(orphan).exists(), "{} not removed", orphan);
+        }
+        assert_eq!(cache_manager.load_index().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_collect_cache_garbage_respects_grace_period() {
+        let temp_dir = TempDir::new().unwrap();
+        let (cache_manager, firmware_dir) = garbage_fixture(&temp_dir);
+
+        // Only the partial download is older than the grace period
+        let old = SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(firmware_dir.join("4.0.0.zip.partial"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let report = cache_manager
+            .collect_cache_garbage(&firmware_dir, GRACE, SystemTime::now())
+            .unwrap();
+
+        assert_eq!(report.orphans.len(), 4);
+        assert_eq!(report.removed, vec!["4.0.0.zip.partial"]);
+        assert_eq!(report.bytes_freed, 4);
+        assert!(firmware_dir.join("5.0.0.zip.tmp").exists());
+        assert!(firmware_dir.join("6.0.0").join("nested").join("app.dat").exists());
+    }
+
+    #[test]
+    fn test_collect_cache_garbage_without_firmware_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+
+        let report = cache_manager
+            .collect_cache_garbage(&temp_dir.path().join("firmware"), GRACE, SystemTime::now())
+            .unwrap();
+
+        assert!(report.orphans.is_empty());
+        assert_eq!(report.bytes_freed, 0);
+    }
+
     #[test]
     fn test_load_index_invalid_json() {
         let temp_dir = TempDir::new().unwrap();
@@ -591,6 +2071,40 @@ mod tests {
         assert!(result.unwrap().is_empty());
     }
 
+    #[test]
+    fn test_load_truncated_index_recovers_from_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let cache_file = temp_dir.path().join("firmware_cache.json");
+
+        cache_manager
+            .update_entry(create_test_metadata("1.0.0"))
+            .unwrap();
+        assert!(temp_dir.path().join("firmware_cache.json.bak").exists());
+
+        // Simulate a crash mid-write leaving a truncated file
+        let contents = fs::read_to_string(&cache_file).unwrap();
+        fs::write(&cache_file, &contents[..contents.len() / 2]).unwrap();
+
+        // Next launch reads the index from disk again
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let index = cache_manager.load_index().unwrap();
+        assert_eq!(index.len(), 1);
+        assert!(index.contains_key("1.0.0"));
+    }
+
+    #[test]
+    fn test_load_truncated_index_without_backup_returns_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_file = temp_dir.path().join("firmware_cache.json");
+        fs::write(&cache_file, "{\n  \"1.0.0\": {\n    \"version\": \"1.0").unwrap();
+
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let index = cache_manager.load_index().unwrap();
+
+        assert!(index.is_empty());
+    }
+
     #[test]
     fn test_save_index_atomic_no_tmp_leftover() {
         let temp_dir = TempDir::new().unwrap();
@@ -599,7 +2113,7 @@ mod tests {
         let mut index = HashMap::new();
         index.insert("v1.0.0".to_string(), create_test_metadata("1.0.0"));
 
-        cache_manager.save_index(&index).unwrap();
+        cache_manager.lock_index().save(index).unwrap();
 
         // Final file should exist
         assert!(temp_dir.path().join("firmware_cache.json").exists());
@@ -617,7 +2131,7 @@ mod tests {
         let mut index = std::collections::HashMap::new();
         index.insert("v1.0.0".to_string(), create_test_metadata("1.0.0"));
 
-        let result = cache_manager.save_index(&index);
+        let result = cache_manager.lock_index().save(index);
 
         assert!(result.is_ok());
         assert!(nested_path.join("firmware_cache.json").exists());
@@ -640,4 +2154,570 @@ mod tests {
         assert_eq!(hash.len(), 64);
         assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
     }
+
+    #[test]
+    fn test_stats_totals_and_breakdown() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(&firmware_dir).unwrap();
+
+        let zip1 = firmware_dir.join("1.0.0.zip");
+        fs::write(&zip1, vec![0u8; 100]).unwrap();
+        cache_manager
+            .update_entry(CachedFirmwareMetadata {
+                zip_path: zip1.to_string_lossy().to_string(),
+                file_size: 100,
+                downloaded_at: "2024-03-01T00:00:00Z".to_string(),
+                ..create_test_metadata("1.0.0")
+            })
+            .unwrap();
+
+        let zip2 = firmware_dir.join("2.0.0.zip");
+        fs::write(&zip2, vec![0u8; 200]).unwrap();
+        cache_manager
+            .update_entry(CachedFirmwareMetadata {
+                zip_path: zip2.to_string_lossy().to_string(),
+                file_size: 200,
+                downloaded_at: "2024-05-01T00:00:00Z".to_string(),
+                ..create_test_metadata("2.0.0")
+            })
+            .unwrap();
+
+        // Extracted directory with nested files
+        let extracted = firmware_dir.join("2.0.0").join("lib");
+        fs::create_dir_all(&extracted).unwrap();
+        fs::write(firmware_dir.join("2.0.0").join("code.py"), vec![0u8; 30]).unwrap();
+        fs::write(extracted.join("module.py"), vec![0u8; 20]).unwrap();
+
+        let stats = cache_manager.stats(&firmware_dir).unwrap();
+
+        assert_eq!(stats.version_count, 2);
+        assert_eq!(stats.total_size, 100 + 200 + 50);
+        assert_eq!(
+            stats.oldest_downloaded_at.as_deref(),
+            Some("2024-03-01T00:00:00Z")
+        );
+        assert_eq!(stats.versions[0].version, "1.0.0");
+        assert_eq!(stats.versions[0].extracted_size, 0);
+        assert_eq!(stats.versions[1].zip_size, 200);
+        assert_eq!(stats.versions[1].extracted_size, 50);
+        assert!(stats.inconsistencies.is_empty());
+    }
+
+    #[test]
+    fn test_stats_reports_inconsistencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(&firmware_dir).unwrap();
+
+        // Indexed but missing on disk
+        cache_manager
+            .update_entry(CachedFirmwareMetadata {
+                zip_path: firmware_dir.join("1.0.0.zip").to_string_lossy().to_string(),
+                ..create_test_metadata("1.0.0")
+            })
+            .unwrap();
+
+        // Size differs from index
+        let zip2 = firmware_dir.join("2.0.0.zip");
+        fs::write(&zip2, vec![0u8; 10]).unwrap();
+        cache_manager
+            .update_entry(CachedFirmwareMetadata {
+                zip_path: zip2.to_string_lossy().to_string(),
+                file_size: 999,
+                ..create_test_metadata("2.0.0")
+            })
+            .unwrap();
+
+        // On disk but not indexed
+        fs::write(firmware_dir.join("3.0.0.zip"), b"orphan").unwrap();
+
+        let stats = cache_manager.stats(&firmware_dir).unwrap();
+
+        assert_eq!(stats.version_count, 2);
+        assert!(!stats.versions[0].zip_exists);
+        assert_eq!(stats.inconsistencies.len(), 3);
+        assert!(stats.inconsistencies[0].starts_with("1.0.0: zip file missing"));
+        assert!(stats.inconsistencies[1].starts_with("2.0.0: zip is 10 bytes"));
+        assert!(stats.inconsistencies[2].starts_with("3.0.0: zip file not in cache index"));
+    }
+
+    #[test]
+    fn test_stats_empty_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+
+        let stats = cache_manager
+            .stats(&temp_dir.path().join("firmware"))
+            .unwrap();
+
+        assert_eq!(stats.version_count, 0);
+        assert_eq!(stats.total_size, 0);
+        assert!(stats.oldest_downloaded_at.is_none());
+    }
+
+    #[test]
+    fn test_concurrent_updates_and_removes_keep_index_consistent() {
+        let temp_dir = TempDir::new().unwrap();
+        let app_dir = temp_dir.path().to_path_buf();
+        let shared = CacheManager::new(&app_dir).unwrap();
+
+        let handles: Vec<_> = (0..8)
+            .map(|thread_id| {
+                // Commands share the managed instance through clones
+                let cache_manager = shared.clone();
+                std::thread::spawn(move || {
+                    for i in 0..10 {
+                        let version = format!("{}.{}.0", thread_id, i);
+                        cache_manager
+                            .update_entry(create_test_metadata(&version))
+                            .unwrap();
+                        if i % 2 == 1 {
+                            cache_manager.remove_entry(&version).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let cache_manager = CacheManager::new(&app_dir).unwrap();
+        let index = cache_manager.load_index().unwrap();
+
+        // Every even entry survives, every odd entry is gone
+        assert_eq!(index.len(), 8 * 5);
+        for thread_id in 0..8 {
+            for i in 0..10 {
+                let version = format!("{}.{}.0", thread_id, i);
+                assert_eq!(index.contains_key(&version), i % 2 == 0, "{}", version);
+            }
+        }
+    }
+
+    fn write_zip(path: &Path, names: &[&str]) {
+        let mut writer = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for name in names {
+            writer
+                .start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            std::io::Write::write_all(&mut writer, b"data").unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn integrity_fixture(temp_dir: &TempDir, names: &[&str]) -> CachedFirmwareMetadata {
+        let zip_path = temp_dir.path().join("1.0.0.zip");
+        write_zip(&zip_path, names);
+        CachedFirmwareMetadata {
+            sha256_hash: CacheManager::calculate_sha256(&zip_path).unwrap(),
+            zip_path: zip_path.to_string_lossy().to_string(),
+            ..create_test_metadata("1.0.0")
+        }
+    }
+
+    #[test]
+    fn test_check_integrity_accepts_intact_zip() {
+        let temp_dir = TempDir::new().unwrap();
+        let metadata = integrity_fixture(
+            &temp_dir,
+            &["manifest.json", "firmware.bin", "firmware.dat"],
+        );
+
+        assert!(metadata.check_integrity().is_ok());
+    }
+
+    #[test]
+    fn test_check_integrity_detects_corruption() {
+        let temp_dir = TempDir::new().unwrap();
+        let metadata = integrity_fixture(
+            &temp_dir,
+            &["manifest.json", "firmware.bin", "firmware.dat"],
+        );
+
+        // Half-overwritten zip
+        let mut bytes = fs::read(&metadata.zip_path).unwrap();
+        bytes.truncate(bytes.len() / 2);
+        fs::write(&metadata.zip_path, &bytes).unwrap();
+
+        let error = metadata.check_integrity().unwrap_err();
+        assert!(error.contains("hash mismatch"), "{}", error);
+    }
+
+    #[test]
+    fn test_check_integrity_checks_contents() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Hash matches, but the archive isn't the DFU package it was indexed as
+        let metadata = integrity_fixture(&temp_dir, &["code.py"]);
+        let error = metadata.check_integrity().unwrap_err();
+        assert!(error.contains("DFU"), "{}", error);
+
+        let empty = integrity_fixture(&temp_dir, &[]);
+        assert_eq!(empty.check_integrity().unwrap_err(), "zip archive is empty");
+
+        let missing = CachedFirmwareMetadata {
+            zip_path: temp_dir
+                .path()
+                .join("gone.zip")
+                .to_string_lossy()
+                .to_string(),
+            ..create_test_metadata("1.0.0")
+        };
+        assert!(missing.check_integrity().is_err());
+    }
+
+    #[test]
+    fn test_resolve_dfu_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let metadata = integrity_fixture(
+            &temp_dir,
+            &["manifest.json", "firmware.bin", "firmware.dat"],
+        );
+        cache_manager.update_entry(metadata.clone()).unwrap();
+
+        // By key or by release tag
+        assert_eq!(
+            cache_manager.resolve_dfu_package("1.0.0").unwrap().zip_path,
+            metadata.zip_path
+        );
+        assert_eq!(
+            cache_manager.resolve_dfu_package("v1.0.0").unwrap().version,
+            "1.0.0"
+        );
+        assert_eq!(
+            cache_manager
+                .entry_by_path(Path::new(&metadata.zip_path))
+                .unwrap()
+                .map(|m| m.version),
+            Some("1.0.0".to_string())
+        );
+
+        let error = cache_manager.resolve_dfu_package("v9.9.9").unwrap_err();
+        assert_eq!(error, "Firmware version v9.9.9 is not cached");
+    }
+
+    #[test]
+    fn test_resolve_dfu_package_rejects_bad_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+
+        // Corrupted on disk since it was indexed
+        let metadata = integrity_fixture(
+            &temp_dir,
+            &["manifest.json", "firmware.bin", "firmware.dat"],
+        );
+        cache_manager.update_entry(metadata.clone()).unwrap();
+        fs::write(&metadata.zip_path, b"garbage").unwrap();
+        let error = cache_manager.resolve_dfu_package("1.0.0").unwrap_err();
+        assert!(error.contains("failed integrity check"), "{}", error);
+
+        // Intact, but not something the DFU flow can flash
+        let bundle = CachedFirmwareMetadata {
+            version: "2.0.0".to_string(),
+            asset_kind: AssetKind::Unknown,
+            ..integrity_fixture(&temp_dir, &["code.py"])
+        };
+        cache_manager.update_entry(bundle).unwrap();
+        let error = cache_manager.resolve_dfu_package("2.0.0").unwrap_err();
+        assert!(error.contains("not a DFU package"), "{}", error);
+    }
+
+    #[test]
+    fn test_asset_kind_from_entry_names() {
+        assert_eq!(
+            AssetKind::from_entry_names(["manifest.json", "firmware.bin", "firmware.dat"]),
+            AssetKind::DfuPackage
+        );
+        assert_eq!(
+            AssetKind::from_entry_names(["boot.py", "code.py", "lib/util.py"]),
+            AssetKind::CircuitPyBundle
+        );
+        assert_eq!(
+            AssetKind::from_entry_names(["bundle/code.py", "bundle/config.py"]),
+            AssetKind::CircuitPyBundle
+        );
+        // A manifest without an init packet isn't a DFU package
+        assert_eq!(
+            AssetKind::from_entry_names(["manifest.json", "readme.txt"]),
+            AssetKind::Unknown
+        );
+    }
+
+    #[test]
+    fn test_asset_kind_serialization() {
+        assert_eq!(
+            serde_json::to_value(AssetKind::CircuitPyBundle).unwrap(),
+            "circuitpy_bundle"
+        );
+        assert_eq!(
+            serde_json::to_value(AssetKind::DfuPackage).unwrap(),
+            "dfu_package"
+        );
+    }
+
+    #[test]
+    fn test_reclassify_unknown() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+
+        let dfu_zip = temp_dir.path().join("1.0.0.zip");
+        write_zip(&dfu_zip, &["manifest.json", "firmware.bin", "firmware.dat"]);
+        let bundle_zip = temp_dir.path().join("1.0.0-circuitpy.zip");
+        write_zip(&bundle_zip, &["code.py"]);
+
+        for (version, path) in [("1.0.0", &dfu_zip), ("1.0.0-circuitpy", &bundle_zip)] {
+            let mut metadata = create_test_metadata(version);
+            metadata.zip_path = path.to_string_lossy().to_string();
+            metadata.asset_kind = AssetKind::Unknown;
+            cache_manager.update_entry(metadata).unwrap();
+        }
+        // Missing zip stays Unknown
+        let mut missing = create_test_metadata("0.9.0");
+        missing.asset_kind = AssetKind::Unknown;
+        cache_manager.update_entry(missing).unwrap();
+
+        let mut reclassified = cache_manager.reclassify_unknown().unwrap();
+        reclassified.sort();
+        assert_eq!(reclassified, vec!["1.0.0", "1.0.0-circuitpy"]);
+
+        let index = cache_manager.load_index().unwrap();
+        assert_eq!(index["1.0.0"].asset_kind, AssetKind::DfuPackage);
+        assert_eq!(
+            index["1.0.0-circuitpy"].asset_kind,
+            AssetKind::CircuitPyBundle
+        );
+        assert_eq!(index["0.9.0"].asset_kind, AssetKind::Unknown);
+    }
+
+    #[test]
+    fn test_compare_versions_published_tags() {
+        // Tag formats used for firmware releases so far, oldest first
+        let published = [
+            "v0.9.0",
+            "1.1",
+            "v1.2.0",
+            "v1.2.1",
+            "v1.10.0",
+            "v2.0.0-beta",
+            "v2.0.0-beta.2",
+            "v2.0.0-beta.10",
+            "v2.0.0",
+        ];
+
+        let mut tags: Vec<&str> = published.iter().rev().copied().collect();
+        tags.sort_by(|a, b| compare_versions(a, b));
+        assert_eq!(tags, published);
+    }
+
+    #[test]
+    fn test_compare_versions_fallbacks() {
+        // Prefix and missing patch only break ties, deterministically
+        assert_eq!(compare_versions("1.2", "1.2.0"), "1.2".cmp("1.2.0"));
+        assert_eq!(compare_versions("v1.2.0", "1.2.0"), "v1.2.0".cmp("1.2.0"));
+        assert_eq!(
+            compare_versions("1.0.0+build5", "1.0.0"),
+            "1.0.0+build5".cmp("1.0.0")
+        );
+        // Non-version tags sort below versions and among themselves by name
+        assert_eq!(compare_versions("nightly", "0.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("custom-a", "custom-b"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_version_at_least() {
+        assert_eq!(version_at_least("2.1.0", "2.1"), Some(true));
+        assert_eq!(version_at_least("v2.3.1", "2.1.0"), Some(true));
+        assert_eq!(version_at_least("2.0.9", "2.1.0"), Some(false));
+        assert_eq!(version_at_least("2.1.0-beta.1", "2.1.0"), Some(false));
+        assert_eq!(version_at_least("unknown", "2.1.0"), None);
+    }
+
+    #[test]
+    fn test_latest_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+
+        for (version, channel, kind) in [
+            ("1.2.0", ReleaseChannel::Stable, AssetKind::DfuPackage),
+            ("1.10.0", ReleaseChannel::Stable, AssetKind::DfuPackage),
+            ("2.0.0-beta.1", ReleaseChannel::Beta, AssetKind::DfuPackage),
+            ("3.0.0", ReleaseChannel::Stable, AssetKind::CircuitPyBundle),
+        ] {
+            let zip_path = temp_dir.path().join(format!("{}.zip", version));
+            fs::write(&zip_path, b"zip").unwrap();
+            let mut metadata = create_test_metadata(version);
+            metadata.zip_path = zip_path.to_string_lossy().to_string();
+            metadata.channel = channel;
+            metadata.asset_kind = kind;
+            cache_manager.update_entry(metadata).unwrap();
+        }
+        // Newest, but its zip is gone
+        cache_manager
+            .update_entry(create_test_metadata("9.9.9"))
+            .unwrap();
+
+        let latest = |kind, channel| {
+            cache_manager
+                .latest_entry(kind, channel)
+                .unwrap()
+                .map(|metadata| metadata.version)
+        };
+        assert_eq!(latest(None, None).as_deref(), Some("3.0.0"));
+        assert_eq!(
+            latest(Some(AssetKind::DfuPackage), Some(ReleaseChannel::Stable)).as_deref(),
+            Some("1.10.0")
+        );
+        assert_eq!(
+            latest(Some(AssetKind::DfuPackage), Some(ReleaseChannel::Beta)).as_deref(),
+            Some("2.0.0-beta.1")
+        );
+        assert_eq!(latest(Some(AssetKind::Unknown), None), None);
+    }
+
+    #[test]
+    fn test_delete_version_uses_recorded_zip_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(&firmware_dir).unwrap();
+
+        // Entry whose zip lives outside the default firmware directory
+        let elsewhere = temp_dir.path().join("elsewhere");
+        fs::create_dir_all(&elsewhere).unwrap();
+        let zip_path = elsewhere.join("1.0.0.zip");
+        fs::write(&zip_path, vec![0u8; 100]).unwrap();
+        let mut metadata = create_test_metadata("1.0.0");
+        metadata.zip_path = zip_path.to_string_lossy().to_string();
+        cache_manager.update_entry(metadata).unwrap();
+
+        let extracted_dir = firmware_dir.join("1.0.0");
+        fs::create_dir_all(&extracted_dir).unwrap();
+        fs::write(extracted_dir.join("code.py"), vec![0u8; 20]).unwrap();
+
+        let bytes_freed = cache_manager
+            .delete_version(&firmware_dir, "1.0.0")
+            .unwrap();
+
+        assert_eq!(bytes_freed, 120);
+        assert!(!zip_path.exists());
+        assert!(!extracted_dir.exists());
+        assert!(cache_manager.get_entry("1.0.0").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delete_version_deletes_unindexed_legacy_zip() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(&firmware_dir).unwrap();
+        let zip_path = firmware_dir.join("1.0.0.zip");
+        fs::write(&zip_path, vec![0u8; 64]).unwrap();
+        let extracted_dir = firmware_dir.join("1.0.0");
+        fs::create_dir_all(&extracted_dir).unwrap();
+        fs::write(extracted_dir.join("code.py"), vec![0u8; 16]).unwrap();
+
+        let bytes_freed = cache_manager
+            .delete_version(&firmware_dir, "1.0.0")
+            .unwrap();
+
+        assert_eq!(bytes_freed, 80);
+        assert!(!zip_path.exists());
+        assert!(!extracted_dir.exists());
+        // Deleting again is a no-op
+        assert_eq!(
+            cache_manager
+                .delete_version(&firmware_dir, "1.0.0")
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_delete_version_without_entry_stays_in_firmware_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(&firmware_dir).unwrap();
+        let outside = temp_dir.path().join("other.zip");
+        fs::write(&outside, vec![0u8; 64]).unwrap();
+
+        let err = cache_manager
+            .delete_version(&firmware_dir, "../other")
+            .unwrap_err();
+
+        assert!(err.contains("not cached"));
+        assert!(outside.exists());
+    }
+
+    #[test]
+    fn test_remove_version_files_stays_in_firmware_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let firmware_dir = temp_dir.path().join("firmware");
+        fs::create_dir_all(&firmware_dir).unwrap();
+        let outside = temp_dir.path().join("settings.json");
+        fs::write(&outside, "{}").unwrap();
+
+        for version in ["..", ""] {
+            remove_version_files(&firmware_dir, version, &firmware_dir.join("missing.zip"))
+                .unwrap();
+        }
+        assert!(outside.exists());
+        assert!(firmware_dir.exists());
+    }
+
+    #[test]
+    fn test_update_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+
+        cache_manager
+            .update_entries(vec![
+                create_test_metadata("1.0.0"),
+                create_test_metadata("1.0.0-circuitpy"),
+            ])
+            .unwrap();
+
+        let index = cache_manager.load_index().unwrap();
+        assert_eq!(index.len(), 2);
+        assert!(index.contains_key("1.0.0-circuitpy"));
+    }
+
+    #[test]
+    fn test_update_entries_keeps_pin_and_last_use() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(temp_dir.path()).unwrap();
+        cache_manager
+            .update_entry(create_test_metadata("1.0.0"))
+            .unwrap();
+        cache_manager.set_pinned("1.0.0", true).unwrap();
+        cache_manager.mark_used("1.0.0").unwrap();
+        let last_used_at = cache_manager
+            .get_entry("1.0.0")
+            .unwrap()
+            .unwrap()
+            .last_used_at;
+
+        // Downloaded again, as a fresh unpinned entry
+        let saved = cache_manager
+            .update_entries(vec![CachedFirmwareMetadata {
+                sha256_hash: "fresh".to_string(),
+                ..create_test_metadata("1.0.0")
+            }])
+            .unwrap();
+
+        let entry = cache_manager.get_entry("1.0.0").unwrap().unwrap();
+        assert_eq!(entry.sha256_hash, "fresh");
+        assert!(entry.pinned);
+        assert!(last_used_at.is_some());
+        assert_eq!(entry.last_used_at, last_used_at);
+        // Reported back to the caller as saved
+        assert!(saved[0].pinned);
+        assert_eq!(saved[0].last_used_at, last_used_at);
+    }
 }
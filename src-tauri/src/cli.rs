@@ -0,0 +1,404 @@
+//! Headless command line mode for scripted flashing.
+//!
+//! Manufacturing lines flash boards from scripts, so the updater also runs
+//! without a window when started with one of these flags:
+//!
+//! ```text
+//! bluebuzzah-updater --detect [--json]
+//! bluebuzzah-updater --validate <zip> [--json]
+//! bluebuzzah-updater --flash <zip> --port <port> [--role PRIMARY|SECONDARY] [--json]
+//! ```
+//!
+//! Progress goes to stdout, as text or as one JSON object per line with
+//! `--json`; errors go to stderr. The exit code tells the script what
+//! happened (see `EXIT_*`). Started without these flags, the GUI runs as
+//! before.
+//!
+//! `--flash` refuses to run while the GUI (or another `--flash`) is running,
+//! since both would drive the same serial ports.
+
+use serde_json::json;
+use std::cell::Cell;
+
+use crate::commands::dfu::parse_device_role;
+use crate::dfu::{
+    find_nrf52_devices, read_firmware_zip, upload_firmware, DfuStage, DfuTimingConfig,
+    EraseWaitOptions,
+};
+use crate::instance::InstanceLock;
+
+/// The command succeeded.
+pub const EXIT_OK: i32 = 0;
+
+/// The flash failed or the firmware package is invalid.
+pub const EXIT_FAILED: i32 = 1;
+
+/// The arguments couldn't be parsed.
+pub const EXIT_USAGE: i32 = 2;
+
+/// No device was found, or none on the port given.
+pub const EXIT_NO_DEVICE: i32 = 3;
+
+/// The app, or another command line flash, is already running.
+pub const EXIT_BUSY: i32 = 4;
+
+/// Flags that start the command line mode rather than the GUI.
+const CLI_FLAGS: &[&str] = &["--detect", "--validate", "--flash", "--help", "-h"];
+
+const USAGE: &str = "\
+Usage:
+  bluebuzzah-updater --detect [--json]
+  bluebuzzah-updater --validate <zip> [--json]
+  bluebuzzah-updater --flash <zip> --port <port> [--role PRIMARY|SECONDARY] [--json]
+
+Exit codes: 0 success, 1 failed, 2 bad arguments, 3 no device found,
+4 the app is already running";
+
+/// What the command line asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CliCommand {
+    Help,
+    Detect,
+    Validate {
+        firmware_path: String,
+    },
+    Flash {
+        firmware_path: String,
+        port: String,
+        role: Option<String>,
+    },
+}
+
+/// A parsed command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CliArgs {
+    command: CliCommand,
+    /// Print one JSON object per line instead of text.
+    json: bool,
+}
+
+/// Run the command line mode if the arguments ask for it.
+///
+/// Returns the exit code, or `None` when the GUI should start instead.
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.iter().any(|arg| CLI_FLAGS.contains(&arg.as_str())) {
+        return None;
+    }
+
+    attach_console();
+    let code = match parse_args(&args) {
+        Ok(args) => run(&args),
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            EXIT_USAGE
+        }
+    };
+    Some(code)
+}
+
+/// Parse the arguments after the program name.
+fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut command = None;
+    let mut port = None;
+    let mut role = None;
+    let mut json = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .filter(|value| !value.starts_with("--"))
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        let next = match arg.as_str() {
+            "--help" | "-h" => Some(CliCommand::Help),
+            "--detect" => Some(CliCommand::Detect),
+            "--validate" => Some(CliCommand::Validate {
+                firmware_path: value(arg)?,
+            }),
+            "--flash" => Some(CliCommand::Flash {
+                firmware_path: value(arg)?,
+                port: String::new(),
+                role: None,
+            }),
+            "--port" => {
+                port = Some(value(arg)?);
+                None
+            }
+            "--role" => {
+                role = Some(parse_device_role(&value(arg)?)?);
+                None
+            }
+            "--json" => {
+                json = true;
+                None
+            }
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        };
+        if let Some(next) = next {
+            if command.is_some() {
+                return Err("Give only one of --detect, --validate and --flash".to_string());
+            }
+            command = Some(next);
+        }
+    }
+
+    let command = match command {
+        Some(CliCommand::Flash { firmware_path, .. }) => CliCommand::Flash {
+            firmware_path,
+            port: port.ok_or("--flash needs --port")?,
+            role,
+        },
+        Some(_) if port.is_some() || role.is_some() => {
+            return Err("--port and --role only apply to --flash".to_string())
+        }
+        Some(command) => command,
+        None => return Err("Give one of --detect, --validate and --flash".to_string()),
+    };
+    Ok(CliArgs { command, json })
+}
+
+/// Run `args`, returning the exit code.
+fn run(args: &CliArgs) -> i32 {
+    match &args.command {
+        CliCommand::Help => {
+            println!("{}", USAGE);
+            EXIT_OK
+        }
+        CliCommand::Detect => detect(args.json),
+        CliCommand::Validate { firmware_path } => validate(firmware_path, args.json),
+        CliCommand::Flash {
+            firmware_path,
+            port,
+            role,
+        } => flash(firmware_path, port, role.as_deref(), args.json),
+    }
+}
+
+/// List the connected devices, one per line.
+fn detect(json: bool) -> i32 {
+    let devices = find_nrf52_devices();
+
+    if json {
+        println!("{}", json!(devices));
+    } else {
+        for device in &devices {
+            println!(
+                "{}\t{}\t{}\t{}",
+                device.port,
+                device.display_label(),
+                device.serial_number.as_deref().unwrap_or("-"),
+                if device.in_bootloader {
+                    "bootloader"
+                } else {
+                    "application"
+                }
+            );
+        }
+    }
+
+    if devices.is_empty() {
+        eprintln!("No devices found");
+        EXIT_NO_DEVICE
+    } else {
+        EXIT_OK
+    }
+}
+
+/// Check that the firmware package at `firmware_path` can be flashed.
+fn validate(firmware_path: &str, json: bool) -> i32 {
+    let package = match read_firmware_zip(firmware_path) {
+        Ok(package) => package,
+        Err(e) => return fail(json, &format!("Invalid firmware package: {}", e)),
+    };
+    let manifest = &package.manifest;
+    let application_version =
+        Some(manifest.application_version).filter(|&version| version != u32::MAX);
+
+    if json {
+        println!(
+            "{}",
+            json!({
+                "event": "validated",
+                "firmwareSize": package.firmware_data.len(),
+                "initSize": package.init_data.len(),
+                "firmwareCrc16": manifest.firmware_crc16,
+                "deviceType": manifest.device_type,
+                "applicationVersion": application_version,
+                "crcValid": package.crc_matches(),
+            })
+        );
+    } else {
+        println!("Firmware size: {} bytes", package.firmware_data.len());
+        println!("Init packet size: {} bytes", package.init_data.len());
+        println!("CRC16: 0x{:04X}", manifest.firmware_crc16);
+        println!("Device type: 0x{:04X}", manifest.device_type);
+        if let Some(version) = application_version {
+            println!("Application version: {}", version);
+        }
+    }
+
+    if package.crc_matches() {
+        EXIT_OK
+    } else {
+        fail(json, "Firmware CRC does not match the manifest")
+    }
+}
+
+/// Flash the firmware package at `firmware_path` to the device on `port`,
+/// printing each stage as it happens.
+fn flash(firmware_path: &str, port: &str, role: Option<&str>, json: bool) -> i32 {
+    // Held until we return, so the app can't start flashing under us
+    let _lock = match InstanceLock::acquire() {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            let message = "The updater is already running; close it before flashing";
+            if json {
+                println!("{}", json!({ "event": "error", "message": message }));
+            }
+            eprintln!("{}", message);
+            return EXIT_BUSY;
+        }
+        Err(e) => return fail(json, &e),
+    };
+
+    let devices = find_nrf52_devices();
+    if !devices.iter().any(|device| device.port == port) {
+        let message = format!("No device found on {}", port);
+        if json {
+            println!("{}", json!({ "event": "error", "message": message }));
+        }
+        eprintln!("{}", message);
+        return EXIT_NO_DEVICE;
+    }
+
+    // Uploading reports every packet; print each whole percent once
+    let last_percent = Cell::new(None);
+    let on_progress = |stage: DfuStage| {
+        let percent = stage.percent().floor() as u32;
+        if matches!(stage, DfuStage::Uploading { .. }) && last_percent.get() == Some(percent) {
+            return;
+        }
+        last_percent.set(Some(percent));
+
+        if json {
+            println!(
+                "{}",
+                json!({
+                    "event": "progress",
+                    "stage": stage.key(),
+                    "percent": stage.percent(),
+                    "message": stage.message(),
+                })
+            );
+        } else {
+            println!("[{:>3}%] {}", percent, stage.message());
+        }
+    };
+
+    let result = upload_firmware(
+        port,
+        firmware_path,
+        role,
+        EraseWaitOptions::default(),
+        DfuTimingConfig::default(),
+        None,
+        on_progress,
+        || false,
+    );
+
+    match result {
+        Ok(()) => {
+            if json {
+                println!("{}", json!({ "event": "complete", "port": port }));
+            }
+            EXIT_OK
+        }
+        Err(e) => fail(json, &format!("Flash failed: {}", e)),
+    }
+}
+
+/// Report `message` as a failure, returning `EXIT_FAILED`.
+fn fail(json: bool, message: &str) -> i32 {
+    if json {
+        println!("{}", json!({ "event": "error", "message": message }));
+    }
+    eprintln!("{}", message);
+    EXIT_FAILED
+}
+
+/// Give a release build on Windows somewhere to print to.
+///
+/// Release builds use the GUI subsystem, so they start without a console.
+/// Attach to the console of the shell that ran us, or open a new one when
+/// started some other way.
+#[cfg(all(target_os = "windows", not(debug_assertions)))]
+fn attach_console() {
+    use winapi::um::consoleapi::AllocConsole;
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // SAFETY: both calls only change which console this process uses
+    unsafe {
+        if AttachConsole(ATTACH_PARENT_PROCESS) == 0 {
+            AllocConsole();
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "windows", not(debug_assertions))))]
+fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse(&["--detect", "--json"]).unwrap(),
+            CliArgs {
+                command: CliCommand::Detect,
+                json: true,
+            }
+        );
+        assert_eq!(
+            parse(&["--validate", "fw.zip"]).unwrap().command,
+            CliCommand::Validate {
+                firmware_path: "fw.zip".to_string()
+            }
+        );
+        assert_eq!(
+            parse(&["--port", "COM3", "--flash", "fw.zip", "--role", "secondary"])
+                .unwrap()
+                .command,
+            CliCommand::Flash {
+                firmware_path: "fw.zip".to_string(),
+                port: "COM3".to_string(),
+                role: Some("SECONDARY".to_string()),
+            }
+        );
+        assert_eq!(parse(&["-h"]).unwrap().command, CliCommand::Help);
+    }
+
+    #[test]
+    fn test_parse_args_rejects_bad_usage() {
+        for args in [
+            &["--json"][..],
+            &["--flash", "fw.zip"],
+            &["--flash", "--port", "COM3"],
+            &["--flash", "fw.zip", "--port", "COM3", "--role", "LEADER"],
+            &["--detect", "--port", "COM3"],
+            &["--detect", "--validate", "fw.zip"],
+            &["--detect", "--verbose"],
+        ] {
+            assert!(parse(args).is_err(), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Tauri commands for updating the app itself.
+//!
+//! The frontend checks with `check_for_app_update` and, if the user accepts,
+//! calls `install_app_update`. The update found by the check is kept here in
+//! between, so the install gets exactly the version the user was shown.
+
+use std::sync::Mutex;
+
+use tauri::ipc::Channel;
+use tauri_plugin_http::reqwest;
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use super::dfu::{hold_off_flashes, is_dfu_in_progress};
+use super::firmware::USER_AGENT;
+use crate::app_update::{
+    download_size, newest_update_release, AppUpdateInfo, AppUpdateProgress, APP_RELEASES_API_URL,
+    APP_RELEASES_PER_PAGE, STABLE_MANIFEST_URL,
+};
+use crate::proxy::{build_http_client, redact_credentials, ProxyMode, ProxySettings};
+use crate::releases::{GitHubRelease, ReleaseChannel, RELEASES_REQUEST_TIMEOUT};
+use crate::settings::SettingsService;
+
+/// The update found by the last `check_for_app_update`, if any.
+#[derive(Default)]
+pub struct PendingAppUpdate(Mutex<Option<Update>>);
+
+impl PendingAppUpdate {
+    fn replace(&self, update: Option<Update>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = update;
+    }
+
+    fn take(&self) -> Option<Update> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    /// Put back an update that failed to install, unless a check made since
+    /// found another.
+    fn restore(&self, update: Update) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert(update);
+    }
+}
+
+/// Check for a newer version of the app on `channel`.
+///
+/// `channel` defaults to the one saved in advanced settings. Returns `None`
+/// when the app is up to date. When the GitHub API can't be reached, the
+/// latest stable release is checked instead, whatever the channel.
+#[tauri::command]
+pub async fn check_for_app_update(
+    channel: Option<ReleaseChannel>,
+    settings_service: tauri::State<'_, SettingsService>,
+    pending: tauri::State<'_, PendingAppUpdate>,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<AppUpdateInfo>, String> {
+    let settings = settings_service.settings_or_default();
+    let channel = channel.unwrap_or(settings.app_update_channel);
+
+    let releases = match fetch_app_releases(&settings.proxy, settings.github_token.as_deref()).await
+    {
+        Ok(releases) => releases,
+        Err(e) => {
+            log::warn!("Checking the latest stable release instead: {}", e);
+            Vec::new()
+        }
+    };
+    let newest = newest_update_release(&releases, channel);
+    let manifest_url = match newest {
+        Some((_, manifest)) => manifest.browser_download_url.as_str(),
+        None => STABLE_MANIFEST_URL,
+    };
+    log::info!("Checking for app update on {:?}: {}", channel, manifest_url);
+
+    let endpoint = manifest_url
+        .parse()
+        .map_err(|e| format!("Invalid update manifest URL {}: {}", manifest_url, e))?;
+    let mut builder = app_handle
+        .updater_builder()
+        .endpoints(vec![endpoint])
+        .map_err(|e| format!("Failed to set up update check: {}", e))?;
+    builder = match settings.proxy.mode {
+        ProxyMode::System => builder,
+        ProxyMode::None => builder.no_proxy(),
+        ProxyMode::Manual => builder.proxy(settings.proxy.manual_proxy_url()?),
+    };
+    let update = builder
+        .build()
+        .map_err(|e| format!("Failed to set up update check: {}", e))?
+        .check()
+        .await
+        .map_err(|e| {
+            redact_credentials(
+                &format!("Failed to check for updates: {}", e),
+                &settings.proxy,
+            )
+        })?;
+
+    let info = update.as_ref().map(|update| AppUpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        release_notes: update
+            .body
+            .clone()
+            .unwrap_or_else(|| "No release notes available.".to_string()),
+        release_date: update.raw_json["pub_date"].as_str().map(str::to_string),
+        download_size: newest
+            .and_then(|(release, _)| download_size(release, update.download_url.as_str())),
+        channel,
+    });
+    pending.replace(update);
+
+    Ok(info)
+}
+
+/// Download and install the update found by the last `check_for_app_update`.
+///
+/// Installing replaces the running app (and on Windows exits it), so a
+/// firmware flash in progress is allowed to finish first, and no flash can
+/// start while the update installs. The frontend relaunches the app
+/// afterwards. If the download or install fails, the update stays pending so
+/// it can be retried without checking again.
+#[tauri::command]
+pub async fn install_app_update(
+    progress: Channel<AppUpdateProgress>,
+    settings_service: tauri::State<'_, SettingsService>,
+    pending: tauri::State<'_, PendingAppUpdate>,
+) -> Result<(), String> {
+    let proxy = settings_service.settings_or_default().proxy;
+    let update = pending
+        .take()
+        .ok_or("No app update to install. Check for updates first.")?;
+
+    let mut downloaded = 0u64;
+    let mut total = 0u64;
+    let downloaded_bytes = update
+        .download(
+            |chunk, content_length| {
+                downloaded += chunk as u64;
+                total = content_length.unwrap_or(0);
+                let _ = progress.send(AppUpdateProgress::new("downloading", downloaded, total));
+            },
+            || {},
+        )
+        .await;
+    let bytes = match downloaded_bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            pending.restore(update);
+            return Err(redact_credentials(
+                &format!("Failed to download app update: {}", e),
+                &proxy,
+            ));
+        }
+    };
+
+    if is_dfu_in_progress() {
+        log::info!(
+            "App update {} waiting for a flash to finish",
+            update.version
+        );
+        let _ = progress.send(AppUpdateProgress::new("waiting", downloaded, total));
+    }
+    let _flashes_held = hold_off_flashes().await;
+
+    log::info!("Installing app update {}", update.version);
+    if let Err(e) = update.install(bytes) {
+        pending.restore(update);
+        return Err(format!("Failed to install app update: {}", e));
+    }
+    let _ = progress.send(AppUpdateProgress::new("ready", downloaded, downloaded));
+
+    Ok(())
+}
+
+/// Fetch the newest app releases from the GitHub API.
+async fn fetch_app_releases(
+    proxy: &ProxySettings,
+    github_token: Option<&str>,
+) -> Result<Vec<GitHubRelease>, String> {
+    let client = build_http_client(
+        reqwest::Client::builder()
+            .timeout(RELEASES_REQUEST_TIMEOUT)
+            .user_agent(USER_AGENT),
+        proxy,
+    )?;
+
+    let mut request = client
+        .get(format!(
+            "{}?per_page={}",
+            APP_RELEASES_API_URL, APP_RELEASES_PER_PAGE
+        ))
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    if let Some(token) = github_token.filter(|token| !token.trim().is_empty()) {
+        request = request.bearer_auth(token.trim());
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| redact_credentials(&format!("Failed to reach GitHub: {}", e), proxy))?;
+    if !response.status().is_success() {
+        return Err(format!("GitHub API error: {}", response.status()));
+    }
+
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read app releases: {}", e))?;
+    serde_json::from_slice(&body).map_err(|e| format!("Failed to parse app releases: {}", e))
+}
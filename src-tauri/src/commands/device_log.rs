@@ -0,0 +1,172 @@
+//! Tauri commands for streaming a device's serial log output.
+//!
+//! A stream holds its port with a preemptible lease: starting a flash on the
+//! same port stops the stream instead of being refused. Every stream ends
+//! with an `ended` event saying why.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::ipc::Channel;
+
+use super::error::CommandError;
+use crate::dfu::SerialMonitor;
+use crate::port_lock::PortLocks;
+
+/// Port lock kind used by log streams.
+const LOG_STREAM_KIND: &str = "monitor";
+
+/// Running streams by port lease ID, with the flag that stops each one.
+static LOG_STREAMS: Mutex<Vec<(u64, Arc<AtomicBool>)>> = Mutex::new(Vec::new());
+
+/// Why a log stream ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStreamEndReason {
+    /// `stop_device_log_stream` was called, or the frontend went away.
+    Stopped,
+    /// The device stopped responding, usually because it was unplugged.
+    Disconnected,
+    /// A flash started on the same port.
+    PreemptedByFlash,
+}
+
+/// Event sent over a log stream's channel.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LogStreamEvent {
+    /// One line of device output, without its line ending.
+    Line {
+        /// When the line was completed (RFC 3339).
+        timestamp: String,
+        /// Line text; bytes that aren't UTF-8 appear as `\xNN`.
+        text: String,
+    },
+    /// Always the last event of a stream.
+    Ended {
+        reason: LogStreamEndReason,
+        /// Error behind a disconnect.
+        detail: Option<String>,
+    },
+}
+
+impl LogStreamEvent {
+    fn line(text: String) -> Self {
+        LogStreamEvent::Line {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            text,
+        }
+    }
+}
+
+fn log_streams() -> std::sync::MutexGuard<'static, Vec<(u64, Arc<AtomicBool>)>> {
+    LOG_STREAMS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Start forwarding a device's serial output over `channel`, one event per
+/// line.
+///
+/// Returns once the port is open; lines then arrive until
+/// `stop_device_log_stream`, a disconnect, or a flash on the same port ends
+/// the stream.
+#[tauri::command]
+pub async fn start_device_log_stream(
+    serial_port: String,
+    channel: Channel<LogStreamEvent>,
+    port_locks: tauri::State<'_, PortLocks>,
+) -> Result<(), CommandError> {
+    let (lease, preempted) = port_locks.acquire_preemptible(&serial_port, LOG_STREAM_KIND)?;
+
+    let monitor = tokio::task::spawn_blocking(move || SerialMonitor::open(&serial_port))
+        .await
+        .map_err(|e| format!("Log stream task panicked: {}", e))?
+        .map_err(|e| format!("Failed to open device log: {}", e))?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    log_streams().push((lease.id(), stop.clone()));
+
+    std::thread::spawn(move || {
+        let id = lease.id();
+        let mut monitor = monitor;
+
+        let (reason, detail) = loop {
+            if preempted.load(Ordering::SeqCst) {
+                break (LogStreamEndReason::PreemptedByFlash, None);
+            }
+            if stop.load(Ordering::SeqCst) {
+                break (LogStreamEndReason::Stopped, None);
+            }
+
+            match monitor.read_lines() {
+                Ok(lines) => {
+                    let delivered = lines
+                        .into_iter()
+                        .all(|text| channel.send(LogStreamEvent::line(text)).is_ok());
+                    if !delivered {
+                        break (LogStreamEndReason::Stopped, None);
+                    }
+                }
+                Err(e) => break (LogStreamEndReason::Disconnected, Some(e.to_string())),
+            }
+        };
+
+        // Release the port before reporting, so a waiting flash can open it
+        let last = monitor.close();
+        log_streams().retain(|(stream_id, _)| *stream_id != id);
+        drop(lease);
+
+        if let Some(text) = last {
+            channel.send(LogStreamEvent::line(text)).ok();
+        }
+        channel.send(LogStreamEvent::Ended { reason, detail }).ok();
+    });
+
+    Ok(())
+}
+
+/// Stop the log stream on `serial_port`.
+///
+/// Returns whether a stream was running. The stream's `ended` event follows
+/// shortly after.
+#[tauri::command]
+pub async fn stop_device_log_stream(
+    serial_port: String,
+    port_locks: tauri::State<'_, PortLocks>,
+) -> Result<bool, String> {
+    let Some(holder) = port_locks.holder(&serial_port) else {
+        return Ok(false);
+    };
+    if holder.kind != LOG_STREAM_KIND {
+        return Ok(false);
+    }
+
+    let streams = log_streams();
+    match streams.iter().find(|(id, _)| *id == holder.id) {
+        Some((_, stop)) => {
+            stop.store(true, Ordering::SeqCst);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_stream_event_serialization() {
+        let ended = LogStreamEvent::Ended {
+            reason: LogStreamEndReason::PreemptedByFlash,
+            detail: None,
+        };
+        let json = serde_json::to_value(&ended).unwrap();
+        assert_eq!(json["event"], "ended");
+        assert_eq!(json["reason"], "preempted_by_flash");
+
+        let json = serde_json::to_value(LogStreamEvent::line("[READY]".into())).unwrap();
+        assert_eq!(json["event"], "line");
+        assert_eq!(json["text"], "[READY]");
+        assert!(json["timestamp"].is_string());
+    }
+}
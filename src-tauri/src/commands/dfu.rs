@@ -3,41 +3,101 @@
 //! These commands expose the DFU functionality to the frontend.
 
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tauri::ipc::Channel;
+use tauri::{Emitter, Manager};
 
+use super::error::CommandError;
+use crate::cache::CacheManager;
 use crate::dfu::{
-    configure_device_with_settings, find_nrf52_devices, upload_firmware, DeviceIdentifier,
-    DfuStage, Nrf52Device,
+    configure_device_role_flexible, configure_device_with_settings, describe_setting_command,
+    estimate_flash_duration_ms, find_nrf52_devices, find_uf2_volumes, flash_uf2 as flash_uf2_image,
+    identify_device as send_identify, profile_command, query_device, query_device_settings,
+    read_firmware_zip, send_raw_command, upload_firmware, BoardModel, DeviceIdentifier, DfuError,
+    DfuStage, DfuTimingConfig, EraseWaitOptions, Nrf52Device, QueryAnswer, SettingsAnswer,
+    Uf2ProgressEvent, DEVICE_COMMAND_TIMEOUT_MS, DEVICE_QUERY_BUDGET_MS, DEVICE_RESCAN_DELAY_MS,
+    GET_PROFILE_COMMAND, GET_ROLE_COMMAND, GET_VERSION_COMMAND, MAX_DEVICE_COMMAND_LEN,
+    MAX_DEVICE_COMMAND_TIMEOUT_MS, NRF52840_DEVICE_TYPE, READ_ONLY_COMMANDS, REBOOT_COMMAND,
 };
-use crate::settings::AdvancedSettings;
+use crate::history::{FlashHistory, FlashRecord};
+use crate::journal::{JournalEntry, JournaledOperation, OperationJournal};
+use crate::port_lock::{PortLease, PortLocks, PortOperation};
+use crate::settings::{AdvancedSettings, DfuTimingSettings, SettingsService};
 
 /// Maximum number of operation-level retries for complete DFU failure.
 /// This catches high-level failures like bootloader entry timeout or device disconnect.
 /// Increased from 1 to 2 (3 total attempts) for better reliability on Windows.
 const MAX_OPERATION_RETRIES: u32 = 2;
 
-/// Global cancellation flag for DFU operations.
-static DFU_CANCELLED: AtomicBool = AtomicBool::new(false);
+/// How often `hold_off_flashes` checks whether a flash has finished.
+const FLASH_IDLE_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Global guard to prevent concurrent flash operations.
 static DFU_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 
+/// Stage of the running flash, reported by `cancel_dfu_flash`.
+static DFU_STAGE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Port lease ID of the device being flashed, reported by `cancel_dfu_flash`.
+static FLASHING_OPERATION: Mutex<Option<u64>> = Mutex::new(None);
+
+/// Firmware versions devices reported to `get_device_info` this session, by
+/// serial number, so configuration can skip settings the firmware predates.
+static FIRMWARE_VERSIONS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
 /// RAII guard that resets DFU_IN_PROGRESS when dropped.
-struct DfuGuard;
+pub(crate) struct DfuGuard;
 
 impl Drop for DfuGuard {
     fn drop(&mut self) {
+        set_flashing_operation(None);
         DFU_IN_PROGRESS.store(false, Ordering::SeqCst);
     }
 }
 
-/// Check if cancellation was requested.
-pub fn is_dfu_cancelled() -> bool {
-    DFU_CANCELLED.load(Ordering::SeqCst)
+fn set_dfu_stage(stage: Option<String>) {
+    *DFU_STAGE.lock().unwrap_or_else(|e| e.into_inner()) = stage;
+}
+
+/// Record which device is being flashed, from the start of its flash.
+fn set_flashing_operation(operation_id: Option<u64>) {
+    *FLASHING_OPERATION.lock().unwrap_or_else(|e| e.into_inner()) = operation_id;
+    set_dfu_stage(None);
+}
+
+/// Record the firmware version the device with `serial_number` runs, or
+/// forget it when `None`.
+fn remember_firmware_version(serial_number: &str, version: Option<&str>) {
+    let mut versions = FIRMWARE_VERSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    match version {
+        Some(version) => versions.insert(serial_number.to_string(), version.to_string()),
+        None => versions.remove(serial_number),
+    };
+}
+
+/// The firmware version last recorded for the device with `serial_number`.
+fn known_firmware_version(serial_number: &str) -> Option<String> {
+    let versions = FIRMWARE_VERSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    versions.get(serial_number).cloned()
+}
+
+/// Check if a flash currently owns a serial port.
+pub fn is_dfu_in_progress() -> bool {
+    DFU_IN_PROGRESS.load(Ordering::SeqCst)
+}
+
+/// Wait for the running flash, if any, to finish, then refuse new flashes
+/// until the returned guard is dropped.
+pub(crate) async fn hold_off_flashes() -> DfuGuard {
+    while DFU_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        tokio::time::sleep(FLASH_IDLE_POLL_INTERVAL).await;
+    }
+    DfuGuard
 }
 
 /// Check if an operation-level error is retriable.
@@ -144,6 +204,9 @@ pub struct DfuDevice {
     pub in_bootloader: bool,
     /// Device serial number (if available).
     pub serial_number: Option<String>,
+    /// Board model derived from the PID, so the frontend needn't decode it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board: Option<BoardModel>,
 }
 
 impl From<Nrf52Device> for DfuDevice {
@@ -155,6 +218,7 @@ impl From<Nrf52Device> for DfuDevice {
             pid: device.pid,
             in_bootloader: device.in_bootloader,
             serial_number: device.serial_number,
+            board: BoardModel::from_pid(device.pid),
         }
     }
 }
@@ -164,6 +228,8 @@ impl From<Nrf52Device> for DfuDevice {
 pub struct DfuProgressEvent {
     /// Current stage name.
     pub stage: String,
+    /// Stable key for translating `message` (e.g. "dfu.stage.uploading").
+    pub message_key: String,
     /// Bytes sent (for uploading stage).
     pub sent: Option<usize>,
     /// Total bytes (for uploading stage).
@@ -172,6 +238,210 @@ pub struct DfuProgressEvent {
     pub percent: f32,
     /// Human-readable message.
     pub message: String,
+    /// Position of the device in a `flash_both_devices` run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_index: Option<usize>,
+    /// Role label of the device in a `flash_both_devices` run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_label: Option<String>,
+    /// Position of the event within its operation, starting at 1.
+    pub seq: u64,
+    /// When the event was created (milliseconds since the Unix epoch).
+    pub emitted_at_ms: u64,
+    /// Port lease ID of the operation that sent the event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<u64>,
+}
+
+/// Numbers the progress events of one operation.
+///
+/// Events pass through an mpsc channel, a forwarding thread and IPC before
+/// reaching the frontend, which uses `seq` to drop stale or reordered ones.
+#[derive(Clone)]
+struct ProgressClock {
+    seq: Arc<AtomicU64>,
+    operation_id: Option<u64>,
+}
+
+impl ProgressClock {
+    fn new(operation_id: Option<u64>) -> Self {
+        Self {
+            seq: Arc::new(AtomicU64::new(0)),
+            operation_id,
+        }
+    }
+
+    /// Same sequence, attributed to another operation.
+    fn for_operation(&self, operation_id: u64) -> Self {
+        Self {
+            seq: Arc::clone(&self.seq),
+            operation_id: Some(operation_id),
+        }
+    }
+
+    /// Next sequence number and the current time in milliseconds.
+    fn tick(&self) -> (u64, u64) {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst) + 1;
+        let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        (seq, now)
+    }
+}
+
+impl DfuProgressEvent {
+    /// Log line for the frontend's installation log.
+    fn log(message: String) -> Self {
+        Self {
+            stage: "log".to_string(),
+            message_key: "dfu.stage.log".to_string(),
+            sent: None,
+            total: None,
+            percent: -1.0,
+            message,
+            device_index: None,
+            device_label: None,
+            seq: 0,
+            emitted_at_ms: 0,
+            operation_id: None,
+        }
+    }
+}
+
+/// Global event mirroring flash and profile progress to every window.
+pub const PROGRESS_EVENT: &str = "dfu://progress";
+
+/// Payload of a `dfu://progress` event.
+#[derive(Debug, Clone, Serialize)]
+struct ProgressBroadcast<'a, E> {
+    /// What the operation is doing ("flash" or "profile").
+    kind: &'static str,
+    /// Port lease ID, as returned by `get_active_operations`.
+    operation_id: Option<u64>,
+    /// The event as sent over the operation's channel.
+    event: &'a E,
+}
+
+/// Mirrors an operation's progress events as global `dfu://progress`
+/// events, so other windows and a reloaded webview can follow along.
+#[derive(Clone)]
+struct ProgressMirror {
+    app_handle: tauri::AppHandle,
+    kind: &'static str,
+}
+
+impl ProgressMirror {
+    fn new(app_handle: &tauri::AppHandle, kind: &'static str) -> Self {
+        Self {
+            app_handle: app_handle.clone(),
+            kind,
+        }
+    }
+
+    fn emit<E: Serialize + Clone>(&self, operation_id: Option<u64>, event: &E) {
+        let broadcast = ProgressBroadcast {
+            kind: self.kind,
+            operation_id,
+            event,
+        };
+        if let Err(e) = self.app_handle.emit(PROGRESS_EVENT, broadcast) {
+            log::warn!("Failed to broadcast progress: {}", e);
+        }
+    }
+}
+
+/// Progress channel for one flash, tagging events with the device they
+/// belong to when several devices are flashed in one command.
+#[derive(Clone)]
+struct ProgressSink {
+    channel: Channel<DfuProgressEvent>,
+    device: Option<(usize, String)>,
+    clock: ProgressClock,
+    mirror: Option<ProgressMirror>,
+    journal: Option<Arc<JournaledOperation>>,
+    /// Set when the flash should stop, by `cancel_dfu_flash` or because the
+    /// frontend went away.
+    cancel: Arc<AtomicBool>,
+}
+
+impl ProgressSink {
+    fn single(channel: Channel<DfuProgressEvent>, clock: ProgressClock) -> Self {
+        Self {
+            channel,
+            device: None,
+            clock,
+            mirror: None,
+            journal: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Also broadcast every event as a global `dfu://progress` event.
+    fn mirrored(mut self, mirror: ProgressMirror) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    /// Also record each stage reached in the operation journal.
+    fn journaled(mut self, journal: Arc<JournaledOperation>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Stop the flash once `cancel` is set, e.g. the flag of its port lease.
+    fn cancelled_by(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    fn device(
+        channel: Channel<DfuProgressEvent>,
+        index: usize,
+        label: &str,
+        clock: ProgressClock,
+    ) -> Self {
+        Self {
+            channel,
+            device: Some((index, label.to_string())),
+            clock,
+            mirror: None,
+            journal: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Number, timestamp and device-tag an event at the point it is created.
+    fn stamp(&self, mut event: DfuProgressEvent) -> DfuProgressEvent {
+        if let Some((index, label)) = &self.device {
+            event.device_index = Some(*index);
+            event.device_label = Some(label.clone());
+        }
+        if event.stage != "log" {
+            set_dfu_stage(Some(event.stage.clone()));
+            if let Some(journal) = &self.journal {
+                journal.set_stage(&event.stage);
+            }
+        }
+        let (seq, emitted_at_ms) = self.clock.tick();
+        event.seq = seq;
+        event.emitted_at_ms = emitted_at_ms;
+        event.operation_id = self.clock.operation_id;
+        event
+    }
+
+    /// Send an event already stamped with `stamp`.
+    fn emit(&self, event: DfuProgressEvent) -> tauri::Result<()> {
+        if let Some(mirror) = &self.mirror {
+            mirror.emit(event.operation_id, &event);
+        }
+        self.channel.send(event)
+    }
+
+    fn send(&self, event: DfuProgressEvent) -> tauri::Result<()> {
+        self.emit(self.stamp(event))
+    }
 }
 
 impl From<DfuStage> for DfuProgressEvent {
@@ -191,14 +461,22 @@ impl From<DfuStage> for DfuProgressEvent {
             DfuStage::Complete => ("complete", None, None),
             DfuStage::Log { .. } => ("log", None, None),
             DfuStage::Cancelled => ("cancelled", None, None),
+            DfuStage::TimedOut => ("timed_out", None, None),
+            DfuStage::Retrying { .. } => ("retrying", None, None),
         };
 
         Self {
             stage: stage_name.to_string(),
+            message_key: stage.key().to_string(),
             sent,
             total,
             percent: stage.percent(),
             message: stage.message(),
+            device_index: None,
+            device_label: None,
+            seq: 0,
+            emitted_at_ms: 0,
+            operation_id: None,
         }
     }
 }
@@ -283,32 +561,240 @@ pub async fn detect_dfu_devices() -> Result<Vec<DfuDevice>, String> {
     Ok(devices)
 }
 
+/// Mark the cached version at `firmware_path` as used, for age-based cleanup.
+///
+/// Sideloaded paths outside the cache simply match no entry.
+fn record_firmware_use(cache_manager: &CacheManager, firmware_path: &str) {
+    if let Err(e) = cache_manager.mark_used_by_path(Path::new(firmware_path)) {
+        log::warn!("Failed to record firmware use: {}", e);
+    }
+}
+
+/// Size the erase wait from the flags passed by the frontend.
+///
+/// The bootloader erases the old image's footprint too, so a cached package
+/// for the firmware being replaced lengthens the wait.
+fn erase_wait_options(
+    full_bank_erase: Option<bool>,
+    previous_firmware_path: Option<String>,
+) -> EraseWaitOptions {
+    let previous_firmware_size =
+        previous_firmware_path.and_then(|path| match read_firmware_zip(&path) {
+            Ok(package) => Some(package.firmware_data.len()),
+            Err(e) => {
+                log::warn!("could not read previous firmware {}: {}", path, e);
+                None
+            }
+        });
+    EraseWaitOptions {
+        previous_firmware_size,
+        full_bank: full_bank_erase.unwrap_or(false),
+    }
+}
+
+/// Take `port` for a flash.
+///
+/// Preempting a serial monitor can wait for it to release the port, so the
+/// lease is taken on a blocking thread.
+async fn acquire_flash_lease(
+    port_locks: &PortLocks,
+    port: &str,
+) -> Result<PortLease, CommandError> {
+    let locks = port_locks.clone();
+    let port = port.to_string();
+    let lease = tokio::task::spawn_blocking(move || locks.acquire(&port, "flash"))
+        .await
+        .map_err(|e| format!("Port lock task panicked: {}", e))??;
+    Ok(lease)
+}
+
+/// Load the DFU timing overrides from the saved settings.
+///
+/// Unreadable settings fall back to the default timing rather than
+/// blocking a flash.
+fn load_dfu_timing(settings_service: &SettingsService) -> DfuTimingSettings {
+    match settings_service.settings() {
+        Ok(settings) => settings.dfu_timing,
+        Err(e) => {
+            log::warn!("could not load DFU timing settings, using defaults: {}", e);
+            DfuTimingSettings::default()
+        }
+    }
+}
+
+/// Log line recording the timing a flash or configuration runs with, so
+/// captured logs show whether support's overrides were in effect.
+fn describe_dfu_timing(timing: &DfuTimingConfig, deadline: Option<Duration>) -> String {
+    match deadline {
+        Some(deadline) => format!(
+            "DFU timing: {}, deadline {}s",
+            timing,
+            deadline.as_secs_f64().round()
+        ),
+        None => format!("DFU timing: {}, no deadline", timing),
+    }
+}
+
+/// Firmware to flash: an explicit zip path, or a version from the cache.
+///
+/// The frontend sends either a plain string or `{ "version": "v2.3.1" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum FirmwareSource {
+    Path(String),
+    Cached { version: String },
+}
+
+/// Result of a successful `flash_dfu_firmware`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashResult {
+    /// Zip that was flashed.
+    pub firmware_path: String,
+    /// Cached version that was flashed; `None` for sideloaded zips.
+    pub firmware_version: Option<String>,
+}
+
+/// Resolve a firmware source to the zip to flash.
+///
+/// Cached versions are integrity-checked and must be DFU packages; explicit
+/// paths are matched back to a cached version where possible.
+fn resolve_firmware(cache: &CacheManager, source: FirmwareSource) -> Result<FlashResult, String> {
+    match source {
+        FirmwareSource::Cached { version } => {
+            let metadata = cache.resolve_dfu_package(&version)?;
+            Ok(FlashResult {
+                firmware_path: metadata.zip_path,
+                firmware_version: Some(metadata.version),
+            })
+        }
+        FirmwareSource::Path(path) => {
+            let firmware_version = cache
+                .entry_by_path(Path::new(&path))?
+                .map(|metadata| metadata.version);
+            Ok(FlashResult {
+                firmware_path: path,
+                firmware_version,
+            })
+        }
+    }
+}
+
 /// Flash firmware to a device via DFU.
 ///
 /// # Arguments
 /// * `serial_port` - Serial port of the device
-/// * `firmware_path` - Path to the firmware.zip file
-/// * `device_role` - Role to configure ("PRIMARY" or "SECONDARY")
+/// * `firmware` - Path to the firmware.zip file, or `{ version }` of a cached release
+/// * `device_role` - Role to configure ("PRIMARY" or "SECONDARY"); omit to flash
+///   without touching the role stored on the device, in which case no "configuring"
+///   progress event is sent
+/// * `full_bank_erase` - Wait for the whole application bank to erase (conservative)
+/// * `previous_firmware_path` - Cached firmware.zip believed to be on the device,
+///   used to size the erase wait when the old image is larger than the new one
+/// * `timeout_seconds` - Give up after this long, retries included; omit to use the
+///   flash deadline from the DFU timing settings, or no limit if that is unset.
+///   Must be at least 1
 /// * `progress` - Channel for progress updates
 ///
 /// This command includes automatic retry logic for transient failures.
 /// If the operation fails with a retriable error (timeout, device disconnect, etc.),
 /// it will wait and retry up to MAX_OPERATION_RETRIES times with progressive delays.
+///
+/// When `timeout_seconds` runs out the flash stops at its next cancellation
+/// check, sends a "timed_out" progress event and fails with error code DFU-024.
+///
+/// Returns the zip that was flashed and, when it came from the cache, its version.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn flash_dfu_firmware(
     serial_port: String,
-    firmware_path: String,
-    device_role: String,
+    firmware: FirmwareSource,
+    device_role: Option<String>,
+    full_bank_erase: Option<bool>,
+    previous_firmware_path: Option<String>,
+    timeout_seconds: Option<u64>,
     progress: Channel<DfuProgressEvent>,
-) -> Result<(), String> {
-    // Prevent concurrent flash operations
+    port_locks: tauri::State<'_, PortLocks>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    settings_service: tauri::State<'_, SettingsService>,
+    history: tauri::State<'_, FlashHistory>,
+    journal: tauri::State<'_, OperationJournal>,
+    app_handle: tauri::AppHandle,
+) -> Result<FlashResult, CommandError> {
+    let timing = load_dfu_timing(&settings_service);
+    let deadline = flash_deadline(timeout_seconds.or(timing.deadline_seconds), Instant::now())?;
+
+    // Prevent concurrent flash operations, before preempting anything on the port
     if DFU_IN_PROGRESS.swap(true, Ordering::SeqCst) {
         return Err("A firmware installation is already in progress".into());
     }
     let _guard = DfuGuard;
 
-    // Reset cancellation flag at start of new operation
-    DFU_CANCELLED.store(false, Ordering::SeqCst);
+    // Refuse a second command aimed at the same device
+    let lease = acquire_flash_lease(&port_locks, &serial_port).await?;
+
+    // Hashing a cached zip for the integrity check is blocking work
+    let cache = cache_manager.inner().clone();
+    let flash_result = tokio::task::spawn_blocking(move || resolve_firmware(&cache, firmware))
+        .await
+        .map_err(|e| format!("Firmware lookup task panicked: {}", e))??;
+
+    let erase_options = erase_wait_options(full_bank_erase, previous_firmware_path);
+
+    flash_with_retries(
+        serial_port,
+        flash_result.firmware_path.clone(),
+        device_role,
+        erase_options,
+        timing.to_config(),
+        deadline,
+        ProgressSink::single(progress, ProgressClock::new(Some(lease.id())))
+            .mirrored(ProgressMirror::new(&app_handle, "flash"))
+            .cancelled_by(lease.cancel_flag()),
+        &cache_manager,
+        &history,
+        &journal,
+    )
+    .await?;
+
+    Ok(flash_result)
+}
+
+/// When a flash started at `now` gives up, for a timeout of `timeout_seconds`.
+///
+/// A zero timeout is refused rather than failing the flash at once; one too
+/// long to represent means no deadline.
+fn flash_deadline(timeout_seconds: Option<u64>, now: Instant) -> Result<Option<Instant>, String> {
+    match timeout_seconds {
+        Some(0) => Err("The flash timeout must be at least 1 second".to_string()),
+        Some(secs) => Ok(now.checked_add(Duration::from_secs(secs))),
+        None => Ok(None),
+    }
+}
+
+/// Flash one device with retries and record the outcome in the flash history.
+///
+/// Shared by single- and multi-device flashes.
+#[allow(clippy::too_many_arguments)]
+async fn flash_with_retries(
+    serial_port: String,
+    firmware_path: String,
+    device_role: Option<String>,
+    erase_options: EraseWaitOptions,
+    timing: DfuTimingConfig,
+    deadline: Option<Instant>,
+    progress: ProgressSink,
+    cache_manager: &CacheManager,
+    history: &FlashHistory,
+    journal: &OperationJournal,
+) -> Result<(), CommandError> {
+    let started_at = chrono::Utc::now();
+    let timer = Instant::now();
+    set_flashing_operation(progress.clock.operation_id);
+
+    // Head every flash log with the timing it ran with
+    let remaining = deadline.map(|deadline| deadline.saturating_duration_since(timer));
+    let header = describe_dfu_timing(&timing, remaining);
+    let _ = progress.send(DfuProgressEvent::log(header));
 
     // Capture device serial number for retry re-scan (before the loop)
     let device_serial: Option<String> = find_nrf52_devices()
@@ -316,129 +802,193 @@ pub async fn flash_dfu_firmware(
         .find(|d| d.port == serial_port)
         .and_then(|d| d.serial_number);
 
+    let firmware_version = cache_manager
+        .entry_by_path(Path::new(&firmware_path))
+        .ok()
+        .flatten()
+        .map(|entry| entry.version);
+
+    // Left behind if the app dies mid-flash, so the next launch can offer
+    // to flash the device again
+    let entry = JournalEntry::new(
+        "flash",
+        &serial_port,
+        device_serial.clone(),
+        firmware_version.clone(),
+        &firmware_path,
+    );
+    let progress = match journal.begin(entry) {
+        Ok(operation) => progress.journaled(Arc::new(operation)),
+        Err(e) => {
+            log::warn!("Flashing without a journal entry: {}", e);
+            progress
+        }
+    };
+
+    let mut attempts = 0;
+    let result = retry_flash(
+        &serial_port,
+        device_serial.as_deref(),
+        &firmware_path,
+        device_role.clone(),
+        erase_options,
+        timing,
+        deadline,
+        progress,
+        &mut attempts,
+    )
+    .await;
+
+    if result.is_ok() {
+        record_firmware_use(cache_manager, &firmware_path);
+        if let Err(e) = journal.resolve(&serial_port, device_serial.as_deref()) {
+            log::warn!("Failed to clear interrupted flashes: {}", e);
+        }
+    }
+
+    // The device now runs the flashed firmware, or possibly none at all
+    if let Some(serial) = &device_serial {
+        let flashed = firmware_version.as_deref().filter(|_| result.is_ok());
+        remember_firmware_version(serial, flashed);
+    }
+
+    let record = FlashRecord {
+        started_at: started_at.to_rfc3339(),
+        duration_ms: timer.elapsed().as_millis() as u64,
+        serial_number: device_serial,
+        port: serial_port,
+        firmware_version,
+        firmware_path,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        role: device_role,
+        profile: None,
+        success: result.is_ok(),
+        attempts,
+        error_code: result
+            .as_ref()
+            .err()
+            .and_then(|e| e.code)
+            .map(str::to_string),
+        error: result.as_ref().err().map(|e| e.message.clone()),
+    };
+    if let Err(e) = history.append(&record) {
+        log::warn!("Failed to record flash: {}", e);
+    }
+
+    result
+}
+
+/// Retry loop around `flash_dfu_firmware_inner`.
+///
+/// `deadline` covers every attempt and the waits between them.
+#[allow(clippy::too_many_arguments)]
+async fn retry_flash(
+    serial_port: &str,
+    device_serial: Option<&str>,
+    firmware_path: &str,
+    device_role: Option<String>,
+    erase_options: EraseWaitOptions,
+    timing: DfuTimingConfig,
+    deadline: Option<Instant>,
+    progress: ProgressSink,
+    attempts: &mut u32,
+) -> Result<(), CommandError> {
+    let serial_port = serial_port.to_string();
+
     for attempt in 0..=MAX_OPERATION_RETRIES {
         // Check for cancellation before each attempt
-        if is_dfu_cancelled() {
-            return Err("Operation cancelled by user".to_string());
+        if progress.is_cancelled() {
+            return Err(DfuError::Cancelled.into());
         }
 
         // Verify device port before each attempt (even the first).
         // In multi-device sessions, a previous device's USB re-enumeration
         // may have caused COM port reassignment on Windows.
         let port_to_use = if attempt == 0 {
-            match find_device_port_for_retry(&serial_port, device_serial.as_deref()) {
+            match find_device_port_for_retry(&serial_port, device_serial) {
                 Some(port) => {
                     if port != serial_port {
-                        let _ = progress.send(DfuProgressEvent {
-                            stage: "log".to_string(),
-                            sent: None,
-                            total: None,
-                            percent: -1.0,
-                            message: format!(
-                                "Device re-enumerated from {} to {}",
-                                serial_port, port
-                            ),
-                        });
+                        let _ = progress.send(DfuProgressEvent::log(format!(
+                            "Device re-enumerated from {} to {}",
+                            serial_port, port
+                        )));
                     }
                     port
                 }
                 None => serial_port.clone(), // Fall back to original port
             }
         } else {
-            let _ = progress.send(DfuProgressEvent {
-                stage: "retrying".to_string(),
-                sent: None,
-                total: None,
-                percent: -1.0,
-                message: format!(
-                    "Retrying firmware installation (attempt {}/{})...",
-                    attempt + 1,
-                    MAX_OPERATION_RETRIES + 1
-                ),
-            });
+            let _ = progress.send(DfuProgressEvent::from(DfuStage::Retrying {
+                attempt: attempt + 1,
+                max_attempts: MAX_OPERATION_RETRIES + 1,
+            }));
 
-            match find_device_port_for_retry(&serial_port, device_serial.as_deref()) {
+            match find_device_port_for_retry(&serial_port, device_serial) {
                 Some(port) => {
                     if port != serial_port {
-                        let _ = progress.send(DfuProgressEvent {
-                            stage: "log".to_string(),
-                            sent: None,
-                            total: None,
-                            percent: -1.0,
-                            message: format!(
-                                "Device re-enumerated from {} to {}",
-                                serial_port, port
-                            ),
-                        });
+                        let _ = progress.send(DfuProgressEvent::log(format!(
+                            "Device re-enumerated from {} to {}",
+                            serial_port, port
+                        )));
                     }
                     port
                 }
                 None => {
-                    let _ = progress.send(DfuProgressEvent {
-                        stage: "log".to_string(),
-                        sent: None,
-                        total: None,
-                        percent: -1.0,
-                        message: "Device not found during re-scan, using original port"
-                            .to_string(),
-                    });
+                    let _ = progress.send(DfuProgressEvent::log(
+                        "Device not found during re-scan, using original port".to_string(),
+                    ));
                     serial_port.clone()
                 }
             }
         };
 
+        *attempts = attempt + 1;
         let result = flash_dfu_firmware_inner(
             port_to_use,
-            firmware_path.clone(),
+            firmware_path.to_string(),
             device_role.clone(),
+            erase_options,
+            timing,
+            deadline,
             progress.clone(),
         )
         .await;
 
         match result {
             Ok(()) => return Ok(()),
-            Err(e) if is_operation_retriable(&e) && attempt < MAX_OPERATION_RETRIES => {
+            Err(e) if is_operation_retriable(&e.message) && attempt < MAX_OPERATION_RETRIES => {
                 // Progressive delay: 3s for first retry, 5s for second
                 let delay_secs = 3 + (attempt as u64 * 2);
 
                 // Log the retry attempt
-                let _ = progress.send(DfuProgressEvent {
-                    stage: "log".to_string(),
-                    sent: None,
-                    total: None,
-                    percent: -1.0,
-                    message: format!(
-                        "Attempt {} failed: {}. Waiting {} seconds before retry...",
-                        attempt + 1,
-                        e,
-                        delay_secs
-                    ),
+                let _ = progress.send(DfuProgressEvent::log(format!(
+                    "Attempt {} failed: {}. Waiting {} seconds before retry...",
+                    attempt + 1,
+                    e,
+                    delay_secs
+                )));
+
+                // Wait before retry to allow device to stabilize. A wait cut
+                // short by the deadline makes the next attempt fail at once.
+                let delay = Duration::from_secs(delay_secs);
+                let delay = deadline.map_or(delay, |deadline| {
+                    delay.min(deadline.saturating_duration_since(Instant::now()))
                 });
+                tokio::time::sleep(delay).await;
 
-                // Wait before retry to allow device to stabilize
-                tokio::time::sleep(Duration::from_secs(delay_secs)).await;
-
-                // Check if cancelled during sleep before resetting
-                if is_dfu_cancelled() {
-                    return Err("Operation cancelled by user".to_string());
+                // Check if cancelled during sleep
+                if progress.is_cancelled() {
+                    return Err(DfuError::Cancelled.into());
                 }
-                // Reset cancellation flag for retry
-                DFU_CANCELLED.store(false, Ordering::SeqCst);
             }
             Err(e) => {
                 // Non-retriable error or max retries exceeded
                 if attempt > 0 {
-                    let _ = progress.send(DfuProgressEvent {
-                        stage: "log".to_string(),
-                        sent: None,
-                        total: None,
-                        percent: -1.0,
-                        message: format!(
-                            "Installation failed after {} attempt(s): {}",
-                            attempt + 1,
-                            e
-                        ),
-                    });
+                    let _ = progress.send(DfuProgressEvent::log(format!(
+                        "Installation failed after {} attempt(s): {}",
+                        attempt + 1,
+                        e
+                    )));
                 }
                 return Err(e);
             }
@@ -446,130 +996,1056 @@ pub async fn flash_dfu_firmware(
     }
 
     // This shouldn't be reached, but just in case
-    Err("Maximum retry attempts exceeded".to_string())
+    Err("Maximum retry attempts exceeded".into())
 }
 
 /// Inner implementation of flash_dfu_firmware without retry logic.
+///
+/// Failures keep the DFU error's key and code when the DFU layer reported one.
 async fn flash_dfu_firmware_inner(
     serial_port: String,
     firmware_path: String,
-    device_role: String,
-    progress: Channel<DfuProgressEvent>,
-) -> Result<(), String> {
-    // Create a channel for progress updates from the blocking thread
-    let (tx, rx) = mpsc::channel::<DfuStage>();
+    device_role: Option<String>,
+    erase_options: EraseWaitOptions,
+    timing: DfuTimingConfig,
+    deadline: Option<Instant>,
+    progress: ProgressSink,
+) -> Result<(), CommandError> {
+    let stamper = progress.clone();
+    let cancel = progress.cancel.clone();
+    let result = with_progress_forwarding(progress, move |tx| {
+        upload_firmware(
+            &serial_port,
+            &firmware_path,
+            device_role.as_deref(),
+            erase_options,
+            timing,
+            deadline,
+            |stage| {
+                let _ = tx.send(stamper.stamp(DfuProgressEvent::from(stage)));
+            },
+            || cancel.load(Ordering::SeqCst),
+        )
+    })
+    .await?;
+
+    result.map_err(CommandError::from)
+}
+
+/// Run `work` in a blocking task, forwarding the events it sends to
+/// `progress` from a separate thread.
+///
+/// `work` owns the only sender, so the forwarding thread ends once `work`
+/// returns or panics, and it is joined either way before this returns. If
+/// the frontend goes away mid-run the operation is cancelled.
+async fn with_progress_forwarding<T, W>(progress: ProgressSink, work: W) -> Result<T, String>
+where
+    T: Send + 'static,
+    W: FnOnce(mpsc::Sender<DfuProgressEvent>) -> T + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<DfuProgressEvent>();
 
-    // Spawn a task to forward progress updates
-    let progress_channel = progress.clone();
     let progress_task = thread::spawn(move || {
-        while let Ok(stage) = rx.recv() {
-            let event = DfuProgressEvent::from(stage);
-            if progress_channel.send(event).is_err() {
+        while let Ok(event) = rx.recv() {
+            if progress.emit(event).is_err() {
                 // Frontend disconnected — cancel the DFU operation
-                eprintln!("[DFU] Warning: progress channel disconnected, cancelling operation");
-                DFU_CANCELLED.store(true, Ordering::SeqCst);
+                log::warn!("progress channel disconnected, cancelling operation");
+                progress.cancel.store(true, Ordering::SeqCst);
                 break;
             }
         }
     });
 
-    // Run DFU in a blocking task with cancellation support
-    let result = tokio::task::spawn_blocking(move || {
-        upload_firmware(
-            &serial_port,
-            &firmware_path,
-            &device_role,
-            |stage| {
-                let _ = tx.send(stage);
-            },
-            is_dfu_cancelled,
-        )
-    })
-    .await
-    .map_err(|e| format!("DFU task panicked: {}", e))?;
+    let result = tokio::task::spawn_blocking(move || work(tx)).await;
 
-    // Wait for progress forwarding to complete
-    let _ = progress_task.join();
+    // Join on every path, including a panic in `work`, so the thread can't leak
+    if progress_task.join().is_err() {
+        log::warn!("progress forwarding thread panicked");
+    }
+
+    result.map_err(|e| format!("DFU task panicked: {}", e))
+}
+
+/// One device in a `flash_both_devices` run.
+#[derive(Debug, Clone)]
+struct FlashTarget {
+    port: String,
+    role: String,
+}
+
+/// How one device fared in a multi-device flash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum DeviceFlashStatus {
+    Success,
+    Failed {
+        error: String,
+        /// Stable key for translating `error`, as on `CommandError`.
+        message_key: &'static str,
+    },
+    /// Never started, because of cancellation or an earlier failure.
+    Skipped {
+        reason: String,
+    },
+}
+
+/// Per-device result returned by `flash_both_devices`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceFlashOutcome {
+    /// Position in the flash order (0 = primary).
+    pub device_index: usize,
+    /// Label matching `device_label` on this device's progress events.
+    pub device_label: String,
+    /// Serial port the device was requested on.
+    pub port: String,
+    #[serde(flatten)]
+    pub status: DeviceFlashStatus,
+}
+
+/// Flash `targets` one after another, stopping at the first failure.
+///
+/// Devices that never start are reported as skipped, whether the run was
+/// cancelled or an earlier device failed.
+async fn flash_in_sequence<F, Fut>(
+    targets: &[FlashTarget],
+    mut flash: F,
+    is_cancelled: impl Fn(usize) -> bool,
+) -> Vec<DeviceFlashOutcome>
+where
+    F: FnMut(usize, FlashTarget) -> Fut,
+    Fut: std::future::Future<Output = Result<(), CommandError>>,
+{
+    let mut outcomes: Vec<DeviceFlashOutcome> = Vec::with_capacity(targets.len());
+
+    for (index, target) in targets.iter().enumerate() {
+        let failed = outcomes
+            .iter()
+            .find(|o| matches!(o.status, DeviceFlashStatus::Failed { .. }));
+
+        let status = if is_cancelled(index) {
+            DeviceFlashStatus::Skipped {
+                reason: "Operation cancelled by user".to_string(),
+            }
+        } else if let Some(failed) = failed {
+            DeviceFlashStatus::Skipped {
+                reason: format!("{} device failed to update", failed.device_label),
+            }
+        } else {
+            match flash(index, target.clone()).await {
+                Ok(()) => DeviceFlashStatus::Success,
+                Err(error) => DeviceFlashStatus::Failed {
+                    error: error.message,
+                    message_key: error.message_key,
+                },
+            }
+        };
+
+        outcomes.push(DeviceFlashOutcome {
+            device_index: index,
+            device_label: target.role.clone(),
+            port: target.port.clone(),
+            status,
+        });
+    }
+
+    outcomes
+}
+
+/// Flash a primary and a secondary device one after the other.
+///
+/// The secondary only starts once the primary has finished, rebooted and
+/// had its role configured, so the two never re-enumerate at the same time.
+/// Progress events carry `device_index`/`device_label` to tell the devices
+/// apart. Per-device failures are reported in the outcomes; the command
+/// itself only fails if another flash is already running.
+///
+/// # Arguments
+/// * `primary_port` - Serial port of the device to configure as PRIMARY
+/// * `secondary_port` - Serial port of the device to configure as SECONDARY
+/// * `firmware_path` - Path to the firmware.zip file
+/// * `full_bank_erase` - Wait for the whole application bank to erase (conservative)
+/// * `previous_firmware_path` - Cached firmware.zip believed to be on the devices
+/// * `progress` - Channel for progress updates from both devices
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn flash_both_devices(
+    primary_port: String,
+    secondary_port: String,
+    firmware_path: String,
+    full_bank_erase: Option<bool>,
+    previous_firmware_path: Option<String>,
+    progress: Channel<DfuProgressEvent>,
+    port_locks: tauri::State<'_, PortLocks>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    settings_service: tauri::State<'_, SettingsService>,
+    history: tauri::State<'_, FlashHistory>,
+    journal: tauri::State<'_, OperationJournal>,
+) -> Result<Vec<DeviceFlashOutcome>, CommandError> {
+    if DFU_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return Err("A firmware installation is already in progress".into());
+    }
+    let _guard = DfuGuard;
+
+    let leases = [
+        acquire_flash_lease(&port_locks, &primary_port).await?,
+        acquire_flash_lease(&port_locks, &secondary_port).await?,
+    ];
+
+    let erase_options = erase_wait_options(full_bank_erase, previous_firmware_path);
+    let timing = load_dfu_timing(&settings_service);
+    let clock = ProgressClock::new(None);
+    let (cache_manager, history, journal) =
+        (cache_manager.inner(), history.inner(), journal.inner());
+    let targets = [
+        FlashTarget {
+            port: primary_port,
+            role: "PRIMARY".to_string(),
+        },
+        FlashTarget {
+            port: secondary_port,
+            role: "SECONDARY".to_string(),
+        },
+    ];
+
+    let outcomes = flash_in_sequence(
+        &targets,
+        |index, target| {
+            let progress = ProgressSink::device(
+                progress.clone(),
+                index,
+                &target.role,
+                clock.for_operation(leases[index].id()),
+            )
+            .cancelled_by(leases[index].cancel_flag());
+            let firmware_path = firmware_path.clone();
+            // Each device gets the whole deadline, from when its flash starts
+            let deadline = timing
+                .deadline_seconds
+                .and_then(|secs| Instant::now().checked_add(Duration::from_secs(secs)));
+            async move {
+                flash_with_retries(
+                    target.port,
+                    firmware_path,
+                    Some(target.role),
+                    erase_options,
+                    timing.to_config(),
+                    deadline,
+                    progress,
+                    cache_manager,
+                    history,
+                    journal,
+                )
+                .await
+            }
+        },
+        |index| leases[index].cancel_flag().load(Ordering::SeqCst),
+    )
+    .await;
+
+    Ok(outcomes)
+}
+
+/// Which mode the device on a port is in, as returned by `is_device_in_bootloader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceMode {
+    Bootloader,
+    Application,
+    /// No BlueBuzzah device on the port (unplugged, or a different device).
+    NotFound,
+}
 
-    result.map_err(|e| format!("{}", e))
+impl DeviceMode {
+    fn on_port(devices: &[Nrf52Device], port: &str) -> Self {
+        match devices.iter().find(|d| d.port == port) {
+            Some(d) if d.in_bootloader => DeviceMode::Bootloader,
+            Some(_) => DeviceMode::Application,
+            None => DeviceMode::NotFound,
+        }
+    }
 }
 
 /// Check if a device is in bootloader mode.
+///
+/// Returns `"bootloader"`, `"application"` or `"not_found"`. A missing device
+/// is scanned for once more after a short delay before reporting
+/// `"not_found"`, since Windows can list the port late.
 #[tauri::command]
-pub async fn is_device_in_bootloader(serial_port: String) -> Result<bool, String> {
+pub async fn is_device_in_bootloader(serial_port: String) -> Result<DeviceMode, String> {
     tokio::task::spawn_blocking(move || {
-        find_nrf52_devices()
-            .into_iter()
-            .find(|d| d.port == serial_port)
-            .map(|d| d.in_bootloader)
-            .unwrap_or(false)
+        let mode = DeviceMode::on_port(&find_nrf52_devices(), &serial_port);
+        if mode != DeviceMode::NotFound {
+            return mode;
+        }
+        thread::sleep(Duration::from_millis(DEVICE_RESCAN_DELAY_MS));
+        DeviceMode::on_port(&find_nrf52_devices(), &serial_port)
     })
     .await
     .map_err(|e| format!("Failed to check device: {}", e))
 }
 
 /// Validate that a firmware zip file is valid.
+///
+/// Also checks the binary's CRC, estimates how long flashing it takes and,
+/// when `serial_port` is given, whether it suits the device on that port.
 #[tauri::command]
-pub async fn validate_firmware_package(firmware_path: String) -> Result<FirmwareInfo, String> {
+pub async fn validate_firmware_package(
+    firmware_path: String,
+    serial_port: Option<String>,
+) -> Result<FirmwareInfo, String> {
     use crate::dfu::read_firmware_zip;
 
     tokio::task::spawn_blocking(move || {
         let package = read_firmware_zip(&firmware_path).map_err(|e| format!("{}", e))?;
+        let manifest = &package.manifest;
+
+        let compatibility = serial_port.map(|port| {
+            let device = find_nrf52_devices().into_iter().find(|d| d.port == port);
+            CompatibilityVerdict::for_device(manifest.device_type, device.as_ref())
+        });
 
         Ok(FirmwareInfo {
             firmware_size: package.firmware_data.len(),
             init_size: package.init_data.len(),
-            firmware_crc16: package.manifest.firmware_crc16,
-            device_type: package.manifest.device_type,
-            dfu_version: package.manifest.dfu_version,
+            firmware_crc16: manifest.firmware_crc16,
+            device_type: manifest.device_type,
+            dfu_version: manifest.dfu_version,
+            application_version: Some(manifest.application_version)
+                .filter(|&version| version != u32::MAX),
+            crc_valid: package.crc_matches(),
+            estimated_duration_ms: estimate_flash_duration_ms(
+                package.firmware_data.len(),
+                &EraseWaitOptions::default(),
+            ),
+            compatibility,
         })
     })
     .await
     .map_err(|e| format!("Validation failed: {}", e))?
 }
 
-/// Cancel any in-progress DFU flash operation.
+/// What `cancel_dfu_flash` found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CancelResult {
+    /// Whether the flash was running and has been asked to stop.
+    pub was_running: bool,
+    /// Port lease ID of the cancelled flash.
+    pub operation_id: Option<u64>,
+    /// Stage the flash had reached (e.g. "uploading").
+    pub stage_at_cancel: Option<String>,
+    /// An operation ID was given but no running flash has it.
+    pub not_found: bool,
+}
+
+/// Decide what a cancel request applies to.
+///
+/// `flashes` are the flash leases held, `flashing` the one whose device is
+/// being flashed now and `stage` the stage it has reached. Only operations
+/// holding a flash lease can be cancelled; a UF2 copy or an app update
+/// install holding off flashes can't.
+fn cancel_outcome(
+    flashes: &[PortOperation],
+    requested: Option<u64>,
+    flashing: Option<u64>,
+    stage: Option<String>,
+) -> CancelResult {
+    let not_running = CancelResult {
+        was_running: false,
+        operation_id: None,
+        stage_at_cancel: None,
+        not_found: false,
+    };
+    if flashes.is_empty() {
+        return not_running;
+    }
+
+    let operation = match requested {
+        Some(id) => match flashes.iter().find(|op| op.id == id) {
+            Some(op) => op,
+            None => {
+                return CancelResult {
+                    not_found: true,
+                    ..not_running
+                }
+            }
+        },
+        None => flashing
+            .and_then(|id| flashes.iter().find(|op| op.id == id))
+            .unwrap_or(&flashes[0]),
+    };
+
+    CancelResult {
+        was_running: true,
+        operation_id: Some(operation.id),
+        stage_at_cancel: stage.filter(|_| flashing == Some(operation.id)),
+        not_found: false,
+    }
+}
+
+/// Cancel an in-progress DFU flash.
 ///
-/// Sets a global cancellation flag that is checked during the DFU process.
-/// The operation will stop at the next safe point.
+/// Sets the cancellation flag of the flash's port lease, which is checked
+/// during the DFU process; the flash stops at the next safe point. With
+/// `operation_id` only that device's flash is cancelled. Without it the
+/// whole run is, including devices of a `flash_both_devices` run that
+/// haven't started yet. Returns immediately with `was_running: false` when
+/// no flash is running, or with `not_found` when `operation_id` names a
+/// flash that isn't running.
 #[tauri::command]
-pub async fn cancel_dfu_flash() -> Result<(), String> {
-    DFU_CANCELLED.store(true, Ordering::SeqCst);
-    Ok(())
+pub async fn cancel_dfu_flash(
+    operation_id: Option<u64>,
+    port_locks: tauri::State<'_, PortLocks>,
+) -> Result<CancelResult, String> {
+    let flashes = port_locks.in_flight("flash");
+    let flashing = *FLASHING_OPERATION.lock().unwrap_or_else(|e| e.into_inner());
+    let stage = DFU_STAGE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let outcome = cancel_outcome(&flashes, operation_id, flashing, stage);
+
+    if outcome.was_running {
+        match operation_id {
+            Some(id) => {
+                port_locks.cancel(id);
+            }
+            None => {
+                for flash in &flashes {
+                    port_locks.cancel(flash.id);
+                }
+            }
+        }
+    }
+    Ok(outcome)
 }
 
-/// Progress event sent to the frontend during profile configuration.
-#[derive(Debug, Clone, Serialize)]
-pub struct ProfileProgressEvent {
-    /// Current stage name: "connecting", "sending", "rebooting", "complete", "error"
-    pub stage: String,
-    /// Progress percentage (0-100).
-    pub percent: f32,
-    /// Human-readable message.
-    pub message: String,
+/// List the device operations in progress, oldest first.
+///
+/// Lets a second window, or a webview that reloaded mid-flash, find running
+/// work and follow it through the `dfu://progress` event, matching on
+/// `operation_id`.
+#[tauri::command]
+pub async fn get_active_operations(
+    port_locks: tauri::State<'_, PortLocks>,
+) -> Result<Vec<PortOperation>, String> {
+    Ok(port_locks.active())
 }
 
-/// Set the therapy profile for a device with optional advanced settings.
+/// List mounted UF2 bootloader drives (FTHR840BOOT after a double-tap reset).
 ///
-/// This command configures a device's therapy profile by sending serial commands.
-/// The device must be in APPLICATION mode (not bootloader mode).
-/// After configuration, the device will automatically reboot.
+/// Lets the frontend offer UF2 flashing when serial DFU can't reach the device.
+#[tauri::command]
+pub async fn detect_uf2_volumes() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(|| {
+        find_uf2_volumes()
+            .into_iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Failed to detect UF2 drives: {}", e))
+}
+
+/// Flash a .uf2 image by copying it onto a UF2 bootloader drive.
 ///
-/// If `advanced_settings` is provided, setting commands are sent BEFORE the
-/// profile command. This allows configuring device behavior like LED state.
+/// The escape hatch for when serial DFU can't get through. If
+/// `serial_port` names the device (its bootloader port), waits for that
+/// device to come back in application mode and returns its new port.
 ///
 /// # Arguments
-/// * `serial_port` - Serial port of the device
-/// * `profile` - Profile to set ("REGULAR", "NOISY", "HYBRID", or "GENTLE")
-/// * `advanced_settings` - Optional advanced settings (LED off, etc.)
+/// * `uf2_path` - Path to the .uf2 file
+/// * `volume_path` - Mounted UF2 bootloader drive
+/// * `serial_port` - Serial port of the same device, if known
 /// * `progress` - Channel for progress updates
 #[tauri::command]
-pub async fn set_device_profile(
-    serial_port: String,
+pub async fn flash_uf2(
+    uf2_path: String,
+    volume_path: String,
+    serial_port: Option<String>,
+    progress: Channel<Uf2ProgressEvent>,
+) -> Result<Option<String>, String> {
+    // Shares the guard with serial DFU; one board is flashed at a time
+    if DFU_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return Err("A firmware installation is already in progress".into());
+    }
+    let _guard = DfuGuard;
+
+    tokio::task::spawn_blocking(move || {
+        let identifier = serial_port
+            .and_then(|port| find_nrf52_devices().into_iter().find(|d| d.port == port))
+            .map(|device| DeviceIdentifier::from_device(&device));
+
+        flash_uf2_image(
+            Path::new(&uf2_path),
+            Path::new(&volume_path),
+            identifier.as_ref(),
+            |event| {
+                let _ = progress.send(event);
+            },
+        )
+        .map(|device| device.map(|d| d.port))
+        .map_err(|e| format!("{}", e))
+    })
+    .await
+    .map_err(|e| format!("UF2 flash task panicked: {}", e))?
+}
+
+/// Blink a device's NeoPixel so it can be told apart from identical boards.
+///
+/// The device must be in application mode, and no flash may be running
+/// since it owns the serial port. Returns whether the firmware acknowledged
+/// the request; older firmware without IDENTIFY returns false.
+#[tauri::command]
+pub async fn identify_device(
+    serial_port: String,
+    port_locks: tauri::State<'_, PortLocks>,
+) -> Result<bool, CommandError> {
+    if is_dfu_in_progress() {
+        return Err("Cannot identify a device while a firmware installation is in progress".into());
+    }
+
+    let lease = port_locks.acquire(&serial_port, "query")?;
+
+    Ok(tokio::task::spawn_blocking(move || {
+        let _lease = lease;
+        let device = find_nrf52_devices()
+            .into_iter()
+            .find(|d| d.port == serial_port)
+            .ok_or_else(|| "Device not found".to_string())?;
+
+        if device.in_bootloader {
+            return Err(
+                "Device is in bootloader mode. Please wait for it to boot into application mode."
+                    .to_string(),
+            );
+        }
+
+        send_identify(&serial_port, |_| {}).map_err(|e| format!("{}", e))
+    })
+    .await
+    .map_err(|e| format!("Identify task panicked: {}", e))??)
+}
+
+/// Audit log of developer commands, in the app log directory.
+pub(crate) const DFU_LOG_FILENAME: &str = "dfu.log";
+
+/// Append a line to the DFU log file. Failures are only warned about.
+fn append_dfu_log(app_handle: &tauri::AppHandle, line: &str) {
+    use std::io::Write;
+
+    let entry = format!("{} {}", chrono::Utc::now().to_rfc3339(), line);
+    let result = app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| {
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(DFU_LOG_FILENAME))
+                .and_then(|mut file| writeln!(file, "{}", entry))
+                .map_err(|e| e.to_string())
+        });
+
+    if let Err(e) = result {
+        log::warn!("Failed to write DFU log: {}", e);
+    }
+}
+
+/// Check a command for `send_device_command` and return it as sent, with
+/// the newline appended.
+///
+/// Commands must be one line of printable text. Without developer mode only
+/// `READ_ONLY_COMMANDS` are allowed.
+fn prepare_device_command(command: &str, developer_mode: bool) -> Result<String, String> {
+    let command = command.trim();
+
+    if command.is_empty() {
+        return Err("Command is empty".to_string());
+    }
+    if command.len() > MAX_DEVICE_COMMAND_LEN {
+        return Err(format!(
+            "Command is longer than {} characters",
+            MAX_DEVICE_COMMAND_LEN
+        ));
+    }
+    if command.chars().any(|c| c.is_control()) {
+        return Err("Command must be a single line of text".to_string());
+    }
+
+    let name = command.split(':').next().unwrap_or(command);
+    if !developer_mode && !READ_ONLY_COMMANDS.contains(&name.to_ascii_uppercase().as_str()) {
+        return Err(format!(
+            "{} is not a read-only query. Enable developer mode to send other commands.",
+            name
+        ));
+    }
+
+    Ok(format!("{}\n", command))
+}
+
+/// Send a command to a device in application mode and return its raw
+/// response text.
+///
+/// Only read-only queries (`READ_ONLY_COMMANDS`) are allowed unless the
+/// `developer_mode` setting is on. Every invocation, including refused ones,
+/// is written to the DFU log file.
+#[tauri::command]
+pub async fn send_device_command(
+    serial_port: String,
+    command: String,
+    timeout_ms: Option<u64>,
+    port_locks: tauri::State<'_, PortLocks>,
+    settings_service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, CommandError> {
+    let result = run_device_command(
+        &serial_port,
+        &command,
+        timeout_ms,
+        &port_locks,
+        &settings_service,
+    )
+    .await;
+
+    let outcome = match &result {
+        Ok(response) => format!("ok ({} bytes)", response.len()),
+        Err(e) => format!("refused or failed: {}", e),
+    };
+    append_dfu_log(
+        &app_handle,
+        &format!(
+            "send_device_command port={} command={:?} {}",
+            serial_port,
+            command.trim(),
+            outcome
+        ),
+    );
+
+    result
+}
+
+async fn run_device_command(
+    serial_port: &str,
+    command: &str,
+    timeout_ms: Option<u64>,
+    port_locks: &PortLocks,
+    settings_service: &SettingsService,
+) -> Result<String, CommandError> {
+    if is_dfu_in_progress() {
+        return Err("Cannot send a command while a firmware installation is in progress".into());
+    }
+
+    let developer_mode = settings_service.settings_or_default().developer_mode;
+    let command = prepare_device_command(command, developer_mode)?;
+    let timeout = Duration::from_millis(
+        timeout_ms
+            .unwrap_or(DEVICE_COMMAND_TIMEOUT_MS)
+            .clamp(1, MAX_DEVICE_COMMAND_TIMEOUT_MS),
+    );
+
+    let lease = port_locks.acquire(serial_port, "command")?;
+    let serial_port = serial_port.to_string();
+
+    Ok(tokio::task::spawn_blocking(move || {
+        let _lease = lease;
+        send_raw_command(&serial_port, &command, timeout, |_| {}).map_err(|e| format!("{}", e))
+    })
+    .await
+    .map_err(|e| format!("Command task panicked: {}", e))??)
+}
+
+/// Queries behind `get_device_info`, with the tag each answer starts with.
+pub const DEVICE_INFO_QUERIES: [(&str, &str); 3] = [
+    (GET_VERSION_COMMAND, "[VERSION]"),
+    (GET_ROLE_COMMAND, "[ROLE]"),
+    (GET_PROFILE_COMMAND, "[PROFILE]"),
+];
+
+/// What a device in application mode reported about itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DeviceInfo {
+    /// Firmware version from GET_VERSION.
+    pub firmware_version: Option<String>,
+    /// Configured role from GET_ROLE ("PRIMARY" or "SECONDARY").
+    pub role: Option<String>,
+    /// Therapy profile from GET_PROFILE.
+    pub profile: Option<String>,
+    /// Queries the device didn't answer in time (e.g. "GET_ROLE").
+    pub timed_out: Vec<String>,
+    /// Queries the firmware rejected, usually because it predates them.
+    pub unsupported: Vec<String>,
+}
+
+impl DeviceInfo {
+    /// Collect the answers to `DEVICE_INFO_QUERIES`, in order.
+    fn from_answers(answers: Vec<QueryAnswer>) -> Self {
+        let mut info = Self::default();
+
+        for ((command, _), answer) in DEVICE_INFO_QUERIES.iter().zip(answers) {
+            let name = command.trim().to_string();
+            let value = match answer {
+                QueryAnswer::Value(value) => Some(value),
+                QueryAnswer::Rejected(_) => {
+                    info.unsupported.push(name);
+                    None
+                }
+                QueryAnswer::TimedOut => {
+                    info.timed_out.push(name);
+                    None
+                }
+            };
+
+            match command.trim() {
+                "GET_VERSION" => info.firmware_version = value,
+                "GET_ROLE" => info.role = value,
+                _ => info.profile = value,
+            }
+        }
+
+        info
+    }
+}
+
+/// Read the firmware version, role and profile from a device.
+///
+/// The device must be in application mode, and no flash may be running
+/// since it owns the serial port. Firmware that only understands some of
+/// the queries still returns what it knows; the rest are listed in
+/// `timed_out` or `unsupported`. Finishes within `DEVICE_QUERY_BUDGET_MS`
+/// of opening the port.
+///
+/// The firmware version is remembered for the session, so configuring the
+/// device later skips settings its firmware predates.
+#[tauri::command]
+pub async fn get_device_info(
+    serial_port: String,
+    port_locks: tauri::State<'_, PortLocks>,
+) -> Result<DeviceInfo, CommandError> {
+    if is_dfu_in_progress() {
+        return Err("Cannot query a device while a firmware installation is in progress".into());
+    }
+
+    let lease = port_locks.acquire(&serial_port, "query")?;
+
+    Ok(tokio::task::spawn_blocking(move || {
+        let _lease = lease;
+        let device = find_nrf52_devices()
+            .into_iter()
+            .find(|d| d.port == serial_port)
+            .ok_or_else(|| "Device not found".to_string())?;
+
+        if device.in_bootloader {
+            return Err(
+                "Device is in bootloader mode. Please wait for it to boot into application mode."
+                    .to_string(),
+            );
+        }
+
+        let info = query_device(
+            &serial_port,
+            &DEVICE_INFO_QUERIES,
+            Duration::from_millis(DEVICE_QUERY_BUDGET_MS),
+            |_| {},
+        )
+        .map(DeviceInfo::from_answers)
+        .map_err(|e| format!("Failed to query device: {}", e))?;
+
+        if let Some(serial) = &device.serial_number {
+            remember_firmware_version(serial, info.firmware_version.as_deref());
+        }
+        Ok(info)
+    })
+    .await
+    .map_err(|e| format!("Device info task panicked: {}", e))??)
+}
+
+/// A saved setting the device isn't running.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettingMismatch {
+    /// Setting key, e.g. "THERAPY_LED_OFF".
+    pub key: String,
+    /// Value from the app's saved settings.
+    pub expected: String,
+    /// Value the device reported, or `None` if it didn't report the setting.
+    pub actual: Option<String>,
+}
+
+/// What `read_device_settings` found on a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeviceSettingsReadout {
+    /// The device reported its settings.
+    Supported {
+        /// Every setting the device reported, by key.
+        settings: BTreeMap<String, String>,
+        /// Saved settings that differ from what the device reported.
+        mismatches: Vec<SettingMismatch>,
+    },
+    /// The firmware doesn't implement GET_SETTINGS.
+    Unsupported { reason: String },
+}
+
+/// Compare the settings a device reported with the setting commands the
+/// app sends it (e.g. "THERAPY_LED_OFF:true"). Settings the app leaves to
+/// the firmware default aren't sent, so they aren't compared.
+fn setting_mismatches(
+    commands: &[String],
+    reported: &BTreeMap<String, String>,
+) -> Vec<SettingMismatch> {
+    commands
+        .iter()
+        .filter_map(|command| {
+            let (key, expected) = command.trim().split_once(':')?;
+            let actual = reported.get(key);
+            if actual.is_some_and(|actual| setting_values_match(expected, actual)) {
+                return None;
+            }
+            Some(SettingMismatch {
+                key: key.to_string(),
+                expected: expected.to_string(),
+                actual: actual.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Whether a reported value matches the one sent, ignoring case and number
+/// formatting ("TRUE" matches "true", "15" matches "15.0").
+fn setting_values_match(expected: &str, actual: &str) -> bool {
+    match (expected.parse::<f64>(), actual.parse::<f64>()) {
+        (Ok(expected), Ok(actual)) => expected == actual,
+        _ => expected.eq_ignore_ascii_case(actual),
+    }
+}
+
+/// Read the settings a device is running and compare them with the saved
+/// ones (the device's overrides layered over the global settings).
+///
+/// The device must be in application mode, and no flash may be running.
+/// Firmware without GET_SETTINGS gives an `Unsupported` readout, not an
+/// error.
+#[tauri::command]
+pub async fn read_device_settings(
+    serial_port: String,
+    port_locks: tauri::State<'_, PortLocks>,
+    settings_service: tauri::State<'_, SettingsService>,
+) -> Result<DeviceSettingsReadout, CommandError> {
+    if is_dfu_in_progress() {
+        return Err("Cannot query a device while a firmware installation is in progress".into());
+    }
+
+    let (settings, _) = load_effective_settings(&settings_service, &serial_port).await?;
+    let expected = settings.to_pre_profile_commands();
+
+    let lease = port_locks.acquire(&serial_port, "query")?;
+
+    Ok(tokio::task::spawn_blocking(move || {
+        let _lease = lease;
+        let device = find_nrf52_devices()
+            .into_iter()
+            .find(|d| d.port == serial_port)
+            .ok_or_else(|| "Device not found".to_string())?;
+
+        if device.in_bootloader {
+            return Err(
+                "Device is in bootloader mode. Please wait for it to boot into application mode."
+                    .to_string(),
+            );
+        }
+
+        let answer = query_device_settings(&serial_port, |_| {})
+            .map_err(|e| format!("Failed to read device settings: {}", e))?;
+
+        Ok(match answer {
+            SettingsAnswer::Settings(settings) => DeviceSettingsReadout::Supported {
+                mismatches: setting_mismatches(&expected, &settings),
+                settings,
+            },
+            SettingsAnswer::Unsupported(reason) => DeviceSettingsReadout::Unsupported { reason },
+        })
+    })
+    .await
+    .map_err(|e| format!("Device settings task panicked: {}", e))??)
+}
+
+/// Progress event sent to the frontend during profile configuration.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileProgressEvent {
+    /// Current stage name: "connecting", "sending", "rebooting", "complete", "error"
+    pub stage: String,
+    /// Progress percentage (0-100).
+    pub percent: f32,
+    /// Human-readable message.
+    pub message: String,
+    /// Position of the event within its operation, starting at 1.
+    pub seq: u64,
+    /// When the event was created (milliseconds since the Unix epoch).
+    pub emitted_at_ms: u64,
+    /// Port lease ID of the operation that sent the event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<u64>,
+}
+
+impl ProfileProgressEvent {
+    fn new(clock: &ProgressClock, stage: &str, percent: f32, message: String) -> Self {
+        let (seq, emitted_at_ms) = clock.tick();
+        Self {
+            stage: stage.to_string(),
+            percent,
+            message,
+            seq,
+            emitted_at_ms,
+            operation_id: clock.operation_id,
+        }
+    }
+}
+
+/// Set the therapy profile for a device with optional advanced settings.
+///
+/// This command configures a device's therapy profile by sending serial commands.
+/// The device must be in APPLICATION mode (not bootloader mode).
+/// After configuration, the device will automatically reboot.
+///
+/// Advanced setting commands are sent BEFORE the profile command. This allows
+/// configuring device behavior like LED state. When `advanced_settings` is
+/// omitted, the settings saved on disk are used, so every path that sets a
+/// profile also applies the user's settings.
+///
+/// # Arguments
+/// * `serial_port` - Serial port of the device
+/// * `profile` - Profile to set ("REGULAR", "NOISY", "HYBRID", "GENTLE", or a custom profile)
+/// * `advanced_settings` - Advanced settings (LED off, etc.); defaults to the saved ones
+/// * `progress` - Channel for progress updates
+#[tauri::command]
+pub async fn set_device_profile(
+    serial_port: String,
     profile: String,
     advanced_settings: Option<AdvancedSettings>,
     progress: Channel<ProfileProgressEvent>,
-) -> Result<(), String> {
+    port_locks: tauri::State<'_, PortLocks>,
+    settings_service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    let lease = port_locks.acquire(&serial_port, "profile")?;
+    let clock = ProgressClock::new(Some(lease.id()));
+
+    let advanced_settings = match advanced_settings {
+        Some(settings) => settings,
+        None => settings_service.settings_or_default(),
+    };
+
+    let mirror = ProgressMirror::new(&app_handle, "profile");
+    configure_profile(
+        serial_port,
+        profile,
+        advanced_settings,
+        progress,
+        mirror,
+        clock,
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Setting commands `apply_device_configuration` sent and skipped, without
+/// the newline (e.g. "THERAPY_LED_OFF:true").
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AppliedConfiguration {
+    pub sent_settings: Vec<String>,
+    /// Settings the device's firmware is known to predate, left unsent.
+    pub skipped_settings: Vec<String>,
+}
+
+/// Apply the saved advanced settings and a therapy profile to a device.
+///
+/// Like `set_device_profile`, but always uses the settings saved on disk,
+/// with the device's own overrides (by serial number) over the global ones.
+/// Returns the setting commands that were sent, and those skipped because
+/// the firmware version from an earlier `get_device_info` predates them.
+///
+/// # Arguments
+/// * `serial_port` - Serial port of the device
+/// * `profile` - Profile to set ("REGULAR", "NOISY", "HYBRID", "GENTLE", or a custom profile)
+/// * `progress` - Channel for progress updates
+#[tauri::command]
+pub async fn apply_device_configuration(
+    serial_port: String,
+    profile: String,
+    progress: Channel<ProfileProgressEvent>,
+    port_locks: tauri::State<'_, PortLocks>,
+    settings_service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<AppliedConfiguration, CommandError> {
+    let lease = port_locks.acquire(&serial_port, "profile")?;
+    let clock = ProgressClock::new(Some(lease.id()));
+
+    let (advanced_settings, layers) =
+        load_effective_settings(&settings_service, &serial_port).await?;
+    let mirror = ProgressMirror::new(&app_handle, "profile");
+    for layer in layers {
+        let message = format!("Setting {}", layer);
+        let _ = send_profile_progress(
+            &progress,
+            &mirror,
+            ProfileProgressEvent::new(&clock, "log", -1.0, message),
+        );
+    }
+
+    configure_profile(
+        serial_port,
+        profile,
+        advanced_settings,
+        progress,
+        mirror,
+        clock,
+    )
+    .await
+}
+
+/// Load the saved settings for the device on `serial_port`: its overrides
+/// layered over the global settings, plus which layer each value came from.
+///
+/// When no device is on `serial_port`, it is taken as a serial number, so
+/// settings can be resolved for devices that aren't connected.
+async fn load_effective_settings(
+    settings_service: &SettingsService,
+    serial_port: &str,
+) -> Result<(AdvancedSettings, Vec<String>), String> {
+    let snapshot = settings_service.snapshot_or_default();
+
+    let port = serial_port.to_string();
+    tokio::task::spawn_blocking(move || {
+        let serial_number = match find_nrf52_devices().into_iter().find(|d| d.port == port) {
+            Some(device) => device.serial_number,
+            None => Some(port),
+        };
+        snapshot.effective(serial_number.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to find device: {}", e))
+}
+
+/// Send a profile progress event over `progress` and mirror it globally.
+fn send_profile_progress(
+    progress: &Channel<ProfileProgressEvent>,
+    mirror: &ProgressMirror,
+    event: ProfileProgressEvent,
+) -> tauri::Result<()> {
+    mirror.emit(event.operation_id, &event);
+    progress.send(event)
+}
+
+/// Send advanced settings and then the profile, bridging protocol log
+/// messages to `progress`.
+async fn configure_profile(
+    serial_port: String,
+    profile: String,
+    advanced_settings: AdvancedSettings,
+    progress: Channel<ProfileProgressEvent>,
+    mirror: ProgressMirror,
+    clock: ProgressClock,
+) -> Result<AppliedConfiguration, CommandError> {
+    let advanced_settings = Some(advanced_settings);
+
     // Get device info and create identifier for tracking
     let device = tokio::task::spawn_blocking({
         let port = serial_port.clone();
@@ -584,48 +2060,57 @@ pub async fn set_device_profile(
     .ok_or_else(|| "Device not found".to_string())?;
 
     let device_identifier = DeviceIdentifier::from_device(&device);
+    let firmware_version = device
+        .serial_number
+        .as_deref()
+        .and_then(known_firmware_version);
 
     // Log tracking method for diagnostics
     if device_identifier.has_serial() {
-        eprintln!("[set_device_profile] Tracking device by serial number");
+        log::info!("Tracking device by serial number");
     } else {
-        eprintln!("[set_device_profile] Device has no serial number - using VID/PID+port pattern");
+        log::info!("Device has no serial number - using VID/PID+port pattern");
     }
 
     // Verify device is in application mode (not bootloader)
     if device.in_bootloader {
         return Err(
             "Device is in bootloader mode. Please wait for it to boot into application mode."
-                .to_string(),
+                .into(),
         );
     }
 
     // Send progress: connecting
-    let _ = progress.send(ProfileProgressEvent {
-        stage: "connecting".to_string(),
-        percent: 10.0,
-        message: "Connecting to device...".to_string(),
-    });
+    let _ = send_profile_progress(
+        &progress,
+        &mirror,
+        ProfileProgressEvent::new(
+            &clock,
+            "connecting",
+            10.0,
+            "Connecting to device...".to_string(),
+        ),
+    );
 
     // Create a channel for status updates from the blocking thread
     let (tx, rx) = mpsc::channel::<ProfileProgressEvent>();
 
     // Spawn a task to forward progress updates
-    let progress_channel = progress.clone();
     let progress_task = thread::spawn(move || {
         while let Ok(event) = rx.recv() {
-            if progress_channel.send(event).is_err() {
-                eprintln!("[DFU] Warning: profile progress channel disconnected");
+            if send_profile_progress(&progress, &mirror, event).is_err() {
+                log::warn!("profile progress channel disconnected");
                 break;
             }
         }
     });
 
-    // Get pre-profile commands from advanced settings
-    let pre_commands = advanced_settings
-        .as_ref()
-        .map(|s| s.to_pre_profile_commands())
-        .unwrap_or_default();
+    // Get pre-profile commands from advanced settings, plus a custom
+    // profile's own commands and the built-in profile it is based on
+    let (pre_commands, base_profile) = match &advanced_settings {
+        Some(settings) => settings.profile_commands(&profile),
+        None => (Vec::new(), Some(profile.clone())),
+    };
 
     let has_settings = !pre_commands.is_empty()
         && advanced_settings
@@ -633,11 +2118,24 @@ pub async fn set_device_profile(
             .map(|s| s.has_non_default_settings())
             .unwrap_or(false);
 
+    let timing = advanced_settings
+        .as_ref()
+        .map(|s| s.dfu_timing.to_config())
+        .unwrap_or_default();
+    let _ = tx.send(ProfileProgressEvent::new(
+        &clock,
+        "log",
+        -1.0,
+        describe_dfu_timing(&timing, None),
+    ));
+
     // Run profile configuration in a blocking task
     let result = tokio::task::spawn_blocking({
         let serial_port = serial_port.clone();
         let profile = profile.clone();
+        let pre_commands = pre_commands.clone();
         let tx = tx.clone();
+        let clock = clock.clone();
 
         move || {
             // Send progress: sending command
@@ -646,61 +2144,58 @@ pub async fn set_device_profile(
             } else {
                 format!("Sending {} profile command...", profile)
             };
-            let _ = tx.send(ProfileProgressEvent {
-                stage: "sending".to_string(),
-                percent: 30.0,
-                message,
-            });
+            let _ = tx.send(ProfileProgressEvent::new(&clock, "sending", 30.0, message));
 
             // Create a logger that forwards to the progress channel
             let tx_log = tx.clone();
+            let log_clock = clock.clone();
             let log = move |msg: &str| {
-                let _ = tx_log.send(ProfileProgressEvent {
-                    stage: "log".to_string(),
-                    percent: -1.0, // Log messages don't affect progress
-                    message: msg.to_string(),
-                });
+                // Log messages don't affect progress
+                let _ = tx_log.send(ProfileProgressEvent::new(
+                    &log_clock,
+                    "log",
+                    -1.0,
+                    msg.to_string(),
+                ));
             };
 
-            // Configure the profile (with or without advanced settings)
-            let config_result = if pre_commands.is_empty() {
-                // No advanced settings - use original function with logging
-                let identifier = device_identifier.clone();
-                configure_device_with_settings(&serial_port, &profile, &[], &identifier, log)
-            } else {
-                // Has advanced settings - use new function with logging
-                let identifier = device_identifier.clone();
-                configure_device_with_settings(
-                    &serial_port,
-                    &profile,
-                    &pre_commands,
-                    &identifier,
-                    log,
-                )
-            };
+            // Configure the profile (with or without advanced settings), tracking
+            // the device through its reboot by serial number or VID/PID+port
+            let config_result = configure_device_with_settings(
+                &serial_port,
+                base_profile.as_deref(),
+                &pre_commands,
+                &device_identifier,
+                firmware_version.as_deref(),
+                timing.max_config_retries,
+                log,
+            );
 
             match &config_result {
-                Ok(()) => {
+                Ok(_) => {
                     // Send progress: rebooting (already handled internally, but we signal it)
-                    let _ = tx.send(ProfileProgressEvent {
-                        stage: "rebooting".to_string(),
-                        percent: 70.0,
-                        message: "Waiting for device to restart...".to_string(),
-                    });
+                    let _ = tx.send(ProfileProgressEvent::new(
+                        &clock,
+                        "rebooting",
+                        70.0,
+                        "Waiting for device to restart...".to_string(),
+                    ));
 
                     // Send progress: complete
-                    let _ = tx.send(ProfileProgressEvent {
-                        stage: "complete".to_string(),
-                        percent: 100.0,
-                        message: format!("Profile set to {}", profile),
-                    });
+                    let _ = tx.send(ProfileProgressEvent::new(
+                        &clock,
+                        "complete",
+                        100.0,
+                        format!("Profile set to {}", profile),
+                    ));
                 }
                 Err(e) => {
-                    let _ = tx.send(ProfileProgressEvent {
-                        stage: "error".to_string(),
-                        percent: 0.0,
-                        message: format!("{}", e),
-                    });
+                    let _ = tx.send(ProfileProgressEvent::new(
+                        &clock,
+                        "error",
+                        0.0,
+                        format!("{}", e),
+                    ));
                 }
             }
 
@@ -714,7 +2209,190 @@ pub async fn set_device_profile(
     drop(tx); // Close the sender to signal completion
     let _ = progress_task.join();
 
-    result.map_err(|e| format!("{}", e))
+    let skipped_settings = result?;
+    Ok(AppliedConfiguration {
+        // Reported back to the caller, without the newline terminators
+        sent_settings: pre_commands
+            .iter()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !skipped_settings.contains(c))
+            .collect(),
+        skipped_settings,
+    })
+}
+
+/// One serial command `preview_device_configuration` reports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PreviewCommand {
+    /// The command as sent, without the newline (e.g. "DEBUG:false").
+    pub command: String,
+    /// What it does, e.g. "Debug Mode = false".
+    pub description: String,
+}
+
+/// The commands that configuring a device for `profile` with `settings`
+/// sends, in order, ending with the command that reboots the device.
+fn preview_commands(
+    settings: &AdvancedSettings,
+    profile: &str,
+) -> Result<Vec<PreviewCommand>, String> {
+    let (setting_commands, base_profile) = settings.profile_commands(profile);
+
+    let mut commands: Vec<PreviewCommand> = setting_commands
+        .iter()
+        .map(|command| PreviewCommand {
+            command: command.trim().to_string(),
+            description: describe_setting_command(command),
+        })
+        .collect();
+
+    commands.push(match base_profile {
+        Some(base_profile) => PreviewCommand {
+            command: profile_command(&base_profile)
+                .map_err(|e| e.to_string())?
+                .trim()
+                .to_string(),
+            description: format!(
+                "Set therapy profile {} (device reboots)",
+                base_profile.to_uppercase()
+            ),
+        },
+        None => PreviewCommand {
+            command: REBOOT_COMMAND.trim().to_string(),
+            description: "Reboot device".to_string(),
+        },
+    });
+
+    Ok(commands)
+}
+
+/// List the serial commands `apply_device_configuration` would send for
+/// `profile`, in order, without opening the serial port.
+///
+/// Uses the saved settings with the device's own overrides over the global
+/// ones. The device is found by serial port or, when it isn't connected,
+/// by USB serial number.
+#[tauri::command]
+pub async fn preview_device_configuration(
+    serial_port_or_serial: String,
+    profile: String,
+    settings_service: tauri::State<'_, SettingsService>,
+) -> Result<Vec<PreviewCommand>, String> {
+    let (settings, _) = load_effective_settings(&settings_service, &serial_port_or_serial).await?;
+    preview_commands(&settings, &profile)
+}
+
+/// Normalize a role name from the frontend to "PRIMARY" or "SECONDARY".
+pub fn parse_device_role(role: &str) -> Result<String, String> {
+    match role.trim().to_uppercase().as_str() {
+        role @ ("PRIMARY" | "SECONDARY") => Ok(role.to_string()),
+        _ => Err(format!(
+            "Invalid role '{}'. Expected PRIMARY or SECONDARY.",
+            role
+        )),
+    }
+}
+
+/// Set the role of a device without reflashing it.
+///
+/// Sends SET_ROLE over serial and waits for the device to reboot, tracking
+/// it by serial number or VID/PID+port pattern. The device must be in
+/// APPLICATION mode and no flash may be running.
+///
+/// # Arguments
+/// * `serial_port` - Serial port of the device
+/// * `role` - Role to set ("PRIMARY" or "SECONDARY")
+/// * `progress` - Channel for progress updates
+#[tauri::command]
+pub async fn set_device_role(
+    serial_port: String,
+    role: String,
+    progress: Channel<ProfileProgressEvent>,
+    port_locks: tauri::State<'_, PortLocks>,
+) -> Result<(), CommandError> {
+    let role = parse_device_role(&role)?;
+    let lease = port_locks.acquire(&serial_port, "role")?;
+    let clock = ProgressClock::new(Some(lease.id()));
+
+    if is_dfu_in_progress() {
+        return Err("Cannot change the role while a firmware installation is in progress".into());
+    }
+
+    let device = tokio::task::spawn_blocking({
+        let port = serial_port.clone();
+        move || find_nrf52_devices().into_iter().find(|d| d.port == port)
+    })
+    .await
+    .map_err(|e| format!("Failed to find device: {}", e))?
+    .ok_or_else(|| "Device not found".to_string())?;
+
+    if device.in_bootloader {
+        return Err(
+            "Device is in bootloader mode. Please wait for it to boot into application mode."
+                .into(),
+        );
+    }
+
+    let device_identifier = DeviceIdentifier::from_device(&device);
+    if !device_identifier.has_serial() {
+        log::info!("Device has no serial number - using VID/PID+port pattern");
+    }
+
+    let _ = progress.send(ProfileProgressEvent::new(
+        &clock,
+        "connecting",
+        10.0,
+        "Connecting to device...".to_string(),
+    ));
+
+    let result = tokio::task::spawn_blocking({
+        let progress = progress.clone();
+        let role = role.clone();
+        let clock = clock.clone();
+
+        move || {
+            let _ = progress.send(ProfileProgressEvent::new(
+                &clock,
+                "sending",
+                30.0,
+                format!("Sending {} role command...", role),
+            ));
+
+            let result = configure_device_role_flexible(&serial_port, &role, &device_identifier);
+            if result.is_ok() {
+                let _ = progress.send(ProfileProgressEvent::new(
+                    &clock,
+                    "rebooting",
+                    70.0,
+                    "Waiting for device to restart...".to_string(),
+                ));
+            }
+            result
+        }
+    })
+    .await
+    .map_err(|e| format!("Role configuration task panicked: {}", e))?;
+
+    match result {
+        Ok(()) => {
+            let _ = progress.send(ProfileProgressEvent::new(
+                &clock,
+                "complete",
+                100.0,
+                format!("Role set to {}", role),
+            ));
+            Ok(())
+        }
+        Err(e) => {
+            let _ = progress.send(ProfileProgressEvent::new(
+                &clock,
+                "error",
+                0.0,
+                format!("{}", e),
+            ));
+            Err(e.into())
+        }
+    }
 }
 
 /// Information about a firmware package.
@@ -730,11 +2408,52 @@ pub struct FirmwareInfo {
     pub device_type: u16,
     /// DFU protocol version.
     pub dfu_version: f32,
+    /// Application version from the manifest, if the package sets one.
+    pub application_version: Option<u32>,
+    /// Whether the firmware binary matches `firmware_crc16`.
+    pub crc_valid: bool,
+    /// Typical end-to-end flash time in milliseconds.
+    pub estimated_duration_ms: u64,
+    /// Verdict for the device on the requested port, if one was given.
+    pub compatibility: Option<CompatibilityVerdict>,
+}
+
+/// Whether a firmware package suits a particular device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompatibilityVerdict {
+    pub compatible: bool,
+    /// Human-readable explanation of the verdict.
+    pub reason: String,
+}
+
+impl CompatibilityVerdict {
+    /// Judge a package's `device_type` against the device found on the port.
+    fn for_device(device_type: u16, device: Option<&Nrf52Device>) -> Self {
+        match device {
+            None => Self {
+                compatible: false,
+                reason: "No compatible device found on the selected port".to_string(),
+            },
+            Some(_) if device_type != NRF52840_DEVICE_TYPE => Self {
+                compatible: false,
+                reason: format!(
+                    "Firmware targets device type 0x{:04X}, not the nRF52840 (0x{:04X})",
+                    device_type, NRF52840_DEVICE_TYPE
+                ),
+            },
+            Some(device) => Self {
+                compatible: true,
+                reason: format!("Compatible with {}", device.display_label()),
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dfu::{DfuResult, DfuTransport, HciDfuProtocol};
+    use crate::settings::{CustomProfile, ScheduleWindow};
 
     #[test]
     fn role_config_failure_does_not_trigger_reflash() {
@@ -766,11 +2485,593 @@ mod tests {
         let event = DfuProgressEvent::from(stage);
 
         assert_eq!(event.stage, "uploading");
+        assert_eq!(event.message_key, "dfu.stage.uploading");
         assert_eq!(event.sent, Some(50000));
         assert_eq!(event.total, Some(100000));
         assert!(event.percent > 0.0);
     }
 
+    fn both_targets() -> Vec<FlashTarget> {
+        vec![
+            FlashTarget {
+                port: "/dev/cu.usbmodem1".to_string(),
+                role: "PRIMARY".to_string(),
+            },
+            FlashTarget {
+                port: "/dev/cu.usbmodem2".to_string(),
+                role: "SECONDARY".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_parse_device_role() {
+        assert_eq!(parse_device_role("primary").unwrap(), "PRIMARY");
+        assert_eq!(parse_device_role(" SECONDARY ").unwrap(), "SECONDARY");
+        assert!(parse_device_role("TERTIARY")
+            .unwrap_err()
+            .contains("TERTIARY"));
+        assert!(parse_device_role("").is_err());
+    }
+
+    #[test]
+    fn test_device_info_from_partial_answers() {
+        let info = DeviceInfo::from_answers(vec![
+            QueryAnswer::Value("2.3.1".to_string()),
+            QueryAnswer::Rejected("[ERROR] Unknown command: GET_ROLE".to_string()),
+            QueryAnswer::TimedOut,
+        ]);
+
+        assert_eq!(
+            info,
+            DeviceInfo {
+                firmware_version: Some("2.3.1".to_string()),
+                role: None,
+                profile: None,
+                timed_out: vec!["GET_PROFILE".to_string()],
+                unsupported: vec!["GET_ROLE".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flash_in_sequence_flashes_in_order() {
+        let mut flashed = Vec::new();
+        let outcomes = flash_in_sequence(
+            &both_targets(),
+            |index, target| {
+                flashed.push((index, target.role));
+                async { Ok(()) }
+            },
+            |_| false,
+        )
+        .await;
+
+        assert_eq!(
+            flashed,
+            vec![(0, "PRIMARY".to_string()), (1, "SECONDARY".to_string())]
+        );
+        assert!(outcomes
+            .iter()
+            .all(|o| o.status == DeviceFlashStatus::Success));
+        assert_eq!(outcomes[1].device_label, "SECONDARY");
+        assert_eq!(outcomes[1].port, "/dev/cu.usbmodem2");
+    }
+
+    #[tokio::test]
+    async fn test_flash_in_sequence_skips_after_failure() {
+        let mut calls = 0;
+        let outcomes = flash_in_sequence(
+            &both_targets(),
+            |_, _| {
+                calls += 1;
+                async { Err(DfuError::BootloaderTimeout { timeout_ms: 30000 }.into()) }
+            },
+            |_| false,
+        )
+        .await;
+
+        assert_eq!(calls, 1);
+        assert_eq!(
+            outcomes[0].status,
+            DeviceFlashStatus::Failed {
+                error: "Bootloader not found within 30000ms".to_string(),
+                message_key: "dfu.error.bootloader_timeout",
+            }
+        );
+        assert_eq!(
+            outcomes[1].status,
+            DeviceFlashStatus::Skipped {
+                reason: "PRIMARY device failed to update".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flash_in_sequence_cancel_skips_remaining() {
+        let cancelled = std::cell::Cell::new(false);
+        let outcomes = flash_in_sequence(
+            &both_targets(),
+            |_, _| {
+                // Cancelled while the primary is flashing, after it completed
+                cancelled.set(true);
+                async { Ok(()) }
+            },
+            |_| cancelled.get(),
+        )
+        .await;
+
+        assert_eq!(outcomes[0].status, DeviceFlashStatus::Success);
+        assert_eq!(
+            outcomes[1].status,
+            DeviceFlashStatus::Skipped {
+                reason: "Operation cancelled by user".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_setting_mismatches() {
+        let settings = AdvancedSettings {
+            disable_led_during_therapy: true,
+            jitter_percent: Some(15.0),
+            ..Default::default()
+        };
+        let reported: BTreeMap<String, String> = [
+            ("THERAPY_LED_OFF", "false"),
+            ("DEBUG", "FALSE"),
+            ("JITTER_PERCENT", "15"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let mismatches = setting_mismatches(&settings.to_pre_profile_commands(), &reported);
+        assert_eq!(
+            mismatches,
+            vec![
+                SettingMismatch {
+                    key: "THERAPY_LED_OFF".to_string(),
+                    expected: "true".to_string(),
+                    actual: Some("false".to_string()),
+                },
+                SettingMismatch {
+                    key: "LOW_POWER".to_string(),
+                    expected: "false".to_string(),
+                    actual: None,
+                },
+            ]
+        );
+
+        let json = serde_json::to_value(DeviceSettingsReadout::Unsupported {
+            reason: "[ERROR] Unknown command".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json["status"], "unsupported");
+    }
+
+    #[test]
+    fn test_remember_firmware_version() {
+        assert_eq!(known_firmware_version("VERSION-TEST"), None);
+
+        remember_firmware_version("VERSION-TEST", Some("2.0.3"));
+        assert_eq!(
+            known_firmware_version("VERSION-TEST").as_deref(),
+            Some("2.0.3")
+        );
+
+        // A flash or a query without an answer forgets it
+        remember_firmware_version("VERSION-TEST", None);
+        assert_eq!(known_firmware_version("VERSION-TEST"), None);
+    }
+
+    #[test]
+    fn test_describe_dfu_timing() {
+        let timing = DfuTimingConfig {
+            bootloader_timeout_ms: 20_000,
+            ack_timeout_ms: 8000,
+            max_packet_retries: 5,
+            max_config_retries: 5,
+        };
+
+        let header = describe_dfu_timing(&timing, Some(Duration::from_millis(599_700)));
+        assert_eq!(
+            header,
+            "DFU timing: bootloader timeout 20000ms, ACK timeout 8000ms, packet retries 5, \
+             config retries 5, deadline 600s"
+        );
+        assert!(describe_dfu_timing(&timing, None).ends_with("no deadline"));
+    }
+
+    #[test]
+    fn test_flash_deadline() {
+        let now = Instant::now();
+
+        assert_eq!(
+            flash_deadline(Some(600), now).unwrap(),
+            Some(now + Duration::from_secs(600))
+        );
+        assert_eq!(flash_deadline(None, now).unwrap(), None);
+        // Too far out to represent: no deadline rather than a panic
+        assert_eq!(flash_deadline(Some(u64::MAX), now).unwrap(), None);
+
+        let err = flash_deadline(Some(0), now).unwrap_err();
+        assert!(err.contains("at least 1 second"), "{}", err);
+    }
+
+    #[test]
+    fn test_preview_commands() {
+        let preview = |settings: &AdvancedSettings, profile: &str| {
+            preview_commands(settings, profile).map(|commands| {
+                commands
+                    .into_iter()
+                    .map(|c| format!("{} | {}", c.command, c.description))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let settings = AdvancedSettings {
+            disable_led_during_therapy: true,
+            jitter_percent: Some(18.0),
+            custom_profiles: vec![CustomProfile {
+                name: "STUDY_A".to_string(),
+                commands: vec!["MIRRORED:false".to_string()],
+                base_profile: None,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            preview(&settings, "noisy").unwrap(),
+            [
+                "THERAPY_LED_OFF:true | Disable LED During Therapy = true",
+                "DEBUG:false | Debug Mode = false",
+                "LOW_POWER:false | Low Power Mode = false",
+                "JITTER_PERCENT:18.0 | Jitter Percent = 18.0",
+                "SET_PROFILE:NOISY | Set therapy profile NOISY (device reboots)",
+            ]
+        );
+        assert_eq!(
+            preview(&settings, "STUDY_A").unwrap()[4..],
+            [
+                "MIRRORED:false | Mirrored = false",
+                "REBOOT | Reboot device"
+            ]
+        );
+        assert!(preview(&settings, "LOUD")
+            .unwrap_err()
+            .contains("Invalid profile"));
+
+        let settings = AdvancedSettings {
+            therapy_schedule: vec![ScheduleWindow {
+                start: "09:00".to_string(),
+                end: "11:00".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            preview(&settings, "regular").unwrap()[3..5],
+            [
+                "SCHEDULE_CLEAR | Clear Therapy Schedule",
+                "SCHEDULE_ADD:09:00,11:00 | Therapy Window = 09:00-11:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_device_flash_outcome_serialization() {
+        let outcome = DeviceFlashOutcome {
+            device_index: 1,
+            device_label: "SECONDARY".to_string(),
+            port: "COM4".to_string(),
+            status: DeviceFlashStatus::Failed {
+                error: "Device disconnected".to_string(),
+                message_key: "dfu.error.device_disconnected",
+            },
+        };
+        let json = serde_json::to_value(&outcome).unwrap();
+
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["error"], "Device disconnected");
+        assert_eq!(json["message_key"], "dfu.error.device_disconnected");
+        assert_eq!(json["device_index"], 1);
+
+        // Single-device events keep their original shape
+        let json = serde_json::to_value(DfuProgressEvent::log("hello".to_string())).unwrap();
+        assert!(json.get("device_index").is_none());
+    }
+
+    #[test]
+    fn test_progress_broadcast_payload() {
+        let clock = ProgressClock::new(Some(7));
+        let event = ProfileProgressEvent::new(&clock, "sending", 30.0, String::new());
+        let broadcast = ProgressBroadcast {
+            kind: "profile",
+            operation_id: event.operation_id,
+            event: &event,
+        };
+
+        let json = serde_json::to_value(&broadcast).unwrap();
+        assert_eq!(json["kind"], "profile");
+        assert_eq!(json["operation_id"], 7);
+        assert_eq!(json["event"]["stage"], "sending");
+    }
+
+    #[test]
+    fn test_cancel_outcome() {
+        let flash = PortOperation {
+            id: 4,
+            kind: "flash".to_string(),
+            port: "COM3".to_string(),
+            started_at: "2026-03-04T10:00:00Z".to_string(),
+        };
+        let secondary = PortOperation {
+            id: 5,
+            port: "COM4".to_string(),
+            ..flash.clone()
+        };
+        let flashes = [flash, secondary];
+        let uploading = || Some("uploading".to_string());
+
+        // Nothing running: report it instead of leaving the UI waiting.
+        // A UF2 copy or update install holds no flash lease and can't be
+        // cancelled either.
+        let outcome = cancel_outcome(&[], None, None, None);
+        assert!(!outcome.was_running);
+        assert!(!outcome.not_found);
+
+        let outcome = cancel_outcome(&flashes, None, Some(4), uploading());
+        assert!(outcome.was_running);
+        assert_eq!(outcome.operation_id, Some(4));
+        assert_eq!(outcome.stage_at_cancel.as_deref(), Some("uploading"));
+
+        // Without an ID, the device actually being flashed is reported
+        let outcome = cancel_outcome(&flashes, None, Some(5), uploading());
+        assert_eq!(outcome.operation_id, Some(5));
+
+        // The waiting secondary can be cancelled without the stage of the
+        // primary being reported as its own
+        let outcome = cancel_outcome(&flashes, Some(5), Some(4), uploading());
+        assert!(outcome.was_running);
+        assert_eq!(outcome.operation_id, Some(5));
+        assert_eq!(outcome.stage_at_cancel, None);
+
+        // A stale ID is not-found, which differs from not-running
+        let outcome = cancel_outcome(&flashes, Some(3), Some(4), uploading());
+        assert!(!outcome.was_running);
+        assert!(outcome.not_found);
+        assert_eq!(outcome.stage_at_cancel, None);
+    }
+
+    #[test]
+    fn test_prepare_device_command() {
+        assert_eq!(
+            prepare_device_command("  GET_VERSION \r\n", false),
+            Ok("GET_VERSION\n".to_string())
+        );
+        assert_eq!(
+            prepare_device_command("get_settings", false),
+            Ok("get_settings\n".to_string())
+        );
+        assert!(prepare_device_command("SET_PROFILE:NOISY", false).is_err());
+        assert_eq!(
+            prepare_device_command("SET_PROFILE:NOISY", true),
+            Ok("SET_PROFILE:NOISY\n".to_string())
+        );
+
+        // Malformed commands are refused even in developer mode
+        assert!(prepare_device_command("", true).is_err());
+        assert!(prepare_device_command("GET_VERSION\nSET_ROLE:PRIMARY", true).is_err());
+        assert!(prepare_device_command(&"A".repeat(MAX_DEVICE_COMMAND_LEN + 1), true).is_err());
+    }
+
+    #[test]
+    fn test_device_mode_on_port() {
+        let device = |port: &str, in_bootloader| Nrf52Device {
+            port: port.to_string(),
+            vid: 0x239A,
+            pid: if in_bootloader { 0x0029 } else { 0x8029 },
+            serial_number: None,
+            in_bootloader,
+            product_name: None,
+            manufacturer: None,
+        };
+        let devices = [device("COM3", true), device("COM4", false)];
+
+        let mode = |port| DeviceMode::on_port(&devices, port);
+
+        assert_eq!(mode("COM3"), DeviceMode::Bootloader);
+        assert_eq!(mode("COM4"), DeviceMode::Application);
+        assert_eq!(mode("COM5"), DeviceMode::NotFound);
+        assert_eq!(
+            serde_json::to_value(DeviceMode::NotFound).unwrap(),
+            "not_found"
+        );
+    }
+
+    #[test]
+    fn test_dfu_device_json_field_names() {
+        // The frontend reads these names directly; changing one breaks detection
+        let device = DfuDevice::from(Nrf52Device {
+            port: "COM3".to_string(),
+            vid: 0x239A,
+            pid: 0x802A,
+            serial_number: Some("ABC123".to_string()),
+            in_bootloader: false,
+            product_name: None,
+            manufacturer: None,
+        });
+        let json = serde_json::to_value(&device).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "port": "COM3",
+                "label": "BlueBuzzah (COM3)",
+                "vid": 0x239A,
+                "pid": 0x802A,
+                "in_bootloader": false,
+                "serial_number": "ABC123",
+                "board": "feather_nrf52840_sense",
+            })
+        );
+
+        // Unknown boards leave the field out rather than sending null
+        let json = serde_json::to_value(DfuDevice {
+            board: None,
+            ..device
+        })
+        .unwrap();
+        assert!(json.get("board").is_none());
+    }
+
+    #[test]
+    fn test_compatibility_verdict() {
+        let device = Nrf52Device {
+            port: "COM3".to_string(),
+            vid: 0x239A,
+            pid: 0x8029,
+            serial_number: None,
+            in_bootloader: false,
+            product_name: Some("Feather nRF52840 Express".to_string()),
+            manufacturer: None,
+        };
+
+        let verdict = CompatibilityVerdict::for_device(NRF52840_DEVICE_TYPE, Some(&device));
+        assert!(verdict.compatible);
+        assert_eq!(verdict.reason, "Compatible with Feather nRF52840 Express");
+
+        let verdict = CompatibilityVerdict::for_device(0x0041, Some(&device));
+        assert!(!verdict.compatible);
+        assert!(verdict.reason.contains("0x0041"));
+
+        assert!(!CompatibilityVerdict::for_device(NRF52840_DEVICE_TYPE, None).compatible);
+    }
+
+    #[test]
+    fn test_progress_clock_numbers_events_in_order() {
+        let clock = ProgressClock::new(Some(7));
+        let secondary = clock.for_operation(8);
+
+        let first = ProfileProgressEvent::new(&clock, "connecting", 10.0, String::new());
+        let second = ProfileProgressEvent::new(&secondary, "sending", 30.0, String::new());
+        let third = ProfileProgressEvent::new(&clock, "complete", 100.0, String::new());
+
+        // One sequence per command, even across the devices it flashes
+        assert_eq!((first.seq, second.seq, third.seq), (1, 2, 3));
+        assert_eq!(first.operation_id, Some(7));
+        assert_eq!(second.operation_id, Some(8));
+        assert!(first.emitted_at_ms > 0);
+        assert!(first.emitted_at_ms <= third.emitted_at_ms);
+
+        let json = serde_json::to_value(&third).unwrap();
+        assert_eq!(json["seq"], 3);
+        assert_eq!(json["operation_id"], 7);
+    }
+
+    #[test]
+    fn test_firmware_source_deserialization() {
+        let path: FirmwareSource = serde_json::from_str(r#""/tmp/firmware.zip""#).unwrap();
+        assert_eq!(path, FirmwareSource::Path("/tmp/firmware.zip".to_string()));
+
+        let cached: FirmwareSource = serde_json::from_str(r#"{"version": "v2.3.1"}"#).unwrap();
+        assert_eq!(
+            cached,
+            FirmwareSource::Cached {
+                version: "v2.3.1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_firmware_for_sideloaded_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cache = CacheManager::new(temp_dir.path()).unwrap();
+
+        let result =
+            resolve_firmware(&cache, FirmwareSource::Path("/tmp/custom.zip".to_string())).unwrap();
+        assert_eq!(result.firmware_path, "/tmp/custom.zip");
+        assert_eq!(result.firmware_version, None);
+
+        let error = resolve_firmware(
+            &cache,
+            FirmwareSource::Cached {
+                version: "v2.3.1".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(error, "Firmware version v2.3.1 is not cached");
+    }
+
+    #[test]
+    fn test_progress_events_without_role_config() {
+        // Without a role the flow goes straight from rebooting to complete;
+        // the final event must still reach 100% on its own.
+        let events: Vec<DfuProgressEvent> = [DfuStage::WaitingForReboot, DfuStage::Complete]
+            .into_iter()
+            .map(DfuProgressEvent::from)
+            .collect();
+
+        assert_eq!(events[0].stage, "rebooting");
+        assert_eq!(events[1].stage, "complete");
+        assert!(events[0].percent < events[1].percent);
+        assert_eq!(events[1].percent, 100.0);
+    }
+
+    /// Transport whose first write panics, standing in for a bug deep in the
+    /// protocol.
+    struct PanickingTransport;
+
+    impl DfuTransport for PanickingTransport {
+        fn write(&mut self, _data: &[u8]) -> DfuResult<()> {
+            panic!("transport exploded");
+        }
+        fn read(&mut self, _buffer: &mut [u8], _timeout_ms: u64) -> DfuResult<usize> {
+            Ok(0)
+        }
+        fn flush(&mut self) -> DfuResult<()> {
+            Ok(())
+        }
+        fn clear_input(&mut self) -> DfuResult<()> {
+            Ok(())
+        }
+        fn keep_alive(&mut self) -> DfuResult<()> {
+            Ok(())
+        }
+        fn is_healthy(&mut self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_progress_forwarding_survives_panic() {
+        let received = Arc::new(AtomicU64::new(0));
+        let counter = received.clone();
+        let channel = Channel::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+        let progress = ProgressSink::single(channel, ProgressClock::new(None));
+        let stamper = progress.clone();
+        let sent = Arc::new(AtomicU64::new(0));
+        let sent_by_work = sent.clone();
+
+        let result = with_progress_forwarding(progress, move |tx| {
+            let log = |msg: &str| {
+                sent_by_work.fetch_add(1, Ordering::SeqCst);
+                let _ = tx.send(stamper.stamp(DfuProgressEvent::log(msg.to_string())));
+            };
+            log("Connecting to bootloader");
+            HciDfuProtocol::new(PanickingTransport, log).send_start_dfu(1024)
+        })
+        .await;
+
+        // Getting here at all means the panic dropped the sender and the
+        // forwarding thread was joined after delivering everything sent
+        assert!(result.err().unwrap().contains("panicked"));
+        assert!(sent.load(Ordering::SeqCst) > 0);
+        assert_eq!(received.load(Ordering::SeqCst), sent.load(Ordering::SeqCst));
+    }
+
     #[test]
     fn test_dfu_guard_resets_on_drop() {
         // Ensure clean state
@@ -0,0 +1,232 @@
+//! Tauri command for the diagnostic bundle sent to support.
+//!
+//! Everything is gathered and written on a blocking thread; the bundle
+//! itself is assembled by `crate::diagnostics`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::Manager;
+
+use super::dfu::{DfuDevice, DFU_LOG_FILENAME};
+use crate::cache::CacheManager;
+use crate::dfu::find_nrf52_devices;
+use crate::diagnostics::{
+    bundle_path, DiagnosticsBundle, DiagnosticsSummary, ErrorStats, PortSnapshot, SystemInfo,
+    MAX_BUNDLE_BYTES,
+};
+use crate::history::FlashHistory;
+use crate::journal::{JournalEntry, OperationJournal};
+use crate::logging::log_files;
+use crate::settings::{AdvancedSettings, DeviceSettings, SettingsService, SettingsSnapshot};
+
+/// Saved settings as put in the bundle, without the GitHub token or proxy
+/// credentials.
+#[derive(Serialize)]
+struct RedactedSettings {
+    settings: AdvancedSettings,
+    devices: BTreeMap<String, DeviceSettings>,
+}
+
+impl From<&SettingsSnapshot> for RedactedSettings {
+    fn from(snapshot: &SettingsSnapshot) -> Self {
+        Self {
+            settings: snapshot.settings.without_secrets(),
+            devices: snapshot.devices.clone(),
+        }
+    }
+}
+
+/// Serial ports and BlueBuzzah devices connected when the bundle was made.
+#[derive(Serialize)]
+struct PortEnumeration {
+    serial_ports: Vec<PortSnapshot>,
+    devices: Vec<DfuDevice>,
+}
+
+/// Write a diagnostic bundle for support to `dest_path`.
+///
+/// The zip holds the app and OS versions, the connected ports, the saved
+/// settings with secrets removed, the firmware cache index (no firmware),
+/// the flash history with its error counts, interrupted flashes and the
+/// newest app and DFU log files, each listed in `manifest.json`. A part that can't be
+/// read is listed as skipped rather than failing the export.
+#[tauri::command]
+pub async fn export_diagnostics(
+    dest_path: String,
+    cache_manager: tauri::State<'_, CacheManager>,
+    history: tauri::State<'_, FlashHistory>,
+    settings_service: tauri::State<'_, SettingsService>,
+    journal: tauri::State<'_, OperationJournal>,
+    app_handle: tauri::AppHandle,
+) -> Result<DiagnosticsSummary, String> {
+    let cache_manager = cache_manager.inner().clone();
+    let history = history.inner().clone();
+    let settings = settings_service
+        .snapshot()
+        .map(|snapshot| RedactedSettings::from(snapshot.as_ref()));
+    let interrupted = journal.interrupted();
+    let log_dir = app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to find the log directory: {}", e))?;
+
+    // Get whatever is buffered into the log file first
+    log::logger().flush();
+    let path = bundle_path(Path::new(&dest_path));
+    log::info!("Exporting diagnostics to {}", path.display());
+
+    tokio::task::spawn_blocking(move || {
+        let mut bundle = DiagnosticsBundle::create(&path, MAX_BUNDLE_BYTES)?;
+        let written = write_bundle(
+            &mut bundle,
+            &cache_manager,
+            &history,
+            settings,
+            interrupted,
+            &log_dir,
+        );
+        match written {
+            Ok(()) => bundle.finish(),
+            Err(e) => {
+                bundle.discard();
+                Err(e)
+            }
+        }
+    })
+    .await
+    .map_err(|e| format!("Diagnostics task panicked: {}", e))?
+}
+
+/// Add each part to `bundle`, most useful first so the size cap only ever
+/// cuts the oldest logs.
+fn write_bundle(
+    bundle: &mut DiagnosticsBundle,
+    cache_manager: &CacheManager,
+    history: &FlashHistory,
+    settings: Result<RedactedSettings, String>,
+    interrupted: Vec<JournalEntry>,
+    log_dir: &Path,
+) -> Result<(), String> {
+    let records = history.load();
+
+    bundle.add_json(
+        "system.json",
+        "App and OS versions",
+        Ok(SystemInfo::current()),
+    )?;
+    bundle.add_json(
+        "ports.json",
+        "Serial ports and devices connected at export",
+        enumerate_ports(),
+    )?;
+    bundle.add_json("settings.json", "Saved settings, secrets removed", settings)?;
+    bundle.add_json(
+        "error_stats.json",
+        "Flash outcomes by error code",
+        Ok(ErrorStats::tally(&records)),
+    )?;
+    bundle.add_json(
+        "interrupted_operations.json",
+        "Flashes a previous run didn't finish",
+        Ok(interrupted),
+    )?;
+    bundle.add_json(
+        "cache_index.json",
+        "Firmware cache index (paths only)",
+        cache_manager
+            .load_index()
+            .map(|index| index.into_iter().collect::<BTreeMap<_, _>>()),
+    )?;
+    bundle.add_json("flash_history.json", "Flash history", Ok(records))?;
+
+    // The current app log, then the DFU command log, then older app logs
+    let mut app_logs = log_files(log_dir).into_iter();
+    let mut logs: Vec<(PathBuf, &str)> = app_logs
+        .next()
+        .map(|path| (path, "App log"))
+        .into_iter()
+        .collect();
+    let dfu_log = log_dir.join(DFU_LOG_FILENAME);
+    if dfu_log.is_file() {
+        logs.push((dfu_log, "DFU and developer command log"));
+    }
+    logs.extend(app_logs.map(|path| (path, "Older app log")));
+
+    for (path, description) in logs {
+        let name = log_entry_name(&path);
+        match fs::read(&path) {
+            Ok(contents) => bundle.add_log(&name, description, &contents)?,
+            Err(e) => bundle.skip(&name, e.to_string()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Every serial port the OS reports, and which are BlueBuzzah devices.
+fn enumerate_ports() -> Result<PortEnumeration, String> {
+    let serial_ports = serialport::available_ports()
+        .map_err(|e| format!("Failed to list serial ports: {}", e))?
+        .into_iter()
+        .map(PortSnapshot::from)
+        .collect();
+    let devices = find_nrf52_devices()
+        .into_iter()
+        .map(DfuDevice::from)
+        .collect();
+
+    Ok(PortEnumeration {
+        serial_ports,
+        devices,
+    })
+}
+
+/// Path of a log file inside the bundle.
+fn log_entry_name(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    format!("logs/{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_bundle_includes_dfu_log() {
+        let dir = tempdir().unwrap();
+        let log_dir = dir.path().join("logs");
+        fs::create_dir_all(&log_dir).unwrap();
+        fs::write(log_dir.join("updater.log"), "current\n").unwrap();
+        fs::write(log_dir.join("updater.1.log"), "older\n").unwrap();
+        fs::write(log_dir.join(DFU_LOG_FILENAME), "dfu\n").unwrap();
+
+        let path = dir.path().join("diagnostics.zip");
+        let mut bundle = DiagnosticsBundle::create(&path, MAX_BUNDLE_BYTES).unwrap();
+        write_bundle(
+            &mut bundle,
+            &CacheManager::new(dir.path()).unwrap(),
+            &FlashHistory::new(dir.path()),
+            Err("Settings unreadable".to_string()),
+            Vec::new(),
+            &log_dir,
+        )
+        .unwrap();
+        let summary = bundle.finish().unwrap();
+
+        let logs: Vec<_> = summary
+            .files
+            .iter()
+            .map(|file| file.name.as_str())
+            .filter(|name| name.starts_with("logs/"))
+            .collect();
+        assert_eq!(
+            logs,
+            ["logs/updater.log", "logs/dfu.log", "logs/updater.1.log"]
+        );
+        assert_eq!(summary.skipped[0].name, "settings.json");
+    }
+}
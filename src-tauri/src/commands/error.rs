@@ -0,0 +1,126 @@
+//! Error returned by commands whose failures the frontend translates.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::dfu::DfuError;
+use crate::port_lock::{PortBusyError, PortOperation};
+
+/// Message key for failures that don't come from the DFU layer.
+pub const GENERIC_ERROR_KEY: &str = "error.generic";
+
+/// Message key for a port that another operation holds.
+pub const PORT_BUSY_KEY: &str = "error.port_busy";
+
+/// A command failure with a stable key for translating its message.
+///
+/// Serialized as `{ message_key, code, message }`, plus `existing` for a
+/// busy port; the frontend looks the key up and falls back to the English
+/// `message`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandError {
+    /// Stable translation key, e.g. "dfu.error.timeout".
+    pub message_key: &'static str,
+    /// DFU error code (e.g. "DFU-021"), when the DFU layer reported one.
+    pub code: Option<&'static str>,
+    /// English message.
+    pub message: String,
+    /// The operation holding the port, when that is why the command failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing: Option<PortOperation>,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<DfuError> for CommandError {
+    fn from(error: DfuError) -> Self {
+        Self {
+            message_key: error.message_key(),
+            code: Some(error.error_code()),
+            message: error.to_string(),
+            existing: None,
+        }
+    }
+}
+
+impl From<PortBusyError> for CommandError {
+    fn from(error: PortBusyError) -> Self {
+        Self {
+            message_key: PORT_BUSY_KEY,
+            code: None,
+            message: error.to_string(),
+            existing: Some(error.existing),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self {
+            message_key: GENERIC_ERROR_KEY,
+            code: None,
+            message,
+            existing: None,
+        }
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dfu_error_keeps_its_key_and_code() {
+        let error = CommandError::from(DfuError::Timeout);
+
+        assert_eq!(error.message_key, "dfu.error.timeout");
+        assert_eq!(error.code, Some("DFU-021"));
+        assert_eq!(error.message, "Timeout waiting for ACK");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "message_key": "dfu.error.timeout",
+                "code": "DFU-021",
+                "message": "Timeout waiting for ACK",
+            })
+        );
+    }
+
+    #[test]
+    fn test_port_busy_carries_existing_operation() {
+        let existing = PortOperation {
+            id: 7,
+            kind: "flash".to_string(),
+            port: "COM3".to_string(),
+            started_at: "2026-01-01T00:00:00+00:00".to_string(),
+        };
+        let error = CommandError::from(PortBusyError {
+            existing: existing.clone(),
+        });
+
+        assert_eq!(error.message_key, PORT_BUSY_KEY);
+        assert_eq!(error.existing, Some(existing));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["existing"]["id"], 7);
+        assert_eq!(json["existing"]["started_at"], "2026-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_plain_message_gets_generic_key() {
+        let error = CommandError::from("Device not found");
+
+        assert_eq!(error.message_key, GENERIC_ERROR_KEY);
+        assert_eq!(error.code, None);
+        assert_eq!(error.to_string(), "Device not found");
+    }
+}
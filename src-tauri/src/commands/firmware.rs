@@ -1,73 +1,219 @@
 use std::fs;
-use std::path::Path;
-use tauri::Manager;
-use crate::cache::{CacheManager, CachedFirmwareMetadata, FirmwareCacheIndex};
+use std::path::{Path, PathBuf};
+use crate::cache::{
+    AssetKind, CacheCleanupResult, CacheClearResult, CacheGarbageReport, CacheMaintenanceOptions,
+    CacheManager, CacheMigrationProgressEvent, CacheStats, CachedFirmwareMetadata,
+    FirmwareCacheIndex,
+};
+use crate::download::{
+    active_partials, asset_cache_key, checksum_matches, clean_stale_partials, download_to_file,
+    partial_path, range_header_value, retry_delay, sleep_unless_cancelled, staging_key,
+    DownloadBody, DownloadFailure, DownloadHandle, DownloadOutcome, DownloadProgressEvent,
+    DownloadResponse, DownloadSource, ReleaseAssetProgressEvent, ReleaseAssetRequest,
+    ReleaseDownloadOutcome, DOWNLOAD_READ_TIMEOUT, MAX_DOWNLOAD_RETRIES, PROGRESS_INTERVAL_BYTES,
+    STALE_PARTIAL_MAX_AGE,
+};
+use crate::paths::AppDataDir;
+use crate::proxy::{build_http_client, redact_credentials, ProxySettings};
+use crate::releases::{
+    annotate_cached, cached_only_releases, fetch_releases, load_releases_cache, resolve_fetch,
+    save_releases_cache, FirmwareReleaseInfo, ReleaseChannel, ReleaseListing, RELEASES_API_URL,
+    RELEASES_REQUEST_TIMEOUT,
+};
+use crate::settings::SettingsService;
+use crate::sideload;
 use chrono;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+use tauri::ipc::{Channel, JavaScriptChannelId};
 use tauri_plugin_http::reqwest;
 
+/// User agent for GitHub requests (the API rejects requests without one).
+pub(crate) const USER_AGENT: &str = concat!("BlueBuzzah-Updater/", env!("CARGO_PKG_VERSION"));
+
+/// List firmware releases for a channel, plus cached versions GitHub no longer lists.
+///
+/// `channel` defaults to the one saved in advanced settings. Uses the GitHub
+/// token from settings when one is configured and marks releases that are
+/// already in the firmware cache.
+///
+/// The last response is kept with its ETag: unchanged lists cost a 304, and
+/// when GitHub is unreachable or rate limited the cached copy is returned
+/// with `stale` set.
+#[tauri::command]
+pub async fn list_firmware_releases(
+    channel: Option<ReleaseChannel>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+    settings_service: tauri::State<'_, SettingsService>,
+) -> Result<ReleaseListing, String> {
+    let app_data_dir = app_data_dir.path();
+
+    let settings = settings_service.settings_or_default();
+    let channel = channel.unwrap_or(settings.release_channel);
+
+    let client = build_http_client(
+        reqwest::Client::builder()
+            .timeout(RELEASES_REQUEST_TIMEOUT)
+            .user_agent(USER_AGENT),
+        &settings.proxy,
+    )?;
+
+    let cached = load_releases_cache(app_data_dir);
+    let etag = cached.as_ref().and_then(|cache| cache.etag.clone());
+    let result = fetch_releases(&client, settings.github_token.as_deref(), etag.as_deref())
+        .await
+        .map_err(|e| redact_credentials(&e, &settings.proxy));
+    let resolved = resolve_fetch(result, cached, &chrono::Utc::now().to_rfc3339())?;
+
+    if resolved.updated {
+        if let Err(e) = save_releases_cache(app_data_dir, &resolved.cache) {
+            log::warn!("Failed to save releases cache: {}", e);
+        }
+    }
+
+    let mut releases: Vec<FirmwareReleaseInfo> = resolved
+        .cache
+        .releases
+        .into_iter()
+        .filter(|release| channel.includes(ReleaseChannel::of(release.prerelease)))
+        .map(FirmwareReleaseInfo::from)
+        .collect();
+
+    let index = with_cache_index(&cache_manager, |cache| cache.load_index()).await?;
+    annotate_cached(&mut releases, &index);
+    let cached_only = cached_only_releases(&index, &releases, channel);
+    releases.extend(cached_only);
+
+    Ok(ReleaseListing {
+        releases,
+        stale: resolved.stale,
+        fetched_at: resolved.cache.fetched_at,
+    })
+}
+
+/// Check that GitHub is reachable with the given proxy settings.
+///
+/// `proxy` defaults to the saved settings, so the UI can test edits before
+/// saving them. Returns a short success message; errors never include the
+/// proxy credentials.
 #[tauri::command]
+pub async fn test_proxy_connection(
+    proxy: Option<ProxySettings>,
+    settings_service: tauri::State<'_, SettingsService>,
+) -> Result<String, String> {
+    let proxy = match proxy {
+        Some(proxy) => proxy,
+        None => settings_service.settings_or_default().proxy,
+    };
+
+    let client = build_http_client(
+        reqwest::Client::builder()
+            .timeout(RELEASES_REQUEST_TIMEOUT)
+            .user_agent(USER_AGENT),
+        &proxy,
+    )?;
+
+    let response = client
+        .head(RELEASES_API_URL)
+        .send()
+        .await
+        .map_err(|e| redact_credentials(&format!("Connection failed: {}", e), &proxy))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+        return Err("Proxy rejected the credentials (HTTP 407)".to_string());
+    }
+
+    // Any HTTP response from GitHub (even a rate limit) proves the route works
+    Ok(format!("Connected to GitHub (HTTP {})", status.as_u16()))
+}
+
+/// Download a firmware zip into the cache.
+///
+/// The download can be stopped with `cancel_download(download_id)`;
+/// `download_id` defaults to the version. A cancelled download returns
+/// `DownloadOutcome::Cancelled`, keeps its partial file for a later resume
+/// and is never added to the cache index. Downloading a cached version
+/// again replaces its zip but keeps its pin. `channel` is the release's
+/// channel, recorded with the cache entry.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn download_firmware(
     url: String,
     version: String,
     tag_name: String,
     published_at: String,
     release_notes: String,
-    app_handle: tauri::AppHandle,
-) -> Result<String, String> {
+    expected_sha256: Option<String>,
+    channel: ReleaseChannel,
+    download_id: Option<String>,
+    progress: Channel<DownloadProgressEvent>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+    settings_service: tauri::State<'_, SettingsService>,
+) -> Result<DownloadOutcome, String> {
+    // Registered until this function returns, so cancel_download can find it
+    let download = DownloadHandle::register(download_id.as_deref().unwrap_or(&version))?;
+
     // Get app data directory
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let app_data_dir = app_data_dir.path();
 
     let firmware_dir = app_data_dir.join("firmware");
     fs::create_dir_all(&firmware_dir)
         .map_err(|e| format!("Failed to create firmware directory: {}", e))?;
 
     let firmware_file = firmware_dir.join(format!("{}.zip", version));
-    let tmp_file = firmware_dir.join(format!("{}.zip.tmp", version));
-
-    // Download the file with connect and total timeouts
-    let client = reqwest::Client::builder()
-        .connect_timeout(Duration::from_secs(30))
-        .timeout(Duration::from_secs(120))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let partial_file = partial_path(&firmware_dir, &version);
+    download.writes_to(&partial_file);
 
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download firmware: {}", e))?;
+    // Download the file with connect and read timeouts, through the configured proxy
+    let settings = settings_service.settings_or_default();
+    let proxy = settings.proxy;
+    let rate_limit = settings.max_download_bytes_per_sec;
+    let client = build_http_client(
+        reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(30))
+            .read_timeout(DOWNLOAD_READ_TIMEOUT),
+        &proxy,
+    )?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "Firmware download failed with HTTP status {}",
-            response.status()
-        ));
-    }
-
-    let bytes = response
-        .bytes()
+    // Retry notifications only; download_firmware doesn't report bytes
+    let mut on_event = |event: TransferEvent| {
+        if let TransferEvent::Retrying { attempt } = event {
+            let _ = progress.send(DownloadProgressEvent {
+                stage: "retrying".to_string(),
+                attempt,
+                max_attempts: MAX_DOWNLOAD_RETRIES,
+                message: format!(
+                    "Connection lost, retrying ({}/{})...",
+                    attempt, MAX_DOWNLOAD_RETRIES
+                ),
+                rate_limit_bytes_per_sec: (rate_limit > 0).then_some(rate_limit),
+            });
+        }
+    };
+    let transfer = Transfer {
+        source: HttpSource {
+            client: &client,
+            url: &url,
+        },
+        partial_file: &partial_file,
+        download: &download,
+        proxy: &proxy,
+        max_bytes_per_sec: rate_limit,
+    };
+    let sha256_hash = match transfer
+        .run(expected_sha256.as_deref(), &mut on_event)
         .await
-        .map_err(|e| format!("Failed to read firmware data: {}", e))?;
-
-    // Write to temp file first to prevent partial downloads from corrupting cache
-    fs::write(&tmp_file, &bytes).map_err(|e| {
-        let _ = fs::remove_file(&tmp_file);
-        format!("Failed to write firmware file: {}", e)
-    })?;
-
-    // Calculate SHA256 hash on the temp file
-    let sha256_hash = CacheManager::calculate_sha256(&tmp_file).map_err(|e| {
-        let _ = fs::remove_file(&tmp_file);
-        format!("Failed to calculate hash: {}", e)
-    })?;
+    {
+        Ok(sha256_hash) => sha256_hash,
+        Err(DownloadFailure::Cancelled) => return Ok(DownloadOutcome::Cancelled),
+        Err(failure) => return Err(redact_credentials(&failure.to_string(), &proxy)),
+    };
 
-    // Atomic rename from temp to final path
-    fs::rename(&tmp_file, &firmware_file).map_err(|e| {
-        let _ = fs::remove_file(&tmp_file);
+    // Atomic rename from partial to final path
+    fs::rename(&partial_file, &firmware_file).map_err(|e| {
+        let _ = fs::remove_file(&partial_file);
         format!("Failed to finalize firmware file: {}", e)
     })?;
 
@@ -77,7 +223,6 @@ pub async fn download_firmware(
         .len();
 
     // Update cache index (no extraction needed - DFU reads directly from zip)
-    let cache_manager = CacheManager::new(&app_data_dir)?;
     let metadata = CachedFirmwareMetadata {
         version: version.clone(),
         tag_name,
@@ -87,26 +232,410 @@ pub async fn download_firmware(
         file_size,
         published_at,
         release_notes,
+        checksum_verified: expected_sha256.is_some(),
+        locally_imported: false,
+        pinned: false,
+        last_used_at: None,
+        channel,
+        asset_kind: AssetKind::detect(&firmware_file),
     };
-    cache_manager.update_entry(metadata)?;
+    // A version downloaded again keeps its pin
+    with_cache_index(&cache_manager, move |cache| {
+        cache.update_entries(vec![metadata])
+    })
+    .await?;
 
     // Return the zip path for DFU flashing
-    Ok(firmware_file.to_string_lossy().to_string())
+    Ok(DownloadOutcome::Completed {
+        path: firmware_file.to_string_lossy().to_string(),
+    })
+}
+
+/// Cancel an in-progress `download_firmware` call.
+///
+/// Returns false if no download with that ID is running.
+#[tauri::command]
+pub async fn cancel_download(download_id: String) -> Result<bool, String> {
+    Ok(crate::download::cancel_download(&download_id))
+}
+
+/// Download several assets of one release as a set.
+///
+/// Assets download one after another with combined progress. Nothing is
+/// added to the cache index until every asset has downloaded and verified;
+/// if one fails or the batch is cancelled, the assets already downloaded
+/// are discarded so the cache never holds half a release. Cancel with
+/// `cancel_download(download_id)`; `download_id` defaults to the tag.
+/// `channel` is the release's channel, recorded with each cache entry.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_release_assets(
+    release_tag: String,
+    version: String,
+    published_at: String,
+    release_notes: String,
+    channel: ReleaseChannel,
+    assets: Vec<ReleaseAssetRequest>,
+    download_id: Option<String>,
+    progress: Channel<ReleaseAssetProgressEvent>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+    settings_service: tauri::State<'_, SettingsService>,
+) -> Result<ReleaseDownloadOutcome, String> {
+    if assets.is_empty() {
+        return Err("No release assets to download".to_string());
+    }
+    if let Some(asset) = assets
+        .iter()
+        .find(|asset| !sideload::is_valid_version(&staging_key(&version, &asset.name)))
+    {
+        return Err(format!("Invalid release asset name: {}", asset.name));
+    }
+
+    let download = DownloadHandle::register(download_id.as_deref().unwrap_or(&release_tag))?;
+
+    let app_data_dir = app_data_dir.path();
+
+    let firmware_dir = app_data_dir.join("firmware");
+    fs::create_dir_all(&firmware_dir)
+        .map_err(|e| format!("Failed to create firmware directory: {}", e))?;
+
+    let settings = settings_service.settings_or_default();
+    let proxy = settings.proxy;
+    let rate_limit = settings.max_download_bytes_per_sec;
+    let client = build_http_client(
+        reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(30))
+            .read_timeout(DOWNLOAD_READ_TIMEOUT),
+        &proxy,
+    )?;
+
+    let asset_count = assets.len();
+    let send = |stage: &str, asset_index: usize, bytes: u64, total: Option<u64>, message| {
+        let _ = progress.send(ReleaseAssetProgressEvent {
+            stage: stage.to_string(),
+            asset_index,
+            asset_count,
+            asset_name: assets[asset_index].name.clone(),
+            bytes_downloaded: bytes,
+            total_bytes: total,
+            message,
+            rate_limit_bytes_per_sec: (rate_limit > 0).then_some(rate_limit),
+        });
+    };
+
+    // Download every asset to its partial file before touching the cache
+    let mut staged: Vec<(PathBuf, String)> = Vec::new();
+    for (asset_index, asset) in assets.iter().enumerate() {
+        let partial_file = partial_path(&firmware_dir, &staging_key(&version, &asset.name));
+        download.writes_to(&partial_file);
+        let message = format!(
+            "Downloading {} ({}/{})...",
+            asset.name,
+            asset_index + 1,
+            asset_count
+        );
+        send("downloading", asset_index, 0, None, message.clone());
+
+        let mut last_reported = 0;
+        let mut on_event = |event: TransferEvent| match event {
+            TransferEvent::Retrying { attempt } => send(
+                "retrying",
+                asset_index,
+                last_reported,
+                None,
+                format!(
+                    "Connection lost, retrying ({}/{})...",
+                    attempt, MAX_DOWNLOAD_RETRIES
+                ),
+            ),
+            TransferEvent::Bytes { downloaded, total } => {
+                if downloaded - last_reported >= PROGRESS_INTERVAL_BYTES
+                    || Some(downloaded) == total
+                {
+                    last_reported = downloaded;
+                    send(
+                        "downloading",
+                        asset_index,
+                        downloaded,
+                        total,
+                        message.clone(),
+                    );
+                }
+            }
+        };
+        let transfer = Transfer {
+            source: HttpSource {
+                client: &client,
+                url: &asset.download_url,
+            },
+            partial_file: &partial_file,
+            download: &download,
+            proxy: &proxy,
+            max_bytes_per_sec: rate_limit,
+        };
+
+        match transfer
+            .run(asset.expected_sha256.as_deref(), &mut on_event)
+            .await
+        {
+            Ok(sha256_hash) => staged.push((partial_file, sha256_hash)),
+            Err(failure) => {
+                discard_files(staged.iter().map(|(path, _)| path));
+                return match failure {
+                    DownloadFailure::Cancelled => Ok(ReleaseDownloadOutcome::Cancelled),
+                    failure => Err(format!(
+                        "Failed to download {}: {}",
+                        asset.name,
+                        redact_credentials(&failure.to_string(), &proxy)
+                    )),
+                };
+            }
+        }
+    }
+
+    // Key each asset by what it contains; two assets must not share an entry
+    let kinds: Vec<AssetKind> = staged
+        .iter()
+        .map(|(path, _)| AssetKind::detect(path))
+        .collect();
+    let keys: Vec<String> = assets
+        .iter()
+        .zip(&kinds)
+        .map(|(asset, kind)| asset_cache_key(&version, &asset.name, *kind))
+        .collect();
+    if let Some(duplicate) = keys
+        .iter()
+        .enumerate()
+        .find(|(i, key)| keys[..*i].contains(key))
+        .map(|(_, key)| key)
+    {
+        discard_files(staged.iter().map(|(path, _)| path));
+        return Err(format!(
+            "Release {} has more than one asset for cache entry {}",
+            release_tag, duplicate
+        ));
+    }
+
+    // Promote the whole set, then index it in one write
+    let mut entries = Vec::new();
+    let mut promoted: Vec<PathBuf> = Vec::new();
+    for (i, (partial_file, sha256_hash)) in staged.iter().enumerate() {
+        let firmware_file = firmware_dir.join(format!("{}.zip", keys[i]));
+        if let Err(e) = fs::rename(partial_file, &firmware_file) {
+            let unpromoted = staged[i..].iter().map(|(path, _)| path);
+            discard_files(promoted.iter().chain(unpromoted));
+            return Err(format!("Failed to finalize firmware file: {}", e));
+        }
+        promoted.push(firmware_file.clone());
+
+        let file_size = fs::metadata(&firmware_file)
+            .map_err(|e| format!("Failed to get file metadata: {}", e))?
+            .len();
+        entries.push(CachedFirmwareMetadata {
+            version: keys[i].clone(),
+            tag_name: release_tag.clone(),
+            sha256_hash: sha256_hash.clone(),
+            zip_path: firmware_file.to_string_lossy().to_string(),
+            downloaded_at: chrono::Utc::now().to_rfc3339(),
+            file_size,
+            published_at: published_at.clone(),
+            release_notes: release_notes.clone(),
+            checksum_verified: assets[i].expected_sha256.is_some(),
+            locally_imported: false,
+            pinned: false,
+            last_used_at: None,
+            channel,
+            asset_kind: kinds[i],
+        });
+    }
+
+    let entries = with_cache_index(&cache_manager, move |cache| cache.update_entries(entries))
+        .await
+        .inspect_err(|_| discard_files(promoted.iter()))?;
+
+    send(
+        "complete",
+        asset_count - 1,
+        0,
+        None,
+        format!("Downloaded {} assets", asset_count),
+    );
+    Ok(ReleaseDownloadOutcome::Completed { assets: entries })
+}
+
+/// Best-effort removal of files from an abandoned download batch.
+fn discard_files<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) {
+    for path in paths {
+        if let Err(e) = fs::remove_file(path) {
+            log::warn!("Failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Progress reported while a [`Transfer`] runs.
+enum TransferEvent {
+    /// A transient failure is about to be retried (1-based attempt).
+    Retrying { attempt: u32 },
+    /// Bytes now in the partial file, and the final size if known.
+    Bytes { downloaded: u64, total: Option<u64> },
+}
+
+/// One file download into a partial file, shared by the download commands.
+struct Transfer<'a> {
+    source: HttpSource<'a>,
+    partial_file: &'a Path,
+    download: &'a DownloadHandle,
+    proxy: &'a ProxySettings,
+    /// Bandwidth cap in bytes per second; 0 means unlimited.
+    max_bytes_per_sec: u64,
+}
+
+impl Transfer<'_> {
+    /// Download with retries, then check the hash against `expected_sha256`.
+    ///
+    /// Transient failures are retried with backoff, each retry resuming
+    /// from the partial file. Returns the SHA256 of the complete file, which
+    /// is left at `partial_file` for the caller to promote. A bad download
+    /// is deleted; a cancelled one is kept for resume.
+    async fn run(
+        &self,
+        expected_sha256: Option<&str>,
+        on_event: &mut impl FnMut(TransferEvent),
+    ) -> Result<String, DownloadFailure> {
+        let mut retry = 0;
+        let sha256_hash = loop {
+            let mut on_progress = |downloaded, total| {
+                on_event(TransferEvent::Bytes { downloaded, total });
+            };
+            match download_to_file(
+                &self.source,
+                self.partial_file,
+                self.max_bytes_per_sec,
+                &mut on_progress,
+                || self.download.is_cancelled(),
+            )
+            .await
+            {
+                Ok(sha256_hash) => break sha256_hash,
+                Err(failure) if failure.is_retriable() && retry < MAX_DOWNLOAD_RETRIES => {
+                    retry += 1;
+                    let delay = retry_delay(retry);
+                    log::warn!(
+                        "{} - retrying ({}/{}) in {}s",
+                        redact_credentials(&failure.to_string(), self.proxy),
+                        retry,
+                        MAX_DOWNLOAD_RETRIES,
+                        delay.as_secs()
+                    );
+                    on_event(TransferEvent::Retrying { attempt: retry });
+                    if !sleep_unless_cancelled(delay, &|| self.download.is_cancelled()).await {
+                        return Err(DownloadFailure::Cancelled);
+                    }
+                }
+                Err(failure) => return Err(failure),
+            }
+        };
+
+        // Last chance to honor a cancel before the download reaches the cache
+        if self.download.is_cancelled() {
+            return Err(DownloadFailure::Cancelled);
+        }
+
+        // Reject corrupted or tampered downloads before they reach the cache
+        if let Some(expected) = expected_sha256 {
+            if !checksum_matches(&sha256_hash, expected) {
+                let _ = fs::remove_file(self.partial_file);
+                return Err(DownloadFailure::Fatal(format!(
+                    "Checksum mismatch: expected {}, got {}",
+                    expected.trim(),
+                    sha256_hash
+                )));
+            }
+        }
+
+        Ok(sha256_hash)
+    }
+}
+
+/// [`DownloadSource`] backed by the app's HTTP client.
+struct HttpSource<'a> {
+    client: &'a reqwest::Client,
+    url: &'a str,
 }
 
+impl DownloadSource for HttpSource<'_> {
+    type Body = reqwest::Response;
+
+    async fn request(&self, offset: u64) -> Result<DownloadResponse<Self::Body>, DownloadFailure> {
+        let mut request = self.client.get(self.url);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, range_header_value(offset));
+        }
+
+        // Connection, DNS, TLS and timeout errors are all network-class
+        let response = request.send().await.map_err(|e| {
+            DownloadFailure::Transient(format!("Failed to download firmware: {}", e))
+        })?;
+        let content_range = response
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+
+        Ok(DownloadResponse {
+            status: response.status().as_u16(),
+            content_length: response.content_length(),
+            content_range,
+            body: response,
+        })
+    }
+}
+
+impl DownloadBody for reqwest::Response {
+    async fn next_chunk(&mut self) -> Result<Option<impl AsRef<[u8]>>, DownloadFailure> {
+        self.chunk().await.map_err(|e| {
+            DownloadFailure::Transient(format!("Failed to read firmware data: {}", e))
+        })
+    }
+}
+
+/// Resolve a cached version to its zip path, or `None` if it isn't cached.
+///
+/// With `kind`, a cached zip of a different asset kind is an error, so the
+/// DFU flow can't be handed a CircuitPython bundle.
+///
+/// Unless `verify` is false, the zip is re-hashed and its contents checked
+/// first. A corrupt entry is deleted and reported as `None` so the caller
+/// downloads it again instead of failing mid-flash.
 #[tauri::command]
 pub async fn get_cached_firmware(
     version: String,
-    app_handle: tauri::AppHandle,
+    kind: Option<AssetKind>,
+    verify: Option<bool>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
 ) -> Result<Option<String>, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let app_data_dir = app_data_dir.path().to_path_buf();
+    let verify = verify.unwrap_or(true);
+
+    // Hashing the zip and updating the index are blocking file I/O
+    with_cache_index(&cache_manager, move |cache| {
+        resolve_cached_firmware(cache, &app_data_dir, &version, kind, verify)
+    })
+    .await
+}
 
+/// Blocking body of `get_cached_firmware`.
+fn resolve_cached_firmware(
+    cache_manager: &CacheManager,
+    app_data_dir: &Path,
+    version: &str,
+    kind: Option<AssetKind>,
+    verify: bool,
+) -> Result<Option<String>, String> {
     // Check cache index first
-    let cache_manager = CacheManager::new(&app_data_dir)?;
-    let entry = cache_manager.get_entry(&version)?;
+    let entry = cache_manager.get_entry(version)?;
 
     match entry {
         Some(metadata) => {
@@ -114,18 +643,50 @@ pub async fn get_cached_firmware(
             let zip_path = Path::new(&metadata.zip_path);
 
             if zip_path.exists() {
+                if verify {
+                    if let Err(reason) = metadata.check_integrity() {
+                        log::warn!(
+                            "Cached firmware {} failed integrity check ({}); removing it",
+                            version,
+                            reason
+                        );
+                        let firmware_dir = app_data_dir.join("firmware");
+                        cache_manager.delete_version(&firmware_dir, version)?;
+                        return Ok(None);
+                    }
+                }
+
+                if let Some(kind) = kind {
+                    // Entries from older indexes haven't been classified yet
+                    let actual = match metadata.asset_kind {
+                        AssetKind::Unknown => AssetKind::detect(zip_path),
+                        known => known,
+                    };
+                    check_asset_kind(version, kind, actual)?;
+                }
+
+                // Resolving a version counts as using it for age-based cleanup
+                if let Err(e) = cache_manager.mark_used(version) {
+                    log::warn!("Failed to record use of {}: {}", version, e);
+                }
+
                 // Return zip path for DFU flashing
                 Ok(Some(metadata.zip_path))
             } else {
                 // Files missing, remove from cache index
-                cache_manager.remove_entry(&version)?;
+                cache_manager.remove_entry(version)?;
                 Ok(None)
             }
         }
         None => {
             // Fallback: check if zip file exists (for backwards compatibility)
-            let firmware_zip = app_data_dir.join("firmware").join(format!("{}.zip", version));
+            let firmware_zip = app_data_dir
+                .join("firmware")
+                .join(format!("{}.zip", version));
             if firmware_zip.exists() {
+                if let Some(kind) = kind {
+                    check_asset_kind(version, kind, AssetKind::detect(&firmware_zip))?;
+                }
                 Ok(Some(firmware_zip.to_string_lossy().to_string()))
             } else {
                 Ok(None)
@@ -134,6 +695,48 @@ pub async fn get_cached_firmware(
     }
 }
 
+/// Newest cached version, compared semver-style rather than as strings.
+///
+/// Lets offline users flash the latest firmware they have. `kind` and
+/// `channel` narrow the candidates; returns `None` when nothing matches.
+#[tauri::command]
+pub async fn get_latest_cached_version(
+    kind: Option<AssetKind>,
+    channel: Option<ReleaseChannel>,
+    cache_manager: tauri::State<'_, CacheManager>,
+) -> Result<Option<CachedFirmwareMetadata>, String> {
+    with_cache_index(&cache_manager, move |cache| cache.latest_entry(kind, channel)).await
+}
+
+/// Run `f` against the cache index on a blocking thread.
+///
+/// Index mutations are written to disk under the index lock, and clearing
+/// the cache holds that lock while it deletes files, so commands never
+/// touch the index from the async runtime.
+async fn with_cache_index<T: Send + 'static>(
+    cache_manager: &CacheManager,
+    f: impl FnOnce(&CacheManager) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let cache_manager = cache_manager.clone();
+    tokio::task::spawn_blocking(move || f(&cache_manager))
+        .await
+        .map_err(|e| format!("Cache task panicked: {}", e))?
+}
+
+/// Error unless a cached zip of kind `actual` satisfies a request for `wanted`.
+fn check_asset_kind(version: &str, wanted: AssetKind, actual: AssetKind) -> Result<(), String> {
+    if actual == wanted {
+        Ok(())
+    } else {
+        Err(format!(
+            "Cached firmware {} is {}, not {}",
+            version,
+            actual.label(),
+            wanted.label()
+        ))
+    }
+}
+
 #[tauri::command]
 pub async fn calculate_sha256(
     file_path: String,
@@ -144,108 +747,382 @@ pub async fn calculate_sha256(
 
 #[tauri::command]
 pub async fn get_cache_index(
-    app_handle: tauri::AppHandle,
+    cache_manager: tauri::State<'_, CacheManager>,
 ) -> Result<FirmwareCacheIndex, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    with_cache_index(&cache_manager, |cache| cache.load_index()).await
+}
+
+#[tauri::command]
+pub async fn get_cache_stats(
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<CacheStats, String> {
+    let app_data_dir = app_data_dir.path().to_path_buf();
+
+    // Walking extracted directories can be slow - keep it off the async runtime
+    let cache_manager = cache_manager.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let firmware_dir = app_data_dir.join("firmware");
+        cache_manager.stats(&firmware_dir)
+    })
+    .await
+    .map_err(|e| format!("Cache stats task panicked: {}", e))?
+}
+
+/// Import a local firmware.zip or `.bbfw` bundle into the cache.
+///
+/// A version that is already cached is only replaced with `overwrite`;
+/// pinned versions have to be unpinned first.
+#[tauri::command]
+pub async fn import_firmware_zip(
+    path: String,
+    version: Option<String>,
+    release_notes: Option<String>,
+    overwrite: Option<bool>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<CachedFirmwareMetadata, String> {
+    let app_data_dir = app_data_dir.path().to_path_buf();
 
-    let cache_manager = CacheManager::new(&app_data_dir)?;
-    cache_manager.load_index()
+    // Validating, copying and hashing the zip is blocking file I/O
+    let cache_manager = cache_manager.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        sideload::import_firmware(
+            &app_data_dir,
+            &cache_manager,
+            Path::new(&path),
+            version.as_deref(),
+            release_notes,
+            overwrite.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| format!("Firmware import task panicked: {}", e))?
 }
 
+#[tauri::command]
+pub async fn export_cached_firmware(
+    version: String,
+    dest_path: String,
+    cache_manager: tauri::State<'_, CacheManager>,
+) -> Result<String, String> {
+    let cache_manager = cache_manager.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        sideload::export_bundle(&cache_manager, &version, Path::new(&dest_path))
+            .map(|bundle_path| bundle_path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| format!("Firmware export task panicked: {}", e))?
+}
+
+/// Delete a cached version from disk and the index.
+///
+/// Deletes the zip recorded in the index, wherever it lives, or the legacy
+/// `<version>.zip` when there is no entry. Pinned versions need `force`.
+/// Returns the number of bytes freed.
 #[tauri::command]
 pub async fn delete_cached_firmware(
     version: String,
-    app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    force: Option<bool>,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<u64, String> {
+    if !sideload::is_valid_version(&version) {
+        return Err(format!("Invalid firmware version: {:?}", version));
+    }
 
-    let firmware_dir = app_data_dir.join("firmware");
+    let firmware_dir = app_data_dir.path().join("firmware");
+    let force = force.unwrap_or(false);
 
-    // Delete zip file
-    let zip_file = firmware_dir.join(format!("{}.zip", version));
-    if zip_file.exists() {
-        fs::remove_file(&zip_file)
-            .map_err(|e| format!("Failed to delete zip file: {}", e))?;
-    }
+    with_cache_index(&cache_manager, move |cache| {
+        // Pinned versions are only deleted on explicit request
+        let pinned = cache
+            .get_entry(&version)?
+            .map(|metadata| metadata.pinned)
+            .unwrap_or(false);
+        if pinned && !force {
+            return Err(format!(
+                "Firmware {} is pinned; unpin it before deleting",
+                version
+            ));
+        }
+
+        // Delete the recorded files and the index entry
+        let bytes_freed = cache.delete_version(&firmware_dir, &version)?;
 
-    // Remove from cache index
-    let cache_manager = CacheManager::new(&app_data_dir)?;
-    cache_manager.remove_entry(&version)?;
+        // Delete any interrupted download of the same version
+        let _ = fs::remove_file(partial_path(&firmware_dir, &version));
 
-    Ok(())
+        Ok(bytes_freed)
+    })
+    .await
 }
 
+/// Clear the firmware cache, keeping pinned versions and unrelated files.
 #[tauri::command]
 pub async fn clear_all_cache(
-    app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<CacheClearResult, String> {
+    let app_data_dir = app_data_dir.path().to_path_buf();
 
-    let firmware_dir = app_data_dir.join("firmware");
+    let cache_manager = cache_manager.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let firmware_dir = app_data_dir.join("firmware");
+        let result = cache_manager.clear_unpinned(&firmware_dir, &active_partials())?;
 
-    // Delete entire firmware directory
-    if firmware_dir.exists() {
-        fs::remove_dir_all(&firmware_dir)
-            .map_err(|e| format!("Failed to delete firmware directory: {}", e))?;
-    }
+        if result.pinned_kept > 0 {
+            log::info!("Kept {} pinned firmware versions", result.pinned_kept);
+        }
+        if !result.skipped_unknown_files.is_empty() {
+            log::info!(
+                "Left {} unrecognized files in the firmware directory",
+                result.skipped_unknown_files.len()
+            );
+        }
+        for failure in &result.failed {
+            log::warn!("{}", failure);
+        }
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Cache clear task panicked: {}", e))?
+}
 
-    // Clear cache index
-    let cache_manager = CacheManager::new(&app_data_dir)?;
-    cache_manager.clear_index()?;
+#[tauri::command]
+pub async fn cleanup_cache_older_than(
+    days: u32,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<CacheCleanupResult, String> {
+    let app_data_dir = app_data_dir.path().to_path_buf();
+
+    let cache_manager = cache_manager.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let firmware_dir = app_data_dir.join("firmware");
+        let result = cache_manager.cleanup_older_than(
+            &firmware_dir,
+            chrono::Duration::days(i64::from(days)),
+            chrono::Utc::now(),
+        )?;
 
-    Ok(())
+        if !result.removed.is_empty() {
+            log::info!(
+                "Removed {} firmware versions unused for {} days ({} bytes)",
+                result.removed.len(),
+                days,
+                result.bytes_freed
+            );
+        }
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Cache cleanup task panicked: {}", e))?
+}
+
+/// Pin a cached version so cache clearing and age-based cleanup keep it.
+///
+/// Errors if the version isn't cached.
+#[tauri::command]
+pub async fn pin_cached_firmware(
+    version: String,
+    cache_manager: tauri::State<'_, CacheManager>,
+) -> Result<(), String> {
+    with_cache_index(&cache_manager, move |cache| {
+        cache.set_pinned(&version, true)
+    })
+    .await
+}
+
+/// Unpin a cached version, making it eligible for cleanup again.
+#[tauri::command]
+pub async fn unpin_cached_firmware(
+    version: String,
+    cache_manager: tauri::State<'_, CacheManager>,
+) -> Result<(), String> {
+    with_cache_index(&cache_manager, move |cache| {
+        cache.set_pinned(&version, false)
+    })
+    .await
 }
 
 #[tauri::command]
 pub async fn verify_cached_firmware(
     version: String,
-    app_handle: tauri::AppHandle,
+    cache_manager: tauri::State<'_, CacheManager>,
 ) -> Result<bool, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    with_cache_index(&cache_manager, move |cache| cache.verify_hash(&version)).await
+}
+
+/// Hash un-indexed zips in the firmware directory into the cache index.
+///
+/// Only needed for caches written by older versions, so the frontend runs
+/// it once per session. Hashing runs off the async runtime; `progress`
+/// reports each zip as it is hashed.
+#[tauri::command]
+pub async fn migrate_firmware_cache(
+    progress: Option<JavaScriptChannelId>,
+    webview: tauri::Webview,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<Vec<String>, String> {
+    let app_data_dir = app_data_dir.path().to_path_buf();
+    let progress = progress.map(|id| id.channel_on(webview));
+    let cache_manager = cache_manager.inner().clone();
 
-    let cache_manager = CacheManager::new(&app_data_dir)?;
-    cache_manager.verify_hash(&version)
+    tokio::task::spawn_blocking(move || {
+        migrate_cache(&cache_manager, &app_data_dir, progress.as_ref())
+    })
+    .await
+    .map_err(|e| format!("Cache migration task panicked: {}", e))?
 }
 
+/// Drop index entries whose files are gone and clean up stale partials.
+///
+/// Returns the versions removed from the index.
+#[tauri::command]
+pub async fn clean_firmware_cache(
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<Vec<String>, String> {
+    let app_data_dir = app_data_dir.path().to_path_buf();
+    let cache_manager = cache_manager.inner().clone();
+
+    tokio::task::spawn_blocking(move || clean_cache(&cache_manager, &app_data_dir))
+        .await
+        .map_err(|e| format!("Cache cleanup task panicked: {}", e))?
+}
+
+/// Remove orphaned files from the firmware directory.
+///
+/// Partial downloads, staged imports and stray files or directories that
+/// nothing in the index references are deleted once they are older than
+/// the stale partial age, so resumable downloads survive.
+#[tauri::command]
+pub async fn collect_cache_garbage(
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
+) -> Result<CacheGarbageReport, String> {
+    let app_data_dir = app_data_dir.path().to_path_buf();
+    let cache_manager = cache_manager.inner().clone();
+
+    tokio::task::spawn_blocking(move || collect_garbage(&cache_manager, &app_data_dir))
+        .await
+        .map_err(|e| format!("Cache garbage collection task panicked: {}", e))?
+}
+
+/// Migrate (unless `options.skip_migration`) and then clean the cache,
+/// collecting garbage too if `options.collect_garbage` is set.
+///
+/// Combines `migrate_firmware_cache`, `clean_firmware_cache` and
+/// `collect_cache_garbage`; with no arguments it behaves as it always has.
 #[tauri::command]
 pub async fn verify_and_clean_cache(
-    app_handle: tauri::AppHandle,
+    options: Option<CacheMaintenanceOptions>,
+    progress: Option<JavaScriptChannelId>,
+    webview: tauri::Webview,
+    cache_manager: tauri::State<'_, CacheManager>,
+    app_data_dir: tauri::State<'_, AppDataDir>,
 ) -> Result<Vec<String>, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let app_data_dir = app_data_dir.path().to_path_buf();
+    let options = options.unwrap_or_default();
+    let progress = progress.map(|id| id.channel_on(webview));
+    let cache_manager = cache_manager.inner().clone();
 
+    tokio::task::spawn_blocking(move || {
+        if !options.skip_migration {
+            migrate_cache(&cache_manager, &app_data_dir, progress.as_ref())?;
+        }
+        let removed = clean_cache(&cache_manager, &app_data_dir)?;
+        if options.collect_garbage {
+            collect_garbage(&cache_manager, &app_data_dir)?;
+        }
+        Ok(removed)
+    })
+    .await
+    .map_err(|e| format!("Cache verification task panicked: {}", e))?
+}
+
+/// Blocking body of `migrate_firmware_cache`.
+fn migrate_cache(
+    cache_manager: &CacheManager,
+    app_data_dir: &Path,
+    progress: Option<&Channel<CacheMigrationProgressEvent>>,
+) -> Result<Vec<String>, String> {
     let firmware_dir = app_data_dir.join("firmware");
-    let cache_manager = CacheManager::new(&app_data_dir)?;
 
-    // First, migrate any existing cached firmware not in the index
-    let migrated = cache_manager.migrate_existing_cache(&firmware_dir)?;
+    let migrated = cache_manager.migrate_existing_cache_with_progress(&firmware_dir, |event| {
+        if let Some(progress) = progress {
+            let _ = progress.send(event);
+        }
+    })?;
     if !migrated.is_empty() {
-        println!("Migrated {} existing cached firmware versions", migrated.len());
+        log::info!("Migrated {} existing cached firmware versions", migrated.len());
+    }
+
+    Ok(migrated)
+}
+
+/// Blocking body of `clean_firmware_cache`.
+fn clean_cache(cache_manager: &CacheManager, app_data_dir: &Path) -> Result<Vec<String>, String> {
+    let firmware_dir = app_data_dir.join("firmware");
+
+    // Classify entries cached before asset kinds were recorded
+    let reclassified = cache_manager.reclassify_unknown()?;
+    if !reclassified.is_empty() {
+        log::info!("Classified {} cached firmware versions", reclassified.len());
     }
 
     // Then, get list of versions with missing files
-    let missing_versions = cache_manager.verify_cache_integrity()?;
+    let index = cache_manager.load_index()?;
+    let (pinned_missing, missing_versions): (Vec<String>, Vec<String>) = cache_manager
+        .verify_cache_integrity()?
+        .into_iter()
+        .partition(|version| index.get(version).map(|m| m.pinned).unwrap_or(false));
+
+    // Pinned entries stay in the index so the missing version remains visible
+    for version in &pinned_missing {
+        log::warn!(
+            "Pinned firmware {} is missing from disk; keeping its index entry",
+            version
+        );
+    }
 
     // Remove stale entries from cache index
     for version in &missing_versions {
         cache_manager.remove_entry(version)?;
     }
 
+    // Drop interrupted downloads that were never resumed
+    let removed_partials = clean_stale_partials(&firmware_dir, STALE_PARTIAL_MAX_AGE)?;
+    if !removed_partials.is_empty() {
+        log::info!("Removed {} stale partial downloads", removed_partials.len());
+    }
+
     Ok(missing_versions)
 }
 
+/// Blocking body of `collect_cache_garbage`.
+fn collect_garbage(
+    cache_manager: &CacheManager,
+    app_data_dir: &Path,
+) -> Result<CacheGarbageReport, String> {
+    let firmware_dir = app_data_dir.join("firmware");
+
+    let report = cache_manager.collect_cache_garbage(
+        &firmware_dir,
+        STALE_PARTIAL_MAX_AGE,
+        SystemTime::now(),
+    )?;
+    if !report.removed.is_empty() {
+        log::info!(
+            "Removed {} orphaned cache entries ({} bytes)",
+            report.removed.len(),
+            report.bytes_freed
+        );
+    }
+
+    Ok(report)
+}
+
 // Tests moved to src-tauri/src/dfu/firmware_reader.rs for DFU zip reading
@@ -0,0 +1,38 @@
+//! Tauri commands for the flash history.
+//!
+//! Entries are recorded by the flash commands; these only read and export.
+
+use std::path::PathBuf;
+
+use crate::history::{FlashHistory, FlashRecord, HistoryFilter};
+
+/// Get flash history records matching `filter`, newest first.
+///
+/// Without a filter every record is returned.
+#[tauri::command]
+pub async fn get_flash_history(
+    filter: Option<HistoryFilter>,
+    history: tauri::State<'_, FlashHistory>,
+) -> Result<Vec<FlashRecord>, String> {
+    let history = history.inner().clone();
+    let filter = filter.unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || history.query(&filter))
+        .await
+        .map_err(|e| format!("History task panicked: {}", e))
+}
+
+/// Export the whole flash history to `path` as JSON.
+///
+/// Returns the number of records exported.
+#[tauri::command]
+pub async fn export_flash_history(
+    path: String,
+    history: tauri::State<'_, FlashHistory>,
+) -> Result<usize, String> {
+    let history = history.inner().clone();
+
+    tokio::task::spawn_blocking(move || history.export(&PathBuf::from(path)))
+        .await
+        .map_err(|e| format!("History export task panicked: {}", e))?
+}
@@ -0,0 +1,38 @@
+//! Tauri commands for operations a previous run didn't finish.
+//!
+//! Entries are written by the flash commands; these only report and dismiss.
+
+use crate::dfu::find_nrf52_devices;
+use crate::journal::{InterruptedOperation, OperationJournal};
+
+/// Get the flashes a previous run was killed in the middle of, oldest first,
+/// each with the port its device is connected on now.
+#[tauri::command]
+pub async fn get_interrupted_operations(
+    journal: tauri::State<'_, OperationJournal>,
+) -> Result<Vec<InterruptedOperation>, String> {
+    let entries = journal.interrupted();
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let devices = tokio::task::spawn_blocking(find_nrf52_devices)
+        .await
+        .map_err(|e| format!("Device scan task panicked: {}", e))?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| InterruptedOperation::locate(entry, &devices))
+        .collect())
+}
+
+/// Forget the interrupted operation `id` without flashing again.
+///
+/// Returns whether there was such an operation.
+#[tauri::command]
+pub async fn dismiss_interrupted_operation(
+    id: String,
+    journal: tauri::State<'_, OperationJournal>,
+) -> Result<bool, String> {
+    journal.dismiss(&id)
+}
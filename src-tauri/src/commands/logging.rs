@@ -0,0 +1,15 @@
+//! Tauri command for the backend log file.
+
+use crate::logging;
+
+/// Change how much the backend logs from now on: "off", "error", "warn",
+/// "info", "debug" or "trace".
+///
+/// Raise it to "debug" to capture the DFU protocol exchange for a support
+/// case. Not saved; the app starts at "info".
+#[tauri::command]
+pub async fn set_log_level(level: String) -> Result<(), String> {
+    let level = logging::set_level(&level)?;
+    log::info!("Log level set to {}", level);
+    Ok(())
+}
@@ -1,3 +1,11 @@
+pub mod app_update;
+pub mod device_log;
+pub mod diagnostics;
 pub mod dfu;
+pub mod error;
 pub mod firmware;
+pub mod history;
+pub mod journal;
+pub mod logging;
+pub mod report;
 pub mod settings;
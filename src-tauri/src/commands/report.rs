@@ -0,0 +1,217 @@
+//! Tauri command for the device health report.
+//!
+//! Gathers everything support asks for about one connected device into a
+//! single JSON document. Each probe records either its value or why it
+//! couldn't be read, so one failing check never hides the others.
+
+use serde::Serialize;
+
+use std::time::Duration;
+
+use super::dfu::{is_dfu_in_progress, DfuDevice, DEVICE_INFO_QUERIES};
+use crate::dfu::{find_nrf52_devices, query_device, QueryAnswer, DEVICE_QUERY_BUDGET_MS};
+
+/// One probe in a device report: the value, or why it couldn't be read.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportProbe<T> {
+    pub value: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ReportProbe<T> {
+    fn from_result(result: Result<T, String>) -> Self {
+        match result {
+            Ok(value) => Self {
+                value: Some(value),
+                error: None,
+            },
+            Err(error) => Self {
+                value: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// Health report for one device, as shown to support.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceReport {
+    /// When the report was generated (RFC 3339).
+    pub generated_at: String,
+    pub app_version: String,
+    /// Host operating system ("macos", "windows", "linux").
+    pub os: String,
+    /// Serial port the report was requested for.
+    pub serial_port: String,
+    /// USB detection details.
+    pub device: ReportProbe<DfuDevice>,
+    /// "application" or "bootloader".
+    pub mode: ReportProbe<String>,
+    /// Firmware version from GET_VERSION.
+    pub firmware_version: ReportProbe<String>,
+    /// Configured role from GET_ROLE.
+    pub role: ReportProbe<String>,
+    /// Therapy profile from GET_PROFILE.
+    pub profile: ReportProbe<String>,
+}
+
+/// Build a report from detection results, querying the device over serial
+/// only when it is in application mode and no flash owns the port.
+fn assemble_report(
+    serial_port: String,
+    device: Option<DfuDevice>,
+    flash_in_progress: bool,
+    query: impl FnOnce() -> Result<Vec<QueryAnswer>, String>,
+) -> DeviceReport {
+    let mode = match &device {
+        Some(device) if device.in_bootloader => Ok("bootloader".to_string()),
+        Some(_) => Ok("application".to_string()),
+        None => Err("Device not found".to_string()),
+    };
+
+    let queries = match &device {
+        None => Err("Device not found".to_string()),
+        Some(device) if device.in_bootloader => {
+            Err("Device is in bootloader mode and can't be queried".to_string())
+        }
+        Some(_) if flash_in_progress => {
+            Err("A firmware installation is in progress on this port".to_string())
+        }
+        Some(_) => query(),
+    };
+    let [firmware_version, role, profile] = match queries {
+        Ok(answers) => {
+            let mut answers = answers.into_iter();
+            [(); 3].map(|_| match answers.next() {
+                Some(QueryAnswer::Value(value)) => Ok(value),
+                Some(QueryAnswer::Rejected(line)) => {
+                    Err(format!("Device rejected the query: {}", line))
+                }
+                Some(QueryAnswer::TimedOut) => Err("No response from device".to_string()),
+                None => Err("Query was not sent".to_string()),
+            })
+        }
+        Err(error) => [(); 3].map(|_| Err(error.clone())),
+    };
+
+    DeviceReport {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        serial_port,
+        device: ReportProbe::from_result(device.ok_or_else(|| "Device not found".to_string())),
+        mode: ReportProbe::from_result(mode),
+        firmware_version: ReportProbe::from_result(firmware_version),
+        role: ReportProbe::from_result(role),
+        profile: ReportProbe::from_result(profile),
+    }
+}
+
+/// Generate a health report for the device on `serial_port`.
+///
+/// Never fails because a probe failed: missing devices, bootloader mode,
+/// a flash in progress and unanswered queries all show up as probe errors.
+#[tauri::command]
+pub async fn generate_device_report(serial_port: String) -> Result<DeviceReport, String> {
+    tokio::task::spawn_blocking(move || {
+        let device = find_nrf52_devices()
+            .into_iter()
+            .find(|d| d.port == serial_port)
+            .map(DfuDevice::from);
+        let port = serial_port.clone();
+
+        assemble_report(serial_port, device, is_dfu_in_progress(), || {
+            query_device(
+                &port,
+                &DEVICE_INFO_QUERIES,
+                Duration::from_millis(DEVICE_QUERY_BUDGET_MS),
+                |_| {},
+            )
+            .map_err(|e| e.to_string())
+        })
+    })
+    .await
+    .map_err(|e| format!("Device report task panicked: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(in_bootloader: bool) -> DfuDevice {
+        DfuDevice {
+            port: "/dev/cu.usbmodem1".to_string(),
+            label: "BlueBuzzah".to_string(),
+            vid: 0x239A,
+            pid: if in_bootloader { 0x0029 } else { 0x8029 },
+            in_bootloader,
+            serial_number: Some("ABC123".to_string()),
+            board: None,
+        }
+    }
+
+    fn port() -> String {
+        "/dev/cu.usbmodem1".to_string()
+    }
+
+    #[test]
+    fn test_report_with_partial_query_support() {
+        let report = assemble_report(port(), Some(device(false)), false, || {
+            Ok(vec![
+                QueryAnswer::Value("2.3.1".to_string()),
+                QueryAnswer::Value("PRIMARY".to_string()),
+                QueryAnswer::TimedOut,
+            ])
+        });
+
+        assert_eq!(report.mode.value.as_deref(), Some("application"));
+        assert_eq!(report.firmware_version.value.as_deref(), Some("2.3.1"));
+        assert_eq!(report.role.value.as_deref(), Some("PRIMARY"));
+        assert!(report.profile.value.is_none());
+        assert_eq!(
+            report.profile.error.as_deref(),
+            Some("No response from device")
+        );
+    }
+
+    #[test]
+    fn test_report_degrades_when_device_missing() {
+        let report = assemble_report(port(), None, false, || panic!("must not query"));
+
+        assert!(report.device.value.is_none());
+        for probe in [&report.mode, &report.firmware_version, &report.role] {
+            assert_eq!(probe.error.as_deref(), Some("Device not found"));
+        }
+    }
+
+    #[test]
+    fn test_report_skips_queries_in_bootloader_or_during_flash() {
+        let report = assemble_report(port(), Some(device(true)), false, || {
+            panic!("must not query")
+        });
+        assert_eq!(report.mode.value.as_deref(), Some("bootloader"));
+        assert!(report.role.error.unwrap().contains("bootloader mode"));
+
+        let report = assemble_report(port(), Some(device(false)), true, || {
+            panic!("must not query")
+        });
+        assert!(report.device.value.is_some());
+        assert!(report
+            .firmware_version
+            .error
+            .unwrap()
+            .contains("in progress"));
+    }
+
+    #[test]
+    fn test_report_records_port_failure_on_every_query() {
+        let report = assemble_report(port(), Some(device(false)), false, || {
+            Err("Port '/dev/cu.usbmodem1' is busy or in use by another application".to_string())
+        });
+
+        for probe in [&report.firmware_version, &report.role, &report.profile] {
+            assert!(probe.error.as_deref().unwrap().contains("busy"));
+        }
+        assert_eq!(report.mode.value.as_deref(), Some("application"));
+    }
+}
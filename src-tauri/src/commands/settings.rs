@@ -3,45 +3,141 @@
 //! Provides get/save operations for advanced therapy settings,
 //! persisting to a JSON file in the app data directory.
 
-use crate::settings::{AdvancedSettings, SettingsManager};
-use tauri::Manager;
+use crate::releases::ReleaseChannel;
+use crate::settings::{
+    AdvancedSettings, AvailableProfile, DeviceSettings, ImportSummary, ResetSettingsResult,
+    SaveSettingsError, SettingsScope, SettingsService, SettingsSnapshot,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::Emitter;
 
-/// Get current advanced settings from disk.
+/// Global event emitted after saved settings change (saved, reset or
+/// imported), so open windows show the new values. The payload is a
+/// `SettingsChanged`.
+pub const SETTINGS_CHANGED_EVENT: &str = "settings://changed";
+
+/// Counts changes to the saved settings since the app started.
+static SETTINGS_REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// Payload of `SETTINGS_CHANGED_EVENT`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingsChanged {
+    /// Which saved settings changed.
+    pub scope: SettingsScope,
+    /// Settings now in effect for the scope: the device's own, layered over
+    /// the global ones, for a device scope, otherwise the global settings.
+    pub settings: AdvancedSettings,
+    /// Revision after the change; notifications with a revision older than
+    /// the settings already shown can be ignored.
+    pub revision: u64,
+}
+
+impl SettingsChanged {
+    /// Describe a change to `scope` that left the settings in `snapshot`,
+    /// taking the next revision.
+    fn new(snapshot: &SettingsSnapshot, scope: SettingsScope) -> Self {
+        let settings = match &scope {
+            SettingsScope::Device { serial_number } => snapshot.effective(Some(serial_number)).0,
+            SettingsScope::Global | SettingsScope::All => snapshot.settings.clone(),
+        };
+        Self {
+            scope,
+            settings,
+            revision: SETTINGS_REVISION.fetch_add(1, Ordering::SeqCst) + 1,
+        }
+    }
+}
+
+/// Global advanced settings with the revision they were read at.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RevisedSettings {
+    #[serde(flatten)]
+    pub settings: AdvancedSettings,
+    pub revision: u64,
+}
+
+/// Send `SETTINGS_CHANGED_EVENT` to every window.
+fn broadcast_settings_changed(app_handle: &tauri::AppHandle, change: SettingsChanged) {
+    if let Err(e) = app_handle.emit(SETTINGS_CHANGED_EVENT, &change) {
+        log::warn!("Failed to broadcast settings change: {}", e);
+    }
+}
+
+/// Validate and save the global settings, then pass the change to `notify`.
+///
+/// `notify` is called once if the settings were saved, and not at all if
+/// they were rejected.
+fn save_global_settings(
+    service: &SettingsService,
+    settings: &AdvancedSettings,
+    notify: impl FnOnce(SettingsChanged),
+) -> Result<(), SaveSettingsError> {
+    settings.validate()?;
+
+    let snapshot = service.save(settings)?;
+    notify(SettingsChanged::new(&snapshot, SettingsScope::Global));
+    Ok(())
+}
+
+/// Validate and save one device's overrides, then pass the change to
+/// `notify`, as `save_global_settings` does.
+fn save_device_overrides(
+    service: &SettingsService,
+    serial_number: &str,
+    settings: &DeviceSettings,
+    notify: impl FnOnce(SettingsChanged),
+) -> Result<(), SaveSettingsError> {
+    if serial_number.trim().is_empty() {
+        return Err("Device settings need a serial number".to_string().into());
+    }
+    settings.validate()?;
+
+    let snapshot = service.save_device(serial_number, settings)?;
+    let scope = SettingsScope::Device {
+        serial_number: serial_number.to_string(),
+    };
+    notify(SettingsChanged::new(&snapshot, scope));
+    Ok(())
+}
+
+/// Get current advanced settings, with their revision.
 ///
 /// Returns default settings if no settings file exists yet.
 #[tauri::command]
 pub async fn get_advanced_settings(
-    app_handle: tauri::AppHandle,
-) -> Result<AdvancedSettings, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    let manager = SettingsManager::new(&app_data_dir);
-    manager.load()
+    service: tauri::State<'_, SettingsService>,
+) -> Result<RevisedSettings, String> {
+    // Read the revision first: a save landing in between then shows up as
+    // a newer notification instead of being mistaken for an old one
+    let revision = SETTINGS_REVISION.load(Ordering::SeqCst);
+    Ok(RevisedSettings {
+        settings: service.settings()?,
+        revision,
+    })
 }
 
 /// Save advanced settings to disk.
 ///
-/// This persists settings across app restarts.
+/// This persists settings across app restarts. Invalid values are rejected
+/// with one error per field, before an unusable proxy breaks every download
+/// or an out-of-range value fails every profile configuration. Open windows
+/// are sent `SETTINGS_CHANGED_EVENT`.
 #[tauri::command]
 pub async fn save_advanced_settings(
     settings: AdvancedSettings,
+    service: tauri::State<'_, SettingsService>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    let manager = SettingsManager::new(&app_data_dir);
-    manager.save(&settings)?;
+) -> Result<(), SaveSettingsError> {
+    save_global_settings(&service, &settings, |change| {
+        broadcast_settings_changed(&app_handle, change)
+    })?;
 
     // Log for debugging
     if settings.has_non_default_settings() {
-        println!(
-            "[Settings] Saved non-default settings: {:?}",
+        log::info!(
+            "Saved non-default settings: {:?}",
             settings.to_pre_profile_commands()
         );
     }
@@ -49,6 +145,140 @@ pub async fn save_advanced_settings(
     Ok(())
 }
 
+/// List the profiles a device can be set to: the four built into the
+/// firmware, then the custom profiles saved in settings.
+#[tauri::command]
+pub async fn get_available_profiles(
+    service: tauri::State<'_, SettingsService>,
+) -> Result<Vec<AvailableProfile>, String> {
+    let snapshot = service.snapshot()?;
+    Ok(snapshot.settings.available_profiles())
+}
+
+/// Get the settings overrides saved for one device.
+///
+/// Returns empty overrides if the device has none; unset fields use the
+/// global advanced settings.
+#[tauri::command]
+pub async fn get_device_settings(
+    serial_number: String,
+    service: tauri::State<'_, SettingsService>,
+) -> Result<DeviceSettings, String> {
+    let snapshot = service.snapshot()?;
+    Ok(snapshot.device(&serial_number))
+}
+
+/// Save the settings overrides for one device, identified by its USB serial
+/// number. Empty overrides remove the device's entry. Invalid values are
+/// rejected, and open windows notified, like in `save_advanced_settings`.
+#[tauri::command]
+pub async fn save_device_settings(
+    serial_number: String,
+    settings: DeviceSettings,
+    service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), SaveSettingsError> {
+    save_device_overrides(&service, &serial_number, &settings, |change| {
+        broadcast_settings_changed(&app_handle, change)
+    })
+}
+
+/// Restore default settings for `scope`: the global settings, one device's
+/// overrides, or everything.
+///
+/// Returns the settings now in effect and the ones that were replaced, so
+/// the reset can be undone by saving them again.
+#[tauri::command]
+pub async fn reset_advanced_settings(
+    scope: SettingsScope,
+    service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<ResetSettingsResult, String> {
+    let (result, snapshot) = service.write(|manager| manager.reset(&scope))?;
+    log::info!("Reset settings: {:?}", scope);
+
+    broadcast_settings_changed(&app_handle, SettingsChanged::new(&snapshot, scope));
+
+    Ok(result)
+}
+
+/// Clear the DFU timing overrides, going back to the platform defaults,
+/// and keep every other setting.
+///
+/// Returns the global settings now saved. Open windows are sent
+/// `SETTINGS_CHANGED_EVENT`.
+#[tauri::command]
+pub async fn reset_dfu_timing(
+    service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<AdvancedSettings, SaveSettingsError> {
+    let mut settings = service.settings()?;
+    settings.dfu_timing = Default::default();
+    save_global_settings(&service, &settings, |change| {
+        broadcast_settings_changed(&app_handle, change)
+    })?;
+    log::info!("Reset DFU timing to defaults");
+
+    Ok(settings)
+}
+
+/// Choose the release channel the app updates itself from, keeping every
+/// other setting. The next `check_for_app_update` uses it.
+///
+/// Open windows are sent `SETTINGS_CHANGED_EVENT`.
+#[tauri::command]
+pub async fn set_update_channel(
+    channel: ReleaseChannel,
+    service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), SaveSettingsError> {
+    let mut settings = service.settings()?;
+    settings.app_update_channel = channel;
+    save_global_settings(&service, &settings, |change| {
+        broadcast_settings_changed(&app_handle, change)
+    })?;
+    log::info!("App update channel set to {:?}", channel);
+
+    Ok(())
+}
+
+/// Export every saved setting to `path` as JSON, for copying to other
+/// machines.
+///
+/// The GitHub token and proxy credentials are left out unless
+/// `include_secrets` is true.
+#[tauri::command]
+pub async fn export_settings(
+    path: String,
+    include_secrets: Option<bool>,
+    service: tauri::State<'_, SettingsService>,
+) -> Result<(), String> {
+    service.export(&PathBuf::from(path), include_secrets.unwrap_or(false))
+}
+
+/// Replace every saved setting with those exported to `path`.
+///
+/// Files from older versions are migrated. Nothing is written unless the
+/// whole file is valid. Returns what changed and any keys that were ignored.
+#[tauri::command]
+pub async fn import_settings(
+    path: String,
+    service: tauri::State<'_, SettingsService>,
+    app_handle: tauri::AppHandle,
+) -> Result<ImportSummary, String> {
+    let (summary, snapshot) = service.write(|manager| manager.import(&PathBuf::from(path)))?;
+    log::info!(
+        "Imported settings: {} changed, {} unknown",
+        summary.changed.len(),
+        summary.unknown_keys.len()
+    );
+
+    let change = SettingsChanged::new(&snapshot, SettingsScope::All);
+    broadcast_settings_changed(&app_handle, change);
+
+    Ok(summary)
+}
+
 /// Get the current operating system platform.
 ///
 /// Returns the OS identifier (e.g., "macos", "windows", "linux").
@@ -56,3 +286,98 @@ pub async fn save_advanced_settings(
 pub async fn get_platform() -> String {
     std::env::consts::OS.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_global_settings_notifies_once() {
+        let dir = tempdir().unwrap();
+        let service = SettingsService::load(dir.path());
+        let settings = AdvancedSettings {
+            debug_mode: true,
+            ..Default::default()
+        };
+
+        let mut changes = Vec::new();
+        save_global_settings(&service, &settings, |change| changes.push(change)).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].scope, SettingsScope::Global);
+        assert_eq!(changes[0].settings, settings);
+
+        save_global_settings(&service, &settings, |change| changes.push(change)).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes[1].revision > changes[0].revision);
+    }
+
+    #[test]
+    fn test_rejected_settings_do_not_notify() {
+        let dir = tempdir().unwrap();
+        let service = SettingsService::load(dir.path());
+        let invalid = AdvancedSettings {
+            jitter_percent: Some(900.0),
+            ..Default::default()
+        };
+
+        let mut notified = 0;
+        let result = save_global_settings(&service, &invalid, |_| notified += 1);
+        assert!(matches!(result, Err(SaveSettingsError::Invalid { .. })));
+
+        let invalid_overrides = DeviceSettings {
+            vibration_intensity: Some(150),
+            ..Default::default()
+        };
+        let result =
+            save_device_overrides(&service, "ABC123", &invalid_overrides, |_| notified += 1);
+        assert!(result.is_err());
+        let result =
+            save_device_overrides(&service, " ", &DeviceSettings::default(), |_| notified += 1);
+        assert!(result.is_err());
+
+        assert_eq!(notified, 0);
+    }
+
+    #[test]
+    fn test_save_device_overrides_notifies_with_effective_settings() {
+        let dir = tempdir().unwrap();
+        let service = SettingsService::load(dir.path());
+        let global = AdvancedSettings {
+            disable_led_during_therapy: true,
+            ..Default::default()
+        };
+        save_global_settings(&service, &global, |_| {}).unwrap();
+
+        let overrides = DeviceSettings {
+            debug_mode: Some(true),
+            ..Default::default()
+        };
+        let mut changes = Vec::new();
+        save_device_overrides(&service, "ABC123", &overrides, |change| {
+            changes.push(change)
+        })
+        .unwrap();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].scope,
+            SettingsScope::Device {
+                serial_number: "ABC123".to_string()
+            }
+        );
+        assert!(changes[0].settings.disable_led_during_therapy);
+        assert!(changes[0].settings.debug_mode);
+    }
+
+    #[test]
+    fn test_revised_settings_serialization() {
+        let json = serde_json::to_value(RevisedSettings {
+            settings: AdvancedSettings::default(),
+            revision: 7,
+        })
+        .unwrap();
+        assert_eq!(json["revision"], 7);
+        assert_eq!(json["debugMode"], false);
+    }
+}
@@ -4,6 +4,7 @@
 // future features like PRN support, retry logic, etc.
 #![allow(dead_code)]
 
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 // ============================================================================
@@ -129,6 +130,10 @@ pub const REBOOT_TIMEOUT_MS: u64 = 10_000;
 /// Default settle delay before polling for device after reboot (milliseconds).
 pub const REBOOT_SETTLE_DELAY_MS: u64 = 2000;
 
+/// Delay before re-scanning for a device that wasn't found.
+/// Windows enumeration can lag the USB device event by a few hundred ms.
+pub const DEVICE_RESCAN_DELAY_MS: u64 = 750;
+
 /// Timeout for role configuration command.
 pub const ROLE_CONFIG_TIMEOUT_MS: u64 = 5000;
 
@@ -168,6 +173,47 @@ pub const MAX_CONFIG_RETRIES: u32 = 2;
 /// Delay between config retries (ms).
 pub const CONFIG_RETRY_DELAY_MS: u64 = 1000;
 
+/// Timeouts and retry limits for one flash or configuration, which support
+/// can raise through the advanced settings for slow USB hubs or busy
+/// machines.
+///
+/// Defaults to the platform values above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DfuTimingConfig {
+    /// Time for the bootloader to appear after entering DFU mode (ms).
+    pub bootloader_timeout_ms: u64,
+    /// Time for the bootloader to ACK each packet (ms).
+    pub ack_timeout_ms: u64,
+    /// Retries for each packet before the transfer fails.
+    pub max_packet_retries: u32,
+    /// Retries for role/profile configuration.
+    pub max_config_retries: u32,
+}
+
+impl Default for DfuTimingConfig {
+    fn default() -> Self {
+        Self {
+            bootloader_timeout_ms: get_bootloader_timeout(),
+            ack_timeout_ms: ACK_TIMEOUT_MS,
+            max_packet_retries: MAX_PACKET_RETRIES,
+            max_config_retries: MAX_CONFIG_RETRIES,
+        }
+    }
+}
+
+impl std::fmt::Display for DfuTimingConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bootloader timeout {}ms, ACK timeout {}ms, packet retries {}, config retries {}",
+            self.bootloader_timeout_ms,
+            self.ack_timeout_ms,
+            self.max_packet_retries,
+            self.max_config_retries
+        )
+    }
+}
+
 // ============================================================================
 // Port Open Configuration
 // ============================================================================
@@ -351,6 +397,10 @@ pub const FLASH_PAGE_WRITE_TIME_MS: u64 = 45;
 /// Number of data frames before flash write delay (8 frames = 4096 bytes = 1 page).
 pub const FRAMES_PER_FLASH_PAGE: usize = 8;
 
+/// Size of the application bank on the nRF52840 (S140 SoftDevice ends at
+/// 0x26000, Adafruit bootloader starts at 0xF4000).
+pub const APPLICATION_BANK_SIZE: usize = 0xF4000 - 0x26000;
+
 /// Calculate wait time after START packet for flash erase.
 ///
 /// Returns duration in milliseconds.
@@ -361,6 +411,66 @@ pub fn calculate_erase_wait_time(firmware_size: usize) -> u64 {
     std::cmp::max(500, wait_ms)
 }
 
+/// Options controlling how long to wait for the bootloader's flash erase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EraseWaitOptions {
+    /// Size in bytes of the application image currently on the device, if known.
+    /// The bootloader erases the old footprint too, so a larger previous image
+    /// means a longer erase than the new image alone would need.
+    pub previous_firmware_size: Option<usize>,
+    /// Wait for the entire application bank to be erased regardless of image sizes.
+    pub full_bank: bool,
+}
+
+/// Calculate the erase wait time taking the previous image and full-bank
+/// option into account.
+///
+/// Uses max(previous, new) pages, or the whole application bank when
+/// `full_bank` is set. Returns duration in milliseconds.
+pub fn calculate_erase_wait_time_with_options(
+    firmware_size: usize,
+    options: &EraseWaitOptions,
+) -> u64 {
+    let erase_size = if options.full_bank {
+        std::cmp::max(APPLICATION_BANK_SIZE, firmware_size)
+    } else {
+        std::cmp::max(firmware_size, options.previous_firmware_size.unwrap_or(0))
+    };
+    calculate_erase_wait_time(erase_size)
+}
+
+/// Typical round trip for one data packet's ACK on a healthy connection.
+/// `ACK_TIMEOUT_MS` is the worst case; this is what a flash usually sees.
+pub const TYPICAL_ACK_LATENCY_MS: u64 = 10;
+
+/// Typical time outside the transfer itself: bootloader entry, connecting,
+/// init packet, validation and the reboot into the new firmware.
+pub const TYPICAL_FLASH_OVERHEAD_MS: u64 = 12_000;
+
+/// Estimate how long a flash of `firmware_size` bytes takes end to end.
+///
+/// Sums the per-packet serial time and ACK latency, the page-write wait
+/// after every flash page, the erase wait and the fixed overhead. Returns
+/// duration in milliseconds.
+pub fn estimate_flash_duration_ms(firmware_size: usize, options: &EraseWaitOptions) -> u64 {
+    let packets = firmware_size.div_ceil(MAX_PACKET_SIZE) as u64;
+    let pages = (firmware_size / FLASH_PAGE_SIZE) as u64;
+
+    // 10 bits per byte on the wire; SLIP framing adds a few bytes per packet
+    let packet_wire_ms = ((MAX_PACKET_SIZE as u64 + 8) * 10 * 1000).div_ceil(DFU_BAUD_RATE as u64);
+    let per_packet_ms =
+        packet_wire_ms + TYPICAL_ACK_LATENCY_MS + INTER_PACKET_DELAY.as_millis() as u64;
+
+    packets * per_packet_ms
+        + pages * FLASH_PAGE_WRITE_TIME_MS
+        + calculate_erase_wait_time_with_options(firmware_size, options)
+        + TYPICAL_FLASH_OVERHEAD_MS
+}
+
+/// `device_type` in the init packet of application images built for the
+/// nRF52840 boards this updater supports.
+pub const NRF52840_DEVICE_TYPE: u16 = 0x0052;
+
 // ============================================================================
 // Role Configuration
 // ============================================================================
@@ -394,6 +504,76 @@ pub const PROFILE_GENTLE_COMMAND: &str = "SET_PROFILE:GENTLE\n";
 /// Timeout for profile configuration command.
 pub const PROFILE_CONFIG_TIMEOUT_MS: u64 = 5000;
 
+/// Profiles built into the firmware, in the order they are offered.
+pub const BUILT_IN_PROFILES: [&str; 4] = ["REGULAR", "NOISY", "HYBRID", "GENTLE"];
+
+/// Restarts application firmware so setting commands take effect without
+/// a SET_PROFILE; sent after custom profiles that have no base profile.
+pub const REBOOT_COMMAND: &str = "REBOOT\n";
+
+// ============================================================================
+// Device Identification
+// ============================================================================
+
+/// Asks application firmware to blink its NeoPixel for a few seconds.
+pub const IDENTIFY_COMMAND: &str = "IDENTIFY\n";
+
+/// Timeout for the identify acknowledgment.
+pub const IDENTIFY_TIMEOUT_MS: u64 = 2000;
+
+// ============================================================================
+// Device Queries
+// ============================================================================
+
+/// Asks application firmware for its version; answered with `[VERSION] x.y.z`.
+pub const GET_VERSION_COMMAND: &str = "GET_VERSION\n";
+
+/// Asks for the configured role; answered with `[ROLE] PRIMARY`.
+pub const GET_ROLE_COMMAND: &str = "GET_ROLE\n";
+
+/// Asks for the therapy profile; answered with `[PROFILE] REGULAR`.
+pub const GET_PROFILE_COMMAND: &str = "GET_PROFILE\n";
+
+/// Asks for the device settings; answered with one `KEY=VALUE` line per
+/// setting, e.g. `THERAPY_LED_OFF=true`.
+pub const GET_SETTINGS_COMMAND: &str = "GET_SETTINGS\n";
+
+/// Timeout for each query response.
+pub const QUERY_TIMEOUT_MS: u64 = 1000;
+
+/// Overall time allowed for one round of device queries, including
+/// draining boot output, so the device screen stays responsive.
+pub const DEVICE_QUERY_BUDGET_MS: u64 = 5000;
+
+// ============================================================================
+// Developer Commands
+// ============================================================================
+
+/// Commands `send_device_command` allows without developer mode.
+pub const READ_ONLY_COMMANDS: &[&str] =
+    &["GET_VERSION", "GET_STATUS", "GET_PROFILE", "GET_SETTINGS"];
+
+/// Longest command `send_device_command` accepts, excluding the newline.
+pub const MAX_DEVICE_COMMAND_LEN: usize = 128;
+
+/// Default and maximum time to collect a command's response.
+pub const DEVICE_COMMAND_TIMEOUT_MS: u64 = 2000;
+pub const MAX_DEVICE_COMMAND_TIMEOUT_MS: u64 = 30_000;
+
+/// Quiet period after which a command's response is considered complete.
+pub const DEVICE_COMMAND_IDLE_MS: u64 = 200;
+
+// ============================================================================
+// Serial Monitor
+// ============================================================================
+
+/// How long each serial monitor read waits, which bounds how quickly a
+/// stream notices it has been stopped.
+pub const MONITOR_READ_TIMEOUT_MS: u64 = 100;
+
+/// Longest line the serial monitor buffers before passing it on in pieces.
+pub const MAX_MONITOR_LINE_BYTES: usize = 4096;
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
@@ -422,6 +602,26 @@ pub fn is_application_pid(pid: u16) -> bool {
     (pid & 0xFF00) == 0x8000 || FEATHER_APP_PIDS.contains(&pid)
 }
 
+/// Supported board, told apart by the low byte of its PID (the same in
+/// application and bootloader mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardModel {
+    FeatherNrf52840Express,
+    FeatherNrf52840Sense,
+}
+
+impl BoardModel {
+    /// Identify the board from its USB PID, if it is one we know.
+    pub fn from_pid(pid: u16) -> Option<Self> {
+        match pid & 0x00FF {
+            0x29 => Some(BoardModel::FeatherNrf52840Express),
+            0x2A => Some(BoardModel::FeatherNrf52840Sense),
+            _ => None,
+        }
+    }
+}
+
 /// Check if a VID/PID combination is a compatible nRF52 device.
 pub fn is_compatible_device(vid: u16, pid: u16) -> bool {
     vid == ADAFRUIT_VID && (is_bootloader_pid(pid) || is_application_pid(pid))
@@ -461,6 +661,17 @@ mod tests {
         assert!(!is_application_pid(0x002A));
     }
 
+    #[test]
+    fn test_board_model_from_pid() {
+        // Same board in application and bootloader mode
+        let express = Some(BoardModel::FeatherNrf52840Express);
+        assert_eq!(BoardModel::from_pid(0x8029), express);
+        assert_eq!(BoardModel::from_pid(0x0029), express);
+        let sense = Some(BoardModel::FeatherNrf52840Sense);
+        assert_eq!(BoardModel::from_pid(0x802A), sense);
+        assert_eq!(BoardModel::from_pid(0x8071), None);
+    }
+
     #[test]
     fn test_is_compatible_device() {
         assert!(is_compatible_device(ADAFRUIT_VID, 0x8029));
@@ -470,8 +681,82 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::manual_range_contains)]
     fn test_get_reboot_settle_delay() {
         let delay = get_reboot_settle_delay();
         assert!(delay >= 1000 && delay <= 5000);
     }
+
+    #[test]
+    fn test_calculate_erase_wait_time() {
+        // Small images hit the 500ms floor
+        assert_eq!(calculate_erase_wait_time(0), 500);
+        assert_eq!(calculate_erase_wait_time(1024), 500);
+        // 10 full pages + 1 = 11 pages
+        assert_eq!(
+            calculate_erase_wait_time(10 * FLASH_PAGE_SIZE),
+            11 * FLASH_PAGE_ERASE_TIME_MS
+        );
+    }
+
+    #[test]
+    fn test_erase_wait_uses_larger_previous_image() {
+        let new_size = 10 * FLASH_PAGE_SIZE;
+        let options = EraseWaitOptions {
+            previous_firmware_size: Some(40 * FLASH_PAGE_SIZE),
+            full_bank: false,
+        };
+        assert_eq!(
+            calculate_erase_wait_time_with_options(new_size, &options),
+            calculate_erase_wait_time(40 * FLASH_PAGE_SIZE)
+        );
+    }
+
+    #[test]
+    fn test_estimate_flash_duration() {
+        let options = EraseWaitOptions::default();
+        let small = estimate_flash_duration_ms(4096, &options);
+        let large = estimate_flash_duration_ms(200 * 1024, &options);
+
+        assert!(small > TYPICAL_FLASH_OVERHEAD_MS);
+        assert!(large > small);
+        // A typical 200 KB application lands in the tens of seconds
+        assert!((20_000..120_000).contains(&large));
+
+        // A full-bank erase only lengthens the estimate
+        let full_bank = EraseWaitOptions {
+            previous_firmware_size: None,
+            full_bank: true,
+        };
+        assert!(estimate_flash_duration_ms(200 * 1024, &full_bank) > large);
+    }
+
+    #[test]
+    fn test_erase_wait_ignores_smaller_previous_image() {
+        let new_size = 40 * FLASH_PAGE_SIZE;
+        let options = EraseWaitOptions {
+            previous_firmware_size: Some(10 * FLASH_PAGE_SIZE),
+            full_bank: false,
+        };
+        assert_eq!(
+            calculate_erase_wait_time_with_options(new_size, &options),
+            calculate_erase_wait_time(new_size)
+        );
+        assert_eq!(
+            calculate_erase_wait_time_with_options(new_size, &EraseWaitOptions::default()),
+            calculate_erase_wait_time(new_size)
+        );
+    }
+
+    #[test]
+    fn test_erase_wait_full_bank() {
+        let options = EraseWaitOptions {
+            previous_firmware_size: Some(10 * FLASH_PAGE_SIZE),
+            full_bank: true,
+        };
+        assert_eq!(
+            calculate_erase_wait_time_with_options(FLASH_PAGE_SIZE, &options),
+            calculate_erase_wait_time(APPLICATION_BANK_SIZE)
+        );
+    }
 }
@@ -407,8 +407,8 @@ pub fn wait_for_bootloader_flexible(
 
             if consecutive_detections >= REQUIRED_CONSECUTIVE {
                 if !identifier.matches(&device) {
-                    eprintln!(
-                        "[DFU] Device found via VidPidPort fallback on port {} \
+                    log::info!(
+                        "Device found via VidPidPort fallback on port {} \
                          (serial number likely changed during first-time DFU)",
                         device.port
                     );
@@ -478,8 +478,8 @@ pub fn wait_for_application_flexible(
 
             if consecutive_detections >= REQUIRED_CONSECUTIVE {
                 if !identifier.matches(&device) {
-                    eprintln!(
-                        "[DFU] Device found via VidPidPort fallback on port {} \
+                    log::info!(
+                        "Device found via VidPidPort fallback on port {} \
                          (serial number likely changed after DFU)",
                         device.port
                     );
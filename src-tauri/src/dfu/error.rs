@@ -56,6 +56,10 @@ pub enum DfuError {
     #[error("Max retries exceeded for {operation}")]
     MaxRetriesExceeded { operation: String },
 
+    /// The caller's overall deadline for the operation passed.
+    #[error("Firmware installation did not finish within the time allowed")]
+    DeadlineExceeded,
+
     /// DFU protocol returned an error response.
     #[error("DFU response error: code {code} - {message}")]
     DfuResponse { code: u8, message: String },
@@ -68,6 +72,10 @@ pub enum DfuError {
     #[error("Invalid manifest: {reason}")]
     InvalidManifest { reason: String },
 
+    /// File is not a UF2 image for this board, or the UF2 drive rejected it.
+    #[error("Invalid UF2 image: {reason}")]
+    InvalidUf2 { reason: String },
+
     /// No compatible nRF52 device found.
     #[error("No compatible device found")]
     NoDeviceFound,
@@ -209,9 +217,11 @@ impl DfuError {
             DfuError::Timeout => "DFU-021",
             DfuError::BootloaderTimeout { .. } => "DFU-022",
             DfuError::MaxRetriesExceeded { .. } => "DFU-023",
+            DfuError::DeadlineExceeded => "DFU-024",
             DfuError::DfuResponse { .. } => "DFU-030",
             DfuError::MissingFile { .. } => "DFU-040",
             DfuError::InvalidManifest { .. } => "DFU-041",
+            DfuError::InvalidUf2 { .. } => "DFU-042",
             DfuError::NoDeviceFound => "DFU-050",
             DfuError::DeviceDisconnected { .. } => "DFU-051",
             DfuError::PortBusy { .. } => "DFU-052",
@@ -226,6 +236,42 @@ impl DfuError {
             DfuError::Cancelled => "DFU-099",
         }
     }
+
+    /// Get a stable, machine-readable key for this error's message.
+    ///
+    /// The frontend translates by key and falls back to the English
+    /// `Display` text. Keys must not change once released.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            DfuError::Serial(_) => "dfu.error.serial",
+            DfuError::Io(_) => "dfu.error.io",
+            DfuError::Zip(_) => "dfu.error.zip",
+            DfuError::Json(_) => "dfu.error.json",
+            DfuError::InvalidSlipEscape => "dfu.error.invalid_slip_escape",
+            DfuError::SlipBufferOverflow { .. } => "dfu.error.slip_buffer_overflow",
+            DfuError::IncompleteSlipFrame => "dfu.error.incomplete_slip_frame",
+            DfuError::CrcMismatch { .. } => "dfu.error.crc_mismatch",
+            DfuError::Timeout => "dfu.error.timeout",
+            DfuError::BootloaderTimeout { .. } => "dfu.error.bootloader_timeout",
+            DfuError::MaxRetriesExceeded { .. } => "dfu.error.max_retries_exceeded",
+            DfuError::DeadlineExceeded => "dfu.error.deadline_exceeded",
+            DfuError::DfuResponse { .. } => "dfu.error.dfu_response",
+            DfuError::MissingFile { .. } => "dfu.error.missing_file",
+            DfuError::InvalidManifest { .. } => "dfu.error.invalid_manifest",
+            DfuError::InvalidUf2 { .. } => "dfu.error.invalid_uf2",
+            DfuError::NoDeviceFound => "dfu.error.no_device_found",
+            DfuError::DeviceDisconnected { .. } => "dfu.error.device_disconnected",
+            DfuError::PortBusy { .. } => "dfu.error.port_busy",
+            DfuError::PortPermissionDenied { .. } => "dfu.error.port_permission_denied",
+            DfuError::SequenceMismatch { .. } => "dfu.error.sequence_mismatch",
+            DfuError::PacketTooLarge { .. } => "dfu.error.packet_too_large",
+            DfuError::RoleConfigFailed { .. } => "dfu.error.role_config_failed",
+            DfuError::ProfileConfigFailed { .. } => "dfu.error.profile_config_failed",
+            DfuError::SettingConfigFailed { .. } => "dfu.error.setting_config_failed",
+            DfuError::NoSerialNumber => "dfu.error.no_serial_number",
+            DfuError::Cancelled => "dfu.error.cancelled",
+        }
+    }
 }
 
 // Note: DFU response status codes are defined in config.rs as DfuResponseStatus
@@ -271,4 +317,111 @@ mod tests {
         assert_eq!(DfuError::Timeout.error_code(), "DFU-021");
         assert_eq!(DfuError::NoDeviceFound.error_code(), "DFU-050");
     }
+
+    #[test]
+    fn test_message_keys_are_stable() {
+        // Snapshot of the message keys the frontend translates.
+        // Changing a key breaks existing translations - add new keys instead.
+        let errors = [
+            DfuError::Serial(serialport::Error::new(
+                serialport::ErrorKind::NoDevice,
+                "gone",
+            )),
+            DfuError::Io(std::io::Error::other("io")),
+            DfuError::Zip(zip::result::ZipError::FileNotFound),
+            DfuError::Json(serde_json::from_str::<u8>("x").unwrap_err()),
+            DfuError::InvalidSlipEscape,
+            DfuError::SlipBufferOverflow {
+                size: 0,
+                max_size: 0,
+            },
+            DfuError::IncompleteSlipFrame,
+            DfuError::CrcMismatch {
+                expected: 0,
+                actual: 0,
+            },
+            DfuError::Timeout,
+            DfuError::BootloaderTimeout { timeout_ms: 0 },
+            DfuError::MaxRetriesExceeded {
+                operation: String::new(),
+            },
+            DfuError::DeadlineExceeded,
+            DfuError::DfuResponse {
+                code: 0,
+                message: String::new(),
+            },
+            DfuError::MissingFile {
+                filename: String::new(),
+            },
+            DfuError::InvalidManifest {
+                reason: String::new(),
+            },
+            DfuError::InvalidUf2 {
+                reason: String::new(),
+            },
+            DfuError::NoDeviceFound,
+            DfuError::DeviceDisconnected {
+                operation: String::new(),
+            },
+            DfuError::PortBusy {
+                port: String::new(),
+            },
+            DfuError::PortPermissionDenied {
+                port: String::new(),
+            },
+            DfuError::SequenceMismatch {
+                expected: 0,
+                actual: 0,
+            },
+            DfuError::PacketTooLarge {
+                size: 0,
+                max_size: 0,
+            },
+            DfuError::RoleConfigFailed {
+                reason: String::new(),
+            },
+            DfuError::ProfileConfigFailed {
+                reason: String::new(),
+            },
+            DfuError::SettingConfigFailed {
+                reason: String::new(),
+            },
+            DfuError::NoSerialNumber,
+            DfuError::Cancelled,
+        ];
+        let keys: Vec<&str> = errors.iter().map(|e| e.message_key()).collect();
+
+        assert_eq!(
+            keys,
+            vec![
+                "dfu.error.serial",
+                "dfu.error.io",
+                "dfu.error.zip",
+                "dfu.error.json",
+                "dfu.error.invalid_slip_escape",
+                "dfu.error.slip_buffer_overflow",
+                "dfu.error.incomplete_slip_frame",
+                "dfu.error.crc_mismatch",
+                "dfu.error.timeout",
+                "dfu.error.bootloader_timeout",
+                "dfu.error.max_retries_exceeded",
+                "dfu.error.deadline_exceeded",
+                "dfu.error.dfu_response",
+                "dfu.error.missing_file",
+                "dfu.error.invalid_manifest",
+                "dfu.error.invalid_uf2",
+                "dfu.error.no_device_found",
+                "dfu.error.device_disconnected",
+                "dfu.error.port_busy",
+                "dfu.error.port_permission_denied",
+                "dfu.error.sequence_mismatch",
+                "dfu.error.packet_too_large",
+                "dfu.error.role_config_failed",
+                "dfu.error.profile_config_failed",
+                "dfu.error.setting_config_failed",
+                "dfu.error.no_serial_number",
+                "dfu.error.cancelled",
+            ]
+        );
+    }
 }
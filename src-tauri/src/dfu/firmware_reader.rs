@@ -11,6 +11,7 @@ use std::path::Path;
 use serde::Deserialize;
 
 use super::error::{DfuError, DfuResult};
+use super::packet::calc_crc16;
 
 /// Contents of a DFU firmware package.
 #[derive(Debug)]
@@ -32,12 +33,21 @@ pub struct ManifestData {
     pub firmware_crc16: u16,
     /// DFU version from manifest.
     pub dfu_version: f32,
+    /// Application version from the init packet; 0xFFFFFFFF when unset.
+    pub application_version: u32,
     /// Name of the binary file.
     bin_file: String,
     /// Name of the init packet file.
     dat_file: String,
 }
 
+impl FirmwarePackage {
+    /// Check the firmware binary against the CRC16 in the manifest.
+    pub fn crc_matches(&self) -> bool {
+        calc_crc16(&self.firmware_data, 0xFFFF) == self.manifest.firmware_crc16
+    }
+}
+
 /// Raw manifest.json structure for deserialization.
 #[derive(Debug, Deserialize)]
 struct RawManifest {
@@ -114,6 +124,11 @@ fn read_manifest(archive: &mut zip::ZipArchive<std::fs::File>) -> DfuResult<Mani
         device_type: raw.manifest.application.init_packet_data.device_type,
         firmware_crc16: raw.manifest.application.init_packet_data.firmware_crc16,
         dfu_version: raw.manifest.dfu_version,
+        application_version: raw
+            .manifest
+            .application
+            .init_packet_data
+            .application_version,
         bin_file: raw.manifest.application.bin_file,
         dat_file: raw.manifest.application.dat_file,
     })
@@ -201,6 +216,21 @@ mod tests {
         assert_eq!(package.manifest.device_type, 82);
         assert_eq!(package.manifest.firmware_crc16, 18974);
         assert_eq!(package.manifest.dfu_version, 0.5);
+        assert_eq!(package.manifest.application_version, u32::MAX);
+    }
+
+    #[test]
+    fn test_crc_matches() {
+        let dir = TempDir::new().unwrap();
+        let crc = calc_crc16(&[0x01, 0x02, 0x03, 0x04], 0xFFFF);
+        let manifest = VALID_MANIFEST.replace("18974", &crc.to_string());
+        let zip_path = create_test_zip(&dir, Some(&manifest), true, true);
+
+        let mut package = read_firmware_zip(&zip_path).unwrap();
+        assert!(package.crc_matches());
+
+        package.firmware_data[0] ^= 0xFF;
+        assert!(!package.crc_matches());
     }
 
     #[test]
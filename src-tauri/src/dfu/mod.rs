@@ -13,7 +13,7 @@
 //! 4. **Firmware Transfer** - Send firmware.bin in chunks
 //! 5. **Validation** - Device validates the firmware CRC
 //! 6. **Activation** - Device applies and boots new firmware
-//! 7. **Configuration** - Send role configuration command
+//! 7. **Configuration** - Send role configuration command (optional)
 //!
 //! # Example
 //!
@@ -27,7 +27,9 @@
 //!     protocol::upload_firmware(
 //!         &device.port,
 //!         "firmware.zip",
-//!         "PRIMARY",
+//!         Some("PRIMARY"),
+//!         EraseWaitOptions::default(),
+//!         DfuTimingConfig::default(),
 //!         |stage| println!("{}: {:.0}%", stage.message(), stage.percent()),
 //!     )?;
 //! }
@@ -37,10 +39,12 @@ mod config;
 mod device;
 mod error;
 mod firmware_reader;
+mod monitor;
 mod packet;
 mod protocol;
 mod slip;
 mod transport;
+mod uf2;
 
 // Re-export public types and functions
 // Only exports what's actually used by the Tauri commands
@@ -55,15 +59,57 @@ pub mod device_pub {
 pub use device_pub::*;
 
 // Protocol
-pub use protocol::{configure_device_with_settings, upload_firmware, DfuStage};
+pub use protocol::{
+    configure_device_role_flexible, configure_device_with_settings, describe_setting_command,
+    identify_device, profile_command, query_device, query_device_settings, send_raw_command,
+    upload_firmware, DfuStage, QueryAnswer, SettingsAnswer,
+};
 
-// Error types — re-exported for use in tests outside this module
-#[cfg(test)]
+// Read-only device queries
+pub use config::{
+    DEVICE_QUERY_BUDGET_MS, GET_PROFILE_COMMAND, GET_ROLE_COMMAND, GET_VERSION_COMMAND,
+};
+
+// Therapy profiles
+pub use config::{BUILT_IN_PROFILES, REBOOT_COMMAND};
+
+// Board identification
+pub use config::BoardModel;
+
+// Flash timing options and estimates
+pub use config::{
+    estimate_flash_duration_ms, DfuTimingConfig, EraseWaitOptions, NRF52840_DEVICE_TYPE,
+};
+
+// Device detection
+pub use config::DEVICE_RESCAN_DELAY_MS;
+
+// Serial monitor
+pub use monitor::SerialMonitor;
+
+// Developer commands
+pub use config::{
+    DEVICE_COMMAND_TIMEOUT_MS, MAX_DEVICE_COMMAND_LEN, MAX_DEVICE_COMMAND_TIMEOUT_MS,
+    READ_ONLY_COMMANDS,
+};
+
+// Error types — commands turn DfuError into a keyed CommandError
 pub use error::DfuError;
+#[cfg(test)]
+pub use error::DfuResult;
+
+// Protocol internals — re-exported so command tests can drive a mock transport
+#[cfg(test)]
+pub use protocol::HciDfuProtocol;
+#[cfg(test)]
+pub use transport::DfuTransport;
 
 // Firmware reading
 pub use firmware_reader::read_firmware_zip;
 
+// UF2 flashing through the bootloader drive
+pub use uf2::{find_uf2_volumes, flash_uf2, Uf2ProgressEvent};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,148 @@
+//! Serial monitor for the log output of application firmware.
+//!
+//! The firmware prints `[TAG] message` lines over USB serial. The monitor
+//! reads them as they arrive and splits them into lines, keeping partial
+//! lines across reads. Bytes that aren't valid UTF-8 are kept as `\xNN`
+//! escapes rather than replaced, so nothing the device sent is lost.
+
+use super::config::{MAX_MONITOR_LINE_BYTES, MONITOR_READ_TIMEOUT_MS};
+use super::error::{DfuError, DfuResult};
+use super::transport::{DfuTransport, SerialTransport};
+
+/// Decode bytes as UTF-8, hex-escaping invalid bytes.
+fn decode_lossless(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            text.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    text
+}
+
+/// Splits a byte stream into lines.
+#[derive(Debug, Default)]
+pub struct LineDecoder {
+    partial: Vec<u8>,
+}
+
+impl LineDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add bytes read from the device and return the lines they complete.
+    ///
+    /// Line endings (`\n` or `\r\n`) are stripped. A line longer than
+    /// `MAX_MONITOR_LINE_BYTES` is returned in pieces.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for &byte in bytes {
+            if byte == b'\n' {
+                if self.partial.last() == Some(&b'\r') {
+                    self.partial.pop();
+                }
+                lines.push(decode_lossless(&self.partial));
+                self.partial.clear();
+            } else {
+                self.partial.push(byte);
+                if self.partial.len() >= MAX_MONITOR_LINE_BYTES {
+                    lines.push(decode_lossless(&self.partial));
+                    self.partial.clear();
+                }
+            }
+        }
+
+        lines
+    }
+
+    /// The unterminated last line, if any.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.partial.is_empty() {
+            return None;
+        }
+        let line = decode_lossless(&self.partial);
+        self.partial.clear();
+        Some(line)
+    }
+}
+
+/// An open serial port being monitored.
+pub struct SerialMonitor {
+    transport: SerialTransport,
+    decoder: LineDecoder,
+}
+
+impl SerialMonitor {
+    /// Open the port of a device in application mode.
+    pub fn open(port_name: &str) -> DfuResult<Self> {
+        Ok(Self {
+            transport: SerialTransport::open(port_name)?,
+            decoder: LineDecoder::new(),
+        })
+    }
+
+    /// Wait up to `MONITOR_READ_TIMEOUT_MS` for output and return the lines
+    /// it completes, which may be none.
+    ///
+    /// Fails with `DeviceDisconnected` once the port stops responding.
+    pub fn read_lines(&mut self) -> DfuResult<Vec<String>> {
+        let mut buffer = [0u8; 256];
+        let bytes_read = self.transport.read(&mut buffer, MONITOR_READ_TIMEOUT_MS)?;
+
+        if bytes_read == 0 && !self.transport.is_healthy() {
+            return Err(DfuError::DeviceDisconnected {
+                operation: "serial monitor".to_string(),
+            });
+        }
+
+        Ok(self.decoder.push(&buffer[..bytes_read]))
+    }
+
+    /// Close the port, returning the unterminated last line, if any.
+    pub fn close(mut self) -> Option<String> {
+        self.decoder.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_decoder_buffers_partial_lines() {
+        let mut decoder = LineDecoder::new();
+
+        assert!(decoder.push(b"[READY] Blue").is_empty());
+        assert_eq!(
+            decoder.push(b"Buzzah\r\n[VERSION] 2.3.1\n[ROLE"),
+            vec!["[READY] BlueBuzzah", "[VERSION] 2.3.1"]
+        );
+        assert_eq!(decoder.push(b"] PRIMARY\r\n"), vec!["[ROLE] PRIMARY"]);
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn test_line_decoder_escapes_invalid_utf8() {
+        let mut decoder = LineDecoder::new();
+
+        // A multi-byte character split across reads is still decoded
+        assert!(decoder.push(&[b'a', 0xC3]).is_empty());
+        assert_eq!(decoder.push(&[0xA9, 0xFF, b'\n']), vec!["a\u{e9}\\xFF"]);
+
+        decoder.push(&[0x80, b'z']);
+        assert_eq!(decoder.finish(), Some("\\x80z".to_string()));
+    }
+
+    #[test]
+    fn test_line_decoder_splits_overlong_lines() {
+        let mut decoder = LineDecoder::new();
+        let lines = decoder.push(&vec![b'x'; MAX_MONITOR_LINE_BYTES + 1]);
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].len(), MAX_MONITOR_LINE_BYTES);
+        assert_eq!(decoder.finish(), Some("x".to_string()));
+    }
+}
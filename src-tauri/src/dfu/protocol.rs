@@ -7,18 +7,21 @@
 //! 4. StopDataPacket - End transfer
 //! 5. Role configuration (post-reboot)
 
+use std::cell::Cell;
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 
 use super::config::{
-    calculate_erase_wait_time, get_bootloader_timeout, get_reboot_settle_delay, get_reboot_timeout,
-    ACK_TIMEOUT_MS, CONFIG_RETRY_DELAY_MS, FIRMWARE_TRANSFER_TIMEOUT_SECS,
-    FLASH_PAGE_WRITE_TIME_MS, FRAMES_PER_FLASH_PAGE, MAX_CONFIG_RETRIES, MAX_PACKET_RETRIES,
+    calculate_erase_wait_time_with_options, get_reboot_settle_delay, get_reboot_timeout,
+    DfuTimingConfig, EraseWaitOptions, CONFIG_RETRY_DELAY_MS, DEVICE_COMMAND_IDLE_MS,
+    FIRMWARE_TRANSFER_TIMEOUT_SECS, FLASH_PAGE_WRITE_TIME_MS, FRAMES_PER_FLASH_PAGE,
+    GET_SETTINGS_COMMAND, IDENTIFY_COMMAND, IDENTIFY_TIMEOUT_MS, MAX_CONFIG_RETRIES,
     PROFILE_CONFIG_TIMEOUT_MS, PROFILE_GENTLE_COMMAND, PROFILE_HYBRID_COMMAND,
-    PROFILE_NOISY_COMMAND, PROFILE_REGULAR_COMMAND, RETRY_BASE_DELAY_MS, ROLE_CONFIG_TIMEOUT_MS,
-    ROLE_PRIMARY_COMMAND, ROLE_SECONDARY_COMMAND,
+    PROFILE_NOISY_COMMAND, PROFILE_REGULAR_COMMAND, QUERY_TIMEOUT_MS, REBOOT_COMMAND,
+    RETRY_BASE_DELAY_MS, ROLE_CONFIG_TIMEOUT_MS, ROLE_PRIMARY_COMMAND, ROLE_SECONDARY_COMMAND,
 };
 use super::device::{
     get_device_by_port, snapshot_ports, wait_for_application_by_serial,
@@ -31,6 +34,7 @@ use super::packet::{
     reset_sequence_number, HciAck, HciSlipDecoder, FIRMWARE_CHUNK_SIZE, IMAGE_TYPE_APPLICATION,
 };
 use super::transport::{DfuTransport, SerialTransport};
+use crate::settings::unsupported_setting;
 
 /// DFU progress stages for UI feedback.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +68,10 @@ pub enum DfuStage {
     Log { message: String },
     /// Operation cancelled by user.
     Cancelled,
+    /// Operation stopped because the caller's deadline passed.
+    TimedOut,
+    /// A failed attempt is being retried; `attempt` counts from 1.
+    Retrying { attempt: u32, max_attempts: u32 },
 }
 
 impl DfuStage {
@@ -90,8 +98,8 @@ impl DfuStage {
             DfuStage::Complete => 100.0,
             // Log messages don't affect progress percentage
             DfuStage::Log { .. } => -1.0,
-            // Cancelled doesn't affect progress percentage
-            DfuStage::Cancelled => -1.0,
+            // Cancelled, timed out and retrying don't affect progress percentage
+            DfuStage::Cancelled | DfuStage::TimedOut | DfuStage::Retrying { .. } => -1.0,
         }
     }
 
@@ -126,15 +134,80 @@ impl DfuStage {
             DfuStage::Complete => "Update complete!".into(),
             DfuStage::Log { message } => message.clone(),
             DfuStage::Cancelled => "Cancelled by user".into(),
+            DfuStage::TimedOut => "Stopped: time limit reached".into(),
+            DfuStage::Retrying {
+                attempt,
+                max_attempts,
+            } => format!(
+                "Retrying firmware installation (attempt {}/{})...",
+                attempt, max_attempts
+            ),
+        }
+    }
+
+    /// Get a stable, machine-readable key for this stage's message.
+    ///
+    /// The frontend uses this to look up a translation, falling back to
+    /// `message()` when none exists. Keys must not change once released;
+    /// any stage parameters are carried separately (e.g. `sent`/`total`).
+    pub fn key(&self) -> &'static str {
+        match self {
+            DfuStage::ReadingPackage => "dfu.stage.reading",
+            DfuStage::DetectedDevice { .. } => "dfu.stage.detected",
+            DfuStage::EnteringBootloader => "dfu.stage.bootloader",
+            DfuStage::WaitingForBootloader => "dfu.stage.waiting",
+            DfuStage::Connecting => "dfu.stage.connecting",
+            DfuStage::Starting => "dfu.stage.starting",
+            DfuStage::SendingInit => "dfu.stage.init",
+            DfuStage::Uploading { .. } => "dfu.stage.uploading",
+            DfuStage::Finalizing => "dfu.stage.finalizing",
+            DfuStage::WaitingForReboot => "dfu.stage.rebooting",
+            DfuStage::ConfiguringRole => "dfu.stage.configuring",
+            DfuStage::Complete => "dfu.stage.complete",
+            DfuStage::Log { .. } => "dfu.stage.log",
+            DfuStage::Cancelled => "dfu.stage.cancelled",
+            DfuStage::TimedOut => "dfu.stage.timed_out",
+            DfuStage::Retrying { .. } => "dfu.stage.retrying",
+        }
+    }
+
+    /// Write this stage to the app log: protocol lines at debug, per-packet
+    /// upload progress at trace and every other stage at info.
+    fn log(&self) {
+        match self {
+            DfuStage::Log { message } => log::debug!("{}", message),
+            DfuStage::Uploading { .. } => log::trace!("{}", self.message()),
+            _ => log::info!("{}", self.message()),
         }
     }
 }
 
+thread_local! {
+    /// Set while a bridged callback forwards a line, so when one bridged
+    /// call wraps another's callback, the line is logged once rather than
+    /// once per bridge.
+    static BRIDGE_FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Wrap a caller's `log` callback so every line also goes to the app log,
+/// which then has the whole exchange whatever the caller shows of it.
+fn bridge_log<L: Fn(&str) + Clone>(forward: L) -> impl Fn(&str) + Clone {
+    move |message: &str| {
+        if BRIDGE_FORWARDING.with(|forwarding| forwarding.replace(true)) {
+            return forward(message);
+        }
+        log::debug!("{}", message);
+        forward(message);
+        BRIDGE_FORWARDING.with(|forwarding| forwarding.set(false));
+    }
+}
+
 /// HCI-based DFU protocol handler.
 pub struct HciDfuProtocol<T: DfuTransport, L: Fn(&str)> {
     transport: T,
     slip_decoder: HciSlipDecoder,
     log: L,
+    timing: DfuTimingConfig,
 }
 
 impl<T: DfuTransport, L: Fn(&str)> HciDfuProtocol<T, L> {
@@ -147,9 +220,16 @@ impl<T: DfuTransport, L: Fn(&str)> HciDfuProtocol<T, L> {
             transport,
             slip_decoder: HciSlipDecoder::new(),
             log,
+            timing: DfuTimingConfig::default(),
         }
     }
 
+    /// Use `timing` for ACK waits and packet retries instead of the defaults.
+    pub fn with_timing(mut self, timing: DfuTimingConfig) -> Self {
+        self.timing = timing;
+        self
+    }
+
     /// Verify the connection is still healthy before a critical operation.
     ///
     /// Returns an error if the connection appears to be stale or disconnected.
@@ -210,14 +290,15 @@ impl<T: DfuTransport, L: Fn(&str)> HciDfuProtocol<T, L> {
 
     /// Send a packet and wait for ACK with automatic retry on transient failures.
     ///
-    /// Uses exponential backoff: 100ms, 200ms, 400ms between retries.
+    /// Uses exponential backoff: 100ms, 200ms, 400ms, ... between retries.
     /// Retries on timeout, CRC mismatch, and sequence mismatch errors.
     /// All retry attempts are logged transparently for debugging.
     fn send_and_wait_ack(&mut self, packet: &[u8]) -> DfuResult<()> {
         // Debug: log packet being sent
         (self.log)(&format!("Sending data ({} bytes)", packet.len()));
 
-        for attempt in 0..=MAX_PACKET_RETRIES {
+        let max_retries = self.timing.max_packet_retries;
+        for attempt in 0..=max_retries {
             match self.send_and_wait_ack_once(packet) {
                 Ok(ack) => {
                     // Log recovery if we had to retry
@@ -230,14 +311,14 @@ impl<T: DfuTransport, L: Fn(&str)> HciDfuProtocol<T, L> {
                     (self.log)(&format!("Received ACK: seq={}", ack.ack_number));
                     return Ok(());
                 }
-                Err(e) if e.is_retriable() && attempt < MAX_PACKET_RETRIES => {
+                Err(e) if e.is_retriable() && attempt < max_retries => {
                     // Calculate exponential backoff delay: 100ms, 200ms, 400ms
                     let delay_ms = RETRY_BASE_DELAY_MS * 2u64.pow(attempt);
 
                     (self.log)(&format!(
                         "Retry {}/{}: {}, waiting {}ms...",
                         attempt + 1,
-                        MAX_PACKET_RETRIES,
+                        max_retries,
                         e,
                         delay_ms
                     ));
@@ -271,7 +352,7 @@ impl<T: DfuTransport, L: Fn(&str)> HciDfuProtocol<T, L> {
 
     /// Wait for an ACK response from the bootloader.
     fn wait_for_ack(&mut self) -> DfuResult<HciAck> {
-        let timeout = Duration::from_millis(ACK_TIMEOUT_MS);
+        let timeout = Duration::from_millis(self.timing.ack_timeout_ms);
         let start = Instant::now();
         let mut buffer = [0u8; 512];
 
@@ -381,13 +462,68 @@ impl<T: DfuTransport, L: Fn(&str)> HciDfuProtocol<T, L> {
 /// # Arguments
 /// * `port_name` - Serial port of the device (application OR bootloader mode)
 /// * `firmware_zip_path` - Path to the firmware.zip file
-/// * `device_role` - Role to configure ("PRIMARY" or "SECONDARY")
+/// * `device_role` - Role to configure ("PRIMARY" or "SECONDARY"), or `None`
+///   to keep the role stored on the device
+/// * `erase_options` - Inputs for the post-START flash erase wait
+/// * `timing` - Bootloader and ACK timeouts and packet retries
+/// * `deadline` - When to give up on the whole upload, if ever
 /// * `on_progress` - Callback for progress updates
 /// * `is_cancelled` - Closure that returns true if cancellation was requested
+///
+/// With a role, the flow ends `WaitingForReboot` → `ConfiguringRole` →
+/// `Complete`. Without one it ends `WaitingForReboot` → `Complete`, and
+/// `ConfiguringRole` is never emitted.
+///
+/// The deadline is observed wherever cancellation is: once it passes, the
+/// upload stops at its next check, emits `TimedOut` instead of `Cancelled`
+/// and fails with `DeadlineExceeded`.
+#[allow(clippy::too_many_arguments)]
 pub fn upload_firmware<P, F, C>(
     port_name: &str,
     firmware_zip_path: P,
-    device_role: &str,
+    device_role: Option<&str>,
+    erase_options: EraseWaitOptions,
+    timing: DfuTimingConfig,
+    deadline: Option<Instant>,
+    on_progress: F,
+    is_cancelled: C,
+) -> DfuResult<()>
+where
+    P: AsRef<Path>,
+    F: Fn(DfuStage),
+    C: Fn() -> bool,
+{
+    let timed_out = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+    let result = upload_firmware_inner(
+        port_name,
+        firmware_zip_path,
+        device_role,
+        erase_options,
+        timing,
+        |stage| {
+            let stage = match stage {
+                DfuStage::Cancelled if timed_out() => DfuStage::TimedOut,
+                stage => stage,
+            };
+            stage.log();
+            on_progress(stage)
+        },
+        || is_cancelled() || timed_out(),
+    );
+
+    match result {
+        Err(DfuError::Cancelled) if timed_out() => Err(DfuError::DeadlineExceeded),
+        result => result,
+    }
+}
+
+fn upload_firmware_inner<P, F, C>(
+    port_name: &str,
+    firmware_zip_path: P,
+    device_role: Option<&str>,
+    erase_options: EraseWaitOptions,
+    timing: DfuTimingConfig,
     on_progress: F,
     is_cancelled: C,
 ) -> DfuResult<()>
@@ -447,7 +583,7 @@ where
 
         on_progress(DfuStage::WaitingForBootloader);
         let bootloader_device =
-            wait_for_bootloader_flexible(&device_identifier, get_bootloader_timeout())?;
+            wait_for_bootloader_flexible(&device_identifier, timing.bootloader_timeout_ms)?;
         bootloader_device.port
     } else {
         // Device is in application mode - use 1200 baud touch to enter bootloader
@@ -461,7 +597,7 @@ where
 
         on_progress(DfuStage::WaitingForBootloader);
         let bootloader_device =
-            wait_for_bootloader_flexible(&device_identifier, get_bootloader_timeout())?;
+            wait_for_bootloader_flexible(&device_identifier, timing.bootloader_timeout_ms)?;
         bootloader_device.port
     };
 
@@ -482,7 +618,7 @@ where
         });
     };
 
-    let mut protocol = HciDfuProtocol::new(transport, log);
+    let mut protocol = HciDfuProtocol::new(transport, log).with_timing(timing);
 
     // Check for cancellation before starting DFU
     if is_cancelled() {
@@ -507,9 +643,18 @@ where
 
     // Wait for flash erase to complete (bootloader erases pages after START)
     // Use wait_with_drain to keep the serial port active on macOS
-    let erase_wait_ms = calculate_erase_wait_time(firmware_size);
+    let erase_wait_ms = calculate_erase_wait_time_with_options(firmware_size, &erase_options);
     on_progress(DfuStage::Log {
-        message: "Waiting for flash erase...".to_string(),
+        message: format!(
+            "Waiting {}ms for flash erase (new image: {} bytes, previous image: {}, full bank: {})",
+            erase_wait_ms,
+            firmware_size,
+            erase_options
+                .previous_firmware_size
+                .map(|size| format!("{} bytes", size))
+                .unwrap_or_else(|| "unknown".to_string()),
+            erase_options.full_bank
+        ),
     });
     protocol.wait_with_drain(erase_wait_ms)?;
     on_progress(DfuStage::Log {
@@ -570,7 +715,11 @@ where
         message: format!("Device found on port {} | snapshot: {}", app_device.port, snapshot_ports()),
     });
 
-    // Step 10: Configure device role (instrumented)
+    // Step 10: Configure device role (instrumented), unless the caller keeps the stored role
+    let Some(device_role) = device_role else {
+        on_progress(DfuStage::Complete);
+        return Ok(());
+    };
     on_progress(DfuStage::ConfiguringRole);
     let role_started = std::time::Instant::now();
     let role_result = configure_device_role_flexible(&app_device.port, device_role, &device_identifier)
@@ -702,8 +851,9 @@ fn configure_device_role(port_name: &str, role: &str, serial_number: &str) -> Df
 /// Configure the device role using flexible device tracking with retry logic.
 ///
 /// Works with both serial number and VID/PID+port pattern tracking.
-/// Includes automatic retry for timing-related failures.
-fn configure_device_role_flexible(
+/// Includes automatic retry for timing-related failures. Used after a flash
+/// and on its own by the `set_device_role` command.
+pub fn configure_device_role_flexible(
     port_name: &str,
     role: &str,
     identifier: &DeviceIdentifier,
@@ -718,7 +868,7 @@ fn configure_device_role_flexible(
             // Re-wait for device and capture updated port
             if let Ok(device) = wait_for_application_flexible(identifier, 5000) {
                 if device.port != current_port {
-                    eprintln!("[configure_device_role] Device reappeared on new port: {}", device.port);
+                    log::info!("Device reappeared on new port: {}", device.port);
                 }
                 current_port = device.port;
             }
@@ -870,7 +1020,7 @@ fn drain_boot_output(transport: &mut SerialTransport) -> DfuResult<bool> {
     Ok(found_marker)
 }
 
-/// Configure the device therapy profile via serial command (serial number tracking).
+/// Configure the device therapy profile using flexible device tracking.
 ///
 /// After receiving SET_PROFILE, the device responds with:
 /// - Success: "[CONFIG] Profile set to REGULAR - restarting..." (then reboots)
@@ -889,20 +1039,6 @@ fn drain_boot_output(transport: &mut SerialTransport) -> DfuResult<bool> {
 /// 1. Send the command and wait for the [CONFIG] acknowledgment
 /// 2. Wait for the device to reboot and reappear
 ///
-/// Note: For flexible device tracking, use `configure_device_profile_flexible()` instead.
-#[allow(dead_code)]
-pub fn configure_device_profile(port_name: &str, profile: &str, serial_number: &str) -> DfuResult<()> {
-    let identifier = DeviceIdentifier::Serial {
-        serial: serial_number.to_string(),
-        vid: super::config::ADAFRUIT_VID,
-        pid: 0x8029,
-        port_pattern: String::new(),
-    };
-    configure_device_profile_flexible(port_name, profile, &identifier, |_| {})
-}
-
-/// Configure the device therapy profile using flexible device tracking.
-///
 /// Works with both serial number and VID/PID+port pattern tracking.
 /// Includes enhanced boot detection and detailed logging.
 ///
@@ -911,12 +1047,17 @@ pub fn configure_device_profile(port_name: &str, profile: &str, serial_number: &
 /// * `profile` - Profile to set ("REGULAR", "NOISY", "HYBRID", or "GENTLE")
 /// * `identifier` - Device identifier for tracking through reboot
 /// * `log` - Callback for debug log messages
+///
+/// Note: `set_device_profile` goes through `configure_device_with_settings()`,
+/// which also applies advanced settings before the profile.
+#[allow(dead_code)]
 pub fn configure_device_profile_flexible<L: Fn(&str)>(
     port_name: &str,
     profile: &str,
     identifier: &DeviceIdentifier,
     log: L,
 ) -> DfuResult<()> {
+    let log = bridge_log(&log);
     let command = match profile.to_uppercase().as_str() {
         "REGULAR" => PROFILE_REGULAR_COMMAND,
         "NOISY" => PROFILE_NOISY_COMMAND,
@@ -1034,6 +1175,50 @@ pub fn configure_device_profile_flexible<L: Fn(&str)>(
 /// Timeout for setting command acknowledgment (shorter than profile commands).
 const SETTING_CONFIG_TIMEOUT_MS: u64 = 2000;
 
+/// Warning for setting commands that the chosen profile ignores.
+///
+/// REGULAR has no jitter, so a `JITTER_PERCENT` override does nothing there.
+fn ignored_settings_warning(profile: &str, pre_profile_commands: &[String]) -> Option<String> {
+    let overrides_jitter = pre_profile_commands
+        .iter()
+        .any(|command| command.starts_with("JITTER_PERCENT:"));
+
+    if overrides_jitter && profile.eq_ignore_ascii_case("REGULAR") {
+        Some("Warning: Jitter override has no effect with the REGULAR profile".to_string())
+    } else {
+        None
+    }
+}
+
+/// Human-readable form of a setting command, e.g. "Debug Mode = true" for
+/// `DEBUG:true`.
+pub fn describe_setting_command(command: &str) -> String {
+    let trimmed = command.trim();
+    if trimmed == "SCHEDULE_CLEAR" {
+        return "Clear Therapy Schedule".to_string();
+    }
+    let (setting_name, setting_value) = trimmed
+        .split_once(':')
+        .unwrap_or((trimmed, "unknown"));
+    if setting_name == "SCHEDULE_ADD" {
+        return format!("Therapy Window = {}", setting_value.replace(',', "-"));
+    }
+
+    let friendly_name = match setting_name {
+        "THERAPY_LED_OFF" => "Disable LED During Therapy",
+        "DEBUG" => "Debug Mode",
+        "LOW_POWER" => "Low Power Mode",
+        "VIBRATION_INTENSITY" => "Vibration Intensity",
+        "SESSION_DURATION" => "Session Duration",
+        "JITTER_PERCENT" => "Jitter Percent",
+        "MIRRORED" => "Mirrored",
+        "AMPLITUDE" => "Amplitude",
+        _ => setting_name,
+    };
+
+    format!("{} = {}", friendly_name, setting_value)
+}
+
 /// Send a single setting command and wait for acknowledgment.
 ///
 /// Unlike profile commands, setting commands do NOT trigger a device reboot.
@@ -1052,19 +1237,7 @@ fn send_setting_command<L: Fn(&str)>(
     command: &str,
     log: &L,
 ) -> DfuResult<()> {
-    // Parse command to create human-readable log message
-    let trimmed = command.trim();
-    let (setting_name, setting_value) = trimmed
-        .split_once(':')
-        .unwrap_or((trimmed, "unknown"));
-
-    let friendly_name = match setting_name {
-        "THERAPY_LED_OFF" => "Disable LED During Therapy",
-        "DEBUG" => "Debug Mode",
-        _ => setting_name,
-    };
-
-    log(&format!("Setting {} = {}", friendly_name, setting_value));
+    log(&format!("Setting {}", describe_setting_command(command)));
 
     transport.write(command.as_bytes())?;
     transport.flush()?;
@@ -1111,33 +1284,68 @@ fn send_setting_command<L: Fn(&str)>(
 /// 1. Opens the serial connection
 /// 2. Drains boot output (waits for device ready)
 /// 3. Sends each advanced setting command (no reboot triggered)
-/// 4. Sends the profile command (triggers reboot)
+/// 4. Sends the profile command, or REBOOT without a profile (triggers reboot)
 /// 5. Waits for device to reappear
 ///
 /// Includes automatic retry logic for timing-related failures.
 ///
+/// When the device's firmware version is known, setting commands it
+/// predates are skipped instead of sent, since its "[ERROR] Unknown command"
+/// would abort the configuration. Returns the skipped commands, without
+/// the newline.
+///
 /// # Arguments
 /// * `port_name` - Serial port of the device
-/// * `profile` - Profile to set ("REGULAR", "NOISY", "HYBRID", or "GENTLE")
+/// * `profile` - Profile to set ("REGULAR", "NOISY", "HYBRID", or "GENTLE"), or
+///   `None` to only apply the commands (custom profiles without a base profile)
 /// * `pre_profile_commands` - Commands to send before SET_PROFILE (from AdvancedSettings)
 /// * `identifier` - Device identifier for tracking through reboot
+/// * `firmware_version` - Version the device reported to GET_VERSION, if known
+/// * `max_retries` - Retries for timing-related failures
 /// * `log` - Callback for debug log messages
 pub fn configure_device_with_settings<L: Fn(&str) + Clone>(
     port_name: &str,
-    profile: &str,
+    profile: Option<&str>,
     pre_profile_commands: &[String],
     identifier: &DeviceIdentifier,
+    firmware_version: Option<&str>,
+    max_retries: u32,
     log: L,
-) -> DfuResult<()> {
+) -> DfuResult<Vec<String>> {
+    let log = bridge_log(log);
+    let mut skipped_settings = Vec::new();
+    let mut commands = Vec::new();
+    for command in pre_profile_commands {
+        let minimum = firmware_version
+            .and_then(|version| unsupported_setting(command, version).map(|m| (version, m)));
+        match minimum {
+            Some((version, minimum)) => {
+                log(&format!(
+                    "Skipping {}: needs firmware {} or newer, device has {}",
+                    describe_setting_command(command),
+                    minimum,
+                    version
+                ));
+                skipped_settings.push(command.trim().to_string());
+            }
+            None => commands.push(command.clone()),
+        }
+    }
+
+    if let Some(warning) = profile.and_then(|profile| ignored_settings_warning(profile, &commands))
+    {
+        log(&warning);
+    }
+
     let mut last_error: Option<DfuError> = None;
     let mut current_port = port_name.to_string();
 
-    for attempt in 0..=MAX_CONFIG_RETRIES {
+    for attempt in 0..=max_retries {
         // On retry, wait for device to stabilize and update port
         if attempt > 0 {
             log(&format!(
                 "Profile configuration retry {}/{}",
-                attempt, MAX_CONFIG_RETRIES
+                attempt, max_retries
             ));
             std::thread::sleep(Duration::from_millis(CONFIG_RETRY_DELAY_MS));
             // Re-wait for device and capture updated port
@@ -1150,12 +1358,12 @@ pub fn configure_device_with_settings<L: Fn(&str) + Clone>(
         match configure_device_with_settings_inner(
             &current_port,
             profile,
-            pre_profile_commands,
+            &commands,
             identifier,
             log.clone(),
         ) {
-            Ok(()) => return Ok(()),
-            Err(e) if e.is_retriable() && attempt < MAX_CONFIG_RETRIES => {
+            Ok(()) => return Ok(skipped_settings),
+            Err(e) if e.is_retriable() && attempt < max_retries => {
                 log(&format!("Profile configuration failed: {}, will retry", e));
                 last_error = Some(e);
             }
@@ -1169,28 +1377,42 @@ pub fn configure_device_with_settings<L: Fn(&str) + Clone>(
     }))
 }
 
+/// SET_PROFILE command for a built-in profile name.
+pub fn profile_command(profile: &str) -> DfuResult<&'static str> {
+    match profile.to_uppercase().as_str() {
+        "REGULAR" => Ok(PROFILE_REGULAR_COMMAND),
+        "NOISY" => Ok(PROFILE_NOISY_COMMAND),
+        "HYBRID" => Ok(PROFILE_HYBRID_COMMAND),
+        "GENTLE" => Ok(PROFILE_GENTLE_COMMAND),
+        _ => Err(DfuError::ProfileConfigFailed {
+            reason: format!(
+                "Invalid profile: {}. Valid profiles: REGULAR, NOISY, HYBRID, GENTLE",
+                profile
+            ),
+        }),
+    }
+}
+
+/// Wait for a device to come back after a command that reboots it.
+fn wait_for_reboot<L: Fn(&str)>(identifier: &DeviceIdentifier, log: &L) -> DfuResult<()> {
+    log("Waiting for device to reboot...");
+    std::thread::sleep(Duration::from_millis(get_reboot_settle_delay()));
+    wait_for_application_flexible(identifier, get_reboot_timeout())?;
+    log("Device reappeared after reboot");
+    Ok(())
+}
+
 /// Inner implementation of settings/profile configuration without retry logic.
 fn configure_device_with_settings_inner<L: Fn(&str)>(
     port_name: &str,
-    profile: &str,
+    profile: Option<&str>,
     pre_profile_commands: &[String],
     identifier: &DeviceIdentifier,
     log: L,
 ) -> DfuResult<()> {
-    let profile_command = match profile.to_uppercase().as_str() {
-        "REGULAR" => PROFILE_REGULAR_COMMAND,
-        "NOISY" => PROFILE_NOISY_COMMAND,
-        "HYBRID" => PROFILE_HYBRID_COMMAND,
-        "GENTLE" => PROFILE_GENTLE_COMMAND,
-        _ => {
-            return Err(DfuError::ProfileConfigFailed {
-                reason: format!(
-                    "Invalid profile: {}. Valid profiles: REGULAR, NOISY, HYBRID, GENTLE",
-                    profile
-                ),
-            })
-        }
-    };
+    let profile = profile
+        .map(|profile| profile_command(profile).map(|command| (profile, command)))
+        .transpose()?;
 
     log(&format!("Opening serial port: {}", port_name));
     let mut transport = SerialTransport::open(port_name)?;
@@ -1227,6 +1449,13 @@ fn configure_device_with_settings_inner<L: Fn(&str)>(
     }
 
     // Phase 2: Send profile command (this triggers reboot)
+    let Some((profile, profile_command)) = profile else {
+        log("Sending reboot command");
+        transport.write(REBOOT_COMMAND.as_bytes())?;
+        transport.flush()?;
+        drop(transport);
+        return wait_for_reboot(identifier, &log);
+    };
     log(&format!("Sending profile command: {}", profile));
     transport.write(profile_command.as_bytes())?;
     transport.flush()?;
@@ -1249,12 +1478,7 @@ fn configure_device_with_settings_inner<L: Fn(&str)>(
                 log("Profile configuration acknowledged");
                 drop(transport);
 
-                log("Waiting for device to reboot...");
-                std::thread::sleep(Duration::from_millis(get_reboot_settle_delay()));
-                wait_for_application_flexible(identifier, get_reboot_timeout())?;
-                log("Device reappeared after reboot");
-
-                return Ok(());
+                return wait_for_reboot(identifier, &log);
             }
 
             if response_str.contains("[ERROR]") {
@@ -1287,9 +1511,414 @@ fn configure_device_with_settings_inner<L: Fn(&str)>(
     })
 }
 
+// =============================================================================
+// Device Identification
+// =============================================================================
+
+/// Outcome of an identify response so far: `Some(true)` once the firmware
+/// acknowledges, `Some(false)` if it rejects the command (firmware without
+/// IDENTIFY answers unknown commands with `[ERROR]`), `None` while waiting.
+fn identify_acknowledged(response: &str) -> Option<bool> {
+    if response.contains("[IDENTIFY]") {
+        Some(true)
+    } else if response.contains("[ERROR]") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Ask a device in application mode to blink its NeoPixel.
+///
+/// Unlike profile commands this doesn't reboot the device. Returns whether
+/// the firmware acknowledged the request; firmware that predates IDENTIFY
+/// rejects it or stays silent, which is not an error.
+///
+/// # Arguments
+/// * `port_name` - Serial port of the device
+/// * `log` - Callback for debug log messages
+pub fn identify_device<L: Fn(&str)>(port_name: &str, log: L) -> DfuResult<bool> {
+    let log = bridge_log(&log);
+    log(&format!("Opening serial port: {}", port_name));
+    let mut transport = SerialTransport::open(port_name)?;
+
+    if !transport.is_healthy() {
+        return Err(DfuError::DeviceDisconnected {
+            operation: "identify health check".to_string(),
+        });
+    }
+
+    transport.clear_input().ok();
+    transport.write(IDENTIFY_COMMAND.as_bytes())?;
+    transport.flush()?;
+
+    let timeout = Duration::from_millis(IDENTIFY_TIMEOUT_MS);
+    let start = Instant::now();
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 256];
+
+    while start.elapsed() < timeout {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        let bytes_read = transport.read(&mut buffer, remaining.as_millis() as u64)?;
+
+        if bytes_read > 0 {
+            response.extend_from_slice(&buffer[..bytes_read]);
+            let response_str = String::from_utf8_lossy(&response);
+
+            if let Some(acknowledged) = identify_acknowledged(&response_str) {
+                log(&format!("Identify response: {}", response_str.trim()));
+                return Ok(acknowledged);
+            }
+        }
+    }
+
+    log("Identify command timeout - firmware may not support IDENTIFY");
+    Ok(false)
+}
+
+// =============================================================================
+// Device Queries
+// =============================================================================
+
+/// Answer to one device query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryAnswer {
+    /// Text after the answer tag, e.g. "2.3.1" for `[VERSION] 2.3.1`.
+    Value(String),
+    /// The firmware replied with an `[ERROR]` line, usually because it
+    /// predates the query.
+    Rejected(String),
+    /// No answer within the query timeout or the call's time budget.
+    TimedOut,
+}
+
+/// Answer found in the response so far: the text after `tag` on its line,
+/// the `[ERROR]` line if the firmware rejected the query, or `None` while waiting.
+fn query_response(response: &str, tag: &str) -> Option<QueryAnswer> {
+    for line in response.lines() {
+        if let Some((_, value)) = line.split_once(tag) {
+            let value = value.trim();
+            if !value.is_empty() {
+                return Some(QueryAnswer::Value(value.to_string()));
+            }
+        }
+        if line.contains("[ERROR]") {
+            return Some(QueryAnswer::Rejected(line.trim().to_string()));
+        }
+    }
+    None
+}
+
+/// Send read-only queries to a device in application mode.
+///
+/// Each query is a command and the tag its answer line starts with, e.g.
+/// `("GET_VERSION\n", "[VERSION]")`. Queries don't reboot the device. A
+/// query the firmware rejects or doesn't answer fails on its own; only
+/// failing to talk to the port fails the whole call. Queries still waiting
+/// when `budget` runs out (counted from opening the port) time out.
+///
+/// # Arguments
+/// * `port_name` - Serial port of the device
+/// * `queries` - Commands to send, with the tag of each answer
+/// * `budget` - Overall time allowed, including draining boot output
+/// * `log` - Callback for debug log messages
+pub fn query_device<L: Fn(&str)>(
+    port_name: &str,
+    queries: &[(&str, &str)],
+    budget: Duration,
+    log: L,
+) -> DfuResult<Vec<QueryAnswer>> {
+    let log = bridge_log(&log);
+    let deadline = Instant::now() + budget;
+
+    log(&format!("Opening serial port: {}", port_name));
+    let mut transport = SerialTransport::open(port_name)?;
+
+    if !transport.is_healthy() {
+        return Err(DfuError::DeviceDisconnected {
+            operation: "query health check".to_string(),
+        });
+    }
+
+    drain_boot_output(&mut transport)?;
+
+    let mut answers = Vec::with_capacity(queries.len());
+    for (command, tag) in queries {
+        let timeout = Duration::from_millis(QUERY_TIMEOUT_MS)
+            .min(deadline.saturating_duration_since(Instant::now()));
+        if timeout.is_zero() {
+            log(&format!("{} skipped: time budget exhausted", command.trim()));
+            answers.push(QueryAnswer::TimedOut);
+            continue;
+        }
+
+        transport.clear_input().ok();
+        transport.write(command.as_bytes())?;
+        transport.flush()?;
+
+        let start = Instant::now();
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 256];
+        let mut answer = None;
+
+        while answer.is_none() && start.elapsed() < timeout {
+            let remaining = timeout.saturating_sub(start.elapsed());
+            let bytes_read = transport.read(&mut buffer, remaining.as_millis() as u64)?;
+
+            if bytes_read > 0 {
+                response.extend_from_slice(&buffer[..bytes_read]);
+                answer = query_response(&String::from_utf8_lossy(&response), tag);
+            }
+        }
+
+        let answer = answer.unwrap_or(QueryAnswer::TimedOut);
+        log(&format!("{} -> {:?}", command.trim(), answer));
+        answers.push(answer);
+    }
+
+    Ok(answers)
+}
+
+/// Answer to GET_SETTINGS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingsAnswer {
+    /// The settings the device reported, e.g. `THERAPY_LED_OFF` -> "true".
+    Settings(BTreeMap<String, String>),
+    /// The firmware rejected GET_SETTINGS or didn't answer it, usually
+    /// because it predates the command.
+    Unsupported(String),
+}
+
+/// Parse the response to GET_SETTINGS: one `KEY=VALUE` line per setting,
+/// optionally after a tag such as `[SETTINGS]`. Other lines are ignored.
+fn settings_response(response: &str) -> SettingsAnswer {
+    let mut settings = BTreeMap::new();
+
+    for line in response.lines() {
+        let line = line.trim();
+        if line.contains("[ERROR]") {
+            return SettingsAnswer::Unsupported(line.to_string());
+        }
+
+        let line = match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+            Some((_, rest)) => rest.trim(),
+            None => line,
+        };
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let is_key = !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if is_key {
+            settings.insert(key.to_string(), value.trim().to_string());
+        }
+    }
+
+    if settings.is_empty() {
+        SettingsAnswer::Unsupported(
+            "No settings reported - firmware may not support GET_SETTINGS".to_string(),
+        )
+    } else {
+        SettingsAnswer::Settings(settings)
+    }
+}
+
+/// Read the current settings from a device in application mode.
+///
+/// Doesn't reboot the device. Firmware that predates GET_SETTINGS rejects
+/// it or stays silent, which is reported as `SettingsAnswer::Unsupported`
+/// rather than an error; only failing to talk to the port is an error.
+///
+/// # Arguments
+/// * `port_name` - Serial port of the device
+/// * `log` - Callback for debug log messages
+pub fn query_device_settings<L: Fn(&str)>(port_name: &str, log: L) -> DfuResult<SettingsAnswer> {
+    let response = send_raw_command(
+        port_name,
+        GET_SETTINGS_COMMAND,
+        Duration::from_millis(QUERY_TIMEOUT_MS),
+        &log,
+    )?;
+
+    // send_raw_command logged the exchange; only the answer is left
+    let answer = settings_response(&response);
+    let message = format!("GET_SETTINGS -> {:?}", answer);
+    log::debug!("{}", message);
+    log(&message);
+    Ok(answer)
+}
+
+/// Send one command to a device in application mode and return whatever it
+/// printed in response.
+///
+/// Collects output until it goes quiet for `DEVICE_COMMAND_IDLE_MS` or
+/// `timeout` runs out. The command is sent as given, so it must already end
+/// with a newline. An empty response is not an error.
+///
+/// # Arguments
+/// * `port_name` - Serial port of the device
+/// * `command` - Command line to send, including the newline
+/// * `timeout` - Longest time to wait for the response
+/// * `log` - Callback for debug log messages
+pub fn send_raw_command<L: Fn(&str)>(
+    port_name: &str,
+    command: &str,
+    timeout: Duration,
+    log: L,
+) -> DfuResult<String> {
+    let log = bridge_log(&log);
+    log(&format!("Opening serial port: {}", port_name));
+    let mut transport = SerialTransport::open(port_name)?;
+
+    if !transport.is_healthy() {
+        return Err(DfuError::DeviceDisconnected {
+            operation: "command health check".to_string(),
+        });
+    }
+
+    drain_boot_output(&mut transport)?;
+
+    transport.clear_input().ok();
+    transport.write(command.as_bytes())?;
+    transport.flush()?;
+
+    let start = Instant::now();
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 256];
+
+    while start.elapsed() < timeout {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        let wait = if response.is_empty() {
+            remaining
+        } else {
+            remaining.min(Duration::from_millis(DEVICE_COMMAND_IDLE_MS))
+        };
+        let bytes_read = transport.read(&mut buffer, wait.as_millis() as u64)?;
+
+        if bytes_read > 0 {
+            response.extend_from_slice(&buffer[..bytes_read]);
+        } else if !response.is_empty() {
+            break;
+        }
+    }
+
+    let response = String::from_utf8_lossy(&response).into_owned();
+    log(&format!("{} -> {} bytes", command.trim(), response.len()));
+    Ok(response)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dfu::BUILT_IN_PROFILES;
+
+    #[test]
+    fn test_nested_bridge_logs_once() {
+        let lines = std::cell::RefCell::new(Vec::new());
+        let caller = |message: &str| {
+            let nested = BRIDGE_FORWARDING.with(|forwarding| forwarding.get());
+            lines.borrow_mut().push((message.to_string(), nested));
+        };
+        let outer = bridge_log(&caller);
+        let inner = bridge_log(&outer);
+
+        inner("from inner");
+        outer("from outer");
+
+        // Only the first bridge to see a line logs it; the caller gets it once
+        assert_eq!(
+            *lines.borrow(),
+            [
+                ("from inner".to_string(), true),
+                ("from outer".to_string(), true)
+            ]
+        );
+        assert!(!BRIDGE_FORWARDING.with(|forwarding| forwarding.get()));
+    }
+
+    #[test]
+    fn test_query_response() {
+        assert_eq!(query_response("", "[VERSION]"), None);
+        assert_eq!(query_response("[READY]\r\n", "[VERSION]"), None);
+        assert_eq!(
+            query_response("[READY]\r\n[VERSION] 2.3.1\r\n", "[VERSION]"),
+            Some(QueryAnswer::Value("2.3.1".to_string()))
+        );
+        // A tag with nothing after it yet is still incomplete
+        assert_eq!(query_response("[ROLE] ", "[ROLE]"), None);
+        assert_eq!(
+            query_response("[ERROR] Unknown command: GET_ROLE\r\n", "[ROLE]"),
+            Some(QueryAnswer::Rejected(
+                "[ERROR] Unknown command: GET_ROLE".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_settings_response() {
+        let response = "GET_SETTINGS\r\n[SETTINGS] THERAPY_LED_OFF=true\r\nDEBUG = false\r\n\
+                        JITTER_PERCENT=23.5\r\nnot a setting\r\n";
+        let SettingsAnswer::Settings(settings) = settings_response(response) else {
+            panic!("expected settings");
+        };
+        assert_eq!(settings.len(), 3);
+        assert_eq!(settings["THERAPY_LED_OFF"], "true");
+        assert_eq!(settings["DEBUG"], "false");
+        assert_eq!(settings["JITTER_PERCENT"], "23.5");
+
+        assert_eq!(
+            settings_response("[ERROR] Unknown command: GET_SETTINGS\r\n"),
+            SettingsAnswer::Unsupported("[ERROR] Unknown command: GET_SETTINGS".to_string())
+        );
+        // Old firmware stays silent
+        assert!(matches!(
+            settings_response(""),
+            SettingsAnswer::Unsupported(_)
+        ));
+    }
+
+    #[test]
+    fn test_identify_acknowledged() {
+        assert_eq!(identify_acknowledged(""), None);
+        assert_eq!(identify_acknowledged("[READY] BlueBuzzah"), None);
+        assert_eq!(identify_acknowledged("[IDENTIFY] Blinking\r\n"), Some(true));
+        assert_eq!(
+            identify_acknowledged("[ERROR] Unknown command: IDENTIFY"),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_profile_command() {
+        assert_eq!(profile_command("noisy").unwrap(), PROFILE_NOISY_COMMAND);
+        for profile in BUILT_IN_PROFILES {
+            assert!(profile_command(profile).is_ok(), "{}", profile);
+        }
+        // Custom profiles are resolved to a base profile before this point
+        assert!(matches!(
+            profile_command("STUDY_A"),
+            Err(DfuError::ProfileConfigFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ignored_settings_warning() {
+        let jitter = vec![
+            "DEBUG:false\n".to_string(),
+            "JITTER_PERCENT:15.0\n".to_string(),
+        ];
+
+        assert!(ignored_settings_warning("REGULAR", &jitter).is_some());
+        assert!(ignored_settings_warning("regular", &jitter).is_some());
+        assert_eq!(ignored_settings_warning("NOISY", &jitter), None);
+        assert_eq!(
+            ignored_settings_warning("REGULAR", &["DEBUG:false\n".to_string()]),
+            None
+        );
+    }
 
     #[test]
     fn test_dfu_stage_percent() {
@@ -1316,4 +1945,59 @@ mod tests {
         };
         assert!(stage.message().contains("75%"));
     }
+
+    #[test]
+    fn test_dfu_stage_keys_are_stable() {
+        // Snapshot of the message keys the frontend translates.
+        // Changing a key breaks existing translations - add new keys instead.
+        let stages = [
+            DfuStage::ReadingPackage,
+            DfuStage::DetectedDevice {
+                pid: 0x8029,
+                in_bootloader: false,
+            },
+            DfuStage::EnteringBootloader,
+            DfuStage::WaitingForBootloader,
+            DfuStage::Connecting,
+            DfuStage::Starting,
+            DfuStage::SendingInit,
+            DfuStage::Uploading { sent: 0, total: 0 },
+            DfuStage::Finalizing,
+            DfuStage::WaitingForReboot,
+            DfuStage::ConfiguringRole,
+            DfuStage::Complete,
+            DfuStage::Log {
+                message: String::new(),
+            },
+            DfuStage::Cancelled,
+            DfuStage::TimedOut,
+            DfuStage::Retrying {
+                attempt: 2,
+                max_attempts: 3,
+            },
+        ];
+        let keys: Vec<&str> = stages.iter().map(|s| s.key()).collect();
+
+        assert_eq!(
+            keys,
+            vec![
+                "dfu.stage.reading",
+                "dfu.stage.detected",
+                "dfu.stage.bootloader",
+                "dfu.stage.waiting",
+                "dfu.stage.connecting",
+                "dfu.stage.starting",
+                "dfu.stage.init",
+                "dfu.stage.uploading",
+                "dfu.stage.finalizing",
+                "dfu.stage.rebooting",
+                "dfu.stage.configuring",
+                "dfu.stage.complete",
+                "dfu.stage.log",
+                "dfu.stage.cancelled",
+                "dfu.stage.timed_out",
+                "dfu.stage.retrying",
+            ]
+        );
+    }
 }
@@ -86,11 +86,11 @@ impl SerialTransport {
 
         // DTR toggle to reset connection state — ensures bootloader is ready
         if let Err(e) = port.write_data_terminal_ready(false) {
-            eprintln!("[DFU] Warning: DTR toggle (false) failed during port open: {}", e);
+            log::warn!("DTR toggle (false) failed during port open: {}", e);
         }
         std::thread::sleep(Duration::from_millis(50));
         if let Err(e) = port.write_data_terminal_ready(true) {
-            eprintln!("[DFU] Warning: DTR toggle (true) failed during port open: {}", e);
+            log::warn!("DTR toggle (true) failed during port open: {}", e);
         }
 
         // Allow port to stabilize after DTR toggle
@@ -214,15 +214,15 @@ impl SerialTransport {
 
         // Toggle DTR to reset the bootloader state
         if let Err(e) = port.write_data_terminal_ready(false) {
-            eprintln!("[DFU] Warning: DTR toggle (false) failed during bootloader reset: {}", e);
+            log::warn!("DTR toggle (false) failed during bootloader reset: {}", e);
         }
         std::thread::sleep(Duration::from_millis(50));
         if let Err(e) = port.write_data_terminal_ready(true) {
-            eprintln!("[DFU] Warning: DTR toggle (true) failed during bootloader reset: {}", e);
+            log::warn!("DTR toggle (true) failed during bootloader reset: {}", e);
         }
         std::thread::sleep(Duration::from_millis(50));
         if let Err(e) = port.write_data_terminal_ready(false) {
-            eprintln!("[DFU] Warning: DTR toggle (false) failed during bootloader reset: {}", e);
+            log::warn!("DTR toggle (false) failed during bootloader reset: {}", e);
         }
 
         // Close the port
@@ -255,7 +255,7 @@ impl DfuTransport for SerialTransport {
             Ok(n) => Ok(n),
             Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(0),
             Err(e) => {
-                eprintln!("[DFU] [read] os_code_hint={:?} kind={:?} msg={}", e.raw_os_error(), e.kind(), e);
+                log::debug!("Serial read failed: os_code_hint={:?} kind={:?} msg={}", e.raw_os_error(), e.kind(), e);
                 Err(DfuError::Io(e))
             }
         }
@@ -278,11 +278,11 @@ impl DfuTransport for SerialTransport {
         #[cfg(target_os = "macos")]
         {
             if let Err(e) = self.port.write_data_terminal_ready(true) {
-                eprintln!("[DFU] Warning: DTR keep-alive toggle (true) failed: {}", e);
+                log::warn!("DTR keep-alive toggle (true) failed: {}", e);
             }
             std::thread::sleep(Duration::from_millis(10));
             if let Err(e) = self.port.write_data_terminal_ready(false) {
-                eprintln!("[DFU] Warning: DTR keep-alive toggle (false) failed: {}", e);
+                log::warn!("DTR keep-alive toggle (false) failed: {}", e);
             }
         }
 
@@ -449,15 +449,15 @@ fn open_port_with_retry(
         match open_port_with_timeout(normalized_name, baud_rate, read_timeout) {
             Ok(port) => {
                 if attempt > 0 {
-                    eprintln!(
-                        "[DFU] Port {} opened successfully on attempt {}/{}",
+                    log::info!(
+                        "Port {} opened successfully on attempt {}/{}",
                         display_port, attempt + 1, max_retries
                     );
                 }
                 return Ok(port);
             }
             Err(e) => {
-                eprintln!("[DFU] {}", describe_serial_error(&format!("open {display_port} attempt {}/{}", attempt + 1, max_retries), &e));
+                log::warn!("{}", describe_serial_error(&format!("open {display_port} attempt {}/{}", attempt + 1, max_retries), &e));
                 let err_str = e.to_string().to_lowercase();
 
                 // Check if error is transient (includes timeout from our wrapper)
@@ -471,8 +471,8 @@ fn open_port_with_retry(
                         PORT_OPEN_MAX_DELAY_MS,
                     );
                     if attempt >= 2 {
-                        eprintln!(
-                            "[DFU] Port {} open attempt {}/{} failed ({}), retrying in {}ms...",
+                        log::warn!(
+                            "Port {} open attempt {}/{} failed ({}), retrying in {}ms...",
                             display_port, attempt + 1, max_retries, err_str, delay
                         );
                     }
@@ -0,0 +1,370 @@
+//! UF2 flashing through the bootloader's mass-storage drive.
+//!
+//! When serial DFU can't get through (a wedged bootloader, a broken CDC
+//! driver), a double-tap reset still exposes the UF2 drive (FTHR840BOOT on
+//! the Feather nRF52840). Copying a .uf2 file onto it flashes the board; the
+//! bootloader then ejects the drive and reboots into the application.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::config::{get_reboot_timeout, PORT_SCAN_INTERVAL};
+use super::device::{wait_for_application_flexible, DeviceIdentifier, Nrf52Device};
+use super::error::{DfuError, DfuResult};
+
+/// Size of every UF2 block.
+const UF2_BLOCK_SIZE: usize = 512;
+
+/// First and second magic numbers at the start of a UF2 block.
+const UF2_MAGIC_START0: u32 = 0x0A32_4655;
+const UF2_MAGIC_START1: u32 = 0x9E5D_5157;
+
+/// Magic number at the end of a UF2 block.
+const UF2_MAGIC_END: u32 = 0x0AB1_6F30;
+
+/// Block flag: the file size field holds a family ID.
+const UF2_FLAG_FAMILY_ID_PRESENT: u32 = 0x0000_2000;
+
+/// UF2 family ID of the nRF52840.
+pub const NRF52840_FAMILY_ID: u32 = 0xADA5_2840;
+
+/// File the UF2 bootloader puts on its drive; it names the board.
+const UF2_INFO_FILE: &str = "INFO_UF2.TXT";
+
+/// Chunk size for copying to the drive, small enough for smooth progress.
+const UF2_COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long the bootloader gets to finish flashing and drop the drive.
+const UF2_VOLUME_REMOVAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Progress event sent to the frontend during a UF2 flash.
+#[derive(Debug, Clone, Serialize)]
+pub struct Uf2ProgressEvent {
+    /// Current stage: "copying", "rebooting", "waiting" or "complete".
+    pub stage: String,
+    /// Bytes copied to the drive so far.
+    pub copied: u64,
+    /// Size of the UF2 file.
+    pub total: u64,
+    /// Progress percentage (0-100).
+    pub percent: f32,
+    /// Human-readable message.
+    pub message: String,
+}
+
+fn read_u32(block: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        block[offset],
+        block[offset + 1],
+        block[offset + 2],
+        block[offset + 3],
+    ])
+}
+
+/// Check `data` is a UF2 image for the nRF52840, returning its block count.
+///
+/// Every block must carry both start magics and the end magic, number
+/// itself consistently, and declare the nRF52840 family ID.
+pub fn validate_uf2(data: &[u8]) -> DfuResult<u32> {
+    if data.is_empty() || !data.len().is_multiple_of(UF2_BLOCK_SIZE) {
+        return Err(DfuError::InvalidUf2 {
+            reason: format!(
+                "file size {} is not a multiple of {} bytes",
+                data.len(),
+                UF2_BLOCK_SIZE
+            ),
+        });
+    }
+
+    let block_count = (data.len() / UF2_BLOCK_SIZE) as u32;
+    for (i, block) in data.chunks_exact(UF2_BLOCK_SIZE).enumerate() {
+        if read_u32(block, 0) != UF2_MAGIC_START0
+            || read_u32(block, 4) != UF2_MAGIC_START1
+            || read_u32(block, UF2_BLOCK_SIZE - 4) != UF2_MAGIC_END
+        {
+            return Err(DfuError::InvalidUf2 {
+                reason: format!("bad magic in block {}", i),
+            });
+        }
+
+        if read_u32(block, 20) != i as u32 || read_u32(block, 24) != block_count {
+            return Err(DfuError::InvalidUf2 {
+                reason: format!("block {} is numbered out of sequence", i),
+            });
+        }
+
+        if read_u32(block, 8) & UF2_FLAG_FAMILY_ID_PRESENT == 0 {
+            return Err(DfuError::InvalidUf2 {
+                reason: format!("block {} has no family ID", i),
+            });
+        }
+        let family_id = read_u32(block, 28);
+        if family_id != NRF52840_FAMILY_ID {
+            return Err(DfuError::InvalidUf2 {
+                reason: format!("family ID 0x{:08X} is not nRF52840", family_id),
+            });
+        }
+    }
+
+    Ok(block_count)
+}
+
+/// Whether `path` is the drive of an nRF52840 UF2 bootloader.
+pub fn is_uf2_volume(path: &Path) -> bool {
+    fs::read_to_string(path.join(UF2_INFO_FILE))
+        .map(|info| info.contains("nRF52840"))
+        .unwrap_or(false)
+}
+
+/// Places removable drives get mounted on this platform.
+fn volume_candidates() -> Vec<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        (b'A'..=b'Z')
+            .map(|letter| PathBuf::from(format!("{}:\\", letter as char)))
+            .collect()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let mut roots = vec![PathBuf::from("/Volumes")];
+        if let Ok(user) = std::env::var("USER") {
+            roots.push(Path::new("/media").join(&user));
+            roots.push(Path::new("/run/media").join(&user));
+        }
+
+        roots
+            .iter()
+            .filter_map(|root| fs::read_dir(root).ok())
+            .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+            .collect()
+    }
+}
+
+/// Find mounted UF2 bootloader drives.
+pub fn find_uf2_volumes() -> Vec<PathBuf> {
+    volume_candidates()
+        .into_iter()
+        .filter(|path| is_uf2_volume(path))
+        .collect()
+}
+
+/// Write `data` to `dest` in chunks, reporting bytes written after each one.
+fn copy_with_progress<F: FnMut(u64)>(data: &[u8], dest: &Path, mut on_chunk: F) -> DfuResult<()> {
+    let mut file = File::create(dest)?;
+    let mut copied = 0u64;
+
+    for chunk in data.chunks(UF2_COPY_CHUNK_SIZE) {
+        file.write_all(chunk)?;
+        copied += chunk.len() as u64;
+        on_chunk(copied);
+    }
+    file.flush()?;
+
+    // The bootloader may already be rebooting once it has every block, so
+    // a failed sync here doesn't mean the flash failed
+    let _ = file.sync_all();
+    Ok(())
+}
+
+/// Wait for a drive to disappear, returning false on timeout.
+fn wait_for_volume_removal(volume: &Path, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if !volume.exists() {
+            return true;
+        }
+        std::thread::sleep(PORT_SCAN_INTERVAL);
+    }
+    false
+}
+
+/// Flash a UF2 image by copying it onto a bootloader drive.
+///
+/// Validates the image, copies it to `volume_path`, waits for the
+/// bootloader to drop the drive, and then, if `identifier` is given, for
+/// that device to come back in application mode.
+///
+/// # Arguments
+/// * `uf2_path` - Path to the .uf2 file
+/// * `volume_path` - Mounted UF2 bootloader drive
+/// * `identifier` - Device to wait for after the reboot, if known
+/// * `progress` - Callback for progress updates
+///
+/// # Returns
+/// The device in application mode, or `None` without an identifier
+pub fn flash_uf2<F: FnMut(Uf2ProgressEvent)>(
+    uf2_path: &Path,
+    volume_path: &Path,
+    identifier: Option<&DeviceIdentifier>,
+    mut progress: F,
+) -> DfuResult<Option<Nrf52Device>> {
+    if !is_uf2_volume(volume_path) {
+        return Err(DfuError::InvalidUf2 {
+            reason: format!(
+                "{} is not an nRF52840 UF2 bootloader drive",
+                volume_path.display()
+            ),
+        });
+    }
+
+    let data = fs::read(uf2_path)?;
+    validate_uf2(&data)?;
+
+    let file_name = uf2_path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "firmware.uf2".into());
+    let total = data.len() as u64;
+
+    copy_with_progress(&data, &volume_path.join(file_name), |copied| {
+        progress(Uf2ProgressEvent {
+            stage: "copying".to_string(),
+            copied,
+            total,
+            percent: copied as f32 / total as f32 * 90.0,
+            message: format!(
+                "Copying firmware ({} / {} KB)...",
+                copied / 1024,
+                total / 1024
+            ),
+        });
+    })?;
+
+    progress(Uf2ProgressEvent {
+        stage: "rebooting".to_string(),
+        copied: total,
+        total,
+        percent: 90.0,
+        message: "Waiting for the bootloader to finish...".to_string(),
+    });
+    if !wait_for_volume_removal(volume_path, UF2_VOLUME_REMOVAL_TIMEOUT) {
+        return Err(DfuError::InvalidUf2 {
+            reason: "the bootloader did not accept the image (drive still mounted)".to_string(),
+        });
+    }
+
+    let device = match identifier {
+        Some(identifier) => {
+            progress(Uf2ProgressEvent {
+                stage: "waiting".to_string(),
+                copied: total,
+                total,
+                percent: 95.0,
+                message: "Waiting for device to restart...".to_string(),
+            });
+            Some(wait_for_application_flexible(
+                identifier,
+                get_reboot_timeout(),
+            )?)
+        }
+        None => None,
+    };
+
+    progress(Uf2ProgressEvent {
+        stage: "complete".to_string(),
+        copied: total,
+        total,
+        percent: 100.0,
+        message: "Firmware installed".to_string(),
+    });
+    Ok(device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Build a UF2 image of `count` blocks for `family_id`.
+    fn uf2_image(count: u32, family_id: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..count {
+            let mut block = vec![0u8; UF2_BLOCK_SIZE];
+            let fields = [
+                UF2_MAGIC_START0,
+                UF2_MAGIC_START1,
+                UF2_FLAG_FAMILY_ID_PRESENT,
+                0x26000 + i * 256, // target address
+                256,               // payload size
+                i,
+                count,
+                family_id,
+            ];
+            for (j, field) in fields.iter().enumerate() {
+                block[j * 4..j * 4 + 4].copy_from_slice(&field.to_le_bytes());
+            }
+            block[UF2_BLOCK_SIZE - 4..].copy_from_slice(&UF2_MAGIC_END.to_le_bytes());
+            data.extend(block);
+        }
+        data
+    }
+
+    #[test]
+    fn test_validate_uf2_accepts_nrf52840_image() {
+        assert_eq!(validate_uf2(&uf2_image(3, NRF52840_FAMILY_ID)).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_validate_uf2_rejects_bad_images() {
+        let reason = |data: &[u8]| validate_uf2(data).unwrap_err().to_string();
+
+        assert!(reason(&[]).contains("multiple of 512"));
+        assert!(reason(&[0u8; 700]).contains("multiple of 512"));
+        assert!(reason(&[0u8; 512]).contains("bad magic"));
+
+        // RP2040 family
+        assert!(reason(&uf2_image(2, 0xE48B_FF56)).contains("not nRF52840"));
+
+        let mut no_family = uf2_image(2, NRF52840_FAMILY_ID);
+        no_family[8..12].copy_from_slice(&0u32.to_le_bytes());
+        assert!(reason(&no_family).contains("no family ID"));
+
+        let mut truncated = uf2_image(3, NRF52840_FAMILY_ID);
+        truncated.truncate(2 * UF2_BLOCK_SIZE);
+        assert!(reason(&truncated).contains("out of sequence"));
+    }
+
+    #[test]
+    fn test_is_uf2_volume() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(!is_uf2_volume(temp_dir.path()));
+
+        fs::write(
+            temp_dir.path().join(UF2_INFO_FILE),
+            "UF2 Bootloader 0.6.0\nModel: Adafruit Feather nRF52840 Express\nBoard-ID: nRF52840-Feather-revD\n",
+        )
+        .unwrap();
+        assert!(is_uf2_volume(temp_dir.path()));
+    }
+
+    #[test]
+    fn test_copy_with_progress_reports_every_chunk() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = vec![7u8; UF2_COPY_CHUNK_SIZE * 2 + 10];
+        let dest = temp_dir.path().join("firmware.uf2");
+        let mut reported = Vec::new();
+
+        copy_with_progress(&data, &dest, |copied| reported.push(copied)).unwrap();
+
+        let chunk = UF2_COPY_CHUNK_SIZE as u64;
+        assert_eq!(reported, vec![chunk, chunk * 2, data.len() as u64]);
+        assert_eq!(fs::read(&dest).unwrap(), data);
+    }
+
+    #[test]
+    fn test_flash_uf2_rejects_non_bootloader_drive() {
+        let temp_dir = TempDir::new().unwrap();
+        let uf2_path = temp_dir.path().join("firmware.uf2");
+        fs::write(&uf2_path, uf2_image(1, NRF52840_FAMILY_ID)).unwrap();
+
+        let err = flash_uf2(&uf2_path, temp_dir.path(), None, |_| {}).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not an nRF52840 UF2 bootloader drive"));
+    }
+}
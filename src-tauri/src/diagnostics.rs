@@ -0,0 +1,412 @@
+//! Diagnostic bundle for support.
+//!
+//! Support used to ask for logs, settings and cache state one at a time.
+//! `export_diagnostics` writes all of it into one zip instead, each part as
+//! its own JSON or text file, listed with its size in `manifest.json`.
+//!
+//! The bundle is capped at `MAX_BUNDLE_BYTES` of content. Parts are added in
+//! order of importance; a log that doesn't fit keeps its newest lines, and a
+//! JSON file that doesn't fit is left out and listed as skipped.
+
+use serde::Serialize;
+use serialport::{SerialPortInfo, SerialPortType};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::history::FlashRecord;
+
+/// Most content, in bytes before compression, put in one bundle.
+pub const MAX_BUNDLE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Name of the file listing the bundle's contents.
+const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// One file in the bundle.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BundleFile {
+    pub name: String,
+    pub description: String,
+    /// Bytes written, before compression.
+    pub size: u64,
+    /// Whether the start of a log was cut to fit the size cap.
+    pub truncated: bool,
+}
+
+/// A part left out of the bundle, and why.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SkippedFile {
+    pub name: String,
+    pub reason: String,
+}
+
+/// What an exported bundle holds, as returned to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsSummary {
+    /// Where the bundle was written; ".zip" is added if missing.
+    pub path: String,
+    pub files: Vec<BundleFile>,
+    pub skipped: Vec<SkippedFile>,
+    /// Content bytes in the bundle, before compression.
+    pub total_bytes: u64,
+}
+
+/// `manifest.json`: what produced the bundle and what is in it.
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    generated_at: String,
+    app_version: &'static str,
+    max_bytes: u64,
+    files: &'a [BundleFile],
+    skipped: &'a [SkippedFile],
+}
+
+/// App and host the bundle was generated on.
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemInfo {
+    pub app_version: &'static str,
+    /// "macos", "windows", "linux".
+    pub os: &'static str,
+    pub os_family: &'static str,
+    pub arch: &'static str,
+    /// Level the log files were written at.
+    pub log_level: String,
+}
+
+impl SystemInfo {
+    pub fn current() -> Self {
+        Self {
+            app_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            os_family: std::env::consts::FAMILY,
+            arch: std::env::consts::ARCH,
+            log_level: log::max_level().to_string(),
+        }
+    }
+}
+
+/// One serial port the OS reports, whether or not it is a BlueBuzzah.
+#[derive(Debug, Clone, Serialize)]
+pub struct PortSnapshot {
+    pub port: String,
+    /// "usb", "bluetooth", "pci" or "unknown".
+    pub kind: &'static str,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub product: Option<String>,
+}
+
+impl From<SerialPortInfo> for PortSnapshot {
+    fn from(info: SerialPortInfo) -> Self {
+        let (kind, usb) = match info.port_type {
+            SerialPortType::UsbPort(usb) => ("usb", Some(usb)),
+            SerialPortType::BluetoothPort => ("bluetooth", None),
+            SerialPortType::PciPort => ("pci", None),
+            SerialPortType::Unknown => ("unknown", None),
+        };
+        Self {
+            port: info.port_name,
+            kind,
+            vid: usb.as_ref().map(|usb| usb.vid),
+            pid: usb.as_ref().map(|usb| usb.pid),
+            serial_number: usb.as_ref().and_then(|usb| usb.serial_number.clone()),
+            product: usb.and_then(|usb| usb.product),
+        }
+    }
+}
+
+/// Flash outcomes tallied from the flash history.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct ErrorStats {
+    pub flashes: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Failed flashes per support code (e.g. "DFU-021"); "uncoded" for
+    /// failures without one.
+    pub by_error_code: BTreeMap<String, usize>,
+}
+
+impl ErrorStats {
+    pub fn tally(records: &[FlashRecord]) -> Self {
+        let mut stats = Self {
+            flashes: records.len(),
+            ..Self::default()
+        };
+        for record in records {
+            if record.success {
+                stats.succeeded += 1;
+                continue;
+            }
+            stats.failed += 1;
+            let code = record.error_code.as_deref().unwrap_or("uncoded");
+            *stats.by_error_code.entry(code.to_string()).or_insert(0) += 1;
+        }
+        stats
+    }
+}
+
+/// `path` with a ".zip" extension, added if it has another or none.
+pub fn bundle_path(path: &Path) -> PathBuf {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+    {
+        return path.to_path_buf();
+    }
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".zip");
+    PathBuf::from(name)
+}
+
+/// A bundle being written, with the content budget it has left.
+pub struct DiagnosticsBundle {
+    path: PathBuf,
+    writer: ZipWriter<File>,
+    remaining: u64,
+    files: Vec<BundleFile>,
+    skipped: Vec<SkippedFile>,
+}
+
+impl DiagnosticsBundle {
+    /// Start a bundle at `path` holding at most `max_bytes` of content.
+    pub fn create(path: &Path, max_bytes: u64) -> Result<Self, String> {
+        let file =
+            File::create(path).map_err(|e| format!("Failed to create diagnostic bundle: {}", e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: ZipWriter::new(file),
+            remaining: max_bytes,
+            files: Vec::new(),
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Add `value` as pretty-printed JSON, or record why it couldn't be
+    /// gathered.
+    pub fn add_json<T: Serialize>(
+        &mut self,
+        name: &str,
+        description: &str,
+        value: Result<T, String>,
+    ) -> Result<(), String> {
+        let json = value.and_then(|value| {
+            serde_json::to_vec_pretty(&value).map_err(|e| format!("Failed to serialize: {}", e))
+        });
+        match json {
+            Ok(json) if json.len() as u64 <= self.remaining => {
+                self.write_file(name, description, &json, false)
+            }
+            Ok(json) => {
+                self.skip(
+                    name,
+                    format!("{} bytes would exceed the bundle size cap", json.len()),
+                );
+                Ok(())
+            }
+            Err(reason) => {
+                self.skip(name, reason);
+                Ok(())
+            }
+        }
+    }
+
+    /// Add a log, keeping only its newest bytes if it doesn't fit.
+    pub fn add_log(
+        &mut self,
+        name: &str,
+        description: &str,
+        contents: &[u8],
+    ) -> Result<(), String> {
+        if self.remaining == 0 {
+            self.skip(name, "Bundle size cap reached".to_string());
+            return Ok(());
+        }
+        let keep = (contents.len() as u64).min(self.remaining) as usize;
+        let tail = &contents[contents.len() - keep..];
+        self.write_file(name, description, tail, keep < contents.len())
+    }
+
+    /// Write the manifest and close the zip.
+    pub fn finish(mut self) -> Result<DiagnosticsSummary, String> {
+        let manifest = Manifest {
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            app_version: env!("CARGO_PKG_VERSION"),
+            max_bytes: MAX_BUNDLE_BYTES,
+            files: &self.files,
+            skipped: &self.skipped,
+        };
+        let json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize bundle manifest: {}", e))?;
+        self.start_entry(MANIFEST_ENTRY)?;
+        self.writer
+            .write_all(&json)
+            .and_then(|_| self.writer.finish().map(|_| ()).map_err(Into::into))
+            .map_err(|e| format!("Failed to write diagnostic bundle: {}", e))?;
+
+        Ok(DiagnosticsSummary {
+            path: self.path.to_string_lossy().to_string(),
+            total_bytes: self.files.iter().map(|file| file.size).sum(),
+            files: self.files,
+            skipped: self.skipped,
+        })
+    }
+
+    /// Close and delete a bundle that couldn't be completed.
+    pub fn discard(self) {
+        drop(self.writer);
+        let _ = fs::remove_file(&self.path);
+    }
+
+    fn write_file(
+        &mut self,
+        name: &str,
+        description: &str,
+        contents: &[u8],
+        truncated: bool,
+    ) -> Result<(), String> {
+        self.start_entry(name)?;
+        self.writer
+            .write_all(contents)
+            .map_err(|e| format!("Failed to write diagnostic bundle: {}", e))?;
+
+        self.remaining -= contents.len() as u64;
+        self.files.push(BundleFile {
+            name: name.to_string(),
+            description: description.to_string(),
+            size: contents.len() as u64,
+            truncated,
+        });
+        Ok(())
+    }
+
+    fn start_entry(&mut self, name: &str) -> Result<(), String> {
+        self.writer
+            .start_file(name, FileOptions::default())
+            .map_err(|e| format!("Failed to write diagnostic bundle: {}", e))
+    }
+
+    /// Record that `name` was left out, and why.
+    pub fn skip(&mut self, name: &str, reason: String) {
+        log::warn!("Leaving {} out of the diagnostic bundle: {}", name, reason);
+        self.skipped.push(SkippedFile {
+            name: name.to_string(),
+            reason,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::tempdir;
+    use zip::ZipArchive;
+
+    fn read_entry(path: &Path, name: &str) -> String {
+        let mut archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let mut contents = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    #[test]
+    fn test_bundle_lists_files_in_manifest() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("diagnostics.zip");
+
+        let mut bundle = DiagnosticsBundle::create(&path, 1024).unwrap();
+        bundle
+            .add_json("system.json", "App and OS", Ok(SystemInfo::current()))
+            .unwrap();
+        bundle
+            .add_json::<()>("cache_index.json", "Cache", Err("Index unreadable".into()))
+            .unwrap();
+        bundle
+            .add_log("logs/updater.log", "Log", b"line\n")
+            .unwrap();
+        let summary = bundle.finish().unwrap();
+
+        assert_eq!(summary.files.len(), 2);
+        assert_eq!(summary.skipped[0].reason, "Index unreadable");
+        assert_eq!(read_entry(&path, "logs/updater.log"), "line\n");
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&read_entry(&path, MANIFEST_ENTRY)).unwrap();
+        assert_eq!(manifest["files"][0]["name"], "system.json");
+        assert_eq!(manifest["skipped"][0]["name"], "cache_index.json");
+    }
+
+    #[test]
+    fn test_bundle_caps_content() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("diagnostics.zip");
+
+        let mut bundle = DiagnosticsBundle::create(&path, 10).unwrap();
+        bundle
+            .add_json("big.json", "Too big", Ok(vec!["0123456789"; 4]))
+            .unwrap();
+        bundle.add_log("new.log", "Newest", b"old\nnew\n").unwrap();
+        bundle.add_log("old.log", "Oldest", b"older\n").unwrap();
+        bundle.add_log("oldest.log", "Oldest", b"x").unwrap();
+        let summary = bundle.finish().unwrap();
+
+        assert_eq!(summary.total_bytes, 10);
+        assert_eq!(read_entry(&path, "new.log"), "old\nnew\n");
+        assert_eq!(read_entry(&path, "old.log"), "r\n");
+        assert!(summary.files[1].truncated);
+        let skipped: Vec<_> = summary.skipped.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(skipped, ["big.json", "oldest.log"]);
+    }
+
+    #[test]
+    fn test_error_stats_tally() {
+        let record = |success: bool, error_code: Option<&str>| FlashRecord {
+            started_at: "2025-01-01T00:00:00Z".to_string(),
+            duration_ms: 0,
+            serial_number: None,
+            port: "COM3".to_string(),
+            firmware_version: None,
+            firmware_path: "firmware.zip".to_string(),
+            app_version: "1.0.0".to_string(),
+            role: None,
+            profile: None,
+            success,
+            attempts: 1,
+            error_code: error_code.map(str::to_string),
+            error: None,
+        };
+
+        let stats = ErrorStats::tally(&[
+            record(true, None),
+            record(false, Some("DFU-021")),
+            record(false, Some("DFU-021")),
+            record(false, None),
+        ]);
+
+        assert_eq!((stats.flashes, stats.succeeded, stats.failed), (4, 1, 3));
+        assert_eq!(stats.by_error_code["DFU-021"], 2);
+        assert_eq!(stats.by_error_code["uncoded"], 1);
+    }
+
+    #[test]
+    fn test_bundle_path_adds_zip_extension() {
+        assert_eq!(
+            bundle_path(Path::new("a/diag.zip")),
+            Path::new("a/diag.zip")
+        );
+        assert_eq!(bundle_path(Path::new("a/diag")), Path::new("a/diag.zip"));
+        assert_eq!(
+            bundle_path(Path::new("a/diag.2025")),
+            Path::new("a/diag.2025.zip")
+        );
+    }
+}
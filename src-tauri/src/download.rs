@@ -0,0 +1,1267 @@
+//! Helpers for resumable firmware downloads.
+//!
+//! Interrupted downloads are kept as `<version>.zip.partial` so the next
+//! attempt can continue with an HTTP `Range` request instead of starting over.
+//! Cancelled downloads keep their partial file for the same reason.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::cache::{AssetKind, CachedFirmwareMetadata};
+
+/// Suffix appended to in-progress downloads.
+pub const PARTIAL_SUFFIX: &str = ".zip.partial";
+
+/// Partial downloads untouched for longer than this are removed during cache cleanup.
+pub const STALE_PARTIAL_MAX_AGE: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// Number of retries after the first download attempt fails with a transient error.
+pub const MAX_DOWNLOAD_RETRIES: u32 = 3;
+
+/// How long a download waits for the next read from the server before the
+/// connection counts as lost.
+///
+/// Per read rather than for the whole transfer, so a large asset on a slow or
+/// rate-limited link is never cut off while data still arrives.
+pub const DOWNLOAD_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often a download waiting out a delay checks for cancellation.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Base delay for download retry backoff (1s, 3s, 9s).
+pub const DOWNLOAD_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Delay before retry number `retry` (1-based), tripling each time.
+pub fn retry_delay(retry: u32) -> Duration {
+    DOWNLOAD_RETRY_BASE_DELAY * 3u32.pow(retry.saturating_sub(1))
+}
+
+/// Whether an HTTP status is worth retrying (server-side or rate limiting).
+///
+/// Client errors like 404 will not fix themselves and fail immediately.
+pub fn is_retriable_status(status: u16) -> bool {
+    status == 408 || status == 429 || (500..600).contains(&status)
+}
+
+/// Why a download attempt failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadFailure {
+    /// Connection, TLS, DNS or stream error - the partial file is kept and
+    /// the attempt may be retried.
+    Transient(String),
+    /// HTTP client error or local failure - retrying won't help.
+    Fatal(String),
+    /// The user cancelled the download - the partial file is kept for resume.
+    Cancelled,
+}
+
+impl DownloadFailure {
+    pub fn is_retriable(&self) -> bool {
+        matches!(self, DownloadFailure::Transient(_))
+    }
+}
+
+impl std::fmt::Display for DownloadFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadFailure::Transient(message) | DownloadFailure::Fatal(message) => {
+                write!(f, "{}", message)
+            }
+            DownloadFailure::Cancelled => write!(f, "Download cancelled"),
+        }
+    }
+}
+
+/// Progress event sent to the frontend during a firmware download.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgressEvent {
+    /// Current stage name: "retrying"
+    pub stage: String,
+    /// Retry number (1-based).
+    pub attempt: u32,
+    /// Maximum number of retries.
+    pub max_attempts: u32,
+    /// Human-readable message.
+    pub message: String,
+    /// Configured bandwidth cap in bytes per second, if any.
+    pub rate_limit_bytes_per_sec: Option<u64>,
+}
+
+/// Minimum bytes between byte-progress events, to avoid flooding the frontend.
+pub const PROGRESS_INTERVAL_BYTES: u64 = 64 * 1024;
+
+/// Progress event sent during `download_release_assets`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseAssetProgressEvent {
+    /// Current stage name: "downloading", "retrying", "complete"
+    pub stage: String,
+    /// Index of the asset being downloaded (0-based).
+    pub asset_index: usize,
+    /// Number of assets in the batch.
+    pub asset_count: usize,
+    pub asset_name: String,
+    /// Bytes of the current asset downloaded so far.
+    pub bytes_downloaded: u64,
+    /// Size of the current asset, if the server reported it.
+    pub total_bytes: Option<u64>,
+    /// Human-readable message.
+    pub message: String,
+    /// Configured bandwidth cap in bytes per second, if any.
+    pub rate_limit_bytes_per_sec: Option<u64>,
+}
+
+/// One release asset requested from `download_release_assets`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseAssetRequest {
+    pub name: String,
+    pub download_url: String,
+    /// Published SHA256, when the release has one for this asset.
+    #[serde(default)]
+    pub expected_sha256: Option<String>,
+}
+
+/// Result of a `download_release_assets` call that did not fail.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum ReleaseDownloadOutcome {
+    /// Every asset was downloaded and added to the cache index.
+    Completed { assets: Vec<CachedFirmwareMetadata> },
+    /// Cancelled; nothing from the batch was added to the cache index.
+    Cancelled,
+}
+
+/// Cache index key for a downloaded release asset.
+///
+/// The DFU package is keyed by the bare version so `get_cached_firmware`
+/// and the flash flow find it; other assets get `<version>-<asset stem>`.
+pub fn asset_cache_key(version: &str, asset_name: &str, kind: AssetKind) -> String {
+    match kind {
+        AssetKind::DfuPackage => version.to_string(),
+        _ => staging_key(version, asset_name),
+    }
+}
+
+/// Key under which a release asset is staged while it downloads.
+pub fn staging_key(version: &str, asset_name: &str) -> String {
+    let stem = asset_name.strip_suffix(".zip").unwrap_or(asset_name);
+    format!("{}-{}", version, stem)
+}
+
+/// Result of a `download_firmware` call that did not fail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum DownloadOutcome {
+    /// Download finished and was added to the cache index.
+    Completed { path: String },
+    /// Download was cancelled; nothing was added to the cache index.
+    Cancelled,
+}
+
+/// An in-flight download: its cancellation flag and the partial files it
+/// is writing.
+struct ActiveDownload {
+    cancelled: Arc<AtomicBool>,
+    partials: Vec<PathBuf>,
+}
+
+/// In-flight downloads, keyed by download ID.
+static ACTIVE_DOWNLOADS: Mutex<BTreeMap<String, ActiveDownload>> = Mutex::new(BTreeMap::new());
+
+fn active_downloads() -> std::sync::MutexGuard<'static, BTreeMap<String, ActiveDownload>> {
+    ACTIVE_DOWNLOADS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Registration of an in-flight download; unregisters itself when dropped.
+pub struct DownloadHandle {
+    id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl DownloadHandle {
+    /// Register a download under `id`. Fails if one is already running with that ID.
+    pub fn register(id: &str) -> Result<Self, String> {
+        let mut downloads = active_downloads();
+        if downloads.contains_key(id) {
+            return Err(format!("Download {} is already in progress", id));
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        downloads.insert(
+            id.to_string(),
+            ActiveDownload {
+                cancelled: Arc::clone(&cancelled),
+                partials: Vec::new(),
+            },
+        );
+        Ok(Self {
+            id: id.to_string(),
+            cancelled,
+        })
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Record that this download writes `partial`, so clearing the cache
+    /// leaves it alone until the download ends.
+    pub fn writes_to(&self, partial: &Path) {
+        if let Some(download) = active_downloads().get_mut(&self.id) {
+            download.partials.push(partial.to_path_buf());
+        }
+    }
+}
+
+impl Drop for DownloadHandle {
+    fn drop(&mut self) {
+        active_downloads().remove(&self.id);
+    }
+}
+
+/// Request cancellation of the download registered under `id`.
+///
+/// Returns false if no such download is running.
+pub fn cancel_download(id: &str) -> bool {
+    match active_downloads().get(id) {
+        Some(download) => {
+            download.cancelled.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Partial files being written by in-flight downloads.
+pub fn active_partials() -> Vec<PathBuf> {
+    active_downloads()
+        .values()
+        .flat_map(|download| download.partials.iter().cloned())
+        .collect()
+}
+
+/// Path of the partial download for a firmware version.
+pub fn partial_path(firmware_dir: &Path, version: &str) -> PathBuf {
+    firmware_dir.join(format!("{}{}", version, PARTIAL_SUFFIX))
+}
+
+/// Size of an existing partial download, or 0 if there is none.
+pub fn existing_partial_len(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Value for the `Range` header that resumes a download at `offset`.
+pub fn range_header_value(offset: u64) -> String {
+    format!("bytes={}-", offset)
+}
+
+/// Parsed `Content-Range: bytes <start>-<end>/<total>` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    pub start: u64,
+    pub end: u64,
+    /// Full resource size, `None` when the server sent `*`.
+    pub total: Option<u64>,
+}
+
+/// Parse a `Content-Range` header value. Returns `None` if malformed.
+pub fn parse_content_range(value: &str) -> Option<ContentRange> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let (start, end) = span.split_once('-')?;
+
+    let start: u64 = start.trim().parse().ok()?;
+    let end: u64 = end.trim().parse().ok()?;
+    if end < start {
+        return None;
+    }
+
+    let total = match total.trim() {
+        "*" => None,
+        t => {
+            let t: u64 = t.parse().ok()?;
+            if end >= t {
+                return None;
+            }
+            Some(t)
+        }
+    };
+
+    Some(ContentRange { start, end, total })
+}
+
+/// What to do with a response body relative to an existing partial file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeAction {
+    /// Server honored the range - append the body to the partial file.
+    Append,
+    /// Nothing to resume, or the server ignored the range - write from scratch.
+    Restart,
+}
+
+/// Decide whether a response can be appended to a partial file of `offset` bytes.
+///
+/// Only a `206 Partial Content` whose `Content-Range` starts exactly at
+/// `offset` is safe to append; anything else restarts clean.
+pub fn resume_action(offset: u64, status: u16, content_range: Option<&str>) -> ResumeAction {
+    if offset == 0 || status != 206 {
+        return ResumeAction::Restart;
+    }
+
+    match content_range.and_then(parse_content_range) {
+        Some(range) if range.start == offset => ResumeAction::Append,
+        _ => ResumeAction::Restart,
+    }
+}
+
+/// Expected size of the finished file, if the server told us.
+pub fn expected_total_len(
+    action: ResumeAction,
+    offset: u64,
+    content_length: Option<u64>,
+    content_range: Option<&str>,
+) -> Option<u64> {
+    match action {
+        ResumeAction::Append => content_range
+            .and_then(parse_content_range)
+            .and_then(|range| range.total)
+            .or_else(|| content_length.map(|len| offset + len)),
+        ResumeAction::Restart => content_length,
+    }
+}
+
+/// Open the partial file for writing according to `action`.
+pub fn open_partial(path: &Path, action: ResumeAction) -> Result<File, String> {
+    let mut options = OpenOptions::new();
+    match action {
+        ResumeAction::Append => options.append(true),
+        ResumeAction::Restart => options.create(true).write(true).truncate(true),
+    };
+    options
+        .open(path)
+        .map_err(|e| format!("Failed to open partial download: {}", e))
+}
+
+/// Writes streamed chunks to the partial file, hashing them as they arrive.
+///
+/// Peak memory stays at one chunk regardless of asset size, and the final
+/// SHA256 is known without re-reading the file. When appending to an
+/// existing partial file, the bytes already on disk are hashed first.
+pub struct PartialWriter {
+    file: File,
+    hasher: Sha256,
+    written: u64,
+}
+
+impl PartialWriter {
+    /// Open `path` according to `action`, ready for the next chunk.
+    pub fn open(path: &Path, action: ResumeAction) -> Result<Self, String> {
+        let mut hasher = Sha256::new();
+        let mut written = 0;
+        if action == ResumeAction::Append {
+            let mut existing =
+                File::open(path).map_err(|e| format!("Failed to open partial download: {}", e))?;
+            let mut buffer = [0u8; 8192];
+            loop {
+                let read = existing
+                    .read(&mut buffer)
+                    .map_err(|e| format!("Failed to read partial download: {}", e))?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                written += read as u64;
+            }
+        }
+
+        Ok(Self {
+            file: open_partial(path, action)?,
+            hasher,
+            written,
+        })
+    }
+
+    /// Append a chunk to the file and the running hash.
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.file.write_all(chunk)?;
+        self.hasher.update(chunk);
+        self.written += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Total bytes in the file so far, including any resumed prefix.
+    pub fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Flush the file and return the lowercase hex SHA256 of its contents.
+    pub fn finish(mut self) -> io::Result<String> {
+        self.file.flush()?;
+        Ok(format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+/// Token-bucket throttle for the `max_download_bytes_per_sec` setting.
+///
+/// Time is passed in as the elapsed duration since the download started,
+/// so the limiter can be driven by a fake clock in tests.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bytes_per_sec: f64,
+    /// Burst allowance: at most a quarter second of traffic at full speed.
+    capacity: f64,
+    /// Available bytes; negative while the download is ahead of the cap.
+    tokens: f64,
+    last: Duration,
+}
+
+impl RateLimiter {
+    /// Limiter for `bytes_per_sec`, or `None` when the limit is 0 (unlimited).
+    pub fn new(bytes_per_sec: u64) -> Option<Self> {
+        if bytes_per_sec == 0 {
+            return None;
+        }
+        let capacity = bytes_per_sec as f64 / 4.0;
+        Some(Self {
+            bytes_per_sec: bytes_per_sec as f64,
+            capacity,
+            tokens: capacity,
+            last: Duration::ZERO,
+        })
+    }
+
+    /// Account for `bytes` received at time `now` and return how long to
+    /// wait before reading the next chunk.
+    pub fn delay_for(&mut self, bytes: u64, now: Duration) -> Duration {
+        let elapsed = now.saturating_sub(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.capacity);
+        self.tokens -= bytes as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_sec)
+        }
+    }
+}
+
+/// Compare a computed SHA256 against a published one (case and whitespace insensitive).
+pub fn checksum_matches(actual: &str, expected: &str) -> bool {
+    actual.trim().eq_ignore_ascii_case(expected.trim())
+}
+
+/// Reply to one download request, as much of it as [`download_to_file`]
+/// needs.
+pub struct DownloadResponse<B> {
+    pub status: u16,
+    pub content_length: Option<u64>,
+    /// The `Content-Range` header, if any.
+    pub content_range: Option<String>,
+    pub body: B,
+}
+
+/// Where [`download_to_file`] fetches from: the HTTP client in the app,
+/// canned responses in tests.
+pub trait DownloadSource {
+    type Body: DownloadBody;
+
+    /// Request the file, asking for the bytes from `offset` on when it is
+    /// non-zero. Failing to get any response is transient.
+    async fn request(&self, offset: u64) -> Result<DownloadResponse<Self::Body>, DownloadFailure>;
+}
+
+/// Body of a [`DownloadResponse`], read a chunk at a time.
+pub trait DownloadBody {
+    /// The next chunk, or `None` once the body has ended.
+    async fn next_chunk(&mut self) -> Result<Option<impl AsRef<[u8]>>, DownloadFailure>;
+}
+
+/// Run one download attempt, streaming into `partial_file`.
+///
+/// Chunks are written and hashed as they arrive, so memory use doesn't grow
+/// with the asset size. Returns the SHA256 of the complete file. Resumes
+/// from an existing partial file when the source honors the range request.
+/// On a transient failure or cancellation the partial file is kept for the
+/// next attempt. With a non-zero `max_bytes_per_sec`, reading pauses
+/// between chunks to hold the transfer under that rate.
+///
+/// `on_progress` gets the bytes now in the partial file and the final size
+/// if known, after every chunk.
+pub async fn download_to_file(
+    source: &impl DownloadSource,
+    partial_file: &Path,
+    max_bytes_per_sec: u64,
+    on_progress: &mut impl FnMut(u64, Option<u64>),
+    is_cancelled: impl Fn() -> bool,
+) -> Result<String, DownloadFailure> {
+    // Resume from a previous interrupted download if one is on disk
+    let mut offset = existing_partial_len(partial_file);
+    let mut response = source.request(offset).await?;
+
+    // Partial file is already complete or larger than the resource - start over
+    if offset > 0 && response.status == 416 {
+        let _ = fs::remove_file(partial_file);
+        offset = 0;
+        response = source.request(offset).await?;
+    }
+
+    let status = response.status;
+    if !(200..300).contains(&status) {
+        let message = format!("Firmware download failed with HTTP status {}", status);
+        return Err(if is_retriable_status(status) {
+            DownloadFailure::Transient(message)
+        } else {
+            DownloadFailure::Fatal(message)
+        });
+    }
+
+    let content_range = response.content_range.as_deref();
+    let action = resume_action(offset, status, content_range);
+    if action == ResumeAction::Append {
+        log::info!(
+            "Resuming download of {} at {} bytes",
+            partial_file.display(),
+            offset
+        );
+    }
+    let expected_len = expected_total_len(action, offset, response.content_length, content_range);
+
+    // Stream into the partial file; on interruption it is kept for the next attempt
+    let mut writer = PartialWriter::open(partial_file, action).map_err(DownloadFailure::Fatal)?;
+    let mut limiter = RateLimiter::new(max_bytes_per_sec);
+    let started = Instant::now();
+    while let Some(chunk) = response.body.next_chunk().await? {
+        if is_cancelled() {
+            return Err(DownloadFailure::Cancelled);
+        }
+        let chunk = chunk.as_ref();
+        writer
+            .write_chunk(chunk)
+            .map_err(|e| DownloadFailure::Fatal(format!("Failed to write firmware file: {}", e)))?;
+        on_progress(writer.bytes_written(), expected_len);
+        if let Some(limiter) = limiter.as_mut() {
+            let delay = limiter.delay_for(chunk.len() as u64, started.elapsed());
+            if !sleep_unless_cancelled(delay, &is_cancelled).await {
+                return Err(DownloadFailure::Cancelled);
+            }
+        }
+    }
+    let actual_len = writer.bytes_written();
+    let sha256_hash = writer
+        .finish()
+        .map_err(|e| DownloadFailure::Fatal(format!("Failed to write firmware file: {}", e)))?;
+
+    if let Some(expected_len) = expected_len {
+        if actual_len < expected_len {
+            return Err(DownloadFailure::Transient(format!(
+                "Download ended early: received {} of {} bytes",
+                actual_len, expected_len
+            )));
+        }
+        if actual_len > expected_len {
+            let _ = fs::remove_file(partial_file);
+            return Err(DownloadFailure::Fatal(format!(
+                "Downloaded firmware size mismatch: expected {} bytes, got {}",
+                expected_len, actual_len
+            )));
+        }
+    }
+
+    Ok(sha256_hash)
+}
+
+/// Wait for `delay`, returning false as soon as `is_cancelled` says so.
+pub async fn sleep_unless_cancelled(delay: Duration, is_cancelled: &impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + delay;
+    loop {
+        if is_cancelled() {
+            return false;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        tokio::time::sleep(remaining.min(CANCEL_POLL_INTERVAL)).await;
+    }
+}
+
+/// Remove partial downloads in `firmware_dir` older than `max_age`.
+///
+/// Returns the paths that were removed.
+pub fn clean_stale_partials(firmware_dir: &Path, max_age: Duration) -> Result<Vec<PathBuf>, String> {
+    if !firmware_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(firmware_dir)
+        .map_err(|e| format!("Failed to read firmware directory: {}", e))?;
+
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        let is_partial = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with(PARTIAL_SUFFIX));
+        if !is_partial || !path.is_file() {
+            continue;
+        }
+
+        let age = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok());
+
+        if age.is_some_and(|age| age > max_age) {
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(path),
+                Err(e) => log::warn!(
+                    "Failed to remove stale partial download {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheManager;
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn test_payload() -> Vec<u8> {
+        (0..10_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Simulate a server response to a Range request for `data`.
+    fn serve_range(data: &[u8], offset: u64) -> (u16, String, Vec<u8>) {
+        let len = data.len() as u64;
+        (
+            206,
+            format!("bytes {}-{}/{}", offset, len - 1, len),
+            data[offset as usize..].to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_retry_delay_backoff() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(3));
+        assert_eq!(retry_delay(3), Duration::from_secs(9));
+    }
+
+    #[test]
+    fn test_is_retriable_status() {
+        assert!(is_retriable_status(500));
+        assert!(is_retriable_status(503));
+        assert!(is_retriable_status(429));
+        assert!(is_retriable_status(408));
+        assert!(!is_retriable_status(404));
+        assert!(!is_retriable_status(403));
+        assert!(!is_retriable_status(400));
+    }
+
+    #[test]
+    fn test_download_failure_is_retriable() {
+        assert!(DownloadFailure::Transient("connection reset".into()).is_retriable());
+        assert!(!DownloadFailure::Fatal("HTTP 404".into()).is_retriable());
+        assert_eq!(
+            DownloadFailure::Fatal("HTTP 404".into()).to_string(),
+            "HTTP 404"
+        );
+    }
+
+    #[test]
+    fn test_checksum_matches() {
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        assert!(checksum_matches(hash, hash));
+        assert!(checksum_matches(hash, &hash.to_uppercase()));
+        assert!(checksum_matches(hash, &format!(" {}\n", hash)));
+        assert!(!checksum_matches(hash, &hash[1..]));
+        assert!(!checksum_matches(hash, ""));
+    }
+
+    #[test]
+    fn test_partial_path() {
+        let path = partial_path(Path::new("/cache/firmware"), "v1.2.0");
+        assert_eq!(path, Path::new("/cache/firmware/v1.2.0.zip.partial"));
+    }
+
+    #[test]
+    fn test_range_header_value() {
+        assert_eq!(range_header_value(9000), "bytes=9000-");
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/200"),
+            Some(ContentRange {
+                start: 100,
+                end: 199,
+                total: Some(200)
+            })
+        );
+        assert_eq!(
+            parse_content_range("bytes 0-49/*"),
+            Some(ContentRange {
+                start: 0,
+                end: 49,
+                total: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_content_range_rejects_malformed() {
+        assert_eq!(parse_content_range(""), None);
+        assert_eq!(parse_content_range("bytes */200"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+        assert_eq!(parse_content_range("bytes 50-10/200"), None);
+        assert_eq!(parse_content_range("bytes 0-200/200"), None);
+    }
+
+    #[test]
+    fn test_resume_action() {
+        // Server honored the range
+        assert_eq!(
+            resume_action(100, 206, Some("bytes 100-199/200")),
+            ResumeAction::Append
+        );
+        // Server ignored the range and sent the whole file
+        assert_eq!(resume_action(100, 200, None), ResumeAction::Restart);
+        // 206 starting somewhere else must not be appended
+        assert_eq!(
+            resume_action(100, 206, Some("bytes 0-199/200")),
+            ResumeAction::Restart
+        );
+        // 206 without Content-Range can't be validated
+        assert_eq!(resume_action(100, 206, None), ResumeAction::Restart);
+        // Nothing to resume
+        assert_eq!(resume_action(0, 200, None), ResumeAction::Restart);
+    }
+
+    #[test]
+    fn test_expected_total_len() {
+        assert_eq!(
+            expected_total_len(ResumeAction::Append, 100, Some(100), Some("bytes 100-199/200")),
+            Some(200)
+        );
+        assert_eq!(
+            expected_total_len(ResumeAction::Append, 100, Some(100), Some("bytes 100-199/*")),
+            Some(200)
+        );
+        assert_eq!(
+            expected_total_len(ResumeAction::Restart, 100, Some(200), None),
+            Some(200)
+        );
+        assert_eq!(expected_total_len(ResumeAction::Restart, 0, None, None), None);
+    }
+
+    #[test]
+    fn test_resume_truncated_download() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = test_payload();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+
+        // Connection dropped at ~90%
+        let cut = data.len() * 9 / 10;
+        fs::write(&path, &data[..cut]).unwrap();
+
+        let offset = existing_partial_len(&path);
+        assert_eq!(offset, cut as u64);
+
+        let (status, content_range, body) = serve_range(&data, offset);
+        let action = resume_action(offset, status, Some(&content_range));
+        assert_eq!(action, ResumeAction::Append);
+        assert_eq!(
+            expected_total_len(action, offset, Some(body.len() as u64), Some(&content_range)),
+            Some(data.len() as u64)
+        );
+
+        let mut file = open_partial(&path, action).unwrap();
+        file.write_all(&body).unwrap();
+        drop(file);
+
+        assert_eq!(fs::read(&path).unwrap(), data);
+
+        let full_path = temp_dir.path().join("full.zip");
+        fs::write(&full_path, &data).unwrap();
+        assert_eq!(
+            CacheManager::calculate_sha256(&path).unwrap(),
+            CacheManager::calculate_sha256(&full_path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_restart_when_range_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = test_payload();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+
+        fs::write(&path, &data[..500]).unwrap();
+        let offset = existing_partial_len(&path);
+
+        // Server ignores Range and returns the full body with 200
+        let action = resume_action(offset, 200, None);
+        assert_eq!(action, ResumeAction::Restart);
+
+        let mut file = open_partial(&path, action).unwrap();
+        file.write_all(&data).unwrap();
+        drop(file);
+
+        // Old bytes must not be duplicated at the front
+        assert_eq!(fs::read(&path).unwrap(), data);
+    }
+
+    #[test]
+    fn test_partial_writer_hashes_streamed_chunks() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = test_payload();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+
+        let mut writer = PartialWriter::open(&path, ResumeAction::Restart).unwrap();
+        for chunk in data.chunks(1000) {
+            writer.write_chunk(chunk).unwrap();
+        }
+        assert_eq!(writer.bytes_written(), data.len() as u64);
+        let hash = writer.finish().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert_eq!(hash, CacheManager::calculate_sha256(&path).unwrap());
+    }
+
+    #[test]
+    fn test_partial_writer_resume_includes_existing_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = test_payload();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+
+        let cut = data.len() / 3;
+        fs::write(&path, &data[..cut]).unwrap();
+
+        let mut writer = PartialWriter::open(&path, ResumeAction::Append).unwrap();
+        assert_eq!(writer.bytes_written(), cut as u64);
+        writer.write_chunk(&data[cut..]).unwrap();
+        let hash = writer.finish().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert_eq!(hash, CacheManager::calculate_sha256(&path).unwrap());
+    }
+
+    #[test]
+    fn test_partial_writer_restart_discards_existing_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = test_payload();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        fs::write(&path, b"stale bytes").unwrap();
+
+        let mut writer = PartialWriter::open(&path, ResumeAction::Restart).unwrap();
+        assert_eq!(writer.bytes_written(), 0);
+        writer.write_chunk(&data).unwrap();
+        let hash = writer.finish().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert_eq!(hash, CacheManager::calculate_sha256(&path).unwrap());
+    }
+
+    /// In-memory stand-in for a server holding `data`.
+    #[derive(Default)]
+    struct FakeSource {
+        data: Vec<u8>,
+        /// Answer range requests with 206 instead of ignoring them.
+        honor_range: bool,
+        /// Drop the connection after this many body bytes.
+        cut_at: Option<usize>,
+        /// Bytes sent beyond the advertised length.
+        extra: usize,
+        /// Offset of every request made.
+        offsets: RefCell<Vec<u64>>,
+    }
+
+    struct FakeBody(VecDeque<Vec<u8>>);
+
+    impl DownloadSource for FakeSource {
+        type Body = FakeBody;
+
+        async fn request(
+            &self,
+            offset: u64,
+        ) -> Result<DownloadResponse<Self::Body>, DownloadFailure> {
+            self.offsets.borrow_mut().push(offset);
+            let (status, content_range, mut body) = if offset >= self.data.len() as u64 {
+                (416, None, Vec::new())
+            } else if offset > 0 && self.honor_range {
+                let (status, content_range, body) = serve_range(&self.data, offset);
+                (status, Some(content_range), body)
+            } else {
+                (200, None, self.data.clone())
+            };
+
+            let content_length = Some(body.len() as u64);
+            if let Some(cut_at) = self.cut_at {
+                body.truncate(cut_at);
+            }
+            body.extend(std::iter::repeat_n(0xff, self.extra));
+            Ok(DownloadResponse {
+                status,
+                content_length,
+                content_range,
+                body: FakeBody(body.chunks(1000).map(<[u8]>::to_vec).collect()),
+            })
+        }
+    }
+
+    impl DownloadBody for FakeBody {
+        async fn next_chunk(&mut self) -> Result<Option<impl AsRef<[u8]>>, DownloadFailure> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    /// Run `download_to_file` with no rate limit and no cancellation.
+    async fn download(source: &FakeSource, path: &Path) -> Result<String, DownloadFailure> {
+        download_to_file(source, path, 0, &mut |_, _| {}, || false).await
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_full_download() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        let data = test_payload();
+        let source = FakeSource {
+            data: data.clone(),
+            ..Default::default()
+        };
+
+        let mut progress = Vec::new();
+        let hash = download_to_file(
+            &source,
+            &path,
+            0,
+            &mut |downloaded, total| progress.push((downloaded, total)),
+            || false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hash, sha256_hex(&data));
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert_eq!(*source.offsets.borrow(), [0]);
+        assert_eq!(progress.len(), 10);
+        assert_eq!(progress.last(), Some(&(10_000, Some(10_000))));
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_resumes_with_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        let data = test_payload();
+        fs::write(&path, &data[..4000]).unwrap();
+        let source = FakeSource {
+            data: data.clone(),
+            honor_range: true,
+            ..Default::default()
+        };
+
+        let hash = download(&source, &path).await.unwrap();
+
+        // The hash covers the bytes kept from the earlier attempt too
+        assert_eq!(hash, sha256_hex(&data));
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert_eq!(*source.offsets.borrow(), [4000]);
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_restarts_after_416() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        let data = test_payload();
+        fs::write(&path, vec![0u8; 12_000]).unwrap();
+        let source = FakeSource {
+            data: data.clone(),
+            honor_range: true,
+            ..Default::default()
+        };
+
+        let hash = download(&source, &path).await.unwrap();
+
+        assert_eq!(hash, sha256_hex(&data));
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert_eq!(*source.offsets.borrow(), [12_000, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_early_eof_is_transient() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        let source = FakeSource {
+            data: test_payload(),
+            cut_at: Some(6000),
+            ..Default::default()
+        };
+
+        let failure = download(&source, &path).await.unwrap_err();
+
+        assert!(failure.is_retriable(), "{}", failure);
+        assert!(failure.to_string().contains("received 6000 of 10000"));
+        // Kept so the retry can resume
+        assert_eq!(existing_partial_len(&path), 6000);
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_rejects_oversize_body() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        let source = FakeSource {
+            data: test_payload(),
+            extra: 10,
+            ..Default::default()
+        };
+
+        let failure = download(&source, &path).await.unwrap_err();
+
+        assert!(matches!(failure, DownloadFailure::Fatal(_)), "{}", failure);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_cancel_mid_stream() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        let source = FakeSource {
+            data: test_payload(),
+            ..Default::default()
+        };
+
+        let cancelled = Cell::new(false);
+        let failure = download_to_file(
+            &source,
+            &path,
+            0,
+            &mut |downloaded, _| cancelled.set(downloaded >= 3000),
+            || cancelled.get(),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(failure, DownloadFailure::Cancelled);
+        // The chunks before the cancel stay for a later resume
+        assert_eq!(existing_partial_len(&path), 3000);
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_cancel_while_rate_limited() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = partial_path(temp_dir.path(), "v1.0.0");
+        let source = FakeSource {
+            data: test_payload(),
+            ..Default::default()
+        };
+
+        // At 1000 B/s the whole file would take about 10s; cancel 200ms in
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let canceller = Arc::clone(&cancelled);
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            canceller.store(true, Ordering::SeqCst);
+        });
+        let started = Instant::now();
+        let failure = download_to_file(&source, &path, 1000, &mut |_, _| {}, || {
+            cancelled.load(Ordering::SeqCst)
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(failure, DownloadFailure::Cancelled);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_rate_limiter_unlimited() {
+        assert!(RateLimiter::new(0).is_none());
+    }
+
+    #[test]
+    fn test_rate_limiter_holds_sustained_rate() {
+        let cap = 100 * 1024;
+        let mut limiter = RateLimiter::new(cap).unwrap();
+        let chunk = 16 * 1024;
+
+        // Fake clock: each chunk arrives 1ms after the previous wait ends,
+        // i.e. the network alone would be far faster than the cap
+        let mut now = Duration::ZERO;
+        let mut received = 0;
+        while now < Duration::from_secs(30) {
+            now += Duration::from_millis(1);
+            received += chunk;
+            now += limiter.delay_for(chunk, now);
+        }
+
+        let rate = received as f64 / now.as_secs_f64();
+        assert!(
+            (rate - cap as f64).abs() <= cap as f64 * 0.1,
+            "sustained rate {} not within 10% of {}",
+            rate,
+            cap
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_no_delay_below_cap() {
+        let mut limiter = RateLimiter::new(100 * 1024).unwrap();
+
+        // 10KB every 200ms is 50KB/s, half the cap
+        let mut now = Duration::ZERO;
+        for _ in 0..50 {
+            now += Duration::from_millis(200);
+            assert_eq!(limiter.delay_for(10 * 1024, now), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_rate_limiter_idle_time_does_not_bank_unlimited_burst() {
+        let cap = 100 * 1024;
+        let mut limiter = RateLimiter::new(cap).unwrap();
+
+        // After a long stall, only a quarter second of burst is allowed
+        let delay = limiter.delay_for(cap, Duration::from_secs(60));
+        assert_eq!(delay, Duration::from_secs_f64(0.75));
+    }
+
+    #[test]
+    fn test_existing_partial_len_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(existing_partial_len(&temp_dir.path().join("none.zip.partial")), 0);
+    }
+
+    #[test]
+    fn test_clean_stale_partials() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+
+        let stale = partial_path(dir, "v1.0.0");
+        let fresh = partial_path(dir, "v2.0.0");
+        let zip = dir.join("v1.0.0.zip");
+        fs::write(&stale, b"old").unwrap();
+        fs::write(&fresh, b"new").unwrap();
+        fs::write(&zip, b"zip").unwrap();
+
+        let old_time = SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60);
+        File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(old_time)
+            .unwrap();
+        File::options()
+            .write(true)
+            .open(&zip)
+            .unwrap()
+            .set_modified(old_time)
+            .unwrap();
+
+        let removed = clean_stale_partials(dir, STALE_PARTIAL_MAX_AGE).unwrap();
+
+        assert_eq!(removed, vec![stale.clone()]);
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        // Completed downloads are never touched, however old
+        assert!(zip.exists());
+    }
+
+    #[test]
+    fn test_clean_stale_partials_missing_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let removed =
+            clean_stale_partials(&temp_dir.path().join("missing"), STALE_PARTIAL_MAX_AGE).unwrap();
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_download() {
+        let handle = DownloadHandle::register("test-cancel").unwrap();
+        assert!(!handle.is_cancelled());
+
+        assert!(cancel_download("test-cancel"));
+        assert!(handle.is_cancelled());
+        assert!(!cancel_download("test-unknown"));
+
+        // Dropping the handle unregisters the download
+        drop(handle);
+        assert!(!cancel_download("test-cancel"));
+    }
+
+    #[test]
+    fn test_register_rejects_duplicate_id() {
+        let _handle = DownloadHandle::register("test-duplicate").unwrap();
+        assert!(DownloadHandle::register("test-duplicate")
+            .err()
+            .unwrap()
+            .contains("already in progress"));
+    }
+
+    #[test]
+    fn test_active_partials_released_with_handle() {
+        let partial = Path::new("/cache/firmware/test-active.zip.partial");
+        let handle = DownloadHandle::register("test-active").unwrap();
+        handle.writes_to(partial);
+        assert!(active_partials().iter().any(|path| path == partial));
+
+        drop(handle);
+        assert!(!active_partials().iter().any(|path| path == partial));
+    }
+
+    #[test]
+    fn test_download_outcome_serialization() {
+        let completed = DownloadOutcome::Completed {
+            path: "/cache/1.0.0.zip".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&completed).unwrap(),
+            serde_json::json!({ "status": "completed", "path": "/cache/1.0.0.zip" })
+        );
+        assert_eq!(
+            serde_json::to_value(DownloadOutcome::Cancelled).unwrap(),
+            serde_json::json!({ "status": "cancelled" })
+        );
+    }
+
+    #[test]
+    fn test_asset_cache_key() {
+        assert_eq!(
+            asset_cache_key("1.0.0", "firmware.zip", AssetKind::DfuPackage),
+            "1.0.0"
+        );
+        assert_eq!(
+            asset_cache_key("1.0.0", "circuitpy.zip", AssetKind::CircuitPyBundle),
+            "1.0.0-circuitpy"
+        );
+        assert_eq!(staging_key("1.0.0", "firmware.zip"), "1.0.0-firmware");
+    }
+}
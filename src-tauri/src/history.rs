@@ -0,0 +1,315 @@
+//! Flash history: an audit trail of every firmware installation.
+//!
+//! Each flash appends one JSON line to `flash_history.jsonl` in the app data
+//! directory, recording the device, firmware, app version and outcome. When
+//! the file grows past `MAX_HISTORY_BYTES` it is rotated to
+//! `flash_history.1.jsonl`, replacing the previous rotation, so the history
+//! never takes more than about twice that on disk.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// History file name stored in app data directory.
+const HISTORY_FILENAME: &str = "flash_history.jsonl";
+
+/// Rotated history file name.
+const ROTATED_HISTORY_FILENAME: &str = "flash_history.1.jsonl";
+
+/// Size at which the history file is rotated.
+const MAX_HISTORY_BYTES: u64 = 1024 * 1024;
+
+/// One firmware installation on one device.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FlashRecord {
+    /// When the flash started (RFC 3339).
+    pub started_at: String,
+    /// How long the flash took, including retries.
+    pub duration_ms: u64,
+    /// USB serial number of the device, if it reports one.
+    pub serial_number: Option<String>,
+    /// Serial port the flash was requested on.
+    pub port: String,
+    /// Firmware version, when the zip came from the cache.
+    pub firmware_version: Option<String>,
+    /// Path of the firmware.zip that was flashed.
+    pub firmware_path: String,
+    /// Version of the updater that performed the flash.
+    pub app_version: String,
+    /// Role configured after the flash, if any.
+    pub role: Option<String>,
+    /// Therapy profile applied as part of the run, if any.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Whether the firmware was installed.
+    pub success: bool,
+    /// Number of attempts made (1 when the first attempt succeeded).
+    pub attempts: u32,
+    /// Support error code of the final failure (e.g. "DFU-021").
+    pub error_code: Option<String>,
+    /// Message of the final failure.
+    pub error: Option<String>,
+}
+
+/// Criteria for `get_flash_history`. Unset fields match every record.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    pub serial_number: Option<String>,
+    pub firmware_version: Option<String>,
+    pub success: Option<bool>,
+    /// Only records started at or after this time (RFC 3339).
+    pub since: Option<String>,
+    /// Maximum number of records returned, newest first.
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, record: &FlashRecord) -> bool {
+        if let Some(serial) = &self.serial_number {
+            if record.serial_number.as_deref() != Some(serial.as_str()) {
+                return false;
+            }
+        }
+        if let Some(version) = &self.firmware_version {
+            if record.firmware_version.as_deref() != Some(version.as_str()) {
+                return false;
+            }
+        }
+        if let Some(success) = self.success {
+            if record.success != success {
+                return false;
+            }
+        }
+        if let Some(since) = &self.since {
+            let since = chrono::DateTime::parse_from_rfc3339(since);
+            let started = chrono::DateTime::parse_from_rfc3339(&record.started_at);
+            if let (Ok(since), Ok(started)) = (since, started) {
+                if started < since {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Appends and reads the flash history, shared as Tauri managed state.
+#[derive(Clone)]
+pub struct FlashHistory {
+    history_path: PathBuf,
+    rotated_path: PathBuf,
+    /// Serializes appends and rotation.
+    lock: Arc<Mutex<()>>,
+}
+
+impl FlashHistory {
+    /// Create a history for the given app data directory.
+    pub fn new(app_data_dir: &Path) -> Self {
+        Self {
+            history_path: app_data_dir.join(HISTORY_FILENAME),
+            rotated_path: app_data_dir.join(ROTATED_HISTORY_FILENAME),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Append a record, rotating the file first if it has grown too large.
+    pub fn append(&self, record: &FlashRecord) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(parent) = self.history_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create history directory: {}", e))?;
+        }
+
+        let size = fs::metadata(&self.history_path)
+            .map(|m| m.len())
+            .unwrap_or(0);
+        if size >= MAX_HISTORY_BYTES {
+            fs::rename(&self.history_path, &self.rotated_path)
+                .map_err(|e| format!("Failed to rotate flash history: {}", e))?;
+        }
+
+        let mut line = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize flash record: {}", e))?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.history_path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write flash history: {}", e))
+    }
+
+    /// All records, oldest first. Unreadable lines are skipped.
+    pub fn load(&self) -> Vec<FlashRecord> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        [&self.rotated_path, &self.history_path]
+            .into_iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .flat_map(|contents| {
+                contents
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .filter_map(|line| match serde_json::from_str(line) {
+                        Ok(record) => Some(record),
+                        Err(e) => {
+                            log::warn!("Skipping unreadable record: {}", e);
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Records matching `filter`, newest first.
+    pub fn query(&self, filter: &HistoryFilter) -> Vec<FlashRecord> {
+        let records = self
+            .load()
+            .into_iter()
+            .rev()
+            .filter(|record| filter.matches(record));
+
+        match filter.limit {
+            Some(limit) => records.take(limit).collect(),
+            None => records.collect(),
+        }
+    }
+
+    /// Write the whole history to `destination` as a JSON array, oldest first.
+    ///
+    /// Returns the number of records exported.
+    pub fn export(&self, destination: &Path) -> Result<usize, String> {
+        let records = self.load();
+        let contents = serde_json::to_string_pretty(&records)
+            .map_err(|e| format!("Failed to serialize flash history: {}", e))?;
+
+        fs::write(destination, contents)
+            .map_err(|e| format!("Failed to export flash history: {}", e))?;
+
+        Ok(records.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn record(serial: &str, version: &str, success: bool, started_at: &str) -> FlashRecord {
+        FlashRecord {
+            started_at: started_at.to_string(),
+            duration_ms: 42_000,
+            serial_number: Some(serial.to_string()),
+            port: "COM3".to_string(),
+            firmware_version: Some(version.to_string()),
+            firmware_path: format!("/cache/{}/firmware.zip", version),
+            app_version: "1.0.0".to_string(),
+            role: Some("PRIMARY".to_string()),
+            profile: None,
+            success,
+            attempts: 1,
+            error_code: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_query_newest_first() {
+        let dir = tempdir().unwrap();
+        let history = FlashHistory::new(dir.path());
+
+        history
+            .append(&record("A1", "v2.3.0", true, "2026-03-01T10:00:00Z"))
+            .unwrap();
+        history
+            .append(&record("B2", "v2.3.1", false, "2026-03-03T10:00:00Z"))
+            .unwrap();
+        history
+            .append(&record("A1", "v2.3.1", true, "2026-03-04T10:00:00Z"))
+            .unwrap();
+
+        let all = history.query(&HistoryFilter::default());
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].started_at, "2026-03-04T10:00:00Z");
+
+        let device = history.query(&HistoryFilter {
+            serial_number: Some("A1".to_string()),
+            success: Some(true),
+            limit: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(device.len(), 1);
+        assert_eq!(device[0].firmware_version.as_deref(), Some("v2.3.1"));
+
+        let since = history.query(&HistoryFilter {
+            since: Some("2026-03-02T00:00:00Z".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(since.len(), 2);
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_file() {
+        let dir = tempdir().unwrap();
+        let history = FlashHistory::new(dir.path());
+
+        // Pretend the current file is already full
+        let full = dir.path().join(HISTORY_FILENAME);
+        let old =
+            serde_json::to_string(&record("A1", "v2.2.0", true, "2026-01-01T00:00:00Z")).unwrap();
+        let padding = " ".repeat(MAX_HISTORY_BYTES as usize);
+        fs::write(&full, format!("{}\n{}\n", old, padding)).unwrap();
+
+        history
+            .append(&record("A1", "v2.3.1", true, "2026-03-04T10:00:00Z"))
+            .unwrap();
+
+        assert!(dir.path().join(ROTATED_HISTORY_FILENAME).exists());
+        assert!(fs::metadata(&full).unwrap().len() < 1024);
+
+        // Rotated records are still returned
+        let records = history.load();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].firmware_version.as_deref(), Some("v2.2.0"));
+    }
+
+    #[test]
+    fn test_load_skips_corrupted_lines() {
+        let dir = tempdir().unwrap();
+        let history = FlashHistory::new(dir.path());
+
+        history
+            .append(&record("A1", "v2.3.1", true, "2026-03-04T10:00:00Z"))
+            .unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(HISTORY_FILENAME))
+            .unwrap();
+        // Simulate a crash mid-write
+        file.write_all(b"{\"started_at\": \"2026-03").unwrap();
+
+        assert_eq!(history.load().len(), 1);
+    }
+
+    #[test]
+    fn test_export_writes_json_array() {
+        let dir = tempdir().unwrap();
+        let history = FlashHistory::new(dir.path());
+        history
+            .append(&record("A1", "v2.3.1", true, "2026-03-04T10:00:00Z"))
+            .unwrap();
+
+        let destination = dir.path().join("export.json");
+        assert_eq!(history.export(&destination).unwrap(), 1);
+
+        let exported: Vec<FlashRecord> =
+            serde_json::from_str(&fs::read_to_string(&destination).unwrap()).unwrap();
+        assert_eq!(exported, history.load());
+    }
+}
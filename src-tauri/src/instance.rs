@@ -0,0 +1,140 @@
+//! Single-instance hand-off.
+//!
+//! Two running copies of the app would both poll the serial ports and fight
+//! over the same device, failing flashes with `PortBusy`. A second launch
+//! therefore hands its arguments to the running instance and exits; the
+//! running instance focuses its window and passes the arguments on to the
+//! frontend as `SECOND_INSTANCE_EVENT`.
+//!
+//! Nothing here touches the serial ports or the running operations, so a
+//! flash in progress carries on undisturbed.
+//!
+//! The headless `--flash` runs before the plugin is set up, so it can't be
+//! handed over that way. Instead the GUI and a headless flash both hold an
+//! `InstanceLock`, and whichever comes second leaves the ports alone.
+
+use serde::Serialize;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Event sent to the frontend when the app is launched again.
+pub const SECOND_INSTANCE_EVENT: &str = "app://second-instance";
+
+/// Lock file shared by every copy of the app, in the temp directory.
+const INSTANCE_LOCK_FILE: &str = "bluebuzzah-updater.lock";
+
+/// Label of the app's only window.
+const MAIN_WINDOW: &str = "main";
+
+/// A launch handed over by a second instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondLaunch {
+    /// Arguments after the program name, e.g. a firmware package to open.
+    pub args: Vec<String>,
+    /// Working directory of the second launch, for relative paths in `args`.
+    pub cwd: String,
+}
+
+impl SecondLaunch {
+    /// Build from the second instance's full argument list.
+    fn new(argv: Vec<String>, cwd: String) -> Self {
+        Self {
+            args: argv.into_iter().skip(1).collect(),
+            cwd,
+        }
+    }
+}
+
+/// Handle a second launch: bring the window forward and forward its
+/// arguments. Called by the single-instance plugin in the running instance.
+pub fn on_second_launch(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    let launch = SecondLaunch::new(argv, cwd);
+    log::info!("Second launch handed over with args {:?}", launch.args);
+
+    focus_main_window(app);
+    if let Err(e) = app.emit(SECOND_INSTANCE_EVENT, &launch) {
+        log::warn!("Failed to forward second launch: {}", e);
+    }
+}
+
+/// Show, restore and focus the main window.
+fn focus_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+        log::warn!("No main window to focus");
+        return;
+    };
+
+    let result = window
+        .show()
+        .and_then(|_| window.unminimize())
+        .and_then(|_| window.set_focus());
+    if let Err(e) = result {
+        log::warn!("Failed to focus main window: {}", e);
+    }
+}
+
+/// OS lock held for as long as this process may use the serial ports.
+///
+/// The lock is released when the process exits, however it exits, so a
+/// crash never leaves it stale.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Take the lock, or `None` when another copy of the app holds it.
+    pub fn acquire() -> Result<Option<Self>, String> {
+        Self::acquire_at(&std::env::temp_dir().join(INSTANCE_LOCK_FILE))
+    }
+
+    fn acquire_at(path: &Path) -> Result<Option<Self>, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(format!("Failed to lock {}: {}", path.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INSTANCE_LOCK_FILE);
+
+        let first = InstanceLock::acquire_at(&path).unwrap();
+        assert!(first.is_some());
+        assert!(InstanceLock::acquire_at(&path).unwrap().is_none());
+
+        drop(first);
+        assert!(InstanceLock::acquire_at(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_second_launch_drops_program_name() {
+        let launch = SecondLaunch::new(
+            vec!["bluebuzzah-updater".to_string(), "firmware.zip".to_string()],
+            "/home/user".to_string(),
+        );
+
+        assert_eq!(launch.args, vec!["firmware.zip".to_string()]);
+        assert_eq!(
+            serde_json::to_value(&launch).unwrap(),
+            serde_json::json!({ "args": ["firmware.zip"], "cwd": "/home/user" })
+        );
+    }
+}
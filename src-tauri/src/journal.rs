@@ -0,0 +1,406 @@
+//! Crash-safe journal of flashes in progress.
+//!
+//! A flash writes one small JSON file to `operations/` in the app data
+//! directory when it starts, rewrites it when the stage changes and deletes
+//! it when the flash ends, whether it succeeded or not. A file still there
+//! at the next launch means the app or the computer died mid-flash, possibly
+//! leaving the device in bootloader mode with no firmware. Those entries are
+//! read once at startup and offered to the user until dismissed or until
+//! the same device is flashed successfully.
+//!
+//! Each write goes to a temporary file that is then renamed over the entry,
+//! so a crash mid-write leaves the previous version, never a torn one.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::dfu::Nrf52Device;
+
+/// Journal directory in the app data directory.
+const JOURNAL_DIRNAME: &str = "operations";
+
+/// Distinguishes entries started in the same millisecond.
+static ENTRY_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// One flash, as recorded in its journal file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    /// Name of the journal file, without the extension.
+    pub id: String,
+    /// What was running, e.g. "flash".
+    pub operation: String,
+    /// Serial port the operation was started on.
+    pub port: String,
+    /// USB serial number of the device, if it reports one.
+    pub serial_number: Option<String>,
+    /// Firmware version, when the zip came from the cache.
+    pub firmware_version: Option<String>,
+    /// Path of the firmware.zip being flashed.
+    pub firmware_path: String,
+    /// Last progress stage reached, e.g. "uploading".
+    pub stage: String,
+    /// When the operation started (RFC 3339).
+    pub started_at: String,
+    /// When the entry was last written (RFC 3339).
+    pub updated_at: String,
+}
+
+impl JournalEntry {
+    /// A new entry for `operation` on the device on `port`.
+    pub fn new(
+        operation: &str,
+        port: &str,
+        serial_number: Option<String>,
+        firmware_version: Option<String>,
+        firmware_path: &str,
+    ) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: format!(
+                "{}-{}",
+                now.timestamp_millis(),
+                ENTRY_SEQUENCE.fetch_add(1, Ordering::SeqCst)
+            ),
+            operation: operation.to_string(),
+            port: port.to_string(),
+            serial_number,
+            firmware_version,
+            firmware_path: firmware_path.to_string(),
+            stage: "starting".to_string(),
+            started_at: now.to_rfc3339(),
+            updated_at: now.to_rfc3339(),
+        }
+    }
+
+    /// Whether the device on `port` with `serial_number` is the device this
+    /// entry was written for: the same serial number when both have one,
+    /// otherwise the same port.
+    fn matches(&self, port: &str, serial_number: Option<&str>) -> bool {
+        match (self.serial_number.as_deref(), serial_number) {
+            (Some(expected), Some(actual)) => expected == actual,
+            _ => self.port == port,
+        }
+    }
+}
+
+/// An operation a previous run didn't finish, with where its device is now.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InterruptedOperation {
+    #[serde(flatten)]
+    pub entry: JournalEntry,
+    /// Port the device is connected on now, if it is connected.
+    pub current_port: Option<String>,
+    /// Whether the device is connected in bootloader mode, so it can be
+    /// flashed again straight away.
+    pub in_bootloader: bool,
+}
+
+impl InterruptedOperation {
+    /// Pair `entry` with its device among `devices`, preferring one in
+    /// bootloader mode.
+    pub fn locate(entry: JournalEntry, devices: &[Nrf52Device]) -> Self {
+        let device = devices
+            .iter()
+            .filter(|device| entry.matches(&device.port, device.serial_number.as_deref()))
+            .max_by_key(|device| device.in_bootloader);
+        Self {
+            current_port: device.map(|device| device.port.clone()),
+            in_bootloader: device.is_some_and(|device| device.in_bootloader),
+            entry,
+        }
+    }
+}
+
+/// An entry an earlier run left behind, and the file it was read from.
+struct Interrupted {
+    path: PathBuf,
+    entry: JournalEntry,
+}
+
+/// The journal directory, and the entries an earlier run left behind.
+pub struct OperationJournal {
+    dir: PathBuf,
+    interrupted: Mutex<Vec<Interrupted>>,
+}
+
+impl OperationJournal {
+    /// Open the journal in `app_data_dir`, reading what an earlier run left.
+    ///
+    /// Nothing has started in this run yet, so every entry found is from an
+    /// interrupted operation. Unreadable entries are logged and skipped.
+    pub fn open(app_data_dir: &Path) -> Self {
+        let dir = app_data_dir.join(JOURNAL_DIRNAME);
+        let mut interrupted: Vec<Interrupted> = Vec::new();
+
+        for path in fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        {
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string()))
+            {
+                Ok(entry) => interrupted.push(Interrupted { path, entry }),
+                Err(e) => log::warn!("Skipping journal entry {}: {}", path.display(), e),
+            }
+        }
+
+        interrupted.sort_by(|a, b| a.entry.started_at.cmp(&b.entry.started_at));
+        for Interrupted { entry, .. } in &interrupted {
+            log::warn!(
+                "Found interrupted {} on {} at stage {}",
+                entry.operation,
+                entry.port,
+                entry.stage
+            );
+        }
+
+        Self {
+            dir,
+            interrupted: Mutex::new(interrupted),
+        }
+    }
+
+    /// Record that `entry` has started. The entry is deleted when the
+    /// returned handle is dropped, which a crash never does.
+    pub fn begin(&self, entry: JournalEntry) -> Result<JournaledOperation, String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create journal directory: {}", e))?;
+
+        let operation = JournaledOperation {
+            path: entry_path(&self.dir, &entry.id),
+            entry: Mutex::new(entry),
+        };
+        operation.write(&operation.entry.lock().unwrap_or_else(|e| e.into_inner()))?;
+        Ok(operation)
+    }
+
+    /// Entries left by an earlier run, oldest first.
+    pub fn interrupted(&self) -> Vec<JournalEntry> {
+        self.interrupted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|interrupted| interrupted.entry.clone())
+            .collect()
+    }
+
+    /// Forget the interrupted entry `id`. Returns whether there was one.
+    pub fn dismiss(&self, id: &str) -> Result<bool, String> {
+        self.forget(|entry| entry.id == id)
+    }
+
+    /// Forget interrupted entries for the device on `port` with
+    /// `serial_number`, once it has been flashed.
+    pub fn resolve(&self, port: &str, serial_number: Option<&str>) -> Result<bool, String> {
+        self.forget(|entry| entry.matches(port, serial_number))
+    }
+
+    fn forget(&self, matches: impl Fn(&JournalEntry) -> bool) -> Result<bool, String> {
+        let mut interrupted = self.interrupted.lock().unwrap_or_else(|e| e.into_inner());
+        let (forgotten, kept): (Vec<_>, Vec<_>) = interrupted
+            .drain(..)
+            .partition(|interrupted| matches(&interrupted.entry));
+        *interrupted = kept;
+
+        // The file name need not match the id inside it
+        for Interrupted { path, .. } in &forgotten {
+            if let Err(e) = fs::remove_file(path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(format!("Failed to remove journal entry: {}", e));
+                }
+            }
+        }
+        Ok(!forgotten.is_empty())
+    }
+}
+
+/// A running operation's journal entry. Dropping it deletes the entry.
+pub struct JournaledOperation {
+    path: PathBuf,
+    entry: Mutex<JournalEntry>,
+}
+
+impl JournaledOperation {
+    /// Record that the operation reached `stage`. Only a change of stage is
+    /// written, so this is cheap to call for every progress event.
+    pub fn set_stage(&self, stage: &str) {
+        let mut entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        if entry.stage == stage {
+            return;
+        }
+
+        entry.stage = stage.to_string();
+        entry.updated_at = chrono::Utc::now().to_rfc3339();
+        if let Err(e) = self.write(&entry) {
+            log::warn!("{}", e);
+        }
+    }
+
+    /// Write `entry` to a temporary file, then move it over the entry.
+    fn write(&self, entry: &JournalEntry) -> Result<(), String> {
+        let contents = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize journal entry: {}", e))?;
+        let tmp_path = self.path.with_extension("json.tmp");
+
+        fs::File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(contents.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp_path, &self.path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&tmp_path);
+                format!("Failed to write journal entry: {}", e)
+            })
+    }
+}
+
+impl Drop for JournaledOperation {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!("Failed to remove journal entry: {}", e);
+        }
+    }
+}
+
+/// Path of the journal file for entry `id`.
+fn entry_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn device(port: &str, serial: Option<&str>, in_bootloader: bool) -> Nrf52Device {
+        Nrf52Device {
+            port: port.to_string(),
+            vid: 0x239A,
+            pid: 0x0029,
+            serial_number: serial.map(str::to_string),
+            in_bootloader,
+            product_name: None,
+            manufacturer: None,
+        }
+    }
+
+    fn entry(port: &str, serial: Option<&str>) -> JournalEntry {
+        JournalEntry::new(
+            "flash",
+            port,
+            serial.map(str::to_string),
+            Some("1.2.0".to_string()),
+            "/cache/1.2.0.zip",
+        )
+    }
+
+    #[test]
+    fn test_finished_operation_leaves_no_entry() {
+        let dir = tempdir().unwrap();
+        let journal = OperationJournal::open(dir.path());
+
+        let operation = journal.begin(entry("COM3", Some("ABC123"))).unwrap();
+        operation.set_stage("uploading");
+        drop(operation);
+
+        assert!(OperationJournal::open(dir.path()).interrupted().is_empty());
+    }
+
+    #[test]
+    fn test_unfinished_operation_is_found_at_next_open() {
+        let dir = tempdir().unwrap();
+        let journal = OperationJournal::open(dir.path());
+
+        let operation = journal.begin(entry("COM3", Some("ABC123"))).unwrap();
+        operation.set_stage("erasing");
+        operation.set_stage("uploading");
+        // A crash never runs the destructor
+        std::mem::forget(operation);
+
+        let reopened = OperationJournal::open(dir.path());
+        let interrupted = reopened.interrupted();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].port, "COM3");
+        assert_eq!(interrupted[0].stage, "uploading");
+        assert_eq!(interrupted[0].firmware_version.as_deref(), Some("1.2.0"));
+
+        // Dismissed entries are gone for good
+        assert!(reopened.dismiss(&interrupted[0].id).unwrap());
+        assert!(!reopened.dismiss(&interrupted[0].id).unwrap());
+        assert!(OperationJournal::open(dir.path()).interrupted().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_forgets_entries_for_flashed_device() {
+        let dir = tempdir().unwrap();
+        let journal = OperationJournal::open(dir.path());
+        std::mem::forget(journal.begin(entry("COM3", Some("ABC123"))).unwrap());
+        std::mem::forget(journal.begin(entry("COM4", Some("DEF456"))).unwrap());
+
+        let reopened = OperationJournal::open(dir.path());
+        assert!(reopened.resolve("COM7", Some("ABC123")).unwrap());
+
+        let remaining = OperationJournal::open(dir.path()).interrupted();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].serial_number.as_deref(), Some("DEF456"));
+    }
+
+    #[test]
+    fn test_dismiss_removes_file_named_differently() {
+        let dir = tempdir().unwrap();
+        let journal_dir = dir.path().join(JOURNAL_DIRNAME);
+        fs::create_dir_all(&journal_dir).unwrap();
+        let renamed = journal_dir.join("copied.json");
+        fs::write(
+            &renamed,
+            serde_json::to_string(&entry("COM3", Some("ABC123"))).unwrap(),
+        )
+        .unwrap();
+
+        let journal = OperationJournal::open(dir.path());
+        let id = journal.interrupted()[0].id.clone();
+        assert!(journal.dismiss(&id).unwrap());
+        assert!(!renamed.exists());
+    }
+
+    #[test]
+    fn test_unreadable_entries_are_skipped() {
+        let dir = tempdir().unwrap();
+        let journal_dir = dir.path().join(JOURNAL_DIRNAME);
+        fs::create_dir_all(&journal_dir).unwrap();
+        fs::write(journal_dir.join("broken.json"), "{").unwrap();
+        fs::write(journal_dir.join("leftover.json.tmp"), "{").unwrap();
+
+        assert!(OperationJournal::open(dir.path()).interrupted().is_empty());
+    }
+
+    #[test]
+    fn test_locate_prefers_device_in_bootloader() {
+        let devices = vec![
+            device("COM3", Some("ABC123"), false),
+            device("COM9", Some("ABC123"), true),
+            device("COM4", Some("DEF456"), true),
+        ];
+
+        let located = InterruptedOperation::locate(entry("COM3", Some("ABC123")), &devices);
+        assert_eq!(located.current_port.as_deref(), Some("COM9"));
+        assert!(located.in_bootloader);
+
+        // Without a serial number only the port identifies the device
+        let located = InterruptedOperation::locate(entry("COM4", None), &devices);
+        assert_eq!(located.current_port.as_deref(), Some("COM4"));
+
+        let located = InterruptedOperation::locate(entry("COM5", Some("XYZ")), &devices);
+        assert_eq!(located.current_port, None);
+        assert!(!located.in_bootloader);
+    }
+}
@@ -0,0 +1,237 @@
+//! Backend log file.
+//!
+//! Every module logs through the `log` macros, with its module path as the
+//! target. Records at or above the current level are written to
+//! `updater.log` in the app log directory, and echoed to stderr for
+//! development. When the file grows past `MAX_LOG_BYTES` it is rotated to
+//! `updater.1.log`, shifting older rotations up to `MAX_ROTATED_LOGS`, so a
+//! support bundle holds the last few sessions without the logs growing
+//! without bound.
+//!
+//! Other crates (tauri, reqwest, ...) log only warnings and errors, whatever
+//! the level, so debug logging isn't drowned out by them.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Log file name in the app log directory.
+const LOG_FILENAME: &str = "updater.log";
+
+/// Size at which the log file is rotated.
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Rotated log files kept, `updater.1.log` being the newest.
+const MAX_ROTATED_LOGS: usize = 4;
+
+/// Level logged until `set_log_level` changes it.
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Target prefix of this crate's own log records.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// The log file, rotated by size.
+struct RotatingFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    /// Open the log file in `dir` for appending, creating `dir` if needed.
+    fn open(dir: &Path, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILENAME))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+        })
+    }
+
+    /// Append `line`, rotating first if it would take the file past its
+    /// size limit.
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift each rotated file up one, dropping the oldest, and start a new
+    /// log file.
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..MAX_ROTATED_LOGS).rev() {
+            let from = self.dir.join(rotated_log_filename(index));
+            if from.exists() {
+                fs::rename(&from, self.dir.join(rotated_log_filename(index + 1)))?;
+            }
+        }
+        fs::rename(
+            self.dir.join(LOG_FILENAME),
+            self.dir.join(rotated_log_filename(1)),
+        )?;
+
+        *self = Self::open(&self.dir, self.max_bytes)?;
+        Ok(())
+    }
+}
+
+/// Name of the `index`th rotated log file, 1 being the newest.
+fn rotated_log_filename(index: usize) -> String {
+    format!("updater.{}.log", index)
+}
+
+/// The log files in `log_dir`, newest first.
+pub fn log_files(log_dir: &Path) -> Vec<PathBuf> {
+    std::iter::once(LOG_FILENAME.to_string())
+        .chain((1..=MAX_ROTATED_LOGS).map(rotated_log_filename))
+        .map(|name| log_dir.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Format `record` as one log file line, e.g.
+/// `2025-01-01T12:00:00.000Z INFO  bluebuzzah_updater::cache: message`.
+fn format_record(record: &Record) -> String {
+    format!(
+        "{} {:<5} {}: {}",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        record.level(),
+        record.target(),
+        record.args()
+    )
+}
+
+/// `log` backend writing to the rotating log file and stderr.
+struct FileLogger {
+    file: Mutex<RotatingFile>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let max_level = if metadata.target().starts_with(CRATE_TARGET) {
+            log::max_level()
+        } else {
+            log::max_level().min(LevelFilter::Warn)
+        };
+        metadata.level() <= max_level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format_record(record);
+        eprintln!("{}", line);
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.write_line(&line) {
+            eprintln!("Failed to write log file: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = file.file.flush();
+    }
+}
+
+/// Start logging to the log file in `log_dir`, at the default level.
+pub fn init(log_dir: &Path) -> Result<(), String> {
+    let file = RotatingFile::open(log_dir, MAX_LOG_BYTES)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    let logger = Box::leak(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }));
+
+    log::set_logger(logger).map_err(|e| format!("Failed to start logging: {}", e))?;
+    log::set_max_level(DEFAULT_LOG_LEVEL);
+    Ok(())
+}
+
+/// Change the level logged from now on: "off", "error", "warn", "info",
+/// "debug" or "trace".
+pub fn set_level(level: &str) -> Result<LevelFilter, String> {
+    let level = LevelFilter::from_str(level.trim()).map_err(|_| {
+        format!(
+            "Invalid log level '{}'. Expected off, error, warn, info, debug or trace.",
+            level
+        )
+    })?;
+
+    log::set_max_level(level);
+    Ok(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rotating_file_keeps_newest_logs() {
+        let dir = tempdir().unwrap();
+        let mut file = RotatingFile::open(dir.path(), 100).unwrap();
+
+        for index in 0..(MAX_ROTATED_LOGS + 3) {
+            file.write_line(&format!("{:>60}", index)).unwrap();
+            file.write_line(&format!("{:>60}", index)).unwrap();
+        }
+
+        // Each line fills most of a file, so every line starts a new one
+        let current = fs::read_to_string(dir.path().join(LOG_FILENAME)).unwrap();
+        assert_eq!(current.trim(), (MAX_ROTATED_LOGS + 2).to_string());
+        let newest = fs::read_to_string(dir.path().join(rotated_log_filename(1))).unwrap();
+        assert_eq!(newest.trim(), (MAX_ROTATED_LOGS + 2).to_string());
+        assert!(dir
+            .path()
+            .join(rotated_log_filename(MAX_ROTATED_LOGS))
+            .exists());
+        assert!(!dir
+            .path()
+            .join(rotated_log_filename(MAX_ROTATED_LOGS + 1))
+            .exists());
+
+        let files = log_files(dir.path());
+        assert_eq!(files.len(), MAX_ROTATED_LOGS + 1);
+        assert_eq!(files[0], dir.path().join(LOG_FILENAME));
+        assert_eq!(files[1], dir.path().join(rotated_log_filename(1)));
+    }
+
+    #[test]
+    fn test_rotating_file_appends_to_existing_log() {
+        let dir = tempdir().unwrap();
+        RotatingFile::open(dir.path(), 1024)
+            .unwrap()
+            .write_line("first")
+            .unwrap();
+        RotatingFile::open(dir.path(), 1024)
+            .unwrap()
+            .write_line("second")
+            .unwrap();
+
+        let contents = fs::read_to_string(dir.path().join(LOG_FILENAME)).unwrap();
+        assert_eq!(contents, "first\nsecond\n");
+    }
+
+    #[test]
+    fn test_set_level_rejects_unknown_level() {
+        let err = set_level("loud").unwrap_err();
+        assert!(err.contains("Invalid log level"));
+    }
+}
@@ -1,64 +1,210 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod app_update;
 mod cache;
+mod cli;
 mod commands;
 mod dfu;
+mod diagnostics;
+mod download;
+mod history;
+mod instance;
+mod journal;
+mod logging;
+mod paths;
+mod port_lock;
+mod proxy;
+mod releases;
 mod settings;
+mod sideload;
 
+use commands::app_update::{check_for_app_update, install_app_update, PendingAppUpdate};
+use commands::device_log::{start_device_log_stream, stop_device_log_stream};
 use commands::dfu::{
+    apply_device_configuration,
     cancel_dfu_flash,
     detect_dfu_devices,
+    detect_uf2_volumes,
+    flash_both_devices,
     flash_dfu_firmware,
+    flash_uf2,
+    get_active_operations,
+    get_device_info,
+    identify_device,
     is_device_in_bootloader,
+    preview_device_configuration,
+    read_device_settings,
+    send_device_command,
     set_device_profile,
+    set_device_role,
     validate_firmware_package,
 };
+use commands::diagnostics::export_diagnostics;
 use commands::firmware::{
     calculate_sha256,
+    cancel_download,
+    clean_firmware_cache,
+    cleanup_cache_older_than,
     clear_all_cache,
+    collect_cache_garbage,
     delete_cached_firmware,
     download_firmware,
+    download_release_assets,
+    export_cached_firmware,
     get_cache_index,
+    get_cache_stats,
     get_cached_firmware,
+    get_latest_cached_version,
+    import_firmware_zip,
+    list_firmware_releases,
+    migrate_firmware_cache,
+    pin_cached_firmware,
+    test_proxy_connection,
+    unpin_cached_firmware,
     verify_and_clean_cache,
     verify_cached_firmware,
 };
-use commands::settings::{get_advanced_settings, get_platform, save_advanced_settings};
+use commands::history::{export_flash_history, get_flash_history};
+use commands::journal::{dismiss_interrupted_operation, get_interrupted_operations};
+use commands::logging::set_log_level;
+use commands::report::generate_device_report;
+use commands::settings::{
+    export_settings, get_advanced_settings, get_available_profiles, get_device_settings,
+    get_platform, import_settings, reset_advanced_settings, reset_dfu_timing,
+    save_advanced_settings, save_device_settings, set_update_channel,
+};
+
+use cache::CacheManager;
+use history::FlashHistory;
+use instance::InstanceLock;
+use journal::OperationJournal;
+use paths::AppDataDir;
+use port_lock::PortLocks;
+use settings::SettingsService;
+use tauri::Manager;
 
 fn main() {
+    // Scripted flashing runs without a window and exits when done
+    if let Some(code) = cli::run_from_args() {
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
+        // Registered first so a second launch exits before setting anything up
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            instance::on_second_launch(app, argv, cwd);
+        }))
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
         .setup(|app| {
+            // Backend log file, started first so setup itself is logged
+            if let Err(e) = logging::init(&app.path().app_log_dir()?) {
+                eprintln!("[Logging] Warning: {}", e);
+            }
+
             #[cfg(desktop)]
             app.handle()
                 .plugin(tauri_plugin_updater::Builder::new().build())?;
+
+            // One shared cache index for all firmware commands
+            let app_data_dir = app.path().app_data_dir()?;
+            app.manage(CacheManager::new(&app_data_dir)?);
+            // Audit trail of every flash
+            app.manage(FlashHistory::new(&app_data_dir));
+            // Saved settings, read once and kept in memory
+            app.manage(SettingsService::load(&app_data_dir));
+            // Flashes in progress, and those an earlier run didn't finish
+            app.manage(OperationJournal::open(&app_data_dir));
+            // Serial ports with a flash or configuration command in flight
+            app.manage(PortLocks::new());
+            // Held until exit so a headless --flash leaves our ports alone
+            match InstanceLock::acquire() {
+                Ok(Some(lock)) => {
+                    app.manage(lock);
+                }
+                Ok(None) => log::warn!("A command line flash is running alongside the app"),
+                Err(e) => log::warn!("{}", e),
+            }
+            // Resolved once here; commands take it as state instead
+            app.manage(AppDataDir::new(app_data_dir));
+            // App update found by the last check, until it is installed
+            app.manage(PendingAppUpdate::default());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             // DFU commands
             detect_dfu_devices,
             flash_dfu_firmware,
+            flash_both_devices,
             cancel_dfu_flash,
+            get_active_operations,
             is_device_in_bootloader,
             validate_firmware_package,
             set_device_profile,
+            apply_device_configuration,
+            set_device_role,
+            identify_device,
+            send_device_command,
+            get_device_info,
+            read_device_settings,
+            preview_device_configuration,
+            detect_uf2_volumes,
+            flash_uf2,
+            // Device log commands
+            start_device_log_stream,
+            stop_device_log_stream,
+            // Report commands
+            generate_device_report,
+            export_diagnostics,
+            // Flash history commands
+            get_flash_history,
+            export_flash_history,
+            // Operation journal commands
+            get_interrupted_operations,
+            dismiss_interrupted_operation,
+            // Logging commands
+            set_log_level,
+            // App update commands
+            check_for_app_update,
+            install_app_update,
             // Firmware cache commands
+            list_firmware_releases,
+            test_proxy_connection,
             download_firmware,
+            cancel_download,
+            download_release_assets,
             get_cached_firmware,
+            get_latest_cached_version,
             calculate_sha256,
             get_cache_index,
+            get_cache_stats,
+            import_firmware_zip,
+            export_cached_firmware,
             delete_cached_firmware,
             clear_all_cache,
+            cleanup_cache_older_than,
+            pin_cached_firmware,
+            unpin_cached_firmware,
             verify_cached_firmware,
+            migrate_firmware_cache,
+            clean_firmware_cache,
+            collect_cache_garbage,
             verify_and_clean_cache,
             // Settings commands
             get_advanced_settings,
             save_advanced_settings,
+            get_device_settings,
+            save_device_settings,
+            reset_advanced_settings,
+            reset_dfu_timing,
+            set_update_channel,
+            export_settings,
+            import_settings,
+            get_available_profiles,
             get_platform
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,21 @@
+//! The app data directory, resolved once at startup.
+//!
+//! Registered as managed state so commands take it as a
+//! `tauri::State<'_, AppDataDir>` parameter instead of each resolving (and
+//! handling a failure to resolve) the path themselves.
+
+use std::path::{Path, PathBuf};
+
+/// Directory holding the firmware cache, settings and flash history.
+#[derive(Debug, Clone)]
+pub struct AppDataDir(PathBuf);
+
+impl AppDataDir {
+    pub fn new(path: PathBuf) -> Self {
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
@@ -0,0 +1,368 @@
+//! Per-port locks for device operations.
+//!
+//! Flashing and profile/role changes own a device's serial port for their
+//! whole run. A second command aimed at the same port (a double-click, or a
+//! second window) would fight over it and both would fail in confusing ways,
+//! so every such command takes a lease on its port first and a busy port is
+//! refused up front.
+//!
+//! The one exception is a preemptible operation such as the device log
+//! stream: a flash on its port asks it to stop and takes the port over.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Operation kind that may take a port from a preemptible operation.
+const PREEMPTING_KIND: &str = "flash";
+
+/// How long a flash waits for a preempted operation to release its port.
+const PREEMPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Interval between checks while waiting for a preempted operation.
+const PREEMPT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// An operation currently holding a port.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PortOperation {
+    /// Unique ID for this operation, increasing per app session.
+    pub id: u64,
+    /// What the operation is doing ("flash", "profile", "role").
+    pub kind: String,
+    /// Port as requested by the command.
+    pub port: String,
+    /// When the operation took the port (RFC 3339).
+    pub started_at: String,
+}
+
+/// Returned when a port already has an operation in flight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PortBusyError {
+    /// The operation holding the port.
+    pub existing: PortOperation,
+}
+
+impl fmt::Display for PortBusyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "An operation is already in progress on this device ({} #{} on {}, started {})",
+            self.existing.kind, self.existing.id, self.existing.port, self.existing.started_at
+        )
+    }
+}
+
+impl std::error::Error for PortBusyError {}
+
+/// Normalize a port name so different spellings of one device share a lock.
+///
+/// Windows COM names are case-insensitive and may carry the `\\.\` device
+/// prefix; on macOS `/dev/tty.*` and `/dev/cu.*` are the same device.
+fn normalize_port(port: &str) -> String {
+    let port = port.trim();
+    let port = port.strip_prefix(r"\\.\").unwrap_or(port);
+
+    if port.len() > 3 && port[..3].eq_ignore_ascii_case("COM") {
+        return port.to_ascii_uppercase();
+    }
+    match port.strip_prefix("/dev/tty.") {
+        Some(name) => format!("/dev/cu.{}", name),
+        None => port.to_string(),
+    }
+}
+
+/// An operation holding a port, with the flag that asks it to yield if it
+/// is preemptible and the flag that asks it to stop.
+struct Holder {
+    operation: PortOperation,
+    preempt: Option<Arc<AtomicBool>>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Ports with an operation in flight, shared as Tauri managed state.
+#[derive(Clone, Default)]
+pub struct PortLocks {
+    operations: Arc<Mutex<HashMap<String, Holder>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl PortLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn operations(&self) -> MutexGuard<'_, HashMap<String, Holder>> {
+        self.operations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Every operation currently holding a port, oldest first.
+    pub fn active(&self) -> Vec<PortOperation> {
+        let mut operations: Vec<PortOperation> = self
+            .operations()
+            .values()
+            .map(|holder| holder.operation.clone())
+            .collect();
+        operations.sort_by_key(|op| op.id);
+        operations
+    }
+
+    /// Operations of the given kind currently holding a port, oldest first.
+    pub fn in_flight(&self, kind: &str) -> Vec<PortOperation> {
+        self.active()
+            .into_iter()
+            .filter(|op| op.kind == kind)
+            .collect()
+    }
+
+    /// The operation holding `port`, if any.
+    pub fn holder(&self, port: &str) -> Option<PortOperation> {
+        self.operations()
+            .get(&normalize_port(port))
+            .map(|holder| holder.operation.clone())
+    }
+
+    /// Ask the operation with lease ID `id` to stop. Returns whether such
+    /// an operation holds a port.
+    pub fn cancel(&self, id: u64) -> bool {
+        match self
+            .operations()
+            .values()
+            .find(|holder| holder.operation.id == id)
+        {
+            Some(holder) => {
+                holder.cancel.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take `port` for an operation of the given kind.
+    ///
+    /// Fails immediately if another operation holds the port, unless this is
+    /// a flash and the holder is preemptible: then the holder is asked to
+    /// stop and the flash waits briefly for it to release the port, so async
+    /// code takes flash leases on a blocking thread. The returned lease
+    /// releases the port when dropped.
+    pub fn acquire(&self, port: &str, kind: &str) -> Result<PortLease, PortBusyError> {
+        let key = normalize_port(port);
+        let deadline = Instant::now() + PREEMPT_TIMEOUT;
+
+        loop {
+            let mut operations = self.operations();
+            let Some(existing) = operations.get(&key) else {
+                return Ok(self.insert(&mut operations, key, port, kind, None));
+            };
+
+            match &existing.preempt {
+                Some(preempt) if kind == PREEMPTING_KIND && Instant::now() < deadline => {
+                    preempt.store(true, Ordering::SeqCst);
+                }
+                _ => {
+                    return Err(PortBusyError {
+                        existing: existing.operation.clone(),
+                    })
+                }
+            }
+
+            drop(operations);
+            thread::sleep(PREEMPT_POLL_INTERVAL);
+        }
+    }
+
+    /// Take `port` for an operation a flash may preempt.
+    ///
+    /// Fails immediately if the port is busy. The returned flag is set when
+    /// a flash wants the port; the operation should then drop its lease.
+    pub fn acquire_preemptible(
+        &self,
+        port: &str,
+        kind: &str,
+    ) -> Result<(PortLease, Arc<AtomicBool>), PortBusyError> {
+        let key = normalize_port(port);
+        let mut operations = self.operations();
+
+        if let Some(existing) = operations.get(&key) {
+            return Err(PortBusyError {
+                existing: existing.operation.clone(),
+            });
+        }
+
+        let preempt = Arc::new(AtomicBool::new(false));
+        let lease = self.insert(&mut operations, key, port, kind, Some(preempt.clone()));
+        Ok((lease, preempt))
+    }
+
+    fn insert(
+        &self,
+        operations: &mut HashMap<String, Holder>,
+        key: String,
+        port: &str,
+        kind: &str,
+        preempt: Option<Arc<AtomicBool>>,
+    ) -> PortLease {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        operations.insert(
+            key.clone(),
+            Holder {
+                operation: PortOperation {
+                    id,
+                    kind: kind.to_string(),
+                    port: port.to_string(),
+                    started_at: chrono::Utc::now().to_rfc3339(),
+                },
+                preempt,
+                cancel: cancel.clone(),
+            },
+        );
+
+        PortLease {
+            locks: self.clone(),
+            key,
+            id,
+            cancel,
+        }
+    }
+}
+
+/// A held port. Dropping it releases the port, so the entry is removed on
+/// completion, error, cancellation and panics alike.
+pub struct PortLease {
+    locks: PortLocks,
+    key: String,
+    id: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl PortLease {
+    /// ID of the operation holding the port.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Flag set by `PortLocks::cancel` when the operation should stop.
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for PortLease {
+    fn drop(&mut self) {
+        let mut operations = self.locks.operations();
+        // Only remove our own entry
+        if operations
+            .get(&self.key)
+            .is_some_and(|holder| holder.operation.id == self.id)
+        {
+            operations.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_port() {
+        assert_eq!(normalize_port("COM3"), "COM3");
+        assert_eq!(normalize_port("com3"), "COM3");
+        assert_eq!(normalize_port(r"\\.\COM12"), "COM12");
+        assert_eq!(normalize_port("/dev/tty.usbmodem1"), "/dev/cu.usbmodem1");
+        assert_eq!(normalize_port("/dev/cu.usbmodem1"), "/dev/cu.usbmodem1");
+        assert_eq!(normalize_port("/dev/ttyACM0"), "/dev/ttyACM0");
+    }
+
+    #[test]
+    fn test_second_operation_on_busy_port_fails() {
+        let locks = PortLocks::new();
+        let lease = locks.acquire("/dev/cu.usbmodem1", "flash").unwrap();
+
+        let err = locks
+            .acquire("/dev/tty.usbmodem1", "profile")
+            .err()
+            .unwrap();
+        assert_eq!(err.existing.id, lease.id);
+        assert_eq!(err.existing.kind, "flash");
+        assert!(err.to_string().contains("flash #1"));
+
+        // Other ports are unaffected
+        let role = locks.acquire("/dev/cu.usbmodem2", "role").unwrap();
+
+        let flashes = locks.in_flight("flash");
+        assert_eq!(flashes.len(), 1);
+        assert_eq!(flashes[0].id, lease.id);
+
+        let kinds: Vec<String> = locks.active().into_iter().map(|op| op.kind).collect();
+        assert_eq!(kinds, ["flash", "role"]);
+        drop(role);
+    }
+
+    #[test]
+    fn test_flash_preempts_preemptible_operation() {
+        let locks = PortLocks::new();
+        let (lease, preempt) = locks.acquire_preemptible("COM3", "monitor").unwrap();
+
+        // Other kinds are refused as usual
+        assert!(locks.acquire("COM3", "profile").is_err());
+        assert!(!preempt.load(Ordering::SeqCst));
+
+        let monitor = std::thread::spawn(move || {
+            while !preempt.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            drop(lease);
+        });
+
+        let flash = locks.acquire("com3", "flash").unwrap();
+        monitor.join().unwrap();
+        assert_eq!(locks.holder("COM3").map(|op| op.kind), Some("flash".into()));
+        assert_eq!(locks.in_flight("flash")[0].id, flash.id);
+        assert!(locks.in_flight("monitor").is_empty());
+    }
+
+    #[test]
+    fn test_cancel_reaches_only_its_operation() {
+        let locks = PortLocks::new();
+        let primary = locks.acquire("COM3", "flash").unwrap();
+        let secondary = locks.acquire("COM4", "flash").unwrap();
+
+        assert!(locks.cancel(secondary.id()));
+        assert!(secondary.cancel_flag().load(Ordering::SeqCst));
+        assert!(!primary.cancel_flag().load(Ordering::SeqCst));
+
+        drop(secondary);
+        assert!(!locks.cancel(2));
+    }
+
+    #[test]
+    fn test_lease_released_on_drop() {
+        let locks = PortLocks::new();
+        drop(locks.acquire("COM3", "flash").unwrap());
+
+        let lease = locks.acquire("com3", "role").unwrap();
+        assert_eq!(lease.id, 2);
+    }
+
+    #[test]
+    fn test_lease_released_on_panic() {
+        let locks = PortLocks::new();
+        let held = locks.clone();
+
+        let result = std::thread::spawn(move || {
+            let _lease = held.acquire("COM3", "flash").unwrap();
+            panic!("flash task panicked");
+        })
+        .join();
+
+        assert!(result.is_err());
+        assert!(locks.acquire("COM3", "flash").is_ok());
+    }
+}
@@ -0,0 +1,722 @@
+//! Firmware release listing from the GitHub releases API.
+//!
+//! Fetching releases in the backend avoids CORS workarounds in the webview
+//! and lets authenticated requests (a GitHub token from settings) lift the
+//! anonymous rate limit.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri_plugin_http::reqwest;
+
+use crate::cache::{CachedFirmwareMetadata, FirmwareCacheIndex};
+
+/// GitHub releases endpoint for the firmware repository.
+pub const RELEASES_API_URL: &str =
+    "https://api.github.com/repos/BlueBuzzah/BlueBuzzah-Firmware/releases";
+
+/// Releases requested per page (GitHub's maximum).
+const RELEASES_PER_PAGE: u32 = 100;
+
+/// Upper bound on pages followed, in case a Link header loops.
+const MAX_RELEASE_PAGES: usize = 10;
+
+/// Timeout for each releases API request.
+pub const RELEASES_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Release notes shown when a release has no body.
+const NO_RELEASE_NOTES: &str = "No release notes available";
+
+/// File in the app data directory holding the last releases response.
+const RELEASES_CACHE_FILENAME: &str = "releases_cache.json";
+
+/// Which releases a user is offered.
+///
+/// Stable users never see prereleases; beta testers see everything.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl ReleaseChannel {
+    /// Channel a release belongs to, from its GitHub prerelease flag.
+    pub fn of(prerelease: bool) -> Self {
+        if prerelease {
+            Self::Beta
+        } else {
+            Self::Stable
+        }
+    }
+
+    /// Whether a release on `release_channel` is shown to users of this channel.
+    pub fn includes(self, release_channel: ReleaseChannel) -> bool {
+        match self {
+            Self::Stable => release_channel == Self::Stable,
+            Self::Beta => true,
+        }
+    }
+}
+
+/// Release as returned by the GitHub API (only the fields we use).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubRelease {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<GitHubAsset>,
+}
+
+/// Release asset as returned by the GitHub API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: u64,
+}
+
+/// Downloadable asset of a firmware release.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub download_url: String,
+    pub size: u64,
+}
+
+/// Firmware release as presented to the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareReleaseInfo {
+    /// Release name, falling back to the tag when the name is empty.
+    pub version: String,
+    pub tag_name: String,
+    pub release_notes: String,
+    /// RFC 3339 publish time; empty for unpublished drafts.
+    pub published_at: String,
+    pub prerelease: bool,
+    /// Firmware zips and checksum files attached to the release.
+    pub assets: Vec<ReleaseAsset>,
+    pub is_cached: bool,
+    pub cached_metadata: Option<CachedFirmwareMetadata>,
+}
+
+/// Result of `list_firmware_releases`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseListing {
+    pub releases: Vec<FirmwareReleaseInfo>,
+    /// True when GitHub couldn't be reached and a cached copy was used.
+    pub stale: bool,
+    /// When the release list was last confirmed with GitHub (RFC 3339).
+    pub fetched_at: String,
+}
+
+/// Last successful releases response, kept for conditional requests and offline use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleasesCache {
+    pub etag: Option<String>,
+    pub fetched_at: String,
+    pub releases: Vec<GitHubRelease>,
+}
+
+/// What GitHub said about the release list.
+#[derive(Debug)]
+pub enum FetchOutcome {
+    /// 304: the cached copy is current.
+    NotModified,
+    Modified {
+        releases: Vec<GitHubRelease>,
+        etag: Option<String>,
+    },
+}
+
+/// Release list to serve, after reconciling a fetch with the cached copy.
+#[derive(Debug)]
+pub struct ResolvedReleases {
+    pub cache: ReleasesCache,
+    pub stale: bool,
+    /// Whether `cache` changed and should be written back.
+    pub updated: bool,
+}
+
+/// Whether an asset is worth listing: firmware zips and published checksums.
+fn is_relevant_asset(name: &str) -> bool {
+    name.to_ascii_lowercase().ends_with(".zip") || name == "SHA256SUMS"
+}
+
+impl From<GitHubRelease> for FirmwareReleaseInfo {
+    fn from(release: GitHubRelease) -> Self {
+        let version = release
+            .name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| release.tag_name.clone());
+
+        Self {
+            version,
+            tag_name: release.tag_name,
+            release_notes: release
+                .body
+                .filter(|body| !body.is_empty())
+                .unwrap_or_else(|| NO_RELEASE_NOTES.to_string()),
+            published_at: release.published_at.unwrap_or_default(),
+            prerelease: release.prerelease,
+            assets: release
+                .assets
+                .into_iter()
+                .filter(|asset| is_relevant_asset(&asset.name))
+                .map(|asset| ReleaseAsset {
+                    name: asset.name,
+                    download_url: asset.browser_download_url,
+                    size: asset.size,
+                })
+                .collect(),
+            is_cached: false,
+            cached_metadata: None,
+        }
+    }
+}
+
+/// Mark releases whose version is present in the cache index.
+pub fn annotate_cached(releases: &mut [FirmwareReleaseInfo], index: &FirmwareCacheIndex) {
+    for release in releases {
+        release.cached_metadata = index.get(&release.version).cloned();
+        release.is_cached = release.cached_metadata.is_some();
+    }
+}
+
+/// Releases for versions in the cache that GitHub didn't list.
+///
+/// Covers sideloaded firmware and releases since removed from GitHub.
+/// Cached beta builds are hidden from stable users but stay on disk, so
+/// switching back to beta shows them again.
+pub fn cached_only_releases(
+    index: &FirmwareCacheIndex,
+    listed: &[FirmwareReleaseInfo],
+    channel: ReleaseChannel,
+) -> Vec<FirmwareReleaseInfo> {
+    index
+        .values()
+        .filter(|metadata| !listed.iter().any(|release| release.version == metadata.version))
+        .filter(|metadata| channel.includes(metadata.channel))
+        .map(|metadata| FirmwareReleaseInfo {
+            version: metadata.version.clone(),
+            tag_name: metadata.tag_name.clone(),
+            release_notes: metadata.release_notes.clone(),
+            published_at: if metadata.published_at.is_empty() {
+                metadata.downloaded_at.clone()
+            } else {
+                metadata.published_at.clone()
+            },
+            prerelease: metadata.channel == ReleaseChannel::Beta,
+            // No download URL; the cached zip is used directly
+            assets: vec![ReleaseAsset {
+                name: format!("{}.zip", metadata.version),
+                download_url: String::new(),
+                size: metadata.file_size,
+            }],
+            is_cached: true,
+            cached_metadata: Some(metadata.clone()),
+        })
+        .collect()
+}
+
+/// Extract the `rel="next"` URL from a GitHub `Link` header.
+pub fn next_page_url(link_header: &str) -> Option<String> {
+    link_header.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#);
+        if !is_next {
+            return None;
+        }
+        let url = url.trim().strip_prefix('<')?.strip_suffix('>')?;
+        Some(url.to_string())
+    })
+}
+
+/// User-facing message for a rate-limited response.
+///
+/// `reset_at` is the `X-RateLimit-Reset` epoch seconds, if GitHub sent one.
+pub fn rate_limit_message(reset_at: Option<i64>, now: i64) -> String {
+    match reset_at {
+        Some(reset_at) => {
+            let wait_minutes = ((reset_at - now) as f64 / 60.0).ceil().max(1.0) as i64;
+            format!(
+                "GitHub API rate limit exceeded. Try again in {} minute{}.",
+                wait_minutes,
+                if wait_minutes == 1 { "" } else { "s" }
+            )
+        }
+        None => "GitHub API rate limit exceeded. Try again later.".to_string(),
+    }
+}
+
+fn releases_cache_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(RELEASES_CACHE_FILENAME)
+}
+
+/// Load the cached releases response, if there is a readable one.
+pub fn load_releases_cache(app_data_dir: &Path) -> Option<ReleasesCache> {
+    let contents = fs::read_to_string(releases_cache_path(app_data_dir)).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            log::warn!("Ignoring unreadable releases cache: {}", e);
+            None
+        }
+    }
+}
+
+/// Save the releases response using atomic write (write-to-tmp then rename).
+pub fn save_releases_cache(app_data_dir: &Path, cache: &ReleasesCache) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(cache)
+        .map_err(|e| format!("Failed to serialize releases cache: {}", e))?;
+
+    fs::create_dir_all(app_data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+
+    let cache_path = releases_cache_path(app_data_dir);
+    let tmp_path = cache_path.with_extension("json.tmp");
+
+    fs::write(&tmp_path, &contents).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Failed to write releases cache: {}", e)
+    })?;
+
+    fs::rename(&tmp_path, &cache_path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Failed to finalize releases cache: {}", e)
+    })
+}
+
+/// Reconcile a fetch result with the cached copy.
+///
+/// A 304 refreshes the cached copy's timestamp. Any fetch error (offline,
+/// rate limited, server error) falls back to the cached copy marked stale,
+/// and only fails when there is nothing cached.
+pub fn resolve_fetch(
+    result: Result<FetchOutcome, String>,
+    cached: Option<ReleasesCache>,
+    now: &str,
+) -> Result<ResolvedReleases, String> {
+    match (result, cached) {
+        (Ok(FetchOutcome::Modified { releases, etag }), _) => Ok(ResolvedReleases {
+            cache: ReleasesCache {
+                etag,
+                fetched_at: now.to_string(),
+                releases,
+            },
+            stale: false,
+            updated: true,
+        }),
+        (Ok(FetchOutcome::NotModified), Some(cached)) => Ok(ResolvedReleases {
+            cache: ReleasesCache {
+                fetched_at: now.to_string(),
+                ..cached
+            },
+            stale: false,
+            updated: true,
+        }),
+        (Ok(FetchOutcome::NotModified), None) => {
+            Err("GitHub reported no changes but no cached releases exist".to_string())
+        }
+        (Err(e), Some(cached)) => {
+            log::warn!(
+                "Using cached releases from {}: {}",
+                cached.fetched_at, e
+            );
+            Ok(ResolvedReleases {
+                cache: cached,
+                stale: true,
+                updated: false,
+            })
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
+/// Fetch every page of firmware releases from GitHub.
+///
+/// Sends `github_token` as a bearer token when set, to use the
+/// authenticated rate limit instead of the per-IP anonymous one. With an
+/// `etag` from a previous response, the first page is requested
+/// conditionally and an unchanged list returns [`FetchOutcome::NotModified`]
+/// (which GitHub doesn't count against the rate limit).
+pub async fn fetch_releases(
+    client: &reqwest::Client,
+    github_token: Option<&str>,
+    etag: Option<&str>,
+) -> Result<FetchOutcome, String> {
+    let mut releases = Vec::new();
+    let mut url = Some(format!("{}?per_page={}", RELEASES_API_URL, RELEASES_PER_PAGE));
+    let mut first_page = true;
+    let mut response_etag = None;
+
+    for _ in 0..MAX_RELEASE_PAGES {
+        let Some(page_url) = url.take() else {
+            break;
+        };
+
+        let mut request = client
+            .get(&page_url)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+        if let Some(token) = github_token.filter(|token| !token.trim().is_empty()) {
+            request = request.bearer_auth(token.trim());
+        }
+        if let Some(etag) = etag.filter(|_| first_page) {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                "Request timed out while fetching firmware releases. Check your internet connection and try again.".to_string()
+            } else {
+                format!("Failed to reach GitHub: {}", e)
+            }
+        })?;
+
+        let status = response.status();
+        if first_page && status == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(FetchOutcome::NotModified);
+        }
+
+        let rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || (status == reqwest::StatusCode::FORBIDDEN
+                && response
+                    .headers()
+                    .get("x-ratelimit-remaining")
+                    .and_then(|value| value.to_str().ok())
+                    .map(|remaining| remaining == "0")
+                    .unwrap_or(true));
+        if rate_limited {
+            let reset_at = response
+                .headers()
+                .get("x-ratelimit-reset")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok());
+            return Err(rate_limit_message(reset_at, chrono::Utc::now().timestamp()));
+        }
+
+        if !status.is_success() {
+            return Err(format!("GitHub API error: {}", status));
+        }
+
+        if first_page {
+            response_etag = response
+                .headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            first_page = false;
+        }
+
+        url = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|value| value.to_str().ok())
+            .and_then(next_page_url);
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read GitHub releases: {}", e))?;
+        let page: Vec<GitHubRelease> = serde_json::from_slice(&body)
+            .map_err(|e| format!("Failed to parse GitHub releases: {}", e))?;
+        releases.extend(page);
+    }
+
+    Ok(FetchOutcome::Modified {
+        releases,
+        etag: response_etag,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn github_release(json: &str) -> GitHubRelease {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn cached_metadata(version: &str) -> CachedFirmwareMetadata {
+        CachedFirmwareMetadata {
+            version: version.to_string(),
+            tag_name: format!("v{}", version),
+            sha256_hash: "abc123".to_string(),
+            zip_path: format!("/cache/{}.zip", version),
+            downloaded_at: "2024-01-01T00:00:00Z".to_string(),
+            file_size: 1024,
+            published_at: "2024-01-01T00:00:00Z".to_string(),
+            release_notes: "Cached".to_string(),
+            checksum_verified: false,
+            locally_imported: false,
+            pinned: false,
+            last_used_at: None,
+            channel: ReleaseChannel::Stable,
+            asset_kind: crate::cache::AssetKind::DfuPackage,
+        }
+    }
+
+    #[test]
+    fn test_parse_github_release() {
+        let release = FirmwareReleaseInfo::from(github_release(
+            r#"{
+                "tag_name": "v2.0.0",
+                "name": "2.0.0",
+                "body": "New features",
+                "published_at": "2024-06-01T00:00:00Z",
+                "prerelease": true,
+                "draft": false,
+                "assets": [
+                    {"name": "firmware.zip", "browser_download_url": "https://example.com/firmware.zip", "size": 5000, "id": 1},
+                    {"name": "SHA256SUMS", "browser_download_url": "https://example.com/SHA256SUMS", "size": 90},
+                    {"name": "notes.pdf", "browser_download_url": "https://example.com/notes.pdf", "size": 300}
+                ]
+            }"#,
+        ));
+
+        assert_eq!(release.version, "2.0.0");
+        assert_eq!(release.tag_name, "v2.0.0");
+        assert_eq!(release.release_notes, "New features");
+        assert_eq!(release.published_at, "2024-06-01T00:00:00Z");
+        assert!(release.prerelease);
+        assert_eq!(
+            release.assets,
+            vec![
+                ReleaseAsset {
+                    name: "firmware.zip".to_string(),
+                    download_url: "https://example.com/firmware.zip".to_string(),
+                    size: 5000,
+                },
+                ReleaseAsset {
+                    name: "SHA256SUMS".to_string(),
+                    download_url: "https://example.com/SHA256SUMS".to_string(),
+                    size: 90,
+                },
+            ]
+        );
+        assert!(!release.is_cached);
+    }
+
+    #[test]
+    fn test_parse_github_release_fallbacks() {
+        let release = FirmwareReleaseInfo::from(github_release(
+            r#"{"tag_name": "v0.9.0", "name": "", "body": null, "published_at": null}"#,
+        ));
+
+        assert_eq!(release.version, "v0.9.0");
+        assert_eq!(release.release_notes, NO_RELEASE_NOTES);
+        assert_eq!(release.published_at, "");
+        assert!(!release.prerelease);
+        assert!(release.assets.is_empty());
+    }
+
+    #[test]
+    fn test_annotate_cached() {
+        let mut releases = vec![
+            FirmwareReleaseInfo::from(github_release(r#"{"tag_name": "v1.0.0", "name": "1.0.0"}"#)),
+            FirmwareReleaseInfo::from(github_release(r#"{"tag_name": "v2.0.0", "name": "2.0.0"}"#)),
+        ];
+        let mut index = FirmwareCacheIndex::new();
+        index.insert("1.0.0".to_string(), cached_metadata("1.0.0"));
+
+        annotate_cached(&mut releases, &index);
+
+        assert!(releases[0].is_cached);
+        assert_eq!(
+            releases[0].cached_metadata.as_ref().unwrap().zip_path,
+            "/cache/1.0.0.zip"
+        );
+        assert!(!releases[1].is_cached);
+        assert!(releases[1].cached_metadata.is_none());
+    }
+
+    #[test]
+    fn test_channel_includes() {
+        assert!(ReleaseChannel::Stable.includes(ReleaseChannel::Stable));
+        assert!(!ReleaseChannel::Stable.includes(ReleaseChannel::Beta));
+        assert!(ReleaseChannel::Beta.includes(ReleaseChannel::Stable));
+        assert!(ReleaseChannel::Beta.includes(ReleaseChannel::Beta));
+        assert_eq!(ReleaseChannel::of(true), ReleaseChannel::Beta);
+        assert_eq!(ReleaseChannel::of(false), ReleaseChannel::Stable);
+    }
+
+    #[test]
+    fn test_channel_serialization() {
+        assert_eq!(
+            serde_json::to_string(&ReleaseChannel::Beta).unwrap(),
+            r#""beta""#
+        );
+        assert_eq!(
+            serde_json::from_str::<ReleaseChannel>(r#""stable""#).unwrap(),
+            ReleaseChannel::Stable
+        );
+    }
+
+    #[test]
+    fn test_cached_only_releases() {
+        let listed = vec![FirmwareReleaseInfo::from(github_release(
+            r#"{"tag_name": "v2.0.0", "name": "2.0.0"}"#,
+        ))];
+        let mut index = FirmwareCacheIndex::new();
+        index.insert("2.0.0".to_string(), cached_metadata("2.0.0"));
+        index.insert("1.0.0".to_string(), cached_metadata("1.0.0"));
+        let mut beta = cached_metadata("3.0.0-beta.1");
+        beta.channel = ReleaseChannel::Beta;
+        beta.published_at = String::new();
+        index.insert(beta.version.clone(), beta);
+
+        // Stable hides the cached beta but keeps it in the index
+        let stable = cached_only_releases(&index, &listed, ReleaseChannel::Stable);
+        assert_eq!(stable.len(), 1);
+        assert_eq!(stable[0].version, "1.0.0");
+        assert!(stable[0].is_cached);
+        assert!(!stable[0].prerelease);
+        assert_eq!(stable[0].assets[0].name, "1.0.0.zip");
+        assert_eq!(stable[0].assets[0].size, 1024);
+
+        let mut beta = cached_only_releases(&index, &listed, ReleaseChannel::Beta);
+        beta.sort_by(|a, b| a.version.cmp(&b.version));
+        assert_eq!(beta.len(), 2);
+        assert_eq!(beta[1].version, "3.0.0-beta.1");
+        assert!(beta[1].prerelease);
+        // Falls back to the download time when the publish time is unknown
+        assert_eq!(beta[1].published_at, "2024-01-01T00:00:00Z");
+    }
+
+    fn releases_cache(fetched_at: &str) -> ReleasesCache {
+        ReleasesCache {
+            etag: Some(r#"W/"abc""#.to_string()),
+            fetched_at: fetched_at.to_string(),
+            releases: vec![github_release(r#"{"tag_name": "v1.0.0", "name": "1.0.0"}"#)],
+        }
+    }
+
+    #[test]
+    fn test_releases_cache_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert!(load_releases_cache(temp_dir.path()).is_none());
+
+        save_releases_cache(temp_dir.path(), &releases_cache("2024-01-01T00:00:00Z")).unwrap();
+
+        let loaded = load_releases_cache(temp_dir.path()).unwrap();
+        assert_eq!(loaded.etag.as_deref(), Some(r#"W/"abc""#));
+        assert_eq!(loaded.fetched_at, "2024-01-01T00:00:00Z");
+        assert_eq!(loaded.releases[0].tag_name, "v1.0.0");
+        assert!(!temp_dir.path().join("releases_cache.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_releases_cache_corrupted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(RELEASES_CACHE_FILENAME), "{ not json").unwrap();
+
+        assert!(load_releases_cache(temp_dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_resolve_fetch_modified() {
+        let resolved = resolve_fetch(
+            Ok(FetchOutcome::Modified {
+                releases: vec![],
+                etag: Some("\"new\"".to_string()),
+            }),
+            Some(releases_cache("2024-01-01T00:00:00Z")),
+            "2024-02-01T00:00:00Z",
+        )
+        .unwrap();
+
+        assert!(!resolved.stale);
+        assert!(resolved.updated);
+        assert_eq!(resolved.cache.etag.as_deref(), Some("\"new\""));
+        assert_eq!(resolved.cache.fetched_at, "2024-02-01T00:00:00Z");
+        assert!(resolved.cache.releases.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_fetch_not_modified() {
+        let resolved = resolve_fetch(
+            Ok(FetchOutcome::NotModified),
+            Some(releases_cache("2024-01-01T00:00:00Z")),
+            "2024-02-01T00:00:00Z",
+        )
+        .unwrap();
+
+        assert!(!resolved.stale);
+        assert!(resolved.updated);
+        assert_eq!(resolved.cache.etag.as_deref(), Some(r#"W/"abc""#));
+        assert_eq!(resolved.cache.fetched_at, "2024-02-01T00:00:00Z");
+        assert_eq!(resolved.cache.releases.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_fetch_error_uses_stale_cache() {
+        let resolved = resolve_fetch(
+            Err("GitHub API rate limit exceeded. Try again later.".to_string()),
+            Some(releases_cache("2024-01-01T00:00:00Z")),
+            "2024-02-01T00:00:00Z",
+        )
+        .unwrap();
+
+        assert!(resolved.stale);
+        assert!(!resolved.updated);
+        // Keeps the time the list was last confirmed
+        assert_eq!(resolved.cache.fetched_at, "2024-01-01T00:00:00Z");
+        assert_eq!(resolved.cache.releases.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_fetch_error_without_cache() {
+        let result = resolve_fetch(
+            Err("Failed to reach GitHub: offline".to_string()),
+            None,
+            "2024-02-01T00:00:00Z",
+        );
+
+        assert_eq!(result.unwrap_err(), "Failed to reach GitHub: offline");
+    }
+
+    #[test]
+    fn test_next_page_url() {
+        let link = r#"<https://api.github.com/repositories/1/releases?per_page=100&page=2>; rel="next", <https://api.github.com/repositories/1/releases?per_page=100&page=5>; rel="last""#;
+        assert_eq!(
+            next_page_url(link),
+            Some("https://api.github.com/repositories/1/releases?per_page=100&page=2".to_string())
+        );
+
+        let last_page = r#"<https://api.github.com/repositories/1/releases?page=1>; rel="prev", <https://api.github.com/repositories/1/releases?page=1>; rel="first""#;
+        assert_eq!(next_page_url(last_page), None);
+        assert_eq!(next_page_url(""), None);
+    }
+
+    #[test]
+    fn test_rate_limit_message() {
+        assert_eq!(
+            rate_limit_message(Some(1_000 + 300), 1_000),
+            "GitHub API rate limit exceeded. Try again in 5 minutes."
+        );
+        assert_eq!(
+            rate_limit_message(Some(1_000 + 30), 1_000),
+            "GitHub API rate limit exceeded. Try again in 1 minute."
+        );
+        // Reset already passed
+        assert_eq!(
+            rate_limit_message(Some(900), 1_000),
+            "GitHub API rate limit exceeded. Try again in 1 minute."
+        );
+        assert_eq!(
+            rate_limit_message(None, 1_000),
+            "GitHub API rate limit exceeded. Try again later."
+        );
+    }
+}
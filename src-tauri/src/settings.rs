@@ -11,10 +11,91 @@
 //! 1. Add the field to `AdvancedSettings` struct with `#[serde(default)]`
 //! 2. Add command generation logic to `to_pre_profile_commands()`
 //! 3. Update the TypeScript `AdvancedSettings` interface to match
+//!
+//! Renaming a field or changing what it means needs a migration instead:
+//! add a function to `MIGRATIONS`, which bumps the schema version.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::cache::version_at_least;
+use crate::dfu::{DfuTimingConfig, BUILT_IN_PROFILES};
+use crate::proxy::ProxySettings;
+use crate::releases::ReleaseChannel;
+
+/// Highest vibration intensity the firmware accepts.
+pub const MAX_VIBRATION_INTENSITY: u8 = 100;
+
+/// Therapy session lengths the firmware accepts, in minutes.
+pub const SESSION_DURATION_RANGE_MINUTES: std::ops::RangeInclusive<u16> = 15..=480;
+
+/// Highest jitter override the firmware accepts, in percent.
+pub const MAX_JITTER_PERCENT: f32 = 50.0;
+
+/// Most commands one custom profile may send.
+pub const MAX_CUSTOM_PROFILE_COMMANDS: usize = 16;
+
+/// Longest custom profile command, excluding the newline.
+pub const MAX_CUSTOM_PROFILE_COMMAND_LEN: usize = 64;
+
+/// Longest custom profile name.
+pub const MAX_CUSTOM_PROFILE_NAME_LEN: usize = 32;
+
+/// Bootloader timeouts a DFU timing override may set, in milliseconds.
+pub const DFU_BOOTLOADER_TIMEOUT_RANGE_MS: std::ops::RangeInclusive<u64> = 5_000..=120_000;
+
+/// ACK timeouts a DFU timing override may set, in milliseconds.
+pub const DFU_ACK_TIMEOUT_RANGE_MS: std::ops::RangeInclusive<u64> = 1_000..=60_000;
+
+/// Most retries a DFU timing override may set. Packet retries back off
+/// exponentially, so more would wait minutes on a dead device.
+pub const MAX_DFU_RETRIES: u32 = 8;
+
+/// Flash deadlines a DFU timing override may set, in seconds.
+pub const DFU_DEADLINE_RANGE_SECONDS: std::ops::RangeInclusive<u64> = 60..=3600;
+
+/// Lowest download bandwidth cap, in bytes per second. Anything slower
+/// would keep a firmware download running for hours.
+pub const MIN_DOWNLOAD_BYTES_PER_SEC: u64 = 16 * 1024;
+
+/// Most windows a therapy schedule may have.
+pub const MAX_SCHEDULE_WINDOWS: usize = 4;
+
+/// Commands custom profiles may send, by prefix. Only setting commands:
+/// profile, role and bootloader commands stay under the app's control.
+pub const CUSTOM_PROFILE_COMMAND_PREFIXES: &[&str] = &[
+    "THERAPY_LED_OFF:",
+    "DEBUG:",
+    "LOW_POWER:",
+    "VIBRATION_INTENSITY:",
+    "SESSION_DURATION:",
+    "JITTER_PERCENT:",
+    "MIRRORED:",
+    "AMPLITUDE:",
+];
+
+/// Oldest firmware that accepts each setting command, by prefix. Older
+/// firmware answers with `[ERROR] Unknown command`; commands not listed
+/// are accepted by every version.
+pub const SETTING_MIN_FIRMWARE: &[(&str, &str)] = &[("VIBRATION_INTENSITY:", "2.1.0")];
+
+/// The minimum firmware version for setting `command`, if firmware
+/// `version` is older than it.
+///
+/// `None` when the firmware accepts the command, or when `version` can't
+/// be compared and the command should be sent anyway.
+pub fn unsupported_setting(command: &str, version: &str) -> Option<&'static str> {
+    let command = command.trim();
+    SETTING_MIN_FIRMWARE
+        .iter()
+        .find(|(prefix, _)| command.starts_with(prefix))
+        .filter(|(_, minimum)| version_at_least(version, minimum) == Some(false))
+        .map(|(_, minimum)| *minimum)
+}
 
 /// Advanced therapy settings that can generate serial commands.
 ///
@@ -41,18 +122,85 @@ pub struct AdvancedSettings {
     #[serde(default)]
     pub selected_profile: Option<String>,
 
+    /// Personal access token for GitHub API requests.
+    /// Optional; raises the release listing rate limit above the anonymous
+    /// 60 requests/hour. Not sent to devices.
+    #[serde(default)]
+    pub github_token: Option<String>,
+
+    /// Release channel offered in the firmware list ("stable" or "beta").
+    /// Beta exposes prerelease firmware; not sent to devices.
+    #[serde(default)]
+    pub release_channel: ReleaseChannel,
+
+    /// Release channel the app updates itself from ("stable" or "beta").
+    /// Beta offers prerelease builds of the updater; not sent to devices.
+    #[serde(default)]
+    pub app_update_channel: ReleaseChannel,
+
+    /// Proxy for firmware downloads and release queries.
+    /// Defaults to the system proxy; not sent to devices.
+    #[serde(default)]
+    pub proxy: ProxySettings,
+
+    /// Download bandwidth cap in bytes per second; 0 means unlimited.
+    /// Lets clinics on a shared uplink throttle firmware downloads.
+    #[serde(default)]
+    pub max_download_bytes_per_sec: u64,
+
+    /// Allows `send_device_command` to send any command, not just the
+    /// read-only queries. For firmware developers; not sent to devices.
+    #[serde(default)]
+    pub developer_mode: bool,
+
+    /// When set, sends VIBRATION_INTENSITY:<value> before SET_PROFILE.
+    /// When unset, the profile's own intensity is kept.
+    /// Lowers intensity for sensitive patients without switching profile (0-100).
+    #[serde(default)]
+    pub vibration_intensity: Option<u8>,
+
+    /// When true, sends LOW_POWER:true before SET_PROFILE.
+    /// When false, sends LOW_POWER:false.
+    /// Dims LEDs and reduces BLE advertising for overnight use.
+    #[serde(default)]
+    pub low_power_mode: bool,
+
+    /// When set, sends SESSION_DURATION:<minutes> before SET_PROFILE.
+    /// When unset, nothing is sent, so older firmware without the setting
+    /// isn't sent a command it doesn't know.
+    #[serde(default)]
+    pub session_duration_minutes: Option<u16>,
+
+    /// When set, sends JITTER_PERCENT:<value> before SET_PROFILE, rounded to
+    /// one decimal place. Overrides the jitter baked into the NOISY and
+    /// HYBRID profiles (0.0-50.0); REGULAR has no jitter to override.
+    #[serde(default)]
+    pub jitter_percent: Option<f32>,
+
+    /// Named profiles defined by the user, selectable like the built-in
+    /// ones. Their commands are sent after the settings above.
+    #[serde(default)]
+    pub custom_profiles: Vec<CustomProfile>,
+
+    /// Overrides for the DFU timeouts and retry limits, for slow USB hubs
+    /// or busy machines. Not sent to devices.
+    #[serde(default)]
+    pub dfu_timing: DfuTimingSettings,
+
+    /// Daily windows the device runs therapy in, e.g. 09:00-11:00 and
+    /// 15:00-17:00. When set, sends SCHEDULE_CLEAR and then one
+    /// SCHEDULE_ADD:<start>,<end> per window before SET_PROFILE. When
+    /// empty, nothing is sent and the device keeps its own schedule.
+    #[serde(default)]
+    pub therapy_schedule: Vec<ScheduleWindow>,
     // =========================================================================
     // EXTENSIBILITY: Add new settings below
     // =========================================================================
-    // Example future settings:
+    // Example future setting:
     //
-    // /// Custom vibration intensity override (0-100).
+    // /// Minutes of inactivity before the device sleeps (0 = never).
     // #[serde(default)]
-    // pub vibration_intensity: Option<u8>,
-    //
-    // /// Enable low-power mode for extended battery life.
-    // #[serde(default)]
-    // pub low_power_mode: bool,
+    // pub auto_sleep_minutes: Option<u16>,
 }
 
 impl AdvancedSettings {
@@ -63,6 +211,9 @@ impl AdvancedSettings {
     ///
     /// These commands configure device behavior but do NOT trigger a reboot.
     /// The SET_PROFILE command (sent after these) triggers the reboot.
+    ///
+    /// Values are sent as they are; settings are checked with `validate()`
+    /// before they are saved or sent.
     pub fn to_pre_profile_commands(&self) -> Vec<String> {
         let mut commands = Vec::new();
 
@@ -84,18 +235,189 @@ impl AdvancedSettings {
         );
         commands.push(debug_command);
 
+        // LOW_POWER setting - always send explicit value
+        let low_power_command = format!(
+            "LOW_POWER:{}\n",
+            if self.low_power_mode { "true" } else { "false" }
+        );
+        commands.push(low_power_command);
+
+        // VIBRATION_INTENSITY setting - only sent when overridden
+        if let Some(intensity) = self.vibration_intensity {
+            commands.push(format!("VIBRATION_INTENSITY:{}\n", intensity));
+        }
+
+        // SESSION_DURATION setting - only sent when set
+        if let Some(minutes) = self.session_duration_minutes {
+            commands.push(format!("SESSION_DURATION:{}\n", minutes));
+        }
+
+        // JITTER_PERCENT setting - only sent when overridden; the firmware
+        // parser expects exactly one decimal place
+        if let Some(jitter) = self.jitter_percent {
+            commands.push(format!("JITTER_PERCENT:{:.1}\n", jitter));
+        }
+
+        // SCHEDULE setting - only sent when set; the old windows are
+        // cleared first so the device ends up with exactly these
+        if !self.therapy_schedule.is_empty() {
+            commands.push("SCHEDULE_CLEAR\n".to_string());
+            for window in &self.therapy_schedule {
+                commands.push(window.to_command());
+            }
+        }
+
         // =====================================================================
         // EXTENSIBILITY: Add new command mappings below
         // =====================================================================
         // Example:
         //
-        // if let Some(intensity) = self.vibration_intensity {
-        //     commands.push(format!("VIBRATION_INTENSITY:{}\n", intensity));
+        // if let Some(minutes) = self.auto_sleep_minutes {
+        //     commands.push(format!("AUTO_SLEEP:{}\n", minutes));
         // }
 
         commands
     }
 
+    /// Check every field, collecting all the values the device (or the
+    /// proxy) would refuse rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), Vec<SettingError>> {
+        let mut errors = Vec::new();
+
+        if let Some(intensity) = self.vibration_intensity {
+            if intensity > MAX_VIBRATION_INTENSITY {
+                errors.push(SettingError::new(
+                    "vibrationIntensity",
+                    format!(
+                        "Vibration intensity must be between 0 and {}, got {}",
+                        MAX_VIBRATION_INTENSITY, intensity
+                    ),
+                ));
+            }
+        }
+        if let Some(minutes) = self.session_duration_minutes {
+            if !SESSION_DURATION_RANGE_MINUTES.contains(&minutes) {
+                errors.push(SettingError::new(
+                    "sessionDurationMinutes",
+                    format!(
+                        "Session duration must be between {} and {} minutes, got {}",
+                        SESSION_DURATION_RANGE_MINUTES.start(),
+                        SESSION_DURATION_RANGE_MINUTES.end(),
+                        minutes
+                    ),
+                ));
+            }
+        }
+        if let Some(jitter) = self.jitter_percent {
+            if !(0.0..=MAX_JITTER_PERCENT).contains(&jitter) {
+                errors.push(SettingError::new(
+                    "jitterPercent",
+                    format!(
+                        "Jitter must be between 0.0 and {:.1} percent, got {}",
+                        MAX_JITTER_PERCENT, jitter
+                    ),
+                ));
+            }
+        }
+        if self.max_download_bytes_per_sec != 0
+            && self.max_download_bytes_per_sec < MIN_DOWNLOAD_BYTES_PER_SEC
+        {
+            errors.push(SettingError::new(
+                "maxDownloadBytesPerSec",
+                format!(
+                    "Download limit must be 0 (unlimited) or at least {} bytes per second, got {}",
+                    MIN_DOWNLOAD_BYTES_PER_SEC, self.max_download_bytes_per_sec
+                ),
+            ));
+        }
+        if let Err(message) = self.proxy.validate() {
+            errors.push(SettingError::new("proxy", message));
+        }
+        self.dfu_timing.validate(&mut errors);
+        validate_schedule(&self.therapy_schedule, &mut errors);
+        for (index, profile) in self.custom_profiles.iter().enumerate() {
+            let field = format!("customProfiles[{}]", index);
+            profile.validate(&field, &mut errors);
+
+            let first = self
+                .custom_profiles
+                .iter()
+                .position(|p| p.name.eq_ignore_ascii_case(&profile.name));
+            if first != Some(index) {
+                errors.push(SettingError::new(
+                    &format!("{}.name", field),
+                    format!("Custom profile {} is defined more than once", profile.name),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The custom profile called `name`, ignoring case.
+    pub fn custom_profile(&self, name: &str) -> Option<&CustomProfile> {
+        self.custom_profiles
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// The setting commands to send for `profile`, in order, and the
+    /// built-in profile to set after them.
+    ///
+    /// A custom profile adds its commands after the settings' own and sets
+    /// its base profile; without one, the device is only rebooted (`None`).
+    /// Any other name is returned as the profile to set.
+    pub fn profile_commands(&self, profile: &str) -> (Vec<String>, Option<String>) {
+        let mut commands = self.to_pre_profile_commands();
+        match self.custom_profile(profile) {
+            Some(custom) => {
+                commands.extend(custom.to_commands());
+                (commands, custom.base_profile.clone())
+            }
+            None => (commands, Some(profile.to_string())),
+        }
+    }
+
+    /// The profiles a device can be set to: the built-in ones, then the
+    /// custom profiles in the order they were defined.
+    pub fn available_profiles(&self) -> Vec<AvailableProfile> {
+        let built_in = BUILT_IN_PROFILES.iter().map(|name| AvailableProfile {
+            name: name.to_string(),
+            built_in: true,
+            base_profile: None,
+        });
+        let custom = self.custom_profiles.iter().map(|profile| AvailableProfile {
+            name: profile.name.clone(),
+            built_in: false,
+            base_profile: profile.base_profile.clone(),
+        });
+        built_in.chain(custom).collect()
+    }
+
+    /// A copy without the GitHub token or proxy credentials, for exports.
+    pub fn without_secrets(&self) -> Self {
+        Self {
+            github_token: None,
+            proxy: self.proxy.without_secrets(),
+            ..self.clone()
+        }
+    }
+
+    /// Take the secrets these settings leave out from `current`, so that
+    /// importing an export without secrets keeps this machine's.
+    fn keep_missing_secrets(&mut self, current: &AdvancedSettings) {
+        if self.github_token.is_none() {
+            self.github_token = current.github_token.clone();
+        }
+        if self.proxy.password.is_none() {
+            self.proxy.password = current.proxy.password.clone();
+        }
+    }
+
     /// Check if these settings differ from defaults.
     /// Useful for logging/debugging to show when non-default settings are applied.
     pub fn has_non_default_settings(&self) -> bool {
@@ -103,205 +425,1690 @@ impl AdvancedSettings {
     }
 }
 
-/// Settings file name stored in app data directory.
-const SETTINGS_FILENAME: &str = "advanced_settings.json";
-
-/// Manages persistence of advanced settings to JSON file.
-pub struct SettingsManager {
-    settings_file_path: PathBuf,
+/// A named profile defined in settings: setting commands sent before a
+/// built-in base profile, for protocols the firmware has no SET_PROFILE
+/// keyword for.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomProfile {
+    /// Name used in place of a built-in profile, e.g. "STUDY_A".
+    pub name: String,
+    /// Commands sent in order, without newlines, e.g. "JITTER_PERCENT:18.0".
+    pub commands: Vec<String>,
+    /// Built-in profile set after the commands. When unset, the device is
+    /// restarted with REBOOT instead.
+    #[serde(default)]
+    pub base_profile: Option<String>,
 }
 
-impl SettingsManager {
-    /// Create a new settings manager for the given app data directory.
-    pub fn new(app_data_dir: &Path) -> Self {
-        let settings_file_path = app_data_dir.join(SETTINGS_FILENAME);
-        Self { settings_file_path }
+impl CustomProfile {
+    /// The commands to send, each with its newline terminator.
+    pub fn to_commands(&self) -> Vec<String> {
+        self.commands
+            .iter()
+            .map(|command| format!("{}\n", command.trim()))
+            .collect()
     }
 
-    /// Load settings from disk, returning defaults on any error (graceful recovery).
-    pub fn load(&self) -> Result<AdvancedSettings, String> {
-        if !self.settings_file_path.exists() {
-            return Ok(AdvancedSettings::default());
+    /// Check the name, commands and base profile, reporting errors under
+    /// `field` (e.g. "customProfiles[0]").
+    fn validate(&self, field: &str, errors: &mut Vec<SettingError>) {
+        let name = self.name.trim();
+        let valid_name = !name.is_empty()
+            && name.len() <= MAX_CUSTOM_PROFILE_NAME_LEN
+            && name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if !valid_name {
+            errors.push(SettingError::new(
+                &format!("{}.name", field),
+                format!(
+                    "Custom profile names must be 1 to {} characters of A-Z, 0-9 and _, got \"{}\"",
+                    MAX_CUSTOM_PROFILE_NAME_LEN, self.name
+                ),
+            ));
+        } else if BUILT_IN_PROFILES.contains(&name) {
+            errors.push(SettingError::new(
+                &format!("{}.name", field),
+                format!("Custom profile {} would replace a built-in profile", name),
+            ));
         }
 
-        let contents = match fs::read_to_string(&self.settings_file_path) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!(
-                    "[Settings] Warning: Failed to read settings file, using defaults: {}",
-                    e
-                );
-                return Ok(AdvancedSettings::default());
+        if self.commands.is_empty() || self.commands.len() > MAX_CUSTOM_PROFILE_COMMANDS {
+            errors.push(SettingError::new(
+                &format!("{}.commands", field),
+                format!(
+                    "Custom profile {} must have 1 to {} commands, got {}",
+                    name,
+                    MAX_CUSTOM_PROFILE_COMMANDS,
+                    self.commands.len()
+                ),
+            ));
+        }
+        for (index, command) in self.commands.iter().enumerate() {
+            let command = command.trim();
+            let allowed = command.len() <= MAX_CUSTOM_PROFILE_COMMAND_LEN
+                && !command.chars().any(|c| c.is_control())
+                && CUSTOM_PROFILE_COMMAND_PREFIXES
+                    .iter()
+                    .any(|prefix| command.starts_with(prefix) && command.len() > prefix.len());
+            if !allowed {
+                errors.push(SettingError::new(
+                    &format!("{}.commands[{}]", field, index),
+                    format!(
+                        "Command \"{}\" is not allowed; custom profiles may send up to {} characters starting with {}",
+                        command,
+                        MAX_CUSTOM_PROFILE_COMMAND_LEN,
+                        CUSTOM_PROFILE_COMMAND_PREFIXES.join(", ")
+                    ),
+                ));
             }
-        };
-
-        // Handle empty file gracefully
-        if contents.trim().is_empty() {
-            return Ok(AdvancedSettings::default());
         }
 
-        match serde_json::from_str(&contents) {
-            Ok(settings) => Ok(settings),
-            Err(e) => {
-                eprintln!(
-                    "[Settings] Warning: Settings file corrupted, using defaults: {}",
-                    e
-                );
-                Ok(AdvancedSettings::default())
+        if let Some(base) = &self.base_profile {
+            if !BUILT_IN_PROFILES.contains(&base.as_str()) {
+                errors.push(SettingError::new(
+                    &format!("{}.baseProfile", field),
+                    format!(
+                        "Base profile must be one of {}, got {}",
+                        BUILT_IN_PROFILES.join(", "),
+                        base
+                    ),
+                ));
             }
         }
     }
+}
 
-    /// Save settings to disk using atomic write (write-to-tmp then rename).
-    pub fn save(&self, settings: &AdvancedSettings) -> Result<(), String> {
-        // Ensure parent directory exists
-        if let Some(parent) = self.settings_file_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
-        }
+/// User overrides for the DFU timing, stored with the advanced settings.
+///
+/// Unset values keep the platform defaults of `DfuTimingConfig`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DfuTimingSettings {
+    /// Time for the bootloader to appear after entering DFU mode (ms).
+    #[serde(default)]
+    pub bootloader_timeout_ms: Option<u64>,
+    /// Time for the bootloader to ACK each packet (ms).
+    #[serde(default)]
+    pub ack_timeout_ms: Option<u64>,
+    /// Retries for each packet and for profile configuration.
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// Give up on a flash after this long, retries included, when the
+    /// caller doesn't set its own timeout (seconds).
+    #[serde(default)]
+    pub deadline_seconds: Option<u64>,
+}
 
-        let contents = serde_json::to_string_pretty(settings)
-            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+impl DfuTimingSettings {
+    /// The timing to flash and configure devices with: the defaults with
+    /// these overrides applied.
+    pub fn to_config(self) -> DfuTimingConfig {
+        let mut config = DfuTimingConfig::default();
+        if let Some(timeout) = self.bootloader_timeout_ms {
+            config.bootloader_timeout_ms = timeout;
+        }
+        if let Some(timeout) = self.ack_timeout_ms {
+            config.ack_timeout_ms = timeout;
+        }
+        if let Some(retries) = self.max_retries {
+            config.max_packet_retries = retries;
+            config.max_config_retries = retries;
+        }
+        config
+    }
 
-        let tmp_path = self.settings_file_path.with_extension("json.tmp");
+    /// Push an error onto `errors` for each override out of range.
+    fn validate(&self, errors: &mut Vec<SettingError>) {
+        let mut check =
+            |field: &str, name: &str, value: Option<u64>, range: &std::ops::RangeInclusive<u64>| {
+                if let Some(value) = value.filter(|value| !range.contains(value)) {
+                    errors.push(SettingError::new(
+                        &format!("dfuTiming.{}", field),
+                        format!(
+                            "{} must be between {} and {}, got {}",
+                            name,
+                            range.start(),
+                            range.end(),
+                            value
+                        ),
+                    ));
+                }
+            };
 
-        fs::write(&tmp_path, &contents).map_err(|e| {
-            let _ = fs::remove_file(&tmp_path);
-            format!("Failed to write settings file: {}", e)
-        })?;
+        check(
+            "bootloaderTimeoutMs",
+            "Bootloader timeout (ms)",
+            self.bootloader_timeout_ms,
+            &DFU_BOOTLOADER_TIMEOUT_RANGE_MS,
+        );
+        check(
+            "ackTimeoutMs",
+            "ACK timeout (ms)",
+            self.ack_timeout_ms,
+            &DFU_ACK_TIMEOUT_RANGE_MS,
+        );
+        check(
+            "maxRetries",
+            "Max retries",
+            self.max_retries.map(u64::from),
+            &(0..=u64::from(MAX_DFU_RETRIES)),
+        );
+        check(
+            "deadlineSeconds",
+            "Flash deadline (seconds)",
+            self.deadline_seconds,
+            &DFU_DEADLINE_RANGE_SECONDS,
+        );
+    }
+}
 
-        fs::rename(&tmp_path, &self.settings_file_path).map_err(|e| {
-            let _ = fs::remove_file(&tmp_path);
-            format!("Failed to finalize settings file: {}", e)
-        })?;
+/// One daily therapy window, as 24-hour "HH:MM" times. The window ends
+/// the same day it starts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleWindow {
+    /// When therapy may start, e.g. "09:00".
+    pub start: String,
+    /// When therapy must stop, e.g. "11:00".
+    pub end: String,
+}
 
-        Ok(())
+impl ScheduleWindow {
+    /// The SCHEDULE_ADD command for this window, with its newline.
+    fn to_command(&self) -> String {
+        format!("SCHEDULE_ADD:{},{}\n", self.start.trim(), self.end.trim())
     }
 
-    /// Get the path where settings are stored.
-    ///
-    /// Useful for debugging or displaying settings location to users.
-    #[allow(dead_code)]
-    pub fn settings_path(&self) -> &Path {
-        &self.settings_file_path
+    /// Start and end in minutes after midnight, if both are valid times
+    /// and the window isn't empty.
+    fn minutes(&self) -> Option<(u16, u16)> {
+        let start = parse_time_of_day(&self.start)?;
+        let end = parse_time_of_day(&self.end)?;
+        (start < end).then_some((start, end))
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
+/// Minutes after midnight for a 24-hour "HH:MM" time.
+fn parse_time_of_day(time: &str) -> Option<u16> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let hours: u16 = hours.parse().ok()?;
+    let minutes: u16 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
 
-    #[test]
-    fn test_default_settings() {
-        let settings = AdvancedSettings::default();
-        assert!(!settings.disable_led_during_therapy);
-        assert!(!settings.debug_mode);
+/// Push an error onto `errors` for each invalid or overlapping window, and
+/// for a schedule with too many.
+fn validate_schedule(schedule: &[ScheduleWindow], errors: &mut Vec<SettingError>) {
+    if schedule.len() > MAX_SCHEDULE_WINDOWS {
+        errors.push(SettingError::new(
+            "therapySchedule",
+            format!(
+                "Therapy schedule may have at most {} windows, got {}",
+                MAX_SCHEDULE_WINDOWS,
+                schedule.len()
+            ),
+        ));
     }
 
-    #[test]
-    fn test_to_pre_profile_commands_default() {
-        let settings = AdvancedSettings::default();
-        let commands = settings.to_pre_profile_commands();
+    let mut valid = Vec::new();
+    for (index, window) in schedule.iter().enumerate() {
+        match window.minutes() {
+            Some(minutes) => valid.push((index, minutes)),
+            None => errors.push(SettingError::new(
+                &format!("therapySchedule[{}]", index),
+                format!(
+                    "Therapy window {}-{} must be two 24-hour HH:MM times, start before end",
+                    window.start, window.end
+                ),
+            )),
+        }
+    }
 
-        assert_eq!(commands.len(), 2);
-        assert_eq!(commands[0], "THERAPY_LED_OFF:false\n");
-        assert_eq!(commands[1], "DEBUG:false\n");
+    // Windows may touch (one ends as the next starts) but not overlap
+    valid.sort_by_key(|&(_, (start, _))| start);
+    let mut latest: Option<(usize, u16)> = None;
+    for (index, (start, end)) in valid {
+        match latest {
+            Some((earlier, earlier_end)) if start < earlier_end => {
+                errors.push(SettingError::new(
+                    &format!("therapySchedule[{}]", index),
+                    format!(
+                        "Therapy window {}-{} overlaps {}-{}",
+                        schedule[index].start,
+                        schedule[index].end,
+                        schedule[earlier].start,
+                        schedule[earlier].end
+                    ),
+                ));
+                if end > earlier_end {
+                    latest = Some((index, end));
+                }
+            }
+            _ => latest = Some((index, end)),
+        }
     }
+}
 
-    #[test]
-    fn test_to_pre_profile_commands_led_disabled() {
-        let settings = AdvancedSettings {
-            disable_led_during_therapy: true,
-            debug_mode: false,
-            selected_profile: None,
-        };
-        let commands = settings.to_pre_profile_commands();
+/// A profile `get_available_profiles` offers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AvailableProfile {
+    pub name: String,
+    /// Whether the firmware defines it, rather than the settings.
+    pub built_in: bool,
+    /// Built-in profile a custom profile is set on top of.
+    pub base_profile: Option<String>,
+}
 
-        assert_eq!(commands.len(), 2);
-        assert_eq!(commands[0], "THERAPY_LED_OFF:true\n");
-        assert_eq!(commands[1], "DEBUG:false\n");
+/// A setting value that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettingError {
+    /// The setting's JSON name, e.g. "jitterPercent".
+    pub field: String,
+    /// What is wrong with the value, ready to show to the user.
+    pub message: String,
+}
+
+impl SettingError {
+    fn new(field: &str, message: String) -> Self {
+        Self {
+            field: field.to_string(),
+            message,
+        }
     }
+}
 
-    #[test]
-    fn test_to_pre_profile_commands_debug_enabled() {
-        let settings = AdvancedSettings {
-            disable_led_during_therapy: false,
-            debug_mode: true,
-            selected_profile: None,
-        };
-        let commands = settings.to_pre_profile_commands();
+/// Join validation errors into one message, for callers that report a
+/// single string.
+pub fn describe_setting_errors(errors: &[SettingError]) -> String {
+    errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
 
-        assert_eq!(commands.len(), 2);
-        assert_eq!(commands[0], "THERAPY_LED_OFF:false\n");
-        assert_eq!(commands[1], "DEBUG:true\n");
-    }
+/// Why settings couldn't be saved.
+///
+/// Serialized as `{ "errors": [...] }` for invalid values, so the UI can
+/// mark each field, and as a plain message for anything else.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum SaveSettingsError {
+    Invalid { errors: Vec<SettingError> },
+    Failed(String),
+}
 
-    #[test]
-    fn test_to_pre_profile_commands_all_enabled() {
-        let settings = AdvancedSettings {
-            disable_led_during_therapy: true,
-            debug_mode: true,
-            selected_profile: None,
-        };
-        let commands = settings.to_pre_profile_commands();
+impl From<Vec<SettingError>> for SaveSettingsError {
+    fn from(errors: Vec<SettingError>) -> Self {
+        SaveSettingsError::Invalid { errors }
+    }
+}
 
-        assert_eq!(commands.len(), 2);
-        assert_eq!(commands[0], "THERAPY_LED_OFF:true\n");
-        assert_eq!(commands[1], "DEBUG:true\n");
+impl From<String> for SaveSettingsError {
+    fn from(message: String) -> Self {
+        SaveSettingsError::Failed(message)
     }
+}
 
-    #[test]
-    fn test_settings_persistence() {
-        let dir = tempdir().unwrap();
-        let manager = SettingsManager::new(dir.path());
+/// Per-device overrides of the settings that are sent to devices.
+///
+/// Unset fields fall back to the global `AdvancedSettings`. Settings that
+/// never reach a device (GitHub token, proxy, ...) can't be overridden.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_led_during_therapy: Option<bool>,
 
-        // Initially returns defaults
-        let loaded = manager.load().unwrap();
-        assert_eq!(loaded, AdvancedSettings::default());
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_mode: Option<bool>,
 
-        // Save custom settings
-        let settings = AdvancedSettings {
-            disable_led_during_therapy: true,
-            debug_mode: true,
-            selected_profile: Some("REGULAR".to_string()),
-        };
-        manager.save(&settings).unwrap();
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_power_mode: Option<bool>,
 
-        // Load returns saved settings
-        let loaded = manager.load().unwrap();
-        assert_eq!(loaded, settings);
-    }
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vibration_intensity: Option<u8>,
 
-    #[test]
-    fn test_has_non_default_settings() {
-        let default = AdvancedSettings::default();
-        assert!(!default.has_non_default_settings());
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_duration_minutes: Option<u16>,
 
-        let custom_led = AdvancedSettings {
-            disable_led_during_therapy: true,
-            debug_mode: false,
-            selected_profile: None,
-        };
-        assert!(custom_led.has_non_default_settings());
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_percent: Option<f32>,
+}
 
-        let custom_debug = AdvancedSettings {
-            disable_led_during_therapy: false,
-            debug_mode: true,
-            selected_profile: None,
-        };
+impl DeviceSettings {
+    /// Whether no setting is overridden.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Layer these overrides over `global`.
+    ///
+    /// Returns the effective settings and one line per device setting
+    /// naming the layer its value came from, e.g. "Debug Mode = true (device)".
+    pub fn resolve(&self, global: &AdvancedSettings) -> (AdvancedSettings, Vec<String>) {
+        let mut settings = global.clone();
+        if let Some(value) = self.disable_led_during_therapy {
+            settings.disable_led_during_therapy = value;
+        }
+        if let Some(value) = self.debug_mode {
+            settings.debug_mode = value;
+        }
+        if let Some(value) = self.low_power_mode {
+            settings.low_power_mode = value;
+        }
+        if self.vibration_intensity.is_some() {
+            settings.vibration_intensity = self.vibration_intensity;
+        }
+        if self.session_duration_minutes.is_some() {
+            settings.session_duration_minutes = self.session_duration_minutes;
+        }
+        if self.jitter_percent.is_some() {
+            settings.jitter_percent = self.jitter_percent;
+        }
+
+        let layer = |overridden: bool| if overridden { "device" } else { "global" };
+        let or_unset = |value: Option<String>| value.unwrap_or_else(|| "not set".to_string());
+        let layers = vec![
+            format!(
+                "Disable LED During Therapy = {} ({})",
+                settings.disable_led_during_therapy,
+                layer(self.disable_led_during_therapy.is_some())
+            ),
+            format!(
+                "Debug Mode = {} ({})",
+                settings.debug_mode,
+                layer(self.debug_mode.is_some())
+            ),
+            format!(
+                "Low Power Mode = {} ({})",
+                settings.low_power_mode,
+                layer(self.low_power_mode.is_some())
+            ),
+            format!(
+                "Vibration Intensity = {} ({})",
+                or_unset(settings.vibration_intensity.map(|v| v.to_string())),
+                layer(self.vibration_intensity.is_some())
+            ),
+            format!(
+                "Session Duration = {} ({})",
+                or_unset(settings.session_duration_minutes.map(|v| v.to_string())),
+                layer(self.session_duration_minutes.is_some())
+            ),
+            format!(
+                "Jitter Percent = {} ({})",
+                or_unset(settings.jitter_percent.map(|v| format!("{:.1}", v))),
+                layer(self.jitter_percent.is_some())
+            ),
+        ];
+
+        (settings, layers)
+    }
+
+    /// Check the overridden values, as `AdvancedSettings::validate` does.
+    pub fn validate(&self) -> Result<(), Vec<SettingError>> {
+        self.resolve(&AdvancedSettings::default()).0.validate()
+    }
+}
+
+/// Which saved settings an operation applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum SettingsScope {
+    /// The global settings; device overrides are kept.
+    Global,
+    /// One device's overrides.
+    Device { serial_number: String },
+    /// The global settings and every device override.
+    All,
+}
+
+/// Settings as they were before a reset, so it can be undone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PreviousSettings {
+    /// Global settings, unless only a device was reset.
+    pub global: Option<AdvancedSettings>,
+    /// Device overrides that were removed, by serial number.
+    pub devices: BTreeMap<String, DeviceSettings>,
+}
+
+/// Result of `SettingsManager::reset`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResetSettingsResult {
+    /// Settings now in effect: the global settings, which after a device
+    /// reset are also that device's settings.
+    pub effective: AdvancedSettings,
+    pub previous: PreviousSettings,
+}
+
+/// Settings file name stored in app data directory.
+const SETTINGS_FILENAME: &str = "advanced_settings.json";
+
+/// Upgrades of the persisted settings file, oldest first.
+///
+/// `MIGRATIONS[n]` turns a file of schema version `n + 1` into one of
+/// version `n + 2`. Files without a `schema_version` are version 1.
+const MIGRATIONS: &[fn(serde_json::Value) -> serde_json::Value] =
+    &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// Schema version written by this build.
+const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Version 2 moved the settings into a versioned envelope.
+fn migrate_v1_to_v2(file: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "schema_version": 2, "settings": file })
+}
+
+/// Version 3 added per-device overrides; the existing settings become the
+/// global layer.
+fn migrate_v2_to_v3(mut file: serde_json::Value) -> serde_json::Value {
+    file["schema_version"] = 3.into();
+    file["devices"] = serde_json::json!({});
+    file
+}
+
+/// Persisted form of the settings file.
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsFile {
+    schema_version: u32,
+    /// Global settings, used for every device without an override.
+    settings: AdvancedSettings,
+    /// Per-device overrides by USB serial number.
+    #[serde(default)]
+    devices: BTreeMap<String, DeviceSettings>,
+}
+
+impl Default for SettingsFile {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            settings: AdvancedSettings::default(),
+            devices: BTreeMap::new(),
+        }
+    }
+}
+
+/// Bring a settings document of any known schema version up to date.
+///
+/// Returns the version the document had and the migrated document.
+fn migrate_file(mut file: serde_json::Value) -> Result<(u64, serde_json::Value), String> {
+    let version = match file.get("schema_version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("Invalid settings schema version: {}", version))?,
+        None => 1,
+    };
+
+    if version == 0 || version > SCHEMA_VERSION as u64 {
+        return Err(format!(
+            "Unrecognized settings schema version {} (this version reads up to {})",
+            version, SCHEMA_VERSION
+        ));
+    }
+
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        file = migrate(file);
+    }
+
+    Ok((version, file))
+}
+
+/// Parse a settings file of any known schema version.
+fn decode_settings(contents: &str) -> Result<SettingsFile, String> {
+    let file: serde_json::Value =
+        serde_json::from_str(contents).map_err(|e| format!("Invalid settings JSON: {}", e))?;
+    let (_, file) = migrate_file(file)?;
+
+    serde_json::from_value(file).map_err(|e| format!("Invalid settings: {}", e))
+}
+
+/// Whether `contents` is valid JSON from a newer schema version than this
+/// app reads.
+fn is_newer_schema(contents: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(contents)
+        .ok()
+        .and_then(|file| file.get("schema_version").and_then(|v| v.as_u64()))
+        .is_some_and(|version| version > SCHEMA_VERSION as u64)
+}
+
+/// Settings document written by `SettingsManager::export`.
+///
+/// The settings file plus the app version that wrote it; `import` reads
+/// either.
+#[derive(Serialize)]
+struct SettingsExport<'a> {
+    schema_version: u32,
+    app_version: &'static str,
+    settings: &'a AdvancedSettings,
+    devices: &'a BTreeMap<String, DeviceSettings>,
+}
+
+/// What `SettingsManager::import` changed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportSummary {
+    /// Schema version of the imported file, before migration.
+    pub schema_version: u64,
+    /// App version that exported the file, if recorded.
+    pub app_version: Option<String>,
+    /// Settings whose value changed, e.g. "settings.debugMode" or
+    /// "devices.ABC123.lowPowerMode".
+    pub changed: Vec<String>,
+    /// Keys this version doesn't know; their values were not imported.
+    pub unknown_keys: Vec<String>,
+}
+
+/// Add the paths of keys in `value` that `template` lacks to `unknown`.
+fn collect_unknown_keys(
+    path: &str,
+    value: &serde_json::Value,
+    template: &serde_json::Value,
+    unknown: &mut Vec<String>,
+) {
+    let (Some(value), Some(template)) = (value.as_object(), template.as_object()) else {
+        return;
+    };
+    for (key, child) in value {
+        let child_path = format!("{}.{}", path, key);
+        match template.get(key) {
+            Some(child_template) => {
+                collect_unknown_keys(&child_path, child, child_template, unknown)
+            }
+            None => unknown.push(child_path),
+        }
+    }
+}
+
+/// Keys of a (migrated) settings document that this version doesn't know.
+fn unknown_keys(file: &serde_json::Value) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let Some(file) = file.as_object() else {
+        return unknown;
+    };
+
+    let settings_template = serde_json::to_value(AdvancedSettings::default()).unwrap_or_default();
+    let device_template = serde_json::to_value(DeviceSettings {
+        disable_led_during_therapy: Some(false),
+        debug_mode: Some(false),
+        low_power_mode: Some(false),
+        vibration_intensity: Some(0),
+        session_duration_minutes: Some(0),
+        jitter_percent: Some(0.0),
+    })
+    .unwrap_or_default();
+
+    for (key, value) in file {
+        match key.as_str() {
+            "schema_version" | "app_version" => {}
+            "settings" => collect_unknown_keys(key, value, &settings_template, &mut unknown),
+            "devices" => {
+                for (serial, overrides) in value.as_object().into_iter().flatten() {
+                    let path = format!("devices.{}", serial);
+                    collect_unknown_keys(&path, overrides, &device_template, &mut unknown);
+                }
+            }
+            _ => unknown.push(key.clone()),
+        }
+    }
+    unknown
+}
+
+/// Add the paths of values that differ between `old` and `new` to `changed`.
+fn collect_changed_paths<'a>(
+    path: &str,
+    old: &'a serde_json::Value,
+    new: &'a serde_json::Value,
+    changed: &mut Vec<String>,
+) {
+    // A device that was added or removed is reported key by key
+    let empty = serde_json::Map::new();
+    let as_object = |value: &'a serde_json::Value, other: &serde_json::Value| match value {
+        serde_json::Value::Null if other.is_object() => Some(&empty),
+        _ => value.as_object(),
+    };
+
+    match (as_object(old, new), as_object(new, old)) {
+        (Some(old), Some(new)) => {
+            let keys: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let null = serde_json::Value::Null;
+                collect_changed_paths(
+                    &format!("{}.{}", path, key),
+                    old.get(key).unwrap_or(&null),
+                    new.get(key).unwrap_or(&null),
+                    changed,
+                );
+            }
+        }
+        _ if old != new => changed.push(path.to_string()),
+        _ => {}
+    }
+}
+
+/// Everything in the settings file, as of one load or save.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SettingsSnapshot {
+    /// Global settings, used for every device without an override.
+    pub settings: AdvancedSettings,
+    /// Per-device overrides by USB serial number.
+    pub devices: BTreeMap<String, DeviceSettings>,
+}
+
+impl SettingsSnapshot {
+    /// The overrides saved for the device with `serial_number`, or empty
+    /// overrides if it has none.
+    pub fn device(&self, serial_number: &str) -> DeviceSettings {
+        self.devices.get(serial_number).cloned().unwrap_or_default()
+    }
+
+    /// The settings to send to a device: its overrides, if it has a serial
+    /// number and any are saved, layered over the global settings.
+    ///
+    /// Also returns which layer each device setting came from.
+    pub fn effective(&self, serial_number: Option<&str>) -> (AdvancedSettings, Vec<String>) {
+        serial_number
+            .map(|serial| self.device(serial))
+            .unwrap_or_default()
+            .resolve(&self.settings)
+    }
+}
+
+impl From<SettingsFile> for SettingsSnapshot {
+    fn from(file: SettingsFile) -> Self {
+        Self {
+            settings: file.settings,
+            devices: file.devices,
+        }
+    }
+}
+
+/// Manages persistence of advanced settings to JSON file.
+pub struct SettingsManager {
+    settings_file_path: PathBuf,
+}
+
+impl SettingsManager {
+    /// Create a new settings manager for the given app data directory.
+    pub fn new(app_data_dir: &Path) -> Self {
+        let settings_file_path = app_data_dir.join(SETTINGS_FILENAME);
+        Self { settings_file_path }
+    }
+
+    /// Load the global settings and every device's overrides from disk,
+    /// falling back to the `.bak` copy of the last successful save if the
+    /// file is empty or corrupt.
+    ///
+    /// Older schema versions are migrated. A file that is corrupt or from a
+    /// newer version is copied aside as `.unrecognized` first, so the next
+    /// save doesn't destroy it. A file that is empty or from a newer version
+    /// without a usable backup loads as defaults; a corrupt one is an error.
+    pub fn load_snapshot(&self) -> Result<SettingsSnapshot, String> {
+        self.load_file().map(SettingsSnapshot::from)
+    }
+
+    /// Load the whole settings file (see `load_snapshot` for the fallbacks).
+    fn load_file(&self) -> Result<SettingsFile, String> {
+        if !self.settings_file_path.exists() {
+            return Ok(SettingsFile::default());
+        }
+
+        let contents = match fs::read_to_string(&self.settings_file_path) {
+            Ok(c) => c,
+            Err(e) => {
+                log::warn!("Failed to read settings file, trying backup: {}", e);
+                return self
+                    .load_backup()
+                    .ok_or_else(|| format!("Failed to read settings file: {}", e));
+            }
+        };
+
+        // An empty file is what a crash before the data reached disk leaves
+        let decoded = if contents.trim().is_empty() {
+            Err("Settings file is empty".to_string())
+        } else {
+            decode_settings(&contents)
+        };
+
+        match decoded {
+            Ok(file) => Ok(file),
+            Err(e) => {
+                log::warn!("Settings file unrecognized, trying backup: {}", e);
+                self.preserve_unrecognized();
+                if let Some(file) = self.load_backup() {
+                    return Ok(file);
+                }
+                // Empty after a crash before the first backup was made, or
+                // written by a newer version: nothing we can recover
+                if contents.trim().is_empty() || is_newer_schema(&contents) {
+                    log::warn!("No usable settings backup, using defaults");
+                    return Ok(SettingsFile::default());
+                }
+                Err(format!(
+                    "Settings file and its backup are unreadable (a copy was kept at {}): {}",
+                    self.unrecognized_path().display(),
+                    e
+                ))
+            }
+        }
+    }
+
+    /// Load the whole settings file to change and write back.
+    ///
+    /// An unreadable file has already been copied aside, so it is replaced
+    /// rather than blocking the save that would recover from it.
+    fn load_file_for_write(&self) -> SettingsFile {
+        self.load_file().unwrap_or_else(|e| {
+            log::warn!("Replacing unreadable settings: {}", e);
+            SettingsFile::default()
+        })
+    }
+
+    /// Path of the backup copy written after each successful save.
+    fn backup_path(&self) -> PathBuf {
+        self.settings_file_path.with_extension("json.bak")
+    }
+
+    /// Path an unrecognized settings file is copied to.
+    fn unrecognized_path(&self) -> PathBuf {
+        self.settings_file_path.with_extension("json.unrecognized")
+    }
+
+    /// Keep a copy of the settings file before it can be overwritten.
+    fn preserve_unrecognized(&self) {
+        if let Err(e) = fs::copy(&self.settings_file_path, self.unrecognized_path()) {
+            log::warn!("Failed to preserve unrecognized settings file: {}", e);
+        }
+    }
+
+    /// Load the backup settings, or `None` if it is missing or unreadable.
+    fn load_backup(&self) -> Option<SettingsFile> {
+        let parsed = fs::read_to_string(self.backup_path())
+            .ok()
+            .and_then(|contents| decode_settings(&contents).ok());
+        if parsed.is_some() {
+            log::warn!("Recovered settings from backup");
+        }
+        parsed
+    }
+
+    /// Save the global settings, keeping any device overrides.
+    pub fn save(&self, settings: &AdvancedSettings) -> Result<(), String> {
+        let mut file = self.load_file_for_write();
+        file.settings = settings.clone();
+        self.write_file(&file)
+    }
+
+    /// Save the overrides for the device with `serial_number`. Empty
+    /// overrides remove the device's entry.
+    pub fn save_device(
+        &self,
+        serial_number: &str,
+        overrides: &DeviceSettings,
+    ) -> Result<(), String> {
+        let mut file = self.load_file_for_write();
+        if overrides.is_empty() {
+            file.devices.remove(serial_number);
+        } else {
+            file.devices
+                .insert(serial_number.to_string(), overrides.clone());
+        }
+        self.write_file(&file)
+    }
+
+    /// Restore defaults for `scope`: the global settings, a device's
+    /// overrides, or both.
+    pub fn reset(&self, scope: &SettingsScope) -> Result<ResetSettingsResult, String> {
+        let mut file = self.load_file_for_write();
+        let mut previous = PreviousSettings::default();
+
+        match scope {
+            SettingsScope::Global => {
+                previous.global = Some(std::mem::take(&mut file.settings));
+            }
+            SettingsScope::Device { serial_number } => {
+                if let Some(overrides) = file.devices.remove(serial_number) {
+                    previous.devices.insert(serial_number.clone(), overrides);
+                }
+            }
+            SettingsScope::All => {
+                previous.global = Some(std::mem::take(&mut file.settings));
+                previous.devices = std::mem::take(&mut file.devices);
+            }
+        }
+
+        self.write_file(&file)?;
+        Ok(ResetSettingsResult {
+            effective: file.settings,
+            previous,
+        })
+    }
+
+    /// Export every saved setting to `destination` as a single JSON document.
+    ///
+    /// The GitHub token and proxy credentials are left out unless
+    /// `include_secrets` is set.
+    pub fn export(&self, destination: &Path, include_secrets: bool) -> Result<(), String> {
+        let file = self.load_file()?;
+        let settings = if include_secrets {
+            file.settings.clone()
+        } else {
+            file.settings.without_secrets()
+        };
+        let export = SettingsExport {
+            schema_version: SCHEMA_VERSION,
+            app_version: env!("CARGO_PKG_VERSION"),
+            settings: &settings,
+            devices: &file.devices,
+        };
+
+        let contents = serde_json::to_string_pretty(&export)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(destination, contents).map_err(|e| format!("Failed to export settings: {}", e))
+    }
+
+    /// Replace every saved setting with those in `source`, an export or a
+    /// settings file from this or an older version.
+    ///
+    /// The file is validated in full before anything is written, and
+    /// secrets it leaves out keep their current values.
+    pub fn import(&self, source: &Path) -> Result<ImportSummary, String> {
+        let contents = fs::read_to_string(source)
+            .map_err(|e| format!("Failed to read settings file: {}", e))?;
+        let document: serde_json::Value =
+            serde_json::from_str(&contents).map_err(|e| format!("Invalid settings JSON: {}", e))?;
+
+        let (schema_version, document) = migrate_file(document)?;
+        let unknown_keys = unknown_keys(&document);
+        let app_version = document
+            .get("app_version")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        let mut imported: SettingsFile =
+            serde_json::from_value(document).map_err(|e| format!("Invalid settings: {}", e))?;
+        imported
+            .settings
+            .validate()
+            .map_err(|errors| describe_setting_errors(&errors))?;
+        for (serial, overrides) in &imported.devices {
+            overrides.validate().map_err(|errors| {
+                format!("Device {}: {}", serial, describe_setting_errors(&errors))
+            })?;
+        }
+
+        let current = self.load_file_for_write();
+        imported.settings.keep_missing_secrets(&current.settings);
+
+        let mut changed = Vec::new();
+        collect_changed_paths(
+            "settings",
+            &serde_json::to_value(&current.settings).unwrap_or_default(),
+            &serde_json::to_value(&imported.settings).unwrap_or_default(),
+            &mut changed,
+        );
+        collect_changed_paths(
+            "devices",
+            &serde_json::to_value(&current.devices).unwrap_or_default(),
+            &serde_json::to_value(&imported.devices).unwrap_or_default(),
+            &mut changed,
+        );
+
+        self.write_file(&imported)?;
+
+        Ok(ImportSummary {
+            schema_version,
+            app_version,
+            changed,
+            unknown_keys,
+        })
+    }
+
+    /// Write the settings file using atomic write (write-to-tmp then rename).
+    ///
+    /// The temp file is synced before the rename, so a crash leaves either
+    /// the old file or the new one, never a partial write. The same contents
+    /// then replace the `.bak` copy, the last known-good settings.
+    fn write_file(&self, file: &SettingsFile) -> Result<(), String> {
+        // Ensure parent directory exists
+        if let Some(parent) = self.settings_file_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+
+        let contents = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+        let tmp_path = self.settings_file_path.with_extension("json.tmp");
+
+        write_synced(&tmp_path, &contents).map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+            format!("Failed to write settings file: {}", e)
+        })?;
+
+        fs::rename(&tmp_path, &self.settings_file_path).map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+            format!("Failed to finalize settings file: {}", e)
+        })?;
+
+        // Keep a last-known-good copy for recovery; the save itself already succeeded
+        let backup_path = self.backup_path();
+        let backup_tmp = self.settings_file_path.with_extension("json.bak.tmp");
+        if let Err(e) =
+            write_synced(&backup_tmp, &contents).and_then(|_| fs::rename(&backup_tmp, &backup_path))
+        {
+            let _ = fs::remove_file(&backup_tmp);
+            log::warn!("Failed to write settings backup: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Get the path where settings are stored.
+    ///
+    /// Useful for debugging or displaying settings location to users.
+    #[allow(dead_code)]
+    pub fn settings_path(&self) -> &Path {
+        &self.settings_file_path
+    }
+}
+
+/// The saved settings, loaded once at startup and kept in memory.
+///
+/// Registered as Tauri managed state. Reads share an immutable snapshot
+/// that is cheap to clone into blocking tasks. Every write goes through
+/// here: it is written to disk atomically by `SettingsManager`, then
+/// swapped in as the new snapshot, so readers see either the old settings
+/// or the new ones, never a mix.
+pub struct SettingsService {
+    manager: SettingsManager,
+    /// Current snapshot, or why the settings file couldn't be read.
+    current: RwLock<Result<Arc<SettingsSnapshot>, String>>,
+    /// Held for each write so the disk and the snapshot change in step.
+    write_lock: Mutex<()>,
+}
+
+impl SettingsService {
+    /// Load the settings in `app_data_dir`.
+    ///
+    /// An unreadable file is reported by every read until a save replaces
+    /// it (see `SettingsManager::load_snapshot`).
+    pub fn load(app_data_dir: &Path) -> Self {
+        let manager = SettingsManager::new(app_data_dir);
+        let current = manager.load_snapshot().map(Arc::new);
+        if let Err(e) = &current {
+            log::warn!("{}", e);
+        }
+
+        Self {
+            manager,
+            current: RwLock::new(current),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// The settings as of the last load or save.
+    pub fn snapshot(&self) -> Result<Arc<SettingsSnapshot>, String> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// The global settings as of the last load or save.
+    pub fn settings(&self) -> Result<AdvancedSettings, String> {
+        Ok(self.snapshot()?.settings.clone())
+    }
+
+    /// The settings as of the last load or save, or the defaults when the
+    /// file is unreadable.
+    ///
+    /// For downloads and device commands, which keep working while the
+    /// settings screen (which still gets the error) sorts the file out.
+    pub fn snapshot_or_default(&self) -> Arc<SettingsSnapshot> {
+        self.snapshot().unwrap_or_else(|e| {
+            log::warn!("Using default settings: {}", e);
+            Arc::default()
+        })
+    }
+
+    /// The global settings, or the defaults when the file is unreadable
+    /// (see `snapshot_or_default`).
+    pub fn settings_or_default(&self) -> AdvancedSettings {
+        self.snapshot_or_default().settings.clone()
+    }
+
+    /// Save the global settings, keeping any device overrides. Returns the
+    /// new snapshot.
+    pub fn save(&self, settings: &AdvancedSettings) -> Result<Arc<SettingsSnapshot>, String> {
+        let ((), snapshot) = self.write(|manager| manager.save(settings))?;
+        Ok(snapshot)
+    }
+
+    /// Save the overrides for the device with `serial_number`, as
+    /// `SettingsManager::save_device` does. Returns the new snapshot.
+    pub fn save_device(
+        &self,
+        serial_number: &str,
+        overrides: &DeviceSettings,
+    ) -> Result<Arc<SettingsSnapshot>, String> {
+        let ((), snapshot) = self.write(|manager| manager.save_device(serial_number, overrides))?;
+        Ok(snapshot)
+    }
+
+    /// Export every saved setting to `destination`, as
+    /// `SettingsManager::export` does. Waits for any write in progress, so
+    /// the export never catches the file half-saved.
+    pub fn export(&self, destination: &Path, include_secrets: bool) -> Result<(), String> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.manager.export(destination, include_secrets)
+    }
+
+    /// Change the saved settings with `change`, e.g. a reset or an import,
+    /// then reload them. Returns what `change` did and the new snapshot.
+    pub fn write<T>(
+        &self,
+        change: impl FnOnce(&SettingsManager) -> Result<T, String>,
+    ) -> Result<(T, Arc<SettingsSnapshot>), String> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let result = change(&self.manager)?;
+
+        let snapshot = self.manager.load_snapshot().map(Arc::new);
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = snapshot.clone();
+        Ok((result, snapshot?))
+    }
+}
+
+/// Write `contents` to `path` and flush it to disk.
+fn write_synced(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::ProxyMode;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_default_settings() {
+        let settings = AdvancedSettings::default();
+        assert!(!settings.disable_led_during_therapy);
+        assert!(!settings.debug_mode);
+    }
+
+    #[test]
+    fn test_to_pre_profile_commands_default() {
+        let settings = AdvancedSettings::default();
+        let commands = settings.to_pre_profile_commands();
+
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], "THERAPY_LED_OFF:false\n");
+        assert_eq!(commands[1], "DEBUG:false\n");
+        assert_eq!(commands[2], "LOW_POWER:false\n");
+    }
+
+    #[test]
+    fn test_to_pre_profile_commands_led_disabled() {
+        let settings = AdvancedSettings {
+            disable_led_during_therapy: true,
+            debug_mode: false,
+            selected_profile: None,
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
+        };
+        let commands = settings.to_pre_profile_commands();
+
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], "THERAPY_LED_OFF:true\n");
+        assert_eq!(commands[1], "DEBUG:false\n");
+        assert_eq!(commands[2], "LOW_POWER:false\n");
+    }
+
+    #[test]
+    fn test_to_pre_profile_commands_debug_enabled() {
+        let settings = AdvancedSettings {
+            disable_led_during_therapy: false,
+            debug_mode: true,
+            selected_profile: None,
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
+        };
+        let commands = settings.to_pre_profile_commands();
+
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], "THERAPY_LED_OFF:false\n");
+        assert_eq!(commands[1], "DEBUG:true\n");
+        assert_eq!(commands[2], "LOW_POWER:false\n");
+    }
+
+    #[test]
+    fn test_to_pre_profile_commands_all_enabled() {
+        let settings = AdvancedSettings {
+            disable_led_during_therapy: true,
+            debug_mode: true,
+            selected_profile: None,
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: true,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
+        };
+        let commands = settings.to_pre_profile_commands();
+
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], "THERAPY_LED_OFF:true\n");
+        assert_eq!(commands[1], "DEBUG:true\n");
+        assert_eq!(commands[2], "LOW_POWER:true\n");
+    }
+
+    #[test]
+    fn test_to_pre_profile_commands_optional_settings() {
+        let settings = AdvancedSettings {
+            vibration_intensity: Some(60),
+            session_duration_minutes: Some(120),
+            ..AdvancedSettings::default()
+        };
+        let commands = settings.to_pre_profile_commands();
+
+        assert_eq!(commands.len(), 5);
+        assert_eq!(commands[0], "THERAPY_LED_OFF:false\n");
+        assert_eq!(commands[1], "DEBUG:false\n");
+        assert_eq!(commands[2], "LOW_POWER:false\n");
+        assert_eq!(commands[3], "VIBRATION_INTENSITY:60\n");
+        assert_eq!(commands[4], "SESSION_DURATION:120\n");
+    }
+
+    #[test]
+    fn test_validate_vibration_intensity() {
+        let mut settings = AdvancedSettings::default();
+        assert!(settings.validate().is_ok());
+
+        settings.vibration_intensity = Some(MAX_VIBRATION_INTENSITY);
+        assert!(settings.validate().is_ok());
+
+        settings.vibration_intensity = Some(101);
+        let errors = settings.validate().unwrap_err();
+        assert_eq!(errors[0].field, "vibrationIntensity");
+        assert!(errors[0].message.contains("between 0 and 100"));
+    }
+
+    #[test]
+    fn test_validate_session_duration() {
+        let mut settings = AdvancedSettings::default();
+
+        for minutes in [15, 60, 240, 480] {
+            settings.session_duration_minutes = Some(minutes);
+            assert!(settings.validate().is_ok(), "{} minutes", minutes);
+        }
+
+        for minutes in [0, 14, 481] {
+            settings.session_duration_minutes = Some(minutes);
+            let errors = settings.validate().unwrap_err();
+            assert_eq!(errors[0].field, "sessionDurationMinutes");
+            assert!(errors[0].message.starts_with("Session duration"));
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_every_invalid_field() {
+        let settings = AdvancedSettings {
+            vibration_intensity: Some(150),
+            jitter_percent: Some(900.0),
+            proxy: ProxySettings {
+                mode: ProxyMode::Manual,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let errors = settings.validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["vibrationIntensity", "jitterPercent", "proxy"]);
+        assert!(describe_setting_errors(&errors).contains("; Jitter must be"));
+
+        let json = serde_json::to_value(SaveSettingsError::from(errors)).unwrap();
+        assert_eq!(json["errors"][1]["field"], "jitterPercent");
+        let json = serde_json::to_value(SaveSettingsError::from("disk full".to_string())).unwrap();
+        assert_eq!(json, "disk full");
+
+        let overrides = DeviceSettings {
+            session_duration_minutes: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(
+            overrides.validate().unwrap_err()[0].field,
+            "sessionDurationMinutes"
+        );
+    }
+
+    #[test]
+    fn test_custom_profiles() {
+        let study = CustomProfile {
+            name: "STUDY_A".to_string(),
+            commands: vec![
+                "JITTER_PERCENT:18.0".to_string(),
+                " MIRRORED:false ".to_string(),
+            ],
+            base_profile: Some("NOISY".to_string()),
+        };
+        let settings = AdvancedSettings {
+            custom_profiles: vec![study.clone()],
+            ..Default::default()
+        };
+
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.custom_profile("study_a"), Some(&study));
+        assert_eq!(settings.custom_profile("REGULAR"), None);
+        assert_eq!(
+            study.to_commands(),
+            ["JITTER_PERCENT:18.0\n", "MIRRORED:false\n"]
+        );
+
+        let names: Vec<String> = settings
+            .available_profiles()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, ["REGULAR", "NOISY", "HYBRID", "GENTLE", "STUDY_A"]);
+    }
+
+    #[test]
+    fn test_validate_custom_profiles() {
+        let profile = |name: &str, commands: &[&str], base: Option<&str>| CustomProfile {
+            name: name.to_string(),
+            commands: commands.iter().map(|c| c.to_string()).collect(),
+            base_profile: base.map(str::to_string),
+        };
+        let settings = AdvancedSettings {
+            custom_profiles: vec![
+                profile("STUDY_A", &["AMPLITUDE:80"], None),
+                profile("study a", &["SET_ROLE:PRIMARY", "DEBUG:"], Some("LOUD")),
+                profile("NOISY", &[], None),
+                profile("STUDY_A", &["LOW_POWER:true"], Some("GENTLE")),
+            ],
+            ..Default::default()
+        };
+
+        let errors = settings.validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "customProfiles[1].name",
+                "customProfiles[1].commands[0]",
+                "customProfiles[1].commands[1]",
+                "customProfiles[1].baseProfile",
+                "customProfiles[2].name",
+                "customProfiles[2].commands",
+                "customProfiles[3].name",
+            ]
+        );
+        assert!(errors[6].message.contains("more than once"));
+
+        let too_long = "A".repeat(MAX_CUSTOM_PROFILE_COMMAND_LEN);
+        let settings = AdvancedSettings {
+            custom_profiles: vec![profile("LONG", &[&format!("DEBUG:{}", too_long)], None)],
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.validate().unwrap_err()[0].field,
+            "customProfiles[0].commands[0]"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_setting() {
+        let intensity = "VIBRATION_INTENSITY:50\n";
+        assert_eq!(unsupported_setting(intensity, "2.0.3"), Some("2.1.0"));
+        assert_eq!(unsupported_setting(intensity, "2.1.0"), None);
+        assert_eq!(unsupported_setting(intensity, "dev-build"), None);
+        assert_eq!(unsupported_setting("DEBUG:true\n", "1.0.0"), None);
+    }
+
+    #[test]
+    fn test_dfu_timing_overrides() {
+        let defaults = DfuTimingConfig::default();
+        assert_eq!(DfuTimingSettings::default().to_config(), defaults);
+
+        let timing = DfuTimingSettings {
+            bootloader_timeout_ms: Some(20_000),
+            max_retries: Some(5),
+            ..Default::default()
+        };
+        let config = timing.to_config();
+        assert_eq!(config.bootloader_timeout_ms, 20_000);
+        assert_eq!(config.ack_timeout_ms, defaults.ack_timeout_ms);
+        assert_eq!(config.max_packet_retries, 5);
+        assert_eq!(config.max_config_retries, 5);
+
+        let settings: AdvancedSettings =
+            serde_json::from_str(r#"{"dfuTiming": {"ackTimeoutMs": 8000}}"#).unwrap();
+        assert_eq!(settings.dfu_timing.ack_timeout_ms, Some(8000));
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_dfu_timing() {
+        let settings = AdvancedSettings {
+            dfu_timing: DfuTimingSettings {
+                bootloader_timeout_ms: Some(500),
+                ack_timeout_ms: Some(60_000),
+                max_retries: Some(MAX_DFU_RETRIES + 1),
+                deadline_seconds: Some(10),
+            },
+            ..AdvancedSettings::default()
+        };
+
+        let fields: Vec<String> = settings
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "dfuTiming.bootloaderTimeoutMs",
+                "dfuTiming.maxRetries",
+                "dfuTiming.deadlineSeconds"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_pre_profile_commands_jitter_format() {
+        let command = |jitter: f32| {
+            let settings = AdvancedSettings {
+                jitter_percent: Some(jitter),
+                ..AdvancedSettings::default()
+            };
+            settings.to_pre_profile_commands().pop().unwrap()
+        };
+
+        assert_eq!(command(23.5), "JITTER_PERCENT:23.5\n");
+        assert_eq!(command(10.0), "JITTER_PERCENT:10.0\n");
+        assert_eq!(command(0.0), "JITTER_PERCENT:0.0\n");
+        assert_eq!(command(12.345), "JITTER_PERCENT:12.3\n");
+        assert_eq!(command(50.0), "JITTER_PERCENT:50.0\n");
+    }
+
+    #[test]
+    fn test_validate_jitter_percent() {
+        let mut settings = AdvancedSettings::default();
+
+        for jitter in [0.0, 23.5, MAX_JITTER_PERCENT] {
+            settings.jitter_percent = Some(jitter);
+            assert!(settings.validate().is_ok(), "{}", jitter);
+        }
+
+        for jitter in [-0.1, 50.1, f32::NAN, f32::INFINITY] {
+            settings.jitter_percent = Some(jitter);
+            let errors = settings.validate().unwrap_err();
+            assert_eq!(errors[0].field, "jitterPercent");
+            assert!(errors[0].message.starts_with("Jitter"));
+        }
+    }
+
+    #[test]
+    fn test_validate_max_download_rate() {
+        let mut settings = AdvancedSettings::default();
+
+        for rate in [0, MIN_DOWNLOAD_BYTES_PER_SEC, 200 * 1024] {
+            settings.max_download_bytes_per_sec = rate;
+            assert!(settings.validate().is_ok(), "{}", rate);
+        }
+
+        for rate in [1, MIN_DOWNLOAD_BYTES_PER_SEC - 1] {
+            settings.max_download_bytes_per_sec = rate;
+            let errors = settings.validate().unwrap_err();
+            assert_eq!(errors[0].field, "maxDownloadBytesPerSec");
+            assert!(errors[0].message.starts_with("Download limit"));
+        }
+    }
+
+    fn window(start: &str, end: &str) -> ScheduleWindow {
+        ScheduleWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_to_pre_profile_commands_therapy_schedule() {
+        // No windows: nothing sent, the device keeps its schedule
+        let mut settings = AdvancedSettings::default();
+        let commands = settings.to_pre_profile_commands();
+        assert!(!commands.iter().any(|c| c.starts_with("SCHEDULE_")));
+
+        settings.therapy_schedule = vec![window("09:00", "11:00")];
+        let commands = settings.to_pre_profile_commands();
+        assert_eq!(commands.len(), 5);
+        assert_eq!(commands[3], "SCHEDULE_CLEAR\n");
+        assert_eq!(commands[4], "SCHEDULE_ADD:09:00,11:00\n");
+
+        settings.therapy_schedule = vec![
+            window("09:00", "11:00"),
+            window("15:00", "17:00"),
+            window("20:30", "21:15"),
+        ];
+        settings.jitter_percent = Some(18.0);
+        let commands = settings.to_pre_profile_commands();
+        assert_eq!(
+            commands[3..],
+            [
+                "JITTER_PERCENT:18.0\n",
+                "SCHEDULE_CLEAR\n",
+                "SCHEDULE_ADD:09:00,11:00\n",
+                "SCHEDULE_ADD:15:00,17:00\n",
+                "SCHEDULE_ADD:20:30,21:15\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_therapy_schedule() {
+        // Windows may touch and be listed in any order
+        let mut settings = AdvancedSettings {
+            therapy_schedule: vec![
+                window("15:00", "17:00"),
+                window("00:00", "09:00"),
+                window("09:00", "11:00"),
+                window("23:00", "23:59"),
+            ],
+            ..AdvancedSettings::default()
+        };
+        assert!(settings.validate().is_ok());
+
+        for (start, end) in [
+            ("24:00", "24:30"),
+            ("09:60", "10:00"),
+            ("9:00", "10:00"),
+            ("09:00", "noon"),
+            ("11:00", "09:00"),
+            ("10:00", "10:00"),
+        ] {
+            settings.therapy_schedule = vec![window(start, end)];
+            let errors = settings.validate().unwrap_err();
+            assert_eq!(errors[0].field, "therapySchedule[0]", "{}-{}", start, end);
+            assert!(errors[0].message.contains("24-hour HH:MM"));
+        }
+
+        settings.therapy_schedule = vec![window("08:00", "08:30"); MAX_SCHEDULE_WINDOWS + 1];
+        let errors = settings.validate().unwrap_err();
+        assert_eq!(errors[0].field, "therapySchedule");
+        assert!(errors[0].message.contains("at most 4 windows"));
+    }
+
+    #[test]
+    fn test_validate_therapy_schedule_rejects_overlaps() {
+        let mut settings = AdvancedSettings {
+            therapy_schedule: vec![window("15:00", "17:00"), window("16:30", "18:00")],
+            ..AdvancedSettings::default()
+        };
+        let errors = settings.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "therapySchedule[1]");
+        assert_eq!(
+            errors[0].message,
+            "Therapy window 16:30-18:00 overlaps 15:00-17:00"
+        );
+
+        // A long window overlapping one that isn't next to it is caught too
+        settings.therapy_schedule = vec![
+            window("08:00", "20:00"),
+            window("09:00", "10:00"),
+            window("12:00", "13:00"),
+        ];
+        let errors = settings.validate().unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["therapySchedule[1]", "therapySchedule[2]"]);
+    }
+
+    #[test]
+    fn test_settings_persistence() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+
+        // Initially returns defaults
+        let loaded = manager.load_snapshot().unwrap().settings;
+        assert_eq!(loaded, AdvancedSettings::default());
+
+        // Save custom settings
+        let settings = AdvancedSettings {
+            disable_led_during_therapy: true,
+            debug_mode: true,
+            selected_profile: Some("REGULAR".to_string()),
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: Some(90),
+            jitter_percent: Some(23.5),
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
+        };
+        manager.save(&settings).unwrap();
+
+        // Load returns saved settings
+        let loaded = manager.load_snapshot().unwrap().settings;
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn test_has_non_default_settings() {
+        let default = AdvancedSettings::default();
+        assert!(!default.has_non_default_settings());
+
+        let custom_led = AdvancedSettings {
+            disable_led_during_therapy: true,
+            debug_mode: false,
+            selected_profile: None,
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
+        };
+        assert!(custom_led.has_non_default_settings());
+
+        let custom_debug = AdvancedSettings {
+            disable_led_during_therapy: false,
+            debug_mode: true,
+            selected_profile: None,
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
+        };
         assert!(custom_debug.has_non_default_settings());
 
         let custom_profile = AdvancedSettings {
             disable_led_during_therapy: false,
             debug_mode: false,
             selected_profile: Some("NOISY".to_string()),
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
         };
         assert!(custom_profile.has_non_default_settings());
+
+        let custom_low_power = AdvancedSettings {
+            low_power_mode: true,
+            ..AdvancedSettings::default()
+        };
+        assert!(custom_low_power.has_non_default_settings());
     }
 
     #[test]
-    fn test_load_corrupted_settings_returns_defaults() {
+    fn test_load_corrupted_settings_without_backup_fails() {
         let dir = tempdir().unwrap();
         let settings_file = dir.path().join("advanced_settings.json");
 
@@ -309,11 +2116,422 @@ mod tests {
         fs::write(&settings_file, "{ not valid json!!!").unwrap();
 
         let manager = SettingsManager::new(dir.path());
-        let result = manager.load();
+        let err = manager.load_snapshot().unwrap_err();
+        assert!(err.contains("unreadable"), "{}", err);
+
+        // The original is kept for inspection
+        let preserved = fs::read_to_string(dir.path().join("advanced_settings.json.unrecognized"));
+        assert_eq!(preserved.unwrap(), "{ not valid json!!!");
+
+        // Saving still replaces the unreadable file
+        manager.save(&AdvancedSettings::default()).unwrap();
+        assert_eq!(
+            manager.load_snapshot().unwrap().settings,
+            AdvancedSettings::default()
+        );
+    }
+
+    #[test]
+    fn test_load_empty_settings_without_backup_uses_defaults() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("advanced_settings.json"), "").unwrap();
+
+        let manager = SettingsManager::new(dir.path());
+
+        assert_eq!(
+            manager.load_snapshot().unwrap(),
+            SettingsSnapshot::default()
+        );
+    }
+
+    #[test]
+    fn test_load_fails_when_settings_and_backup_corrupt() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+        manager.save(&AdvancedSettings::default()).unwrap();
+
+        fs::write(dir.path().join("advanced_settings.json"), "{\"schema_ver").unwrap();
+        fs::write(dir.path().join("advanced_settings.json.bak"), "").unwrap();
+
+        assert!(manager.load_snapshot().is_err());
+    }
+
+    #[test]
+    fn test_load_settings_without_newer_fields() {
+        let dir = tempdir().unwrap();
+        let settings_file = dir.path().join("advanced_settings.json");
+
+        // Written by a version that predates low power mode
+        fs::write(
+            &settings_file,
+            r#"{"disableLedDuringTherapy": true, "debugMode": false}"#,
+        )
+        .unwrap();
+
+        let loaded = SettingsManager::new(dir.path())
+            .load_snapshot()
+            .unwrap()
+            .settings;
+        assert!(loaded.disable_led_during_therapy);
+        assert!(!loaded.low_power_mode);
+        assert_eq!(loaded.vibration_intensity, None);
+    }
+
+    #[test]
+    fn test_save_writes_schema_version() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+
+        manager.save(&AdvancedSettings::default()).unwrap();
+
+        let contents = fs::read_to_string(dir.path().join("advanced_settings.json")).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["settings"]["debugMode"], false);
+    }
+
+    #[test]
+    fn test_load_migrates_unversioned_settings() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+
+        // Version 1: the settings object on its own
+        fs::write(
+            dir.path().join("advanced_settings.json"),
+            r#"{"disableLedDuringTherapy": true, "selectedProfile": "NOISY"}"#,
+        )
+        .unwrap();
+
+        let loaded = manager.load_snapshot().unwrap().settings;
+        assert!(loaded.disable_led_during_therapy);
+        assert_eq!(loaded.selected_profile.as_deref(), Some("NOISY"));
+        let preserved = dir.path().join("advanced_settings.json.unrecognized");
+        assert!(!preserved.exists());
+    }
+
+    #[test]
+    fn test_migrate_v1_to_v2() {
+        let v1 = serde_json::json!({ "debugMode": true });
+        assert_eq!(
+            migrate_v1_to_v2(v1),
+            serde_json::json!({ "schema_version": 2, "settings": { "debugMode": true } })
+        );
+    }
+
+    #[test]
+    fn test_migrate_v2_to_v3() {
+        let v2 = serde_json::json!({ "schema_version": 2, "settings": { "debugMode": true } });
+        assert_eq!(
+            migrate_v2_to_v3(v2),
+            serde_json::json!({
+                "schema_version": 3,
+                "settings": { "debugMode": true },
+                "devices": {}
+            })
+        );
+    }
+
+    #[test]
+    fn test_device_settings_resolve() {
+        let global = AdvancedSettings {
+            disable_led_during_therapy: true,
+            vibration_intensity: Some(80),
+            ..AdvancedSettings::default()
+        };
+        let overrides = DeviceSettings {
+            debug_mode: Some(true),
+            vibration_intensity: Some(40),
+            ..DeviceSettings::default()
+        };
+
+        let (settings, layers) = overrides.resolve(&global);
+        assert!(settings.disable_led_during_therapy);
+        assert!(settings.debug_mode);
+        assert_eq!(settings.vibration_intensity, Some(40));
+        assert!(layers.contains(&"Disable LED During Therapy = true (global)".to_string()));
+        assert!(layers.contains(&"Debug Mode = true (device)".to_string()));
+        assert!(layers.contains(&"Vibration Intensity = 40 (device)".to_string()));
+        assert!(layers.contains(&"Session Duration = not set (global)".to_string()));
+
+        // No overrides leaves the global settings as they are
+        let (settings, _) = DeviceSettings::default().resolve(&global);
+        assert_eq!(settings, global);
+    }
+
+    #[test]
+    fn test_reset_settings() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+
+        let global = AdvancedSettings {
+            debug_mode: true,
+            ..AdvancedSettings::default()
+        };
+        let overrides = DeviceSettings {
+            low_power_mode: Some(true),
+            ..DeviceSettings::default()
+        };
+        manager.save(&global).unwrap();
+        manager.save_device("ABC123", &overrides).unwrap();
+        manager.save_device("DEF456", &overrides).unwrap();
+
+        // A device reset keeps the global settings and other devices
+        let scope = SettingsScope::Device {
+            serial_number: "ABC123".to_string(),
+        };
+        let result = manager.reset(&scope).unwrap();
+        assert_eq!(result.effective, global);
+        assert_eq!(result.previous.global, None);
+        assert_eq!(result.previous.devices.get("ABC123"), Some(&overrides));
+        assert!(manager.load_snapshot().unwrap().device("ABC123").is_empty());
+        assert_eq!(manager.load_snapshot().unwrap().device("DEF456"), overrides);
+
+        // A global reset keeps device overrides
+        let result = manager.reset(&SettingsScope::Global).unwrap();
+        assert_eq!(result.effective, AdvancedSettings::default());
+        assert_eq!(result.previous.global, Some(global.clone()));
+        assert_eq!(
+            manager.load_snapshot().unwrap().settings,
+            AdvancedSettings::default()
+        );
+        assert_eq!(manager.load_snapshot().unwrap().device("DEF456"), overrides);
+
+        // Everything
+        manager.save(&global).unwrap();
+        let result = manager.reset(&SettingsScope::All).unwrap();
+        assert_eq!(result.previous.global, Some(global));
+        assert_eq!(result.previous.devices.len(), 1);
+        assert!(manager.load_snapshot().unwrap().device("DEF456").is_empty());
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let source = tempdir().unwrap();
+        let manager = SettingsManager::new(source.path());
+        let settings = AdvancedSettings {
+            debug_mode: true,
+            github_token: Some("ghp_secret".to_string()),
+            proxy: ProxySettings {
+                mode: crate::proxy::ProxyMode::Manual,
+                url: Some("http://proxy.hospital.local:8080".to_string()),
+                username: Some("nurse".to_string()),
+                password: Some("hunter2".to_string()),
+            },
+            ..AdvancedSettings::default()
+        };
+        manager.save(&settings).unwrap();
+        let overrides = DeviceSettings {
+            low_power_mode: Some(true),
+            ..DeviceSettings::default()
+        };
+        manager.save_device("ABC123", &overrides).unwrap();
+
+        let export_path = source.path().join("export.json");
+        manager.export(&export_path, false).unwrap();
+
+        let contents = fs::read_to_string(&export_path).unwrap();
+        assert!(!contents.contains("ghp_secret"));
+        assert!(!contents.contains("hunter2"));
+        let json: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["app_version"], env!("CARGO_PKG_VERSION"));
+
+        // Importing on a fresh machine brings everything but the secrets
+        let target = tempdir().unwrap();
+        let target_manager = SettingsManager::new(target.path());
+        let summary = target_manager.import(&export_path).unwrap();
+        assert_eq!(summary.schema_version, SCHEMA_VERSION as u64);
+        assert!(summary.unknown_keys.is_empty());
+        assert!(summary.changed.contains(&"settings.debugMode".to_string()));
+        assert!(summary
+            .changed
+            .contains(&"devices.ABC123.lowPowerMode".to_string()));
+
+        let imported = target_manager.load_snapshot().unwrap().settings;
+        assert!(imported.debug_mode);
+        assert_eq!(imported.github_token, None);
+        assert_eq!(imported.proxy.password, None);
+        assert_eq!(
+            target_manager.load_snapshot().unwrap().device("ABC123"),
+            overrides
+        );
+
+        // Importing back keeps the secrets already saved here
+        let summary = manager.import(&export_path).unwrap();
+        assert!(summary.changed.is_empty(), "{:?}", summary.changed);
+        assert_eq!(manager.load_snapshot().unwrap().settings, settings);
+    }
+
+    #[test]
+    fn test_import_reports_unknown_keys_and_migrates() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+        let path = dir.path().join("old.json");
+
+        // A bare version 1 settings file with a key this version lacks
+        fs::write(&path, r#"{"debugMode": true, "logLevel": "trace"}"#).unwrap();
+
+        let summary = manager.import(&path).unwrap();
+        assert_eq!(summary.schema_version, 1);
+        assert_eq!(summary.app_version, None);
+        assert_eq!(summary.unknown_keys, vec!["settings.logLevel"]);
+        assert_eq!(summary.changed, vec!["settings.debugMode"]);
+        assert!(manager.load_snapshot().unwrap().settings.debug_mode);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_settings_without_writing() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+        manager.save(&AdvancedSettings::default()).unwrap();
+        let path = dir.path().join("bad.json");
+
+        let document = format!(
+            r#"{{"schema_version": {}, "settings": {{"vibrationIntensity": 150}}}}"#,
+            SCHEMA_VERSION
+        );
+        fs::write(&path, document).unwrap();
+        let err = manager.import(&path).unwrap_err();
+        assert!(err.contains("Vibration intensity"), "{}", err);
+
+        let future = r#"{"schema_version": 99, "settings": {}}"#;
+        fs::write(&path, future).unwrap();
+        let err = manager.import(&path).unwrap_err();
+        assert!(err.contains("Unrecognized"), "{}", err);
+
+        assert_eq!(
+            manager.load_snapshot().unwrap().settings,
+            AdvancedSettings::default()
+        );
+    }
+
+    #[test]
+    fn test_settings_scope_serde() {
+        let scope: SettingsScope =
+            serde_json::from_str(r#"{"scope": "device", "serial_number": "ABC123"}"#).unwrap();
+        assert_eq!(
+            scope,
+            SettingsScope::Device {
+                serial_number: "ABC123".to_string()
+            }
+        );
+        let scope: SettingsScope = serde_json::from_str(r#"{"scope": "all"}"#).unwrap();
+        assert_eq!(scope, SettingsScope::All);
+    }
+
+    #[test]
+    fn test_device_settings_persistence() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+
+        let global = AdvancedSettings {
+            disable_led_during_therapy: true,
+            ..AdvancedSettings::default()
+        };
+        manager.save(&global).unwrap();
+
+        let overrides = DeviceSettings {
+            disable_led_during_therapy: Some(false),
+            ..DeviceSettings::default()
+        };
+        manager.save_device("ABC123", &overrides).unwrap();
+
+        // Saving the global settings keeps the device overrides
+        manager.save(&global).unwrap();
+        assert_eq!(manager.load_snapshot().unwrap().device("ABC123"), overrides);
+        assert_eq!(manager.load_snapshot().unwrap().settings, global);
+
+        let (effective, _) = manager.load_snapshot().unwrap().effective(Some("ABC123"));
+        assert!(!effective.disable_led_during_therapy);
+        let (effective, _) = manager.load_snapshot().unwrap().effective(Some("OTHER"));
+        assert!(effective.disable_led_during_therapy);
+        let (effective, _) = manager.load_snapshot().unwrap().effective(None);
+        assert!(effective.disable_led_during_therapy);
+
+        // Empty overrides remove the entry
+        let cleared = DeviceSettings::default();
+        manager.save_device("ABC123", &cleared).unwrap();
+        assert!(manager.load_snapshot().unwrap().device("ABC123").is_empty());
+    }
+
+    #[test]
+    fn test_load_future_schema_version_preserves_file() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+        let settings_file = dir.path().join("advanced_settings.json");
+
+        let future = format!(
+            r#"{{"schema_version": {}, "settings": {{"logLevel": "trace"}}}}"#,
+            SCHEMA_VERSION + 1
+        );
+        fs::write(&settings_file, &future).unwrap();
+
+        assert_eq!(
+            manager.load_snapshot().unwrap().settings,
+            AdvancedSettings::default()
+        );
+        let preserved = dir.path().join("advanced_settings.json.unrecognized");
+        assert_eq!(fs::read_to_string(&preserved).unwrap(), future);
+
+        // Saving replaces the settings file but not the preserved copy
+        manager.save(&AdvancedSettings::default()).unwrap();
+        assert_eq!(fs::read_to_string(&preserved).unwrap(), future);
+    }
+
+    #[test]
+    fn test_load_truncated_settings_recovers_from_backup() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+        let settings_file = dir.path().join("advanced_settings.json");
+
+        let settings = AdvancedSettings {
+            disable_led_during_therapy: true,
+            debug_mode: true,
+            selected_profile: Some("NOISY".to_string()),
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
+        };
+        manager.save(&settings).unwrap();
+
+        // Simulate a crash mid-write leaving a truncated file
+        let contents = fs::read_to_string(&settings_file).unwrap();
+        fs::write(&settings_file, &contents[..contents.len() / 2]).unwrap();
+
+        assert_eq!(manager.load_snapshot().unwrap().settings, settings);
+
+        // Or one whose data never reached the disk
+        fs::write(&settings_file, "").unwrap();
+        assert_eq!(manager.load_snapshot().unwrap().settings, settings);
+    }
+
+    #[test]
+    fn test_save_keeps_backup_of_last_save() {
+        let dir = tempdir().unwrap();
+        let manager = SettingsManager::new(dir.path());
+
+        let settings = AdvancedSettings {
+            debug_mode: true,
+            ..AdvancedSettings::default()
+        };
+        manager.save(&settings).unwrap();
 
-        // Graceful recovery: returns defaults instead of error
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), AdvancedSettings::default());
+        assert_eq!(manager.load_snapshot().unwrap().settings, settings);
+        let backup = fs::read_to_string(dir.path().join("advanced_settings.json.bak")).unwrap();
+        assert_eq!(decode_settings(&backup).unwrap().settings, settings);
+        assert!(!dir.path().join("advanced_settings.json.bak.tmp").exists());
+        let unrecognized = dir.path().join("advanced_settings.json.unrecognized");
+        assert!(!unrecognized.exists());
     }
 
     #[test]
@@ -325,6 +2543,19 @@ mod tests {
             disable_led_during_therapy: true,
             debug_mode: false,
             selected_profile: Some("REGULAR".to_string()),
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: None,
+            low_power_mode: false,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
         };
         manager.save(&settings).unwrap();
 
@@ -334,12 +2565,107 @@ mod tests {
         assert!(!dir.path().join("advanced_settings.json.tmp").exists());
     }
 
+    #[test]
+    fn test_settings_service_writes_through() {
+        let dir = tempdir().unwrap();
+        let service = SettingsService::load(dir.path());
+        assert_eq!(service.settings().unwrap(), AdvancedSettings::default());
+
+        let settings = AdvancedSettings {
+            debug_mode: true,
+            ..AdvancedSettings::default()
+        };
+        let snapshot = service.save(&settings).unwrap();
+        assert_eq!(snapshot.settings, settings);
+        assert_eq!(service.settings().unwrap(), settings);
+
+        let overrides = DeviceSettings {
+            debug_mode: Some(false),
+            ..DeviceSettings::default()
+        };
+        let snapshot = service.save_device("ABC123", &overrides).unwrap();
+        assert_eq!(snapshot.device("ABC123"), overrides);
+
+        // A fresh load sees what the service wrote
+        let reloaded = SettingsService::load(dir.path()).snapshot().unwrap();
+        assert_eq!(*reloaded, *snapshot);
+
+        let export_path = dir.path().join("export.json");
+        service.export(&export_path, false).unwrap();
+        let exported: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&export_path).unwrap()).unwrap();
+        assert_eq!(exported["settings"]["debugMode"], true);
+
+        let (_, snapshot) = service
+            .write(|manager| manager.reset(&SettingsScope::All))
+            .unwrap();
+        assert_eq!(*snapshot, SettingsSnapshot::default());
+        assert_eq!(*service.snapshot().unwrap(), SettingsSnapshot::default());
+    }
+
+    #[test]
+    fn test_settings_service_falls_back_to_defaults_when_unreadable() {
+        let dir = tempdir().unwrap();
+        let settings_file = dir.path().join("advanced_settings.json");
+        fs::write(&settings_file, "{ not valid json").unwrap();
+
+        let service = SettingsService::load(dir.path());
+
+        assert!(service.settings().is_err());
+        assert_eq!(service.settings_or_default(), AdvancedSettings::default());
+        assert_eq!(*service.snapshot_or_default(), SettingsSnapshot::default());
+    }
+
+    #[test]
+    fn test_settings_service_reads_during_save_are_never_torn() {
+        let dir = tempdir().unwrap();
+        let service = SettingsService::load(dir.path());
+
+        let old = AdvancedSettings::default();
+        let new = AdvancedSettings {
+            disable_led_during_therapy: true,
+            debug_mode: true,
+            selected_profile: Some("NOISY".to_string()),
+            low_power_mode: true,
+            ..AdvancedSettings::default()
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..200 {
+                        let settings = service.settings().unwrap();
+                        assert!(settings == old || settings == new);
+                    }
+                });
+            }
+
+            for i in 0..20 {
+                let settings = if i % 2 == 0 { &new } else { &old };
+                service.save(settings).unwrap();
+            }
+        });
+    }
+
     #[test]
     fn test_serde_camel_case() {
         let settings = AdvancedSettings {
             disable_led_during_therapy: true,
             debug_mode: true,
             selected_profile: Some("REGULAR".to_string()),
+            github_token: None,
+            release_channel: ReleaseChannel::Stable,
+            app_update_channel: ReleaseChannel::Stable,
+            proxy: ProxySettings::default(),
+            max_download_bytes_per_sec: 0,
+            developer_mode: false,
+            vibration_intensity: Some(60),
+            low_power_mode: true,
+            session_duration_minutes: None,
+            jitter_percent: None,
+            custom_profiles: Vec::new(),
+            dfu_timing: DfuTimingSettings::default(),
+            therapy_schedule: Vec::new(),
         };
         let json = serde_json::to_string(&settings).unwrap();
 
@@ -350,5 +2676,9 @@ mod tests {
         assert!(!json.contains("debug_mode"));
         assert!(json.contains("selectedProfile"));
         assert!(!json.contains("selected_profile"));
+        assert!(json.contains("vibrationIntensity"));
+        assert!(!json.contains("vibration_intensity"));
+        assert!(json.contains("lowPowerMode"));
+        assert!(!json.contains("low_power_mode"));
     }
 }
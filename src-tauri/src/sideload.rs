@@ -0,0 +1,714 @@
+//! Importing and exporting firmware packages as local files.
+//!
+//! Air-gapped machines can't reach GitHub, so firmware arrives on a USB
+//! stick. Importing validates the package and places it in the firmware
+//! cache exactly like a downloaded version, so the flash flow doesn't
+//! need to know where it came from.
+//!
+//! A `.bbfw` bundle is a zip holding the cached `firmware.zip` alongside a
+//! `metadata.json` with its hash and release notes, so a machine with
+//! internet can hand a version to one without.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::cache::{AssetKind, CacheManager, CachedFirmwareMetadata};
+use crate::dfu::read_firmware_zip;
+use crate::download::checksum_matches;
+use crate::releases::ReleaseChannel;
+
+/// Release notes used when the user doesn't provide any.
+const DEFAULT_IMPORT_NOTES: &str = "Imported from local file";
+
+/// File extension of exported firmware bundles.
+pub const BUNDLE_EXTENSION: &str = "bbfw";
+
+/// Bundle entry holding the firmware package.
+const BUNDLE_FIRMWARE_ENTRY: &str = "firmware.zip";
+
+/// Bundle entry holding the [`BundleMetadata`] JSON.
+const BUNDLE_METADATA_ENTRY: &str = "metadata.json";
+
+/// Metadata stored alongside the firmware package in a `.bbfw` bundle.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleMetadata {
+    pub version: String,
+    pub tag_name: String,
+    pub sha256_hash: String,
+    pub published_at: String,
+    pub release_notes: String,
+    /// Absent in bundles exported before release channels existed.
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+}
+
+/// Check that a version string is safe to use as a file name in the cache.
+pub fn is_valid_version(version: &str) -> bool {
+    !version.is_empty()
+        && version != "."
+        && version != ".."
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'))
+}
+
+/// Check whether a path names a `.bbfw` bundle rather than a plain zip.
+pub fn is_bundle(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case(BUNDLE_EXTENSION))
+        .unwrap_or(false)
+}
+
+/// Validate a local firmware.zip or `.bbfw` bundle and add it to the firmware cache.
+///
+/// `version` is required for plain zips; for bundles it defaults to the
+/// version recorded in the bundle. Bundles whose embedded hash doesn't match
+/// the contained zip are refused.
+///
+/// The package is staged as `<app_data_dir>/firmware/<version>.zip.tmp` and
+/// only renamed into place once it validates, so a failed import never
+/// leaves a half-written cache entry. The index entry is marked as locally
+/// imported.
+///
+/// A version that is already cached is only replaced with `overwrite`, and
+/// a pinned one not at all until it is unpinned.
+pub fn import_firmware(
+    app_data_dir: &Path,
+    cache_manager: &CacheManager,
+    source: &Path,
+    version: Option<&str>,
+    release_notes: Option<String>,
+    overwrite: bool,
+) -> Result<CachedFirmwareMetadata, String> {
+    let firmware_dir = app_data_dir.join("firmware");
+
+    let (version, tmp_file, bundle) = if is_bundle(source) {
+        let mut archive = open_bundle(source)?;
+        let bundle = read_bundle_metadata(&mut archive)?;
+        let version = version.unwrap_or(&bundle.version).to_string();
+        let tmp_file = staging_path(&firmware_dir, &version)?;
+        check_replaceable(cache_manager, &version, overwrite)?;
+
+        extract_bundle_firmware(&mut archive, &tmp_file)?;
+
+        let actual_hash = CacheManager::calculate_sha256(&tmp_file).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp_file);
+        })?;
+        if !checksum_matches(&actual_hash, &bundle.sha256_hash) {
+            let _ = fs::remove_file(&tmp_file);
+            return Err(format!(
+                "Bundle checksum mismatch: expected {}, got {}",
+                bundle.sha256_hash, actual_hash
+            ));
+        }
+
+        (version, tmp_file, Some(bundle))
+    } else {
+        let version = version
+            .ok_or("A firmware version is required to import a zip")?
+            .to_string();
+        let tmp_file = staging_path(&firmware_dir, &version)?;
+        check_replaceable(cache_manager, &version, overwrite)?;
+
+        fs::copy(source, &tmp_file).map_err(|e| {
+            let _ = fs::remove_file(&tmp_file);
+            format!("Failed to copy firmware file: {}", e)
+        })?;
+
+        (version, tmp_file, None)
+    };
+
+    // Reject anything the DFU flow couldn't flash
+    if let Err(e) = read_firmware_zip(&tmp_file) {
+        let _ = fs::remove_file(&tmp_file);
+        return Err(format!("Invalid firmware package: {}", e));
+    }
+
+    let sha256_hash = CacheManager::calculate_sha256(&tmp_file).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp_file);
+    })?;
+
+    // The replaced version's files may not be where the new zip goes
+    if cache_manager.get_entry(&version)?.is_some() {
+        cache_manager
+            .delete_version(&firmware_dir, &version)
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&tmp_file);
+            })?;
+    }
+
+    let firmware_file = firmware_dir.join(format!("{}.zip", version));
+    fs::rename(&tmp_file, &firmware_file).map_err(|e| {
+        let _ = fs::remove_file(&tmp_file);
+        format!("Failed to finalize firmware file: {}", e)
+    })?;
+
+    let file_size = fs::metadata(&firmware_file)
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?
+        .len();
+
+    let metadata = CachedFirmwareMetadata {
+        tag_name: bundle
+            .as_ref()
+            .map(|bundle| bundle.tag_name.clone())
+            .unwrap_or_else(|| version.clone()),
+        version,
+        sha256_hash,
+        zip_path: firmware_file.to_string_lossy().to_string(),
+        downloaded_at: chrono::Utc::now().to_rfc3339(),
+        file_size,
+        // Unknown for plain zips
+        published_at: bundle
+            .as_ref()
+            .map(|bundle| bundle.published_at.clone())
+            .unwrap_or_default(),
+        release_notes: release_notes
+            .or_else(|| bundle.as_ref().map(|bundle| bundle.release_notes.clone()))
+            .filter(|notes| !notes.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_IMPORT_NOTES.to_string()),
+        checksum_verified: false,
+        locally_imported: true,
+        pinned: false,
+        last_used_at: None,
+        channel: bundle.map(|bundle| bundle.channel).unwrap_or_default(),
+        // read_firmware_zip accepted it above, so it is a DFU package
+        asset_kind: AssetKind::DfuPackage,
+    };
+
+    cache_manager.update_entry(metadata.clone())?;
+
+    Ok(metadata)
+}
+
+/// Refuse to import over a cached `version` unless `overwrite` is set, and
+/// over a pinned one in any case.
+fn check_replaceable(
+    cache_manager: &CacheManager,
+    version: &str,
+    overwrite: bool,
+) -> Result<(), String> {
+    match cache_manager.get_entry(version)? {
+        Some(existing) if existing.pinned => Err(format!(
+            "Firmware {} is pinned; unpin it before replacing it",
+            version
+        )),
+        Some(_) if !overwrite => Err(format!(
+            "Firmware {} is already cached; import it with overwrite to replace it",
+            version
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Export a cached version as a `.bbfw` bundle in `dest_dir`.
+///
+/// The cached zip is re-hashed first so a corrupted cache entry is never
+/// handed to another machine. Returns the path of the written bundle.
+pub fn export_bundle(
+    cache_manager: &CacheManager,
+    version: &str,
+    dest_dir: &Path,
+) -> Result<PathBuf, String> {
+    let entry = cache_manager
+        .get_entry(version)?
+        .ok_or_else(|| format!("Firmware version {} is not cached", version))?;
+
+    let zip_path = Path::new(&entry.zip_path);
+    if !zip_path.exists() {
+        return Err(format!("Cached firmware file for {} is missing", version));
+    }
+
+    let sha256_hash = CacheManager::calculate_sha256(zip_path)?;
+    if sha256_hash != entry.sha256_hash {
+        return Err(format!(
+            "Cached firmware {} failed hash verification; download it again before exporting",
+            version
+        ));
+    }
+
+    let bundle = BundleMetadata {
+        version: entry.version.clone(),
+        tag_name: entry.tag_name.clone(),
+        sha256_hash,
+        published_at: entry.published_at.clone(),
+        release_notes: entry.release_notes.clone(),
+        channel: entry.channel,
+    };
+
+    let bundle_path = dest_dir.join(format!(
+        "bluebuzzah-firmware-{}.{}",
+        version, BUNDLE_EXTENSION
+    ));
+    let tmp_path = dest_dir.join(format!(
+        "bluebuzzah-firmware-{}.{}.tmp",
+        version, BUNDLE_EXTENSION
+    ));
+
+    write_bundle(&tmp_path, &bundle, zip_path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp_path);
+    })?;
+
+    fs::rename(&tmp_path, &bundle_path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Failed to finalize firmware bundle: {}", e)
+    })?;
+
+    Ok(bundle_path)
+}
+
+/// Validate `version` and return the staging path for it in `firmware_dir`.
+fn staging_path(firmware_dir: &Path, version: &str) -> Result<PathBuf, String> {
+    if !is_valid_version(version) {
+        return Err(format!("Invalid firmware version: {:?}", version));
+    }
+
+    fs::create_dir_all(firmware_dir)
+        .map_err(|e| format!("Failed to create firmware directory: {}", e))?;
+
+    Ok(firmware_dir.join(format!("{}.zip.tmp", version)))
+}
+
+fn open_bundle(path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open firmware bundle: {}", e))?;
+    ZipArchive::new(file).map_err(|e| format!("Invalid firmware bundle: {}", e))
+}
+
+fn read_bundle_metadata(archive: &mut ZipArchive<File>) -> Result<BundleMetadata, String> {
+    let entry = archive.by_name(BUNDLE_METADATA_ENTRY).map_err(|e| {
+        format!(
+            "Invalid firmware bundle: missing {}: {}",
+            BUNDLE_METADATA_ENTRY, e
+        )
+    })?;
+
+    serde_json::from_reader(entry).map_err(|e| {
+        format!(
+            "Invalid firmware bundle: bad {}: {}",
+            BUNDLE_METADATA_ENTRY, e
+        )
+    })
+}
+
+fn extract_bundle_firmware(archive: &mut ZipArchive<File>, dest: &Path) -> Result<(), String> {
+    let mut entry = archive.by_name(BUNDLE_FIRMWARE_ENTRY).map_err(|e| {
+        format!(
+            "Invalid firmware bundle: missing {}: {}",
+            BUNDLE_FIRMWARE_ENTRY, e
+        )
+    })?;
+
+    let mut out = File::create(dest).map_err(|e| format!("Failed to create file: {}", e))?;
+    io::copy(&mut entry, &mut out).map_err(|e| {
+        let _ = fs::remove_file(dest);
+        format!("Failed to extract firmware from bundle: {}", e)
+    })?;
+
+    Ok(())
+}
+
+fn write_bundle(path: &Path, bundle: &BundleMetadata, firmware_zip: &Path) -> Result<(), String> {
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create firmware bundle: {}", e))?;
+    let mut writer = ZipWriter::new(file);
+    // The firmware zip is already compressed
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    let json = serde_json::to_string_pretty(bundle)
+        .map_err(|e| format!("Failed to serialize bundle metadata: {}", e))?;
+    writer
+        .start_file(BUNDLE_METADATA_ENTRY, options)
+        .map_err(|e| format!("Failed to write firmware bundle: {}", e))?;
+    writer
+        .write_all(json.as_bytes())
+        .map_err(|e| format!("Failed to write firmware bundle: {}", e))?;
+
+    let mut firmware =
+        File::open(firmware_zip).map_err(|e| format!("Failed to open cached firmware: {}", e))?;
+    writer
+        .start_file(BUNDLE_FIRMWARE_ENTRY, options)
+        .map_err(|e| format!("Failed to write firmware bundle: {}", e))?;
+    io::copy(&mut firmware, &mut writer)
+        .map_err(|e| format!("Failed to write firmware bundle: {}", e))?;
+
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to write firmware bundle: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const VALID_MANIFEST: &str = r#"{
+        "manifest": {
+            "application": {
+                "bin_file": "firmware.bin",
+                "dat_file": "firmware.dat",
+                "init_packet_data": {
+                    "application_version": 4294967295,
+                    "device_revision": 65535,
+                    "device_type": 82,
+                    "firmware_crc16": 18974,
+                    "softdevice_req": [182]
+                }
+            },
+            "dfu_version": 0.5
+        }
+    }"#;
+
+    fn create_dfu_zip(path: &Path) {
+        let file = fs::File::create(path).unwrap();
+        let mut zip = ZipWriter::new(file);
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+        zip.start_file("manifest.json", options).unwrap();
+        zip.write_all(VALID_MANIFEST.as_bytes()).unwrap();
+        zip.start_file("firmware.bin", options).unwrap();
+        zip.write_all(&[0x01, 0x02, 0x03, 0x04]).unwrap();
+        zip.start_file("firmware.dat", options).unwrap();
+        zip.write_all(&[0x0A, 0x0B, 0x0C]).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_is_valid_version() {
+        assert!(is_valid_version("1.2.0"));
+        assert!(is_valid_version("v2.0.0-beta_1+build"));
+        assert!(!is_valid_version(""));
+        assert!(!is_valid_version(".."));
+        assert!(!is_valid_version("../1.0.0"));
+        assert!(!is_valid_version("1.0/evil"));
+        assert!(!is_valid_version("1.0\\evil"));
+    }
+
+    #[test]
+    fn test_import_valid_zip() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let usb_dir = TempDir::new().unwrap();
+        let source = usb_dir.path().join("firmware.zip");
+        create_dfu_zip(&source);
+
+        let metadata = import_firmware(
+            app_dir.path(),
+            &cache_manager,
+            &source,
+            Some("2.1.0"),
+            Some("Clinic build".to_string()),
+            false,
+        )
+        .unwrap();
+
+        let cached = app_dir.path().join("firmware").join("2.1.0.zip");
+        assert!(cached.exists());
+        assert!(!app_dir
+            .path()
+            .join("firmware")
+            .join("2.1.0.zip.tmp")
+            .exists());
+        assert!(metadata.locally_imported);
+        assert!(!metadata.checksum_verified);
+        assert_eq!(metadata.release_notes, "Clinic build");
+        assert_eq!(
+            metadata.sha256_hash,
+            CacheManager::calculate_sha256(&source).unwrap()
+        );
+
+        // Indexed like any downloaded version
+        let entry = cache_manager.get_entry("2.1.0").unwrap().unwrap();
+        assert_eq!(entry.zip_path, cached.to_string_lossy());
+        assert!(cache_manager.verify_hash("2.1.0").unwrap());
+    }
+
+    #[test]
+    fn test_import_over_cached_version_needs_overwrite() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let usb_dir = TempDir::new().unwrap();
+        let source = usb_dir.path().join("firmware.zip");
+        create_dfu_zip(&source);
+        let import = |notes: &str, overwrite| {
+            import_firmware(
+                app_dir.path(),
+                &cache_manager,
+                &source,
+                Some("2.1.0"),
+                Some(notes.to_string()),
+                overwrite,
+            )
+        };
+
+        import("First", false).unwrap();
+        let err = import("Second", false).unwrap_err();
+        assert!(err.contains("already cached"));
+        assert_eq!(
+            cache_manager
+                .get_entry("2.1.0")
+                .unwrap()
+                .unwrap()
+                .release_notes,
+            "First"
+        );
+
+        import("Second", true).unwrap();
+        assert_eq!(
+            cache_manager
+                .get_entry("2.1.0")
+                .unwrap()
+                .unwrap()
+                .release_notes,
+            "Second"
+        );
+
+        // Pinned versions are never replaced
+        cache_manager.set_pinned("2.1.0", true).unwrap();
+        let err = import("Third", true).unwrap_err();
+        assert!(err.contains("pinned"));
+        let entry = cache_manager.get_entry("2.1.0").unwrap().unwrap();
+        assert!(entry.pinned);
+        assert_eq!(entry.release_notes, "Second");
+        assert!(!app_dir
+            .path()
+            .join("firmware")
+            .join("2.1.0.zip.tmp")
+            .exists());
+    }
+
+    #[test]
+    fn test_import_defaults_release_notes() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let source = app_dir.path().join("source.zip");
+        create_dfu_zip(&source);
+
+        let metadata = import_firmware(
+            app_dir.path(),
+            &cache_manager,
+            &source,
+            Some("1.0.0"),
+            None,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.release_notes, DEFAULT_IMPORT_NOTES);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_package() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let source = app_dir.path().join("not-firmware.zip");
+        fs::write(&source, b"definitely not a zip").unwrap();
+
+        let result = import_firmware(
+            app_dir.path(),
+            &cache_manager,
+            &source,
+            Some("1.0.0"),
+            None,
+            false,
+        );
+
+        assert!(result.unwrap_err().starts_with("Invalid firmware package"));
+        assert!(!app_dir.path().join("firmware").join("1.0.0.zip").exists());
+        assert!(!app_dir
+            .path()
+            .join("firmware")
+            .join("1.0.0.zip.tmp")
+            .exists());
+        assert!(cache_manager.get_entry("1.0.0").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_import_rejects_unsafe_version() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let source = app_dir.path().join("source.zip");
+        create_dfu_zip(&source);
+
+        let result = import_firmware(
+            app_dir.path(),
+            &cache_manager,
+            &source,
+            Some("../escape"),
+            None,
+            false,
+        );
+
+        assert!(result.unwrap_err().starts_with("Invalid firmware version"));
+    }
+
+    #[test]
+    fn test_import_zip_requires_version() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let source = app_dir.path().join("source.zip");
+        create_dfu_zip(&source);
+
+        let result = import_firmware(app_dir.path(), &cache_manager, &source, None, None, false);
+
+        assert!(result.unwrap_err().contains("version is required"));
+    }
+
+    #[test]
+    fn test_is_bundle() {
+        assert!(is_bundle(Path::new("/media/usb/firmware-2.1.0.bbfw")));
+        assert!(is_bundle(Path::new("FIRMWARE.BBFW")));
+        assert!(!is_bundle(Path::new("firmware.zip")));
+        assert!(!is_bundle(Path::new("bbfw")));
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let online_dir = TempDir::new().unwrap();
+        let online_cache = CacheManager::new(online_dir.path()).unwrap();
+        let source = online_dir.path().join("source.zip");
+        create_dfu_zip(&source);
+        let original = import_firmware(
+            online_dir.path(),
+            &online_cache,
+            &source,
+            Some("2.1.0"),
+            Some("Validated for clinic use".to_string()),
+            false,
+        )
+        .unwrap();
+        let mut beta = original.clone();
+        beta.channel = ReleaseChannel::Beta;
+        online_cache.update_entry(beta).unwrap();
+
+        let usb_dir = TempDir::new().unwrap();
+        let bundle_path = export_bundle(&online_cache, "2.1.0", usb_dir.path()).unwrap();
+        assert_eq!(
+            bundle_path,
+            usb_dir.path().join("bluebuzzah-firmware-2.1.0.bbfw")
+        );
+        assert!(!usb_dir
+            .path()
+            .join("bluebuzzah-firmware-2.1.0.bbfw.tmp")
+            .exists());
+
+        let offline_dir = TempDir::new().unwrap();
+        let offline_cache = CacheManager::new(offline_dir.path()).unwrap();
+        let imported = import_firmware(
+            offline_dir.path(),
+            &offline_cache,
+            &bundle_path,
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(imported.version, "2.1.0");
+        assert_eq!(imported.tag_name, original.tag_name);
+        assert_eq!(imported.sha256_hash, original.sha256_hash);
+        assert_eq!(imported.release_notes, "Validated for clinic use");
+        assert!(imported.locally_imported);
+        assert_eq!(imported.channel, ReleaseChannel::Beta);
+
+        assert!(offline_cache.verify_hash("2.1.0").unwrap());
+    }
+
+    #[test]
+    fn test_import_bundle_rejects_hash_mismatch() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let firmware = app_dir.path().join("firmware.zip");
+        create_dfu_zip(&firmware);
+
+        let bundle = BundleMetadata {
+            version: "2.1.0".to_string(),
+            tag_name: "v2.1.0".to_string(),
+            sha256_hash: "0".repeat(64),
+            published_at: "2024-01-01T00:00:00Z".to_string(),
+            release_notes: "Tampered".to_string(),
+            channel: ReleaseChannel::Stable,
+        };
+        let bundle_path = app_dir.path().join("tampered.bbfw");
+        write_bundle(&bundle_path, &bundle, &firmware).unwrap();
+
+        let result = import_firmware(
+            app_dir.path(),
+            &cache_manager,
+            &bundle_path,
+            None,
+            None,
+            false,
+        );
+
+        assert!(result.unwrap_err().starts_with("Bundle checksum mismatch"));
+        assert!(!app_dir.path().join("firmware").join("2.1.0.zip").exists());
+        assert!(!app_dir
+            .path()
+            .join("firmware")
+            .join("2.1.0.zip.tmp")
+            .exists());
+    }
+
+    #[test]
+    fn test_import_bundle_without_metadata() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let bundle_path = app_dir.path().join("plain.bbfw");
+        create_dfu_zip(&bundle_path);
+
+        let result = import_firmware(
+            app_dir.path(),
+            &cache_manager,
+            &bundle_path,
+            None,
+            None,
+            false,
+        );
+
+        assert!(result.unwrap_err().contains("missing metadata.json"));
+    }
+
+    #[test]
+    fn test_export_uncached_version() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+
+        let result = export_bundle(&cache_manager, "9.9.9", app_dir.path());
+
+        assert!(result.unwrap_err().contains("not cached"));
+    }
+
+    #[test]
+    fn test_export_refuses_corrupted_cache() {
+        let app_dir = TempDir::new().unwrap();
+        let cache_manager = CacheManager::new(app_dir.path()).unwrap();
+        let source = app_dir.path().join("source.zip");
+        create_dfu_zip(&source);
+        let metadata = import_firmware(
+            app_dir.path(),
+            &cache_manager,
+            &source,
+            Some("2.1.0"),
+            None,
+            false,
+        )
+        .unwrap();
+        fs::write(&metadata.zip_path, b"corrupted").unwrap();
+
+        let usb_dir = TempDir::new().unwrap();
+        let result = export_bundle(&cache_manager, "2.1.0", usb_dir.path());
+
+        assert!(result.unwrap_err().contains("failed hash verification"));
+        assert_eq!(fs::read_dir(usb_dir.path()).unwrap().count(), 0);
+    }
+}
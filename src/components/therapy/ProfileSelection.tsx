@@ -17,9 +17,10 @@ import {
 } from '@/components/ui/tooltip';
 import { useToast } from '@/components/ui/use-toast';
 import { THERAPY_PROFILES } from '@/lib/therapy-profiles';
+import { listen } from '@tauri-apps/api/event';
 import { useSettingsStore } from '@/stores/settingsStore';
 import { useTherapyStore } from '@/stores/therapyStore';
-import type { TherapyProfile } from '@/types';
+import type { SettingsChanged, TherapyProfile } from '@/types';
 import {
 	Activity,
 	ArrowRight,
@@ -54,6 +55,7 @@ export function ProfileSelection({
     setSettings,
     setSelectedProfile,
     loadFromBackend,
+    applyChange,
     isLoaded,
     loadError,
   } = useSettingsStore();
@@ -69,6 +71,16 @@ export function ProfileSelection({
     }
   }, [isLoaded, loadFromBackend]);
 
+  // Follow settings changed elsewhere, e.g. saved from another window
+  useEffect(() => {
+    const unlisten = listen<SettingsChanged>('settings://changed', (event) => {
+      applyChange(event.payload);
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [applyChange]);
+
   // Show toast when settings fail to load from backend (fire once per error)
   const shownErrorRef = useRef<string | null>(null);
   useEffect(() => {
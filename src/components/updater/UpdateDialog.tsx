@@ -228,6 +228,12 @@ ${error.details}`;
                 </p>
               )}
 
+              {updateInfo.downloadSize !== null && (
+                <p className="text-xs text-muted-foreground">
+                  Download size: {formatBytes(updateInfo.downloadSize)}
+                </p>
+              )}
+
               <div className="max-h-48 overflow-y-auto rounded border border-border bg-secondary/50 p-3 text-sm">
                 <p className="mb-2 font-medium">Release Notes:</p>
                 <div
@@ -271,7 +277,11 @@ ${error.details}`;
             {isInstalling ? (
               <>
                 <RefreshCw className="mr-2 h-4 w-4 animate-spin" />
-                {isDownloading ? 'Downloading...' : 'Installing...'}
+                {isDownloading
+                  ? 'Downloading...'
+                  : progress?.stage === 'waiting'
+                    ? 'Waiting for firmware flash...'
+                    : 'Installing...'}
               </>
             ) : (
               <>
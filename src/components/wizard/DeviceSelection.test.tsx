@@ -8,6 +8,7 @@ import { createMockDevice, createMockDevices } from '@/test/factories';
 vi.mock('@/services/DeviceService', () => ({
   deviceService: {
     detectDevices: vi.fn(),
+    getFlashHistory: vi.fn(),
   },
 }));
 
@@ -141,6 +142,41 @@ describe('DeviceSelection', () => {
       });
     });
 
+    it('shows when a device was last updated', async () => {
+      const mockDevice = createMockDevice({ serialNumber: 'SN12345678' });
+      vi.mocked(deviceService.detectDevices).mockResolvedValue([mockDevice]);
+      vi.mocked(deviceService.getFlashHistory).mockResolvedValue([
+        {
+          started_at: '2026-03-04T12:00:00Z',
+          duration_ms: 42000,
+          serial_number: 'SN12345678',
+          port: '/dev/cu.usbmodem1234',
+          firmware_version: 'v2.3.1',
+          firmware_path: '/cache/v2.3.1/firmware.zip',
+          app_version: '1.0.0',
+          role: 'PRIMARY',
+          profile: null,
+          success: true,
+          attempts: 1,
+          error_code: null,
+          error: null,
+        },
+      ]);
+
+      render(
+        <DeviceSelection
+          selectedDevices={[]}
+          onDevicesChange={mockOnDevicesChange}
+          onRoleChange={mockOnRoleChange}
+        />
+      );
+
+      await waitFor(() => {
+        expect(screen.getByText(/Last updated to v2\.3\.1 on/)).toBeInTheDocument();
+      });
+      expect(deviceService.getFlashHistory).toHaveBeenCalledWith({ success: true });
+    });
+
     it('does not show serial number when not available', async () => {
       const mockDevice = createMockDevice({
         path: '/dev/cu.usbmodem1234',
@@ -16,7 +16,7 @@ import {
 } from '@/components/ui/select';
 import { useToast } from '@/components/ui/use-toast';
 import { deviceService } from '@/services/DeviceService';
-import { Device, DeviceRole } from '@/types';
+import { Device, DeviceRole, FlashRecord } from '@/types';
 import { AlertCircle, CheckCircle2, CircuitBoard, RefreshCw } from 'lucide-react';
 import { useEffect, useRef, useState } from 'react';
 
@@ -33,6 +33,8 @@ export function DeviceSelection({
 }: DeviceSelectionProps) {
   const [availableDevices, setAvailableDevices] = useState<Device[]>([]);
   const [loading, setLoading] = useState(true);
+  // Latest successful flash per device serial number
+  const [lastFlashes, setLastFlashes] = useState<Map<string, FlashRecord>>(new Map());
   const { toast } = useToast();
   const callIdRef = useRef(0);
 
@@ -50,6 +52,7 @@ export function DeviceSelection({
       if (currentCallId !== callIdRef.current) return;
 
       setAvailableDevices(devices);
+      loadLastFlashes();
 
       if (devices.length === 0) {
         toast({
@@ -77,6 +80,29 @@ export function DeviceSelection({
     }
   };
 
+  const loadLastFlashes = async () => {
+    const records = (await deviceService.getFlashHistory({ success: true })) ?? [];
+
+    // Records come newest first, so the first per serial is the latest
+    const latest = new Map<string, FlashRecord>();
+    for (const record of records) {
+      if (record.serial_number && !latest.has(record.serial_number)) {
+        latest.set(record.serial_number, record);
+      }
+    }
+    setLastFlashes(latest);
+  };
+
+  const describeLastFlash = (record: FlashRecord) => {
+    const date = new Date(record.started_at).toLocaleDateString(undefined, {
+      month: 'long',
+      day: 'numeric',
+    });
+    return record.firmware_version
+      ? `Last updated to ${record.firmware_version} on ${date}`
+      : `Last updated on ${date}`;
+  };
+
   const toggleDeviceSelection = (device: Device) => {
     const isSelected = selectedDevices.some((d) => d.path === device.path);
 
@@ -183,6 +209,9 @@ export function DeviceSelection({
           {availableDevices.map((device) => {
             const selected = isDeviceSelected(device.path);
             const role = getDeviceRole(device.path);
+            const lastFlash = device.serialNumber
+              ? lastFlashes.get(device.serialNumber)
+              : undefined;
 
             return (
               <Card
@@ -208,6 +237,11 @@ export function DeviceSelection({
                             S/N: {device.serialNumber}
                           </p>
                         )}
+                        {lastFlash && (
+                          <p className="text-xs text-muted-foreground mt-1">
+                            {describeLastFlash(lastFlash)}
+                          </p>
+                        )}
                       </div>
                     </div>
                     {selected && (
@@ -7,8 +7,10 @@ import { createMockRelease, createMockDevice, createMockBundle } from '@/test/fa
 
 // Mock services
 vi.mock('@/services/FirmwareService', () => ({
+  DownloadCancelledError: class DownloadCancelledError extends Error {},
   firmwareService: {
     downloadFirmware: vi.fn(),
+    cancelDownload: vi.fn(),
   },
 }));
 
@@ -16,7 +18,12 @@ vi.mock('@/services/DeviceService', () => ({
   deviceService: {
     validateDevices: vi.fn(),
     deployFirmware: vi.fn(),
-    cancelFlash: vi.fn().mockResolvedValue(undefined),
+    cancelFlash: vi.fn().mockResolvedValue({
+      was_running: true,
+      operation_id: null,
+      stage_at_cancel: null,
+      not_found: false,
+    }),
   },
 }));
 
@@ -38,6 +45,13 @@ vi.mock('@/components/ui/use-toast', () => ({
   }),
 }));
 
+const cancelled = {
+  was_running: true,
+  operation_id: null,
+  stage_at_cancel: null,
+  not_found: false,
+};
+
 describe('InstallationProgress', () => {
   const mockOnComplete = vi.fn();
   const mockOnProgressUpdate = vi.fn();
@@ -48,7 +62,7 @@ describe('InstallationProgress', () => {
   beforeEach(() => {
     vi.resetAllMocks();
     // Re-apply cancelFlash default after reset (cleanup calls it on unmount)
-    vi.mocked(deviceService.cancelFlash).mockResolvedValue(undefined);
+    vi.mocked(deviceService.cancelFlash).mockResolvedValue(cancelled);
     mockLogs.length = 0; // Clear logs between tests
   });
 
@@ -615,7 +629,7 @@ describe('InstallationProgress', () => {
       vi.mocked(deviceService.validateDevices).mockImplementation(
         () => new Promise(() => {}) // Never resolves — keeps component busy
       );
-      vi.mocked(deviceService.cancelFlash).mockResolvedValue(undefined);
+      vi.mocked(deviceService.cancelFlash).mockResolvedValue(cancelled);
 
       const { unmount } = render(
         <InstallationProgress
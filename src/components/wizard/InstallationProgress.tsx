@@ -21,7 +21,7 @@ import { Progress } from '@/components/ui/progress';
 import { useCopyToClipboard } from '@/hooks/useCopyToClipboard';
 import { formatValidationErrors, getErrorGuidance } from '@/lib/error-messages';
 import { deviceService } from '@/services/DeviceService';
-import { firmwareService } from '@/services/FirmwareService';
+import { DownloadCancelledError, firmwareService } from '@/services/FirmwareService';
 import { useWizardStore } from '@/stores/wizardStore';
 import { Device, DeviceUpdateResult, FirmwareRelease, UpdateProgress, UpdateResult } from '@/types';
 import {
@@ -149,7 +149,13 @@ export function InstallationProgress({
     cancelledRef.current = true;
     addLog('Cancellation requested - stopping installation...');
     try {
-      await deviceService.cancelFlash();
+      if (stage === 'downloading') {
+        await firmwareService.cancelDownload(release.version);
+      }
+      const result = await deviceService.cancelFlash();
+      if (!result.was_running && stage !== 'downloading') {
+        addLog('No firmware installation was running');
+      }
     } catch (err) {
       console.error('Failed to cancel:', err);
     }
@@ -199,7 +205,9 @@ export function InstallationProgress({
       setStage('downloading');
       setDownloadProgress(0);
 
-      const firmware = await firmwareService.downloadFirmware(release);
+      const firmware = await firmwareService.downloadFirmware(release, (progress) => {
+        addLog(`⚠ ${progress.message}`);
+      });
       setDownloadProgress(100);
       addLog('Firmware download complete');
 
@@ -327,6 +335,10 @@ export function InstallationProgress({
         });
       }
     } catch (err) {
+      if (err instanceof DownloadCancelledError) {
+        addLog('Firmware download cancelled');
+        return;
+      }
       const errorMessage =
         typeof err === 'string'
           ? err
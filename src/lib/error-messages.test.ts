@@ -1,5 +1,7 @@
 import { describe, it, expect } from 'vitest';
 import {
+  CommandError,
+  toCommandError,
   getErrorGuidance,
   getErrorGuidanceForPlatform,
   formatValidationErrors,
@@ -261,3 +263,44 @@ describe('getErrorGuidanceForPlatform', () => {
     expect(winGuidance!.resolutionSteps.some(s => s.includes('admin/root'))).toBe(true);
   });
 });
+
+describe('toCommandError', () => {
+  it('wraps keyed command errors', () => {
+    const error = toCommandError({
+      message_key: 'dfu.error.timeout',
+      code: 'DFU-021',
+      message: 'Timeout waiting for ACK',
+    });
+
+    expect(error).toBeInstanceOf(CommandError);
+    expect((error as CommandError).message).toBe('Timeout waiting for ACK');
+    expect((error as CommandError).messageKey).toBe('dfu.error.timeout');
+    expect((error as CommandError).code).toBe('DFU-021');
+    expect((error as CommandError).existing).toBeNull();
+  });
+
+  it('keeps the operation holding a busy port', () => {
+    const existing = {
+      id: 7,
+      kind: 'flash',
+      port: '/dev/ttyACM0',
+      started_at: '2026-01-01T00:00:00Z',
+    };
+    const error = toCommandError({
+      message_key: 'error.port_busy',
+      code: null,
+      message: 'An operation is already in progress on this device (flash #7 on /dev/ttyACM0, started 2026-01-01T00:00:00Z)',
+      existing,
+    });
+
+    expect(error).toBeInstanceOf(CommandError);
+    expect((error as CommandError).messageKey).toBe('error.port_busy');
+    expect((error as CommandError).existing).toEqual(existing);
+  });
+
+  it('passes other errors through', () => {
+    const error = new Error('plain');
+    expect(toCommandError(error)).toBe(error);
+    expect(toCommandError('Device not found')).toBe('Device not found');
+  });
+});
@@ -1,5 +1,37 @@
 // Error message mapping and troubleshooting guidance
 
+import type { ActiveOperation, CommandErrorPayload } from '@/types';
+
+/**
+ * A keyed command failure. `messageKey` is stable for translation and
+ * `message` is the English fallback. `existing` is the operation holding
+ * the port when the key is `error.port_busy`.
+ */
+export class CommandError extends Error {
+  readonly messageKey: string;
+  readonly code: string | null;
+  readonly existing: ActiveOperation | null;
+
+  constructor(payload: CommandErrorPayload) {
+    super(payload.message);
+    this.name = 'CommandError';
+    this.messageKey = payload.message_key;
+    this.code = payload.code;
+    this.existing = payload.existing ?? null;
+  }
+}
+
+/**
+ * Wrap a keyed error rejected by a command in a CommandError, so callers
+ * can read `.message` as for any Error. Other errors pass through.
+ */
+export function toCommandError(error: unknown): unknown {
+  if (error && typeof error === 'object' && 'message_key' in error && 'message' in error) {
+    return new CommandError(error as CommandErrorPayload);
+  }
+  return error;
+}
+
 export interface ErrorGuidance {
   title: string;
   description: string;
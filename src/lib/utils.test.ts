@@ -1,5 +1,5 @@
 import { describe, it, expect } from 'vitest';
-import { formatBytes, formatDate, truncateText } from './utils';
+import { formatBytes, formatDate, formatDuration, truncateText } from './utils';
 
 describe('formatBytes', () => {
   it('returns "0 Bytes" for zero', () => {
@@ -63,6 +63,22 @@ describe('formatDate', () => {
   });
 });
 
+describe('formatDuration', () => {
+  it('formats seconds only', () => {
+    expect(formatDuration(0)).toBe('0 s');
+    expect(formatDuration(45_000)).toBe('45 s');
+  });
+
+  it('formats minutes and seconds', () => {
+    expect(formatDuration(220_000)).toBe('3 min 40 s');
+    expect(formatDuration(120_000)).toBe('2 min');
+  });
+
+  it('rounds to the nearest second', () => {
+    expect(formatDuration(59_600)).toBe('1 min');
+  });
+});
+
 describe('truncateText', () => {
   it('returns original text if shorter than maxLength', () => {
     expect(truncateText('hello', 10)).toBe('hello');
@@ -23,6 +23,17 @@ export function formatDate(date: Date): string {
   }).format(date);
 }
 
+// Rough durations for the UI, e.g. "3 min 40 s"
+export function formatDuration(ms: number): string {
+  const totalSeconds = Math.max(0, Math.round(ms / 1000));
+  const minutes = Math.floor(totalSeconds / 60);
+  const seconds = totalSeconds % 60;
+
+  if (minutes === 0) return `${seconds} s`;
+  if (seconds === 0) return `${minutes} min`;
+  return `${minutes} min ${seconds} s`;
+}
+
 export function truncateText(text: string, maxLength: number): string {
   if (text.length <= maxLength) return text;
   return text.substring(0, maxLength) + '...';
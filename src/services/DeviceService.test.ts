@@ -1,11 +1,13 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest';
-import { invoke } from '@tauri-apps/api/core';
+import { Channel, invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { DeviceService } from './DeviceService';
 import {
   createMockDevice,
   createMockBundle,
 } from '@/test/factories';
 import { mockConsole } from '@/test/setup';
+import type { DfuProgress, LogStreamEvent, ProgressBroadcast } from '@/types';
 
 // Note: Tauri API is mocked in test/setup.ts
 
@@ -27,6 +29,7 @@ describe('DeviceService', () => {
           pid: 0x8029,
           in_bootloader: false,
           serial_number: 'ABC123',
+          board: 'feather_nrf52840_express',
         },
         {
           port: '/dev/cu.usbmodem5678',
@@ -47,6 +50,8 @@ describe('DeviceService', () => {
       expect(devices[0].vid).toBe(0x239a);
       expect(devices[0].inBootloader).toBe(false);
       expect(devices[1].inBootloader).toBe(true);
+      expect(devices[0].board).toBe('feather_nrf52840_express');
+      expect(devices[1].board).toBeUndefined();
       expect(invoke).toHaveBeenCalledWith('detect_dfu_devices');
     });
 
@@ -153,6 +158,20 @@ describe('DeviceService', () => {
       );
     });
 
+    it('flashes without a role when skipRoleConfig is set', async () => {
+      const device = createMockDevice({ role: undefined });
+      vi.mocked(invoke).mockResolvedValueOnce(undefined);
+
+      await service.deployFirmware(device, createMockBundle(), undefined, undefined, {
+        skipRoleConfig: true,
+      });
+
+      expect(invoke).toHaveBeenCalledWith(
+        'flash_dfu_firmware',
+        expect.objectContaining({ deviceRole: null })
+      );
+    });
+
     it('reports initial progress', async () => {
       const device = createMockDevice({ role: 'PRIMARY' });
       const firmware = createMockBundle();
@@ -191,6 +210,33 @@ describe('DeviceService', () => {
       );
     });
 
+    it('reports the message of a keyed command error', async () => {
+      const progressCallback = vi.fn();
+      vi.mocked(invoke).mockRejectedValueOnce({
+        message_key: 'dfu.error.bootloader_timeout',
+        code: 'DFU-022',
+        message: 'Bootloader not found within 30000ms',
+      });
+
+      await expect(
+        service.deployFirmware(
+          createMockDevice({ role: 'PRIMARY' }),
+          createMockBundle(),
+          progressCallback
+        )
+      ).rejects.toMatchObject({
+        name: 'CommandError',
+        messageKey: 'dfu.error.bootloader_timeout',
+      });
+
+      expect(progressCallback).toHaveBeenCalledWith(
+        expect.objectContaining({
+          stage: 'error',
+          message: 'Error: Bootloader not found within 30000ms',
+        })
+      );
+    });
+
     it('calls flash_dfu_firmware with correct parameters', async () => {
       const device = createMockDevice({ role: 'PRIMARY', path: '/dev/cu.usbmodem1234' });
       const firmware = createMockBundle({ localPath: '/tmp/firmware.zip' });
@@ -201,12 +247,50 @@ describe('DeviceService', () => {
 
       expect(invoke).toHaveBeenCalledWith('flash_dfu_firmware', {
         serialPort: '/dev/cu.usbmodem1234',
-        firmwarePath: '/tmp/firmware.zip',
+        firmware: '/tmp/firmware.zip',
         deviceRole: 'PRIMARY',
+        timeoutSeconds: null,
         progress: expect.any(Object),
       });
     });
 
+    it('passes timeoutSeconds through', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce(undefined);
+
+      await service.deployFirmware(createMockDevice(), createMockBundle(), undefined, undefined, {
+        timeoutSeconds: 300,
+      });
+
+      expect(invoke).toHaveBeenCalledWith(
+        'flash_dfu_firmware',
+        expect.objectContaining({ timeoutSeconds: 300 })
+      );
+    });
+
+    it('ignores progress events older than the last one received', async () => {
+      const device = createMockDevice({ role: 'PRIMARY' });
+      const logCallback = vi.fn();
+      const logEvent = (seq: number, message: string): DfuProgress => ({
+        stage: 'log',
+        message_key: 'dfu.stage.log',
+        percent: -1,
+        message,
+        seq,
+        emitted_at_ms: 1_700_000_000_000 + seq,
+      });
+
+      vi.mocked(invoke).mockImplementationOnce(async (_command, args) => {
+        const channel = (args as { progress: Channel<DfuProgress> }).progress;
+        channel.onmessage(logEvent(2, 'newer'));
+        channel.onmessage(logEvent(1, 'stale'));
+        channel.onmessage(logEvent(3, 'newest'));
+      });
+
+      await service.deployFirmware(device, createMockBundle(), undefined, logCallback);
+
+      expect(logCallback.mock.calls).toEqual([['newer'], ['newest']]);
+    });
+
     it('reports complete stage on success', async () => {
       const device = createMockDevice({ role: 'SECONDARY' });
       const firmware = createMockBundle();
@@ -226,6 +310,365 @@ describe('DeviceService', () => {
     });
   });
 
+  describe('getDeviceMode', () => {
+    it('passes through not_found for an unplugged device', async () => {
+      const device = createMockDevice({ path: 'COM3' });
+      vi.mocked(invoke).mockResolvedValueOnce('not_found');
+
+      await expect(service.getDeviceMode(device)).resolves.toBe('not_found');
+      expect(invoke).toHaveBeenCalledWith('is_device_in_bootloader', { serialPort: 'COM3' });
+    });
+  });
+
+  describe('onProgressBroadcast', () => {
+    it('passes each dfu://progress payload to the callback', async () => {
+      const unlisten = vi.fn();
+      let handler: ((event: { payload: ProgressBroadcast }) => void) | undefined;
+      vi.mocked(listen).mockImplementationOnce((_event, cb) => {
+        handler = cb as typeof handler;
+        return Promise.resolve(unlisten);
+      });
+      const onBroadcast = vi.fn();
+
+      await expect(service.onProgressBroadcast(onBroadcast)).resolves.toBe(unlisten);
+      expect(listen).toHaveBeenCalledWith('dfu://progress', expect.any(Function));
+
+      const broadcast: ProgressBroadcast = {
+        kind: 'profile',
+        operation_id: 4,
+        event: { stage: 'sending', percent: 40, message: 'Sending', seq: 2, emitted_at_ms: 0 },
+      };
+      handler?.({ payload: broadcast });
+      expect(onBroadcast).toHaveBeenCalledWith(broadcast);
+    });
+  });
+
+  describe('sendDeviceCommand', () => {
+    it('returns the raw response', async () => {
+      const device = createMockDevice({ path: 'COM3' });
+      vi.mocked(invoke).mockResolvedValueOnce('[VERSION] 2.3.1\r\n');
+
+      await expect(service.sendDeviceCommand(device, 'GET_VERSION')).resolves.toBe(
+        '[VERSION] 2.3.1\r\n'
+      );
+      expect(invoke).toHaveBeenCalledWith('send_device_command', {
+        serialPort: 'COM3',
+        command: 'GET_VERSION',
+        timeoutMs: null,
+      });
+    });
+  });
+
+  describe('identifyDevice', () => {
+    it('calls identify_device with the serial port', async () => {
+      const device = createMockDevice({ path: '/dev/cu.usbmodem1' });
+      vi.mocked(invoke).mockResolvedValueOnce(true);
+
+      await expect(service.identifyDevice(device)).resolves.toBe(true);
+      expect(invoke).toHaveBeenCalledWith('identify_device', {
+        serialPort: '/dev/cu.usbmodem1',
+      });
+    });
+
+    it('rethrows backend errors', async () => {
+      const device = createMockDevice();
+      vi.mocked(invoke).mockRejectedValueOnce(
+        'Cannot identify a device while a firmware installation is in progress'
+      );
+
+      await expect(service.identifyDevice(device)).rejects.toBe(
+        'Cannot identify a device while a firmware installation is in progress'
+      );
+      expect(mockConsole.error).toHaveBeenCalled();
+    });
+  });
+
+  describe('flashBothDevices', () => {
+    it('calls flash_both_devices with both ports', async () => {
+      const primary = createMockDevice({ path: '/dev/cu.usbmodem1', role: 'PRIMARY' });
+      const secondary = createMockDevice({ path: '/dev/cu.usbmodem2', role: 'SECONDARY' });
+      const outcomes = [
+        {
+          device_index: 0,
+          device_label: 'PRIMARY',
+          port: '/dev/cu.usbmodem1',
+          status: 'failed',
+          error: 'Timeout',
+        },
+        {
+          device_index: 1,
+          device_label: 'SECONDARY',
+          port: '/dev/cu.usbmodem2',
+          status: 'skipped',
+          reason: 'PRIMARY device failed to update',
+        },
+      ];
+      vi.mocked(invoke).mockResolvedValueOnce(outcomes);
+
+      await expect(
+        service.flashBothDevices(primary, secondary, createMockBundle())
+      ).resolves.toEqual(outcomes);
+      expect(invoke).toHaveBeenCalledWith(
+        'flash_both_devices',
+        expect.objectContaining({
+          primaryPort: '/dev/cu.usbmodem1',
+          secondaryPort: '/dev/cu.usbmodem2',
+        })
+      );
+    });
+
+    it('rethrows when another flash is running', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce('A firmware installation is already in progress');
+
+      await expect(
+        service.flashBothDevices(createMockDevice(), createMockDevice(), createMockBundle())
+      ).rejects.toBe('A firmware installation is already in progress');
+    });
+  });
+
+  describe('getDeviceInfo', () => {
+    it('calls get_device_info with the serial port', async () => {
+      const device = createMockDevice({ path: '/dev/cu.usbmodem1' });
+      const info = {
+        firmware_version: '2.3.1',
+        role: 'PRIMARY',
+        profile: null,
+        timed_out: ['GET_PROFILE'],
+        unsupported: [],
+      };
+      vi.mocked(invoke).mockResolvedValueOnce(info);
+
+      await expect(service.getDeviceInfo(device)).resolves.toEqual(info);
+      expect(invoke).toHaveBeenCalledWith('get_device_info', {
+        serialPort: '/dev/cu.usbmodem1',
+      });
+    });
+
+    it('rethrows when the device is busy', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(
+        'Cannot query a device while a firmware installation is in progress'
+      );
+
+      await expect(service.getDeviceInfo(createMockDevice())).rejects.toBe(
+        'Cannot query a device while a firmware installation is in progress'
+      );
+      expect(mockConsole.error).toHaveBeenCalled();
+    });
+  });
+
+  describe('readDeviceSettings', () => {
+    it('calls read_device_settings with the serial port', async () => {
+      const device = createMockDevice({ path: '/dev/cu.usbmodem1' });
+      const readout = {
+        status: 'supported',
+        settings: { THERAPY_LED_OFF: 'false', DEBUG: 'false' },
+        mismatches: [{ key: 'THERAPY_LED_OFF', expected: 'true', actual: 'false' }],
+      };
+      vi.mocked(invoke).mockResolvedValueOnce(readout);
+
+      await expect(service.readDeviceSettings(device)).resolves.toEqual(readout);
+      expect(invoke).toHaveBeenCalledWith('read_device_settings', {
+        serialPort: '/dev/cu.usbmodem1',
+      });
+    });
+  });
+
+  describe('generateDeviceReport', () => {
+    it('calls generate_device_report with the serial port', async () => {
+      const device = createMockDevice({ path: '/dev/cu.usbmodem1' });
+      const report = {
+        serial_port: '/dev/cu.usbmodem1',
+        mode: { value: 'application', error: null },
+        profile: { value: null, error: 'No response to GET_PROFILE' },
+      };
+      vi.mocked(invoke).mockResolvedValueOnce(report);
+
+      await expect(service.generateDeviceReport(device)).resolves.toEqual(report);
+      expect(invoke).toHaveBeenCalledWith('generate_device_report', {
+        serialPort: '/dev/cu.usbmodem1',
+      });
+    });
+
+    it('rethrows backend errors', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce('Device report task panicked');
+
+      await expect(service.generateDeviceReport(createMockDevice())).rejects.toBe(
+        'Device report task panicked'
+      );
+      expect(mockConsole.error).toHaveBeenCalled();
+    });
+  });
+
+  describe('validateFirmwarePackage', () => {
+    it('passes the device port when a device is given', async () => {
+      const device = createMockDevice({ path: 'COM3' });
+      const info = { crc_valid: true, estimated_duration_ms: 220000, compatibility: null };
+      vi.mocked(invoke).mockResolvedValueOnce(info);
+
+      const result = await service.validateFirmwarePackage('/tmp/firmware.zip', device);
+
+      expect(invoke).toHaveBeenCalledWith('validate_firmware_package', {
+        firmwarePath: '/tmp/firmware.zip',
+        serialPort: 'COM3',
+      });
+      expect(result).toEqual(info);
+    });
+
+    it('validates without a device', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce({});
+
+      await service.validateFirmwarePackage('/tmp/firmware.zip');
+
+      expect(invoke).toHaveBeenCalledWith('validate_firmware_package', {
+        firmwarePath: '/tmp/firmware.zip',
+        serialPort: null,
+      });
+    });
+  });
+
+  describe('getFlashHistory', () => {
+    it('passes the filter to get_flash_history', async () => {
+      const records = [{ serial_number: 'ABC123', firmware_version: 'v2.3.1', success: true }];
+      vi.mocked(invoke).mockResolvedValueOnce(records);
+
+      const result = await service.getFlashHistory({ serial_number: 'ABC123', limit: 1 });
+
+      expect(invoke).toHaveBeenCalledWith('get_flash_history', {
+        filter: { serial_number: 'ABC123', limit: 1 },
+      });
+      expect(result).toEqual(records);
+    });
+
+    it('returns empty array on error', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('unreadable'));
+
+      expect(await service.getFlashHistory()).toEqual([]);
+      expect(mockConsole.error).toHaveBeenCalled();
+    });
+  });
+
+  describe('getInterruptedOperations', () => {
+    it('returns the interrupted operations from the backend', async () => {
+      const operations = [{ id: '1700000000000-0', port: 'COM3', in_bootloader: true }];
+      vi.mocked(invoke).mockResolvedValueOnce(operations);
+
+      expect(await service.getInterruptedOperations()).toEqual(operations);
+      expect(invoke).toHaveBeenCalledWith('get_interrupted_operations');
+    });
+
+    it('returns empty array on error', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('unreadable'));
+
+      expect(await service.getInterruptedOperations()).toEqual([]);
+      expect(mockConsole.error).toHaveBeenCalled();
+    });
+  });
+
+  describe('exportDiagnostics', () => {
+    it('calls export_diagnostics with the destination', async () => {
+      const summary = { path: '/tmp/diagnostics.zip', files: [], skipped: [], total_bytes: 0 };
+      vi.mocked(invoke).mockResolvedValueOnce(summary);
+
+      expect(await service.exportDiagnostics('/tmp/diagnostics')).toEqual(summary);
+      expect(invoke).toHaveBeenCalledWith('export_diagnostics', { destPath: '/tmp/diagnostics' });
+    });
+  });
+
+  describe('exportFlashHistory', () => {
+    it('calls export_flash_history with the path', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce(12);
+
+      expect(await service.exportFlashHistory('/tmp/history.json')).toBe(12);
+      expect(invoke).toHaveBeenCalledWith('export_flash_history', { path: '/tmp/history.json' });
+    });
+  });
+
+  describe('detectUf2Volumes', () => {
+    it('returns mounted UF2 drives', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce(['/Volumes/FTHR840BOOT']);
+
+      await expect(service.detectUf2Volumes()).resolves.toEqual(['/Volumes/FTHR840BOOT']);
+      expect(invoke).toHaveBeenCalledWith('detect_uf2_volumes');
+    });
+
+    it('returns empty array on error', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('Scan failed'));
+
+      await expect(service.detectUf2Volumes()).resolves.toEqual([]);
+    });
+  });
+
+  describe('flashUf2', () => {
+    it('calls flash_uf2 with the device port', async () => {
+      const device = createMockDevice({ path: '/dev/cu.usbmodem1' });
+      vi.mocked(invoke).mockResolvedValueOnce('/dev/cu.usbmodem2');
+
+      const port = await service.flashUf2(
+        '/tmp/firmware.uf2',
+        '/Volumes/FTHR840BOOT',
+        device
+      );
+
+      expect(port).toBe('/dev/cu.usbmodem2');
+      expect(invoke).toHaveBeenCalledWith('flash_uf2', {
+        uf2Path: '/tmp/firmware.uf2',
+        volumePath: '/Volumes/FTHR840BOOT',
+        serialPort: '/dev/cu.usbmodem1',
+        progress: expect.anything(),
+      });
+    });
+
+    it('rethrows backend errors', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce('Invalid UF2 image: bad magic in block 0');
+
+      await expect(
+        service.flashUf2('/tmp/bad.uf2', '/Volumes/FTHR840BOOT')
+      ).rejects.toBe('Invalid UF2 image: bad magic in block 0');
+    });
+  });
+
+  describe('startDeviceLog', () => {
+    it('forwards stream events to the callback', async () => {
+      const device = createMockDevice({ path: 'COM3' });
+      const onEvent = vi.fn();
+      const line: LogStreamEvent = {
+        event: 'line',
+        timestamp: '2026-03-04T10:00:00Z',
+        text: '[READY] BlueBuzzah',
+      };
+      const ended: LogStreamEvent = {
+        event: 'ended',
+        reason: 'preempted_by_flash',
+        detail: null,
+      };
+
+      vi.mocked(invoke).mockImplementationOnce(async (_command, args) => {
+        const channel = (args as { channel: Channel<LogStreamEvent> }).channel;
+        channel.onmessage(line);
+        channel.onmessage(ended);
+      });
+
+      await service.startDeviceLog(device, onEvent);
+
+      expect(invoke).toHaveBeenCalledWith('start_device_log_stream', {
+        serialPort: 'COM3',
+        channel: expect.anything(),
+      });
+      expect(onEvent.mock.calls).toEqual([[line], [ended]]);
+    });
+
+    it('rethrows when the port is busy', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(
+        'An operation is already in progress on this device (flash #1 on COM3, started now)'
+      );
+
+      await expect(service.startDeviceLog(createMockDevice(), vi.fn())).rejects.toMatch(
+        'already in progress'
+      );
+      expect(mockConsole.error).toHaveBeenCalled();
+    });
+  });
+
   describe('performBatchUpdate', () => {
     it('updates all devices and returns success result', async () => {
       const devices = [
@@ -1,27 +1,84 @@
 import {
+  ActiveOperation,
+  BoardModel,
+  CancelFlashResult,
   Device,
+  DeviceFlashOutcome,
+  DeviceInfo,
+  DeviceMode,
+  DeviceReport,
+  DeviceSettingsReadout,
   DeviceUpdateResult,
+  DiagnosticsSummary,
   DfuProgress,
   FirmwareBundle,
+  FirmwareInfo,
+  FlashHistoryFilter,
+  FlashRecord,
+  InterruptedOperation,
+  LogLevel,
+  LogStreamEvent,
+  ProgressBroadcast,
+  SecondLaunch,
   UpdateProgress,
   UpdateResult,
   UpdateStage,
+  Uf2Progress,
   ValidationResult,
 } from '@/types';
 import { Channel, invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+import { toCommandError } from '@/lib/error-messages';
 import { createProgressThrottle } from '@/lib/throttle';
 
+export interface DeployOptions {
+  /** Keep the role stored on the device instead of sending device.role. */
+  skipRoleConfig?: boolean;
+  /** Give up after this many seconds, retries included (error code DFU-024). */
+  timeoutSeconds?: number;
+}
+
 export interface IDeviceRepository {
   detectDevices(): Promise<Device[]>;
   deployFirmware(
     device: Device,
     firmware: FirmwareBundle,
     onProgress?: (progress: UpdateProgress) => void,
-    onLog?: (message: string) => void
+    onLog?: (message: string) => void,
+    options?: DeployOptions
   ): Promise<void>;
+  flashBothDevices(
+    primary: Device,
+    secondary: Device,
+    firmware: FirmwareBundle,
+    onProgress?: (progress: DfuProgress) => void
+  ): Promise<DeviceFlashOutcome[]>;
   validateDevice(device: Device): Promise<ValidationResult>;
   validateDevices(devices: Device[]): Promise<Map<string, ValidationResult>>;
-  cancelFlash(): Promise<void>;
+  cancelFlash(operationId?: number): Promise<CancelFlashResult>;
+  getActiveOperations(): Promise<ActiveOperation[]>;
+  onProgressBroadcast(callback: (broadcast: ProgressBroadcast) => void): Promise<UnlistenFn>;
+  getDeviceMode(device: Device): Promise<DeviceMode>;
+  identifyDevice(device: Device): Promise<boolean>;
+  sendDeviceCommand(device: Device, command: string, timeoutMs?: number): Promise<string>;
+  getDeviceInfo(device: Device): Promise<DeviceInfo>;
+  readDeviceSettings(device: Device): Promise<DeviceSettingsReadout>;
+  generateDeviceReport(device: Device): Promise<DeviceReport>;
+  exportDiagnostics(destPath: string): Promise<DiagnosticsSummary>;
+  validateFirmwarePackage(firmwarePath: string, device?: Device): Promise<FirmwareInfo>;
+  getFlashHistory(filter?: FlashHistoryFilter): Promise<FlashRecord[]>;
+  exportFlashHistory(path: string): Promise<number>;
+  getInterruptedOperations(): Promise<InterruptedOperation[]>;
+  dismissInterruptedOperation(id: string): Promise<boolean>;
+  startDeviceLog(device: Device, onEvent: (event: LogStreamEvent) => void): Promise<void>;
+  stopDeviceLog(device: Device): Promise<boolean>;
+  detectUf2Volumes(): Promise<string[]>;
+  flashUf2(
+    uf2Path: string,
+    volumePath: string,
+    device?: Device,
+    onProgress?: (progress: Uf2Progress) => void
+  ): Promise<string | null>;
 }
 
 // Map DFU stages to UpdateStage enum (returns null for log events)
@@ -87,6 +144,7 @@ export class DeviceService implements IDeviceRepository {
         pid: number;
         in_bootloader: boolean;
         serial_number: string | null;
+        board?: BoardModel;
       }[]>('detect_dfu_devices');
 
       // Map to Device interface
@@ -98,6 +156,7 @@ export class DeviceService implements IDeviceRepository {
         pid: d.pid,
         inBootloader: d.in_bootloader,
         serialNumber: d.serial_number ?? undefined,
+        board: d.board,
       }));
     } catch (error) {
       console.error('Failed to detect devices:', error);
@@ -115,7 +174,8 @@ export class DeviceService implements IDeviceRepository {
     device: Device,
     firmware: FirmwareBundle,
     onProgress?: (progress: UpdateProgress) => void,
-    onLog?: (message: string) => void
+    onLog?: (message: string) => void,
+    options?: DeployOptions
   ): Promise<void> {
     // Create throttled progress callback (100ms interval, 1% minimum change)
     const throttledProgress = onProgress
@@ -123,8 +183,10 @@ export class DeviceService implements IDeviceRepository {
       : null;
 
     try {
+      const skipRoleConfig = options?.skipRoleConfig ?? false;
+
       // Validate device role
-      if (!device.role) {
+      if (!skipRoleConfig && !device.role) {
         throw new Error('Device role not set');
       }
 
@@ -141,7 +203,14 @@ export class DeviceService implements IDeviceRepository {
       // Create a channel to receive DFU progress updates
       const progressChannel = new Channel<DfuProgress>();
 
+      // Events can arrive out of order over IPC; ignore any older than the last one seen
+      let lastSeq = 0;
       progressChannel.onmessage = (dfuProgress) => {
+        if (dfuProgress.seq <= lastSeq) {
+          return;
+        }
+        lastSeq = dfuProgress.seq;
+
         const mappedStage = mapDfuStageToUpdateStage(dfuProgress.stage);
 
         // Log events go to log callback only, not to progress display
@@ -170,9 +239,12 @@ export class DeviceService implements IDeviceRepository {
       // Call the DFU flash command
       await invoke('flash_dfu_firmware', {
         serialPort: device.path,
-        firmwarePath: firmware.localPath,
-        deviceRole: device.role,
+        firmware: firmware.localPath,
+        deviceRole: skipRoleConfig ? null : device.role,
+        timeoutSeconds: options?.timeoutSeconds ?? null,
         progress: progressChannel,
+      }).catch((error) => {
+        throw toCommandError(error);
       });
 
       // Flush any pending throttled updates before final complete
@@ -208,6 +280,35 @@ export class DeviceService implements IDeviceRepository {
     }
   }
 
+  /**
+   * Flash a primary and a secondary device in one backend call. The secondary
+   * starts only after the primary has rebooted; progress events carry
+   * device_index/device_label so each device can have its own track.
+   */
+  async flashBothDevices(
+    primary: Device,
+    secondary: Device,
+    firmware: FirmwareBundle,
+    onProgress?: (progress: DfuProgress) => void
+  ): Promise<DeviceFlashOutcome[]> {
+    const progress = new Channel<DfuProgress>();
+    if (onProgress) {
+      progress.onmessage = onProgress;
+    }
+
+    try {
+      return await invoke<DeviceFlashOutcome[]>('flash_both_devices', {
+        primaryPort: primary.path,
+        secondaryPort: secondary.path,
+        firmwarePath: firmware.localPath,
+        progress,
+      });
+    } catch (error) {
+      console.error('Failed to flash devices:', error);
+      throw toCommandError(error);
+    }
+  }
+
   async validateDevice(device: Device): Promise<ValidationResult> {
     // For DFU devices, validation is simpler - just check if the device is accessible
     // Note: Bootloader mode devices are now supported - the protocol auto-detects and handles them
@@ -292,17 +393,304 @@ export class DeviceService implements IDeviceRepository {
   }
 
   /**
-   * Cancel any in-progress firmware flash operation.
+   * Cancel the in-progress firmware flash operation.
    * Sets a global cancellation flag that is checked during the DFU process.
+   * Resolves with was_running: false when there was nothing to cancel.
    */
-  async cancelFlash(): Promise<void> {
+  async cancelFlash(operationId?: number): Promise<CancelFlashResult> {
     try {
-      await invoke('cancel_dfu_flash');
+      return await invoke<CancelFlashResult>('cancel_dfu_flash', {
+        operationId: operationId ?? null,
+      });
     } catch (error) {
       console.error('Failed to cancel flash:', error);
       throw error;
     }
   }
+
+  /**
+   * List the operations currently holding device ports, oldest first.
+   */
+  async getActiveOperations(): Promise<ActiveOperation[]> {
+    try {
+      return await invoke<ActiveOperation[]>('get_active_operations');
+    } catch (error) {
+      console.error('Failed to get active operations:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Follow the progress of every flash and profile operation, including ones
+   * started by another window or before a reload. Resolves with a function
+   * that stops listening.
+   */
+  async onProgressBroadcast(
+    callback: (broadcast: ProgressBroadcast) => void
+  ): Promise<UnlistenFn> {
+    return listen<ProgressBroadcast>('dfu://progress', (event) => callback(event.payload));
+  }
+
+  /**
+   * Follow launches of the app while it is already running. The window has
+   * already been focused; resolves with a function that stops listening.
+   */
+  async onSecondLaunch(callback: (launch: SecondLaunch) => void): Promise<UnlistenFn> {
+    return listen<SecondLaunch>('app://second-instance', (event) => callback(event.payload));
+  }
+
+  /**
+   * Check whether a device is in bootloader or application mode, or
+   * 'not_found' when it has been unplugged.
+   */
+  async getDeviceMode(device: Device): Promise<DeviceMode> {
+    try {
+      return await invoke<DeviceMode>('is_device_in_bootloader', { serialPort: device.path });
+    } catch (error) {
+      console.error('Failed to check device mode:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Blink a device's NeoPixel so it can be told apart from identical boards.
+   * Resolves with whether the firmware acknowledged; older firmware doesn't.
+   */
+  async identifyDevice(device: Device): Promise<boolean> {
+    try {
+      return await invoke<boolean>('identify_device', { serialPort: device.path });
+    } catch (error) {
+      console.error('Failed to identify device:', error);
+      throw toCommandError(error);
+    }
+  }
+
+  /**
+   * Send a serial command and resolve with the device's raw response.
+   * Only read-only queries are accepted unless developer mode is enabled.
+   */
+  async sendDeviceCommand(device: Device, command: string, timeoutMs?: number): Promise<string> {
+    try {
+      return await invoke<string>('send_device_command', {
+        serialPort: device.path,
+        command,
+        timeoutMs: timeoutMs ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to send device command:', error);
+      throw toCommandError(error);
+    }
+  }
+
+  /**
+   * Read the firmware version, role and profile from a device in application
+   * mode. Queries older firmware doesn't answer come back as null fields.
+   */
+  async getDeviceInfo(device: Device): Promise<DeviceInfo> {
+    try {
+      return await invoke<DeviceInfo>('get_device_info', { serialPort: device.path });
+    } catch (error) {
+      console.error('Failed to get device info:', error);
+      throw toCommandError(error);
+    }
+  }
+
+  /**
+   * Read the settings a device is running and compare them with the saved
+   * ones. Firmware without GET_SETTINGS resolves with an unsupported readout.
+   */
+  async readDeviceSettings(device: Device): Promise<DeviceSettingsReadout> {
+    try {
+      return await invoke<DeviceSettingsReadout>('read_device_settings', {
+        serialPort: device.path,
+      });
+    } catch (error) {
+      console.error('Failed to read device settings:', error);
+      throw toCommandError(error);
+    }
+  }
+
+  /**
+   * Collect a health report for support. Probes that fail are reported
+   * inside the result rather than rejecting the whole call.
+   */
+  async generateDeviceReport(device: Device): Promise<DeviceReport> {
+    try {
+      return await invoke<DeviceReport>('generate_device_report', { serialPort: device.path });
+    } catch (error) {
+      console.error('Failed to generate device report:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Write one zip for support with the logs, redacted settings, cache index,
+   * flash history and connected ports. Resolves with what was included.
+   */
+  async exportDiagnostics(destPath: string): Promise<DiagnosticsSummary> {
+    try {
+      return await invoke<DiagnosticsSummary>('export_diagnostics', { destPath });
+    } catch (error) {
+      console.error('Failed to export diagnostics:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Check a firmware.zip before flashing: CRC, estimated flash time and,
+   * when a device is given, whether the package suits it.
+   */
+  async validateFirmwarePackage(firmwarePath: string, device?: Device): Promise<FirmwareInfo> {
+    try {
+      return await invoke<FirmwareInfo>('validate_firmware_package', {
+        firmwarePath,
+        serialPort: device?.path ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to validate firmware package:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Read the flash history, newest first. Every flash is recorded by the
+   * backend with the device serial, firmware version and outcome.
+   */
+  async getFlashHistory(filter?: FlashHistoryFilter): Promise<FlashRecord[]> {
+    try {
+      return await invoke<FlashRecord[]>('get_flash_history', { filter: filter ?? null });
+    } catch (error) {
+      console.error('Failed to read flash history:', error);
+      return [];
+    }
+  }
+
+  /**
+   * Export the whole flash history to a JSON file for audits.
+   * Resolves with the number of records written.
+   */
+  async exportFlashHistory(path: string): Promise<number> {
+    try {
+      return await invoke<number>('export_flash_history', { path });
+    } catch (error) {
+      console.error('Failed to export flash history:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * List flashes an earlier run of the app was killed in the middle of,
+   * oldest first, with where each device is connected now. A device left in
+   * bootloader mode can be flashed again straight away.
+   */
+  async getInterruptedOperations(): Promise<InterruptedOperation[]> {
+    try {
+      return await invoke<InterruptedOperation[]>('get_interrupted_operations');
+    } catch (error) {
+      console.error('Failed to read interrupted operations:', error);
+      return [];
+    }
+  }
+
+  /**
+   * Forget an interrupted flash without flashing again. Finishing a flash of
+   * the same device clears its interrupted entries too.
+   */
+  async dismissInterruptedOperation(id: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>('dismiss_interrupted_operation', { id });
+    } catch (error) {
+      console.error('Failed to dismiss interrupted operation:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * List mounted UF2 bootloader drives (FTHR840BOOT after a double-tap reset),
+   * so the UI can offer UF2 flashing when serial DFU can't reach a board.
+   */
+  async detectUf2Volumes(): Promise<string[]> {
+    try {
+      return await invoke<string[]>('detect_uf2_volumes');
+    } catch (error) {
+      console.error('Failed to detect UF2 drives:', error);
+      return [];
+    }
+  }
+
+  /**
+   * Flash a .uf2 image by copying it onto a UF2 bootloader drive. When the
+   * device is given, resolves with its port once it is back in application
+   * mode; otherwise resolves with null after the bootloader reboots.
+   */
+  async flashUf2(
+    uf2Path: string,
+    volumePath: string,
+    device?: Device,
+    onProgress?: (progress: Uf2Progress) => void
+  ): Promise<string | null> {
+    const progress = new Channel<Uf2Progress>();
+    if (onProgress) {
+      progress.onmessage = onProgress;
+    }
+
+    try {
+      return await invoke<string | null>('flash_uf2', {
+        uf2Path,
+        volumePath,
+        serialPort: device?.path,
+        progress,
+      });
+    } catch (error) {
+      console.error('Failed to flash UF2:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Stream a device's serial output line by line. Resolves once the port is
+   * open; the stream runs until stopDeviceLog, a disconnect or a flash on the
+   * same device, and always finishes with an 'ended' event.
+   */
+  async startDeviceLog(
+    device: Device,
+    onEvent: (event: LogStreamEvent) => void
+  ): Promise<void> {
+    const channel = new Channel<LogStreamEvent>();
+    channel.onmessage = onEvent;
+
+    try {
+      await invoke('start_device_log_stream', { serialPort: device.path, channel });
+    } catch (error) {
+      console.error('Failed to start device log:', error);
+      throw toCommandError(error);
+    }
+  }
+
+  /**
+   * Stop a device's log stream. Resolves with whether one was running.
+   */
+  async stopDeviceLog(device: Device): Promise<boolean> {
+    try {
+      return await invoke<boolean>('stop_device_log_stream', { serialPort: device.path });
+    } catch (error) {
+      console.error('Failed to stop device log:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Change how much the backend writes to its log file, e.g. 'debug' while
+   * reproducing a problem for a support bundle.
+   */
+  async setLogLevel(level: LogLevel): Promise<void> {
+    try {
+      await invoke('set_log_level', { level });
+    } catch (error) {
+      console.error('Failed to set log level:', error);
+      throw error;
+    }
+  }
 }
 
 // Singleton instance
@@ -1,13 +1,14 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest';
 import { invoke } from '@tauri-apps/api/core';
-import { FirmwareService } from './FirmwareService';
+import { DownloadCancelledError, FirmwareService } from './FirmwareService';
 import {
-  createMockGitHubRelease,
-  createMockGitHubAsset,
+  createMockReleaseInfo,
+  createMockReleaseAssetInfo,
   createMockRelease,
   createMockCachedMetadata,
 } from '@/test/factories';
 import { mockConsole } from '@/test/setup';
+import type { FirmwareReleaseInfo } from '@/types';
 
 // Note: Tauri API is mocked in test/setup.ts
 
@@ -20,50 +21,49 @@ describe('FirmwareService', () => {
   });
 
   describe('fetchReleases', () => {
-    it('returns parsed releases from GitHub API', async () => {
-      const mockGitHubReleases = [
-        createMockGitHubRelease({ name: '1.0.0', tag_name: 'v1.0.0' }),
-        createMockGitHubRelease({ name: '0.9.0', tag_name: 'v0.9.0' }),
-      ];
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: true,
-        json: () => Promise.resolve(mockGitHubReleases),
-      } as Response);
+    const createMockListing = (releases: FirmwareReleaseInfo[], stale = false) => ({
+      releases,
+      stale,
+      fetched_at: '2024-06-01T12:00:00Z',
+    });
 
-      // Mock verify_and_clean_cache and get_cache_index
+    it('returns releases from the backend', async () => {
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-      vi.mocked(invoke).mockResolvedValueOnce({}); // get_cache_index
+      vi.mocked(invoke).mockResolvedValueOnce(
+        createMockListing([
+          createMockReleaseInfo({ version: '1.0.0', published_at: '2024-06-01T00:00:00Z' }),
+          createMockReleaseInfo({ version: '0.9.0', published_at: '2024-01-01T00:00:00Z' }),
+        ])
+      ); // list_firmware_releases
 
       const releases = await service.fetchReleases();
 
+      expect(invoke).toHaveBeenCalledWith('list_firmware_releases', { channel: undefined });
       expect(releases).toHaveLength(2);
       expect(releases[0].version).toBe('1.0.0');
       expect(releases[1].version).toBe('0.9.0');
     });
 
-    it('maps GitHub release to FirmwareRelease type', async () => {
-      const mockGitHubRelease = createMockGitHubRelease({
-        name: '2.0.0',
-        tag_name: 'v2.0.0',
-        body: 'New features',
-        published_at: '2024-06-01T00:00:00Z',
-        assets: [
-          createMockGitHubAsset({
-            name: 'firmware.zip',
-            browser_download_url: 'https://test.com/firmware.zip',
-            size: 5000,
-          }),
-        ],
-      });
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: true,
-        json: () => Promise.resolve([mockGitHubRelease]),
-      } as Response);
-
+    it('maps backend release to FirmwareRelease type', async () => {
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-      vi.mocked(invoke).mockResolvedValueOnce({}); // get_cache_index
+      vi.mocked(invoke).mockResolvedValueOnce(
+        createMockListing([
+          createMockReleaseInfo({
+            version: '2.0.0',
+            tag_name: 'v2.0.0',
+            release_notes: 'New features',
+            published_at: '2024-06-01T00:00:00Z',
+            prerelease: true,
+            assets: [
+              createMockReleaseAssetInfo({
+                name: 'firmware.zip',
+                download_url: 'https://test.com/firmware.zip',
+                size: 5000,
+              }),
+            ],
+          }),
+        ])
+      ); // list_firmware_releases
 
       const releases = await service.fetchReleases();
 
@@ -71,6 +71,8 @@ describe('FirmwareService', () => {
         version: '2.0.0',
         tagName: 'v2.0.0',
         releaseNotes: 'New features',
+        downloadUrl: 'https://test.com/firmware.zip',
+        isPrerelease: true,
         assets: expect.arrayContaining([
           expect.objectContaining({
             name: 'firmware.zip',
@@ -83,134 +85,47 @@ describe('FirmwareService', () => {
     });
 
     it('handles empty releases array', async () => {
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: true,
-        json: () => Promise.resolve([]),
-      } as Response);
-
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-      vi.mocked(invoke).mockResolvedValueOnce({}); // get_cache_index
+      vi.mocked(invoke).mockResolvedValueOnce(createMockListing([])); // list_firmware_releases
 
       const releases = await service.fetchReleases();
 
       expect(releases).toEqual([]);
     });
 
-    it('handles API error (network failure)', async () => {
-      vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-
-      vi.mocked(global.fetch).mockRejectedValueOnce(new Error('Network error'));
-
-      await expect(service.fetchReleases()).rejects.toThrow('Failed to fetch firmware releases');
-      expect(mockConsole.error).toHaveBeenCalledWith(
-        'Failed to fetch releases:',
-        expect.any(Error)
-      );
-    });
-
-    it('handles API error (non-ok response)', async () => {
-      vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: false,
-        statusText: 'Not Found',
-      } as Response);
-
-      await expect(service.fetchReleases()).rejects.toThrow('GitHub API error');
-      expect(mockConsole.error).toHaveBeenCalledWith(
-        'Failed to fetch releases:',
-        expect.any(Error)
-      );
-    });
-
-    it('handles rate limiting response (429)', async () => {
-      vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: false,
-        statusText: 'Too Many Requests',
-        status: 429,
-        headers: new Headers(),
-      } as Response);
-
-      await expect(service.fetchReleases()).rejects.toThrow('GitHub API rate limit exceeded. Try again later.');
-      expect(mockConsole.error).toHaveBeenCalledWith(
-        'Failed to fetch releases:',
-        expect.any(Error)
-      );
-    });
-
-    it('handles 403 rate limit with X-RateLimit-Reset header', async () => {
+    it('surfaces backend error messages', async () => {
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
+      vi.mocked(invoke).mockRejectedValueOnce(
+        'GitHub API rate limit exceeded. Try again in 5 minutes.'
+      ); // list_firmware_releases
 
-      const resetTimestamp = Math.floor(Date.now() / 1000) + 300; // 5 minutes from now
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: false,
-        status: 403,
-        statusText: 'Forbidden',
-        headers: new Headers({
-          'X-RateLimit-Reset': String(resetTimestamp),
-        }),
-      } as Response);
-
-      await expect(service.fetchReleases()).rejects.toThrow(/GitHub API rate limit exceeded.*minute/);
-      expect(mockConsole.error).toHaveBeenCalledWith(
-        'Failed to fetch releases:',
-        expect.any(Error)
+      await expect(service.fetchReleases()).rejects.toThrow(
+        'Failed to fetch firmware releases: GitHub API rate limit exceeded. Try again in 5 minutes.'
       );
-    });
-
-    it('handles 403 rate limit without reset header', async () => {
-      vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: false,
-        status: 403,
-        statusText: 'Forbidden',
-        headers: new Headers(),
-      } as Response);
-
-      await expect(service.fetchReleases()).rejects.toThrow('GitHub API rate limit exceeded. Try again later.');
       expect(mockConsole.error).toHaveBeenCalledWith(
         'Failed to fetch releases:',
-        expect.any(Error)
+        'GitHub API rate limit exceeded. Try again in 5 minutes.'
       );
     });
 
-    it('handles fetch timeout via AbortController', async () => {
+    it('handles Error rejections', async () => {
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('IPC failure')); // list_firmware_releases
 
-      const abortError = new DOMException('The operation was aborted.', 'AbortError');
-      vi.mocked(global.fetch).mockRejectedValueOnce(abortError);
-
-      await expect(service.fetchReleases()).rejects.toThrow('Request timed out while fetching firmware releases');
-      expect(mockConsole.error).toHaveBeenCalledWith(
-        'Failed to fetch releases:',
-        expect.any(Error)
+      await expect(service.fetchReleases()).rejects.toThrow(
+        'Failed to fetch firmware releases: IPC failure'
       );
     });
 
     it('sorts releases by date (newest first)', async () => {
-      const oldRelease = createMockGitHubRelease({
-        name: '0.5.0',
-        published_at: '2023-01-01T00:00:00Z',
-      });
-      const newRelease = createMockGitHubRelease({
-        name: '2.0.0',
-        published_at: '2024-12-01T00:00:00Z',
-      });
-      const midRelease = createMockGitHubRelease({
-        name: '1.0.0',
-        published_at: '2024-06-01T00:00:00Z',
-      });
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: true,
-        json: () => Promise.resolve([oldRelease, newRelease, midRelease]),
-      } as Response);
-
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-      vi.mocked(invoke).mockResolvedValueOnce({}); // get_cache_index
+      vi.mocked(invoke).mockResolvedValueOnce(
+        createMockListing([
+          createMockReleaseInfo({ version: '0.5.0', published_at: '2023-01-01T00:00:00Z' }),
+          createMockReleaseInfo({ version: '2.0.0', published_at: '2024-12-01T00:00:00Z' }),
+          createMockReleaseInfo({ version: '1.0.0', published_at: '2024-06-01T00:00:00Z' }),
+        ])
+      ); // list_firmware_releases
 
       const releases = await service.fetchReleases();
 
@@ -220,49 +135,46 @@ describe('FirmwareService', () => {
     });
 
     it('marks releases that are cached', async () => {
-      const mockGitHubRelease = createMockGitHubRelease({
-        name: '1.0.0',
-        tag_name: 'v1.0.0',
-      });
-
-      const mockCacheIndex = {
-        '1.0.0': createMockCachedMetadata({ version: '1.0.0' }),
-      };
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: true,
-        json: () => Promise.resolve([mockGitHubRelease]),
-      } as Response);
+      const cachedMetadata = createMockCachedMetadata({ version: '1.0.0' });
 
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-      vi.mocked(invoke).mockResolvedValueOnce(mockCacheIndex); // get_cache_index
+      vi.mocked(invoke).mockResolvedValueOnce(
+        createMockListing([
+          createMockReleaseInfo({
+            version: '1.0.0',
+            is_cached: true,
+            cached_metadata: cachedMetadata,
+          }),
+        ])
+      ); // list_firmware_releases
 
       const releases = await service.fetchReleases();
 
+      expect(releases).toHaveLength(1);
       expect(releases[0].isCached).toBe(true);
-      expect(releases[0].cachedMetadata).toBeDefined();
+      expect(releases[0].cachedMetadata).toEqual(cachedMetadata);
+      expect(releases[0].sha256Hash).toBe(cachedMetadata.sha256_hash);
     });
 
-    it('includes cached-only releases not in GitHub response', async () => {
-      const mockGitHubRelease = createMockGitHubRelease({
-        name: '2.0.0',
-        tag_name: 'v2.0.0',
+    it('includes cached-only releases returned by the backend', async () => {
+      const cachedMetadata = createMockCachedMetadata({
+        version: '1.0.0',
+        published_at: '2024-01-01T00:00:00Z',
       });
 
-      const mockCacheIndex = {
-        '1.0.0': createMockCachedMetadata({
-          version: '1.0.0',
-          published_at: '2024-01-01T00:00:00Z',
-        }),
-      };
-
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: true,
-        json: () => Promise.resolve([mockGitHubRelease]),
-      } as Response);
-
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-      vi.mocked(invoke).mockResolvedValueOnce(mockCacheIndex); // get_cache_index
+      vi.mocked(invoke).mockResolvedValueOnce(
+        createMockListing([
+          createMockReleaseInfo({ version: '2.0.0', tag_name: 'v2.0.0' }),
+          createMockReleaseInfo({
+            version: '1.0.0',
+            published_at: '2024-01-01T00:00:00Z',
+            assets: [createMockReleaseAssetInfo({ name: '1.0.0.zip', download_url: '' })],
+            is_cached: true,
+            cached_metadata: cachedMetadata,
+          }),
+        ])
+      ); // list_firmware_releases
 
       const releases = await service.fetchReleases();
 
@@ -271,28 +183,31 @@ describe('FirmwareService', () => {
       expect(releases.find((r) => r.version === '1.0.0')?.isCached).toBe(true);
     });
 
-    it('uses release name as version, falls back to tag_name', async () => {
-      const releaseWithName = createMockGitHubRelease({
-        name: 'Release Name',
-        tag_name: 'v1.0.0',
-      });
-      const releaseWithoutName = createMockGitHubRelease({
-        name: '',
-        tag_name: 'v0.9.0',
-      });
+    it('requests the given release channel', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
+      vi.mocked(invoke).mockResolvedValueOnce(
+        createMockListing([
+          createMockReleaseInfo({ version: '2.0.0-beta.1', prerelease: true }),
+        ])
+      ); // list_firmware_releases
 
-      vi.mocked(global.fetch).mockResolvedValueOnce({
-        ok: true,
-        json: () => Promise.resolve([releaseWithName, releaseWithoutName]),
-      } as Response);
+      const releases = await service.fetchReleases('beta');
 
+      expect(invoke).toHaveBeenCalledWith('list_firmware_releases', { channel: 'beta' });
+      expect(releases[0].isPrerelease).toBe(true);
+    });
+
+    it('reports stale listings from the offline copy', async () => {
       vi.mocked(invoke).mockResolvedValueOnce([]); // verify_and_clean_cache
-      vi.mocked(invoke).mockResolvedValueOnce({}); // get_cache_index
+      vi.mocked(invoke).mockResolvedValueOnce(
+        createMockListing([createMockReleaseInfo()], true)
+      ); // list_firmware_releases
 
-      const releases = await service.fetchReleases();
+      const listing = await service.fetchReleaseListing();
 
-      expect(releases.find((r) => r.version === 'Release Name')).toBeDefined();
-      expect(releases.find((r) => r.version === 'v0.9.0')).toBeDefined();
+      expect(listing.stale).toBe(true);
+      expect(listing.fetchedAt).toEqual(new Date('2024-06-01T12:00:00Z'));
+      expect(listing.releases).toHaveLength(1);
     });
   });
 
@@ -314,7 +229,7 @@ describe('FirmwareService', () => {
 
       vi.mocked(invoke)
         .mockResolvedValueOnce(null) // get_cached_firmware
-        .mockResolvedValueOnce('/cache/firmware/v1.0.0'); // download_firmware
+        .mockResolvedValueOnce({ status: 'completed', path: '/cache/firmware/v1.0.0' }); // download_firmware
 
       await service.downloadFirmware(release);
 
@@ -324,7 +239,35 @@ describe('FirmwareService', () => {
         tagName: 'v1.0.0',
         publishedAt: expect.any(String),
         releaseNotes: 'Test notes',
+        channel: 'stable',
+        downloadId: '1.0.0',
+        progress: expect.any(Object),
+      });
+    });
+
+    it('passes checksum published in release notes', async () => {
+      const hash = 'a'.repeat(64);
+      const release = createMockRelease({
+        releaseNotes: `Changes\n\nSHA256:\n${hash}  firmware.zip`,
+        assets: [
+          {
+            name: 'firmware.zip',
+            downloadUrl: 'https://test.com/firmware.zip',
+            size: 1024,
+          },
+        ],
       });
+
+      vi.mocked(invoke)
+        .mockResolvedValueOnce(null) // get_cached_firmware
+        .mockResolvedValueOnce({ status: 'completed', path: '/cache/firmware/v1.0.0' }); // download_firmware
+
+      await service.downloadFirmware(release);
+
+      expect(invoke).toHaveBeenCalledWith(
+        'download_firmware',
+        expect.objectContaining({ expectedSha256: hash })
+      );
     });
 
     it('returns local path on success', async () => {
@@ -332,7 +275,7 @@ describe('FirmwareService', () => {
 
       vi.mocked(invoke)
         .mockResolvedValueOnce(null) // get_cached_firmware
-        .mockResolvedValueOnce('/cache/firmware/v1.0.0'); // download_firmware
+        .mockResolvedValueOnce({ status: 'completed', path: '/cache/firmware/v1.0.0' }); // download_firmware
 
       const result = await service.downloadFirmware(release);
 
@@ -354,6 +297,18 @@ describe('FirmwareService', () => {
       expect(invoke).toHaveBeenCalledTimes(1);
     });
 
+    it('throws DownloadCancelledError when the download is cancelled', async () => {
+      const release = createMockRelease();
+
+      vi.mocked(invoke)
+        .mockResolvedValueOnce(null) // get_cached_firmware
+        .mockResolvedValueOnce({ status: 'cancelled' }); // download_firmware
+
+      await expect(service.downloadFirmware(release)).rejects.toBeInstanceOf(
+        DownloadCancelledError
+      );
+    });
+
     it('handles download failure', async () => {
       const release = createMockRelease();
 
@@ -413,6 +368,28 @@ describe('FirmwareService', () => {
       expect(invoke).toHaveBeenCalledWith('get_cached_firmware', { version: '1.0.0' });
     });
 
+    it('passes the asset kind filter', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce('/cache/firmware/v1.0.0');
+
+      await service.getCachedFirmware('1.0.0', 'dfu_package');
+
+      expect(invoke).toHaveBeenCalledWith('get_cached_firmware', {
+        version: '1.0.0',
+        kind: 'dfu_package',
+      });
+    });
+
+    it('can skip the integrity check', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce('/cache/firmware/v1.0.0');
+
+      await service.getCachedFirmware('1.0.0', undefined, false);
+
+      expect(invoke).toHaveBeenCalledWith('get_cached_firmware', {
+        version: '1.0.0',
+        verify: false,
+      });
+    });
+
     it('returns null when not cached', async () => {
       vi.mocked(invoke).mockResolvedValueOnce(null);
 
@@ -462,15 +439,101 @@ describe('FirmwareService', () => {
     });
   });
 
+  describe('importFirmwareZip', () => {
+    it('calls import_firmware_zip command', async () => {
+      const metadata = {
+        version: '2.1.0',
+        tag_name: '2.1.0',
+        sha256_hash: 'abc123',
+        zip_path: '/cache/firmware/2.1.0.zip',
+        downloaded_at: '2024-01-01T00:00:00Z',
+        file_size: 1024,
+        published_at: '',
+        release_notes: 'Clinic build',
+        checksum_verified: false,
+        locally_imported: true,
+      };
+      vi.mocked(invoke).mockResolvedValueOnce(metadata);
+
+      const result = await service.importFirmwareZip('/media/usb/firmware.zip', '2.1.0', 'Clinic build');
+
+      expect(invoke).toHaveBeenCalledWith('import_firmware_zip', {
+        path: '/media/usb/firmware.zip',
+        version: '2.1.0',
+        releaseNotes: 'Clinic build',
+        overwrite: undefined,
+      });
+      expect(result).toEqual(metadata);
+    });
+
+    it('passes overwrite through', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce({});
+
+      await service.importFirmwareZip('/media/usb/firmware.zip', '2.1.0', undefined, true);
+
+      expect(invoke).toHaveBeenCalledWith('import_firmware_zip', {
+        path: '/media/usb/firmware.zip',
+        version: '2.1.0',
+        releaseNotes: undefined,
+        overwrite: true,
+      });
+    });
+
+    it('throws error on failure', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('Invalid firmware package'));
+
+      await expect(
+        service.importFirmwareZip('/media/usb/firmware.zip', '2.1.0')
+      ).rejects.toThrow('Failed to import firmware');
+      expect(mockConsole.error).toHaveBeenCalledWith(
+        'Failed to import firmware:',
+        expect.any(Error)
+      );
+    });
+  });
+
+  describe('exportCachedFirmware', () => {
+    it('calls export_cached_firmware command', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce('/media/usb/bluebuzzah-firmware-2.1.0.bbfw');
+
+      const result = await service.exportCachedFirmware('2.1.0', '/media/usb');
+
+      expect(invoke).toHaveBeenCalledWith('export_cached_firmware', {
+        version: '2.1.0',
+        destPath: '/media/usb',
+      });
+      expect(result).toBe('/media/usb/bluebuzzah-firmware-2.1.0.bbfw');
+    });
+
+    it('throws error on failure', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('not cached'));
+
+      await expect(service.exportCachedFirmware('2.1.0', '/media/usb')).rejects.toThrow(
+        'Failed to export firmware'
+      );
+    });
+  });
+
   describe('deleteCachedFirmware', () => {
     it('calls delete_cached_firmware command', async () => {
-      vi.mocked(invoke).mockResolvedValueOnce(undefined);
+      vi.mocked(invoke).mockResolvedValueOnce(4096);
 
-      await service.deleteCachedFirmware('1.0.0');
+      await expect(service.deleteCachedFirmware('1.0.0')).resolves.toBe(4096);
 
       expect(invoke).toHaveBeenCalledWith('delete_cached_firmware', { version: '1.0.0' });
     });
 
+    it('passes force flag for pinned versions', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce(undefined);
+
+      await service.deleteCachedFirmware('1.0.0', true);
+
+      expect(invoke).toHaveBeenCalledWith('delete_cached_firmware', {
+        version: '1.0.0',
+        force: true,
+      });
+    });
+
     it('throws error on failure', async () => {
       vi.mocked(invoke).mockRejectedValueOnce(new Error('Delete failed'));
 
@@ -486,10 +549,16 @@ describe('FirmwareService', () => {
 
   describe('clearAllCache', () => {
     it('calls clear_all_cache command', async () => {
-      vi.mocked(invoke).mockResolvedValueOnce(undefined);
-
-      await service.clearAllCache();
+      const result = {
+        versions_removed: ['1.0.0'],
+        bytes_freed: 4096,
+        skipped_unknown_files: ['notes.txt'],
+        pinned_kept: 2,
+        failed: [],
+      };
+      vi.mocked(invoke).mockResolvedValueOnce(result);
 
+      await expect(service.clearAllCache()).resolves.toEqual(result);
       expect(invoke).toHaveBeenCalledWith('clear_all_cache');
     });
 
@@ -504,6 +573,136 @@ describe('FirmwareService', () => {
     });
   });
 
+  describe('getLatestCachedVersion', () => {
+    it('calls get_latest_cached_version with filters', async () => {
+      const metadata = createMockCachedMetadata({ version: '1.10.0' });
+      vi.mocked(invoke).mockResolvedValueOnce(metadata);
+
+      const result = await service.getLatestCachedVersion('dfu_package', 'stable');
+
+      expect(result).toEqual(metadata);
+      expect(invoke).toHaveBeenCalledWith('get_latest_cached_version', {
+        kind: 'dfu_package',
+        channel: 'stable',
+      });
+    });
+
+    it('returns null on error', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('Failed'));
+
+      await expect(service.getLatestCachedVersion()).resolves.toBeNull();
+    });
+  });
+
+  describe('downloadReleaseAssets', () => {
+    const release = createMockRelease({
+      assets: [
+        { name: 'firmware.zip', downloadUrl: 'https://test.com/firmware.zip', size: 1024 },
+        { name: 'circuitpy.zip', downloadUrl: 'https://test.com/circuitpy.zip', size: 2048 },
+        { name: 'readme.md', downloadUrl: 'https://test.com/readme.md', size: 10 },
+      ],
+    });
+
+    it('downloads every zip asset in one command', async () => {
+      const cached = [
+        createMockCachedMetadata({ version: '1.0.0' }),
+        createMockCachedMetadata({ version: '1.0.0-circuitpy' }),
+      ];
+      vi.mocked(invoke).mockResolvedValueOnce({ status: 'completed', assets: cached });
+
+      await expect(service.downloadReleaseAssets(release)).resolves.toEqual(cached);
+      expect(invoke).toHaveBeenCalledWith(
+        'download_release_assets',
+        expect.objectContaining({
+          releaseTag: 'v1.0.0',
+          version: '1.0.0',
+          downloadId: '1.0.0',
+          assets: [
+            { name: 'firmware.zip', downloadUrl: 'https://test.com/firmware.zip' },
+            { name: 'circuitpy.zip', downloadUrl: 'https://test.com/circuitpy.zip' },
+          ],
+        })
+      );
+    });
+
+    it('throws DownloadCancelledError when cancelled', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce({ status: 'cancelled' });
+
+      await expect(service.downloadReleaseAssets(release)).rejects.toBeInstanceOf(
+        DownloadCancelledError
+      );
+    });
+
+    it('includes the backend error message', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce('Failed to download circuitpy.zip: HTTP 404');
+
+      await expect(service.downloadReleaseAssets(release)).rejects.toThrow(
+        'Failed to download release assets: Failed to download circuitpy.zip: HTTP 404'
+      );
+    });
+  });
+
+  describe('cancelDownload', () => {
+    it('calls cancel_download with the version as download ID', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce(true);
+
+      await expect(service.cancelDownload('1.0.0')).resolves.toBe(true);
+      expect(invoke).toHaveBeenCalledWith('cancel_download', { downloadId: '1.0.0' });
+    });
+
+    it('returns false on error', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('Failed'));
+
+      await expect(service.cancelDownload('1.0.0')).resolves.toBe(false);
+    });
+  });
+
+  describe('cleanupCacheOlderThan', () => {
+    it('calls cleanup_cache_older_than command', async () => {
+      const result = { removed: ['1.0.0'], bytes_freed: 2048 };
+      vi.mocked(invoke).mockResolvedValueOnce(result);
+
+      await expect(service.cleanupCacheOlderThan(90)).resolves.toEqual(result);
+      expect(invoke).toHaveBeenCalledWith('cleanup_cache_older_than', { days: 90 });
+    });
+
+    it('throws error on failure', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('Cleanup failed'));
+
+      await expect(service.cleanupCacheOlderThan(90)).rejects.toThrow(
+        'Failed to clean up cache'
+      );
+    });
+  });
+
+  describe('pinCachedFirmware', () => {
+    it('calls pin_cached_firmware command', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce(undefined);
+
+      await service.pinCachedFirmware('1.0.0');
+
+      expect(invoke).toHaveBeenCalledWith('pin_cached_firmware', { version: '1.0.0' });
+    });
+
+    it('throws error on failure', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('not cached'));
+
+      await expect(service.pinCachedFirmware('1.0.0')).rejects.toThrow(
+        'Failed to pin cached firmware'
+      );
+    });
+  });
+
+  describe('unpinCachedFirmware', () => {
+    it('calls unpin_cached_firmware command', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce(undefined);
+
+      await service.unpinCachedFirmware('1.0.0');
+
+      expect(invoke).toHaveBeenCalledWith('unpin_cached_firmware', { version: '1.0.0' });
+    });
+  });
+
   describe('verifyCachedFirmware', () => {
     it('returns true when firmware is valid', async () => {
       vi.mocked(invoke).mockResolvedValueOnce(true);
@@ -535,6 +734,30 @@ describe('FirmwareService', () => {
     });
   });
 
+  describe('collectCacheGarbage', () => {
+    it('calls collect_cache_garbage command', async () => {
+      const report = {
+        indexed: ['1.0.0.zip'],
+        migratable: ['2.0.0.zip'],
+        orphans: ['3.0.0.zip.partial', 'notes.txt'],
+        removed: ['3.0.0.zip.partial'],
+        bytes_freed: 1024,
+      };
+      vi.mocked(invoke).mockResolvedValueOnce(report);
+
+      await expect(service.collectCacheGarbage()).resolves.toEqual(report);
+      expect(invoke).toHaveBeenCalledWith('collect_cache_garbage');
+    });
+
+    it('throws error on failure', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('GC failed'));
+
+      await expect(service.collectCacheGarbage()).rejects.toThrow(
+        'Failed to collect cache garbage'
+      );
+    });
+  });
+
   describe('verifyAndCleanCache', () => {
     it('returns removed versions', async () => {
       vi.mocked(invoke).mockResolvedValueOnce(['1.0.0', '2.0.0']);
@@ -542,7 +765,41 @@ describe('FirmwareService', () => {
       const result = await service.verifyAndCleanCache();
 
       expect(result).toEqual(['1.0.0', '2.0.0']);
-      expect(invoke).toHaveBeenCalledWith('verify_and_clean_cache');
+      expect(invoke).toHaveBeenCalledWith('verify_and_clean_cache', {
+        options: { skipMigration: false, collectGarbage: false },
+      });
+    });
+
+    it('migrates only on the first call', async () => {
+      vi.mocked(invoke).mockResolvedValue([]);
+
+      await service.verifyAndCleanCache();
+      await service.verifyAndCleanCache();
+
+      expect(invoke).toHaveBeenLastCalledWith('verify_and_clean_cache', {
+        options: { skipMigration: true, collectGarbage: false },
+      });
+    });
+
+    it('skips migration after migrateCache has run', async () => {
+      vi.mocked(invoke).mockResolvedValue([]);
+
+      await service.migrateCache();
+      await service.verifyAndCleanCache();
+
+      expect(invoke).toHaveBeenLastCalledWith('verify_and_clean_cache', {
+        options: { skipMigration: true, collectGarbage: false },
+      });
+    });
+
+    it('collects garbage when asked', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce([]);
+
+      await service.verifyAndCleanCache({ collectGarbage: true });
+
+      expect(invoke).toHaveBeenCalledWith('verify_and_clean_cache', {
+        options: { skipMigration: false, collectGarbage: true },
+      });
     });
 
     it('returns empty array on success with no removals', async () => {
@@ -565,4 +822,56 @@ describe('FirmwareService', () => {
       );
     });
   });
+
+  describe('migrateCache', () => {
+    it('returns migrated versions and forwards progress', async () => {
+      const onProgress = vi.fn();
+      vi.mocked(invoke).mockImplementationOnce(async (_cmd, args) => {
+        const { progress } = args as {
+          progress: { onmessage: (p: unknown) => void };
+        };
+        progress.onmessage({ file_name: 'v1.0.0.zip', current: 1, total: 1, message: '' });
+        return ['v1.0.0'];
+      });
+
+      const result = await service.migrateCache(onProgress);
+
+      expect(result).toEqual(['v1.0.0']);
+      expect(onProgress).toHaveBeenCalledWith(
+        expect.objectContaining({ file_name: 'v1.0.0.zip', current: 1 })
+      );
+    });
+
+    it('returns empty array on error', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(new Error('Failed'));
+
+      const result = await service.migrateCache();
+
+      expect(result).toEqual([]);
+      expect(mockConsole.error).toHaveBeenCalledWith(
+        'Failed to migrate firmware cache:',
+        expect.any(Error)
+      );
+    });
+  });
+
+  describe('testProxyConnection', () => {
+    it('calls test_proxy_connection with the given settings', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce('Connected to GitHub (HTTP 200)');
+      const proxy = { mode: 'manual' as const, url: 'http://proxy:8080' };
+
+      await expect(service.testProxyConnection(proxy)).resolves.toBe(
+        'Connected to GitHub (HTTP 200)'
+      );
+      expect(invoke).toHaveBeenCalledWith('test_proxy_connection', { proxy });
+    });
+
+    it('includes the backend error message', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce('Proxy rejected the credentials (HTTP 407)');
+
+      await expect(service.testProxyConnection()).rejects.toThrow(
+        'Proxy connection test failed: Proxy rejected the credentials (HTTP 407)'
+      );
+    });
+  });
 });
@@ -1,71 +1,111 @@
 import {
+    AssetKind,
+    CacheCleanupResult,
+    CacheClearResult,
+    CachedFirmwareMetadata,
+    CacheGarbageReport,
+    CacheMaintenanceOptions,
+    CacheMigrationProgress,
+    CacheStats,
+    DownloadOutcome,
+    DownloadProgress,
     FirmwareBundle,
     FirmwareCacheIndex,
     FirmwareRelease,
-    GitHubRelease,
+    FirmwareReleaseInfo,
+    ProxySettings,
+    ReleaseAssetProgress,
+    ReleaseChannel,
+    ReleaseDownloadOutcome,
+    ReleaseListing,
+    ReleaseListingInfo,
 } from '@/types';
-import { invoke } from '@tauri-apps/api/core';
+import { Channel, invoke } from '@tauri-apps/api/core';
+
+/**
+ * Thrown by downloadFirmware when the download was cancelled with
+ * cancelDownload, so callers can tell it apart from a failure.
+ */
+export class DownloadCancelledError extends Error {
+  constructor(version: string) {
+    super(`Download of firmware ${version} was cancelled`);
+    this.name = 'DownloadCancelledError';
+  }
+}
 
 export interface IFirmwareRepository {
-  fetchReleases(): Promise<FirmwareRelease[]>;
-  downloadFirmware(release: FirmwareRelease): Promise<FirmwareBundle>;
-  getCachedFirmware(version: string): Promise<string | null>;
+  fetchReleases(channel?: ReleaseChannel): Promise<FirmwareRelease[]>;
+  fetchReleaseListing(channel?: ReleaseChannel): Promise<ReleaseListing>;
+  downloadFirmware(
+    release: FirmwareRelease,
+    onProgress?: (progress: DownloadProgress) => void
+  ): Promise<FirmwareBundle>;
+  downloadReleaseAssets(
+    release: FirmwareRelease,
+    onProgress?: (progress: ReleaseAssetProgress) => void
+  ): Promise<CachedFirmwareMetadata[]>;
+  cancelDownload(version: string): Promise<boolean>;
+  getCachedFirmware(
+    version: string,
+    kind?: AssetKind,
+    verify?: boolean
+  ): Promise<string | null>;
+  getLatestCachedVersion(
+    kind?: AssetKind,
+    channel?: ReleaseChannel
+  ): Promise<CachedFirmwareMetadata | null>;
   getCacheIndex(): Promise<FirmwareCacheIndex>;
-  deleteCachedFirmware(version: string): Promise<void>;
-  clearAllCache(): Promise<void>;
+  getCacheStats(): Promise<CacheStats>;
+  importFirmwareZip(
+    path: string,
+    version?: string,
+    releaseNotes?: string,
+    overwrite?: boolean
+  ): Promise<CachedFirmwareMetadata>;
+  exportCachedFirmware(version: string, destPath: string): Promise<string>;
+  deleteCachedFirmware(version: string, force?: boolean): Promise<number>;
+  clearAllCache(): Promise<CacheClearResult>;
+  cleanupCacheOlderThan(days: number): Promise<CacheCleanupResult>;
+  pinCachedFirmware(version: string): Promise<void>;
+  unpinCachedFirmware(version: string): Promise<void>;
   verifyCachedFirmware(version: string): Promise<boolean>;
-  verifyAndCleanCache(): Promise<string[]>;
+  migrateCache(onProgress?: (progress: CacheMigrationProgress) => void): Promise<string[]>;
+  verifyAndCleanCache(options?: CacheMaintenanceOptions): Promise<string[]>;
+  collectCacheGarbage(): Promise<CacheGarbageReport>;
+  testProxyConnection(proxy?: ProxySettings): Promise<string>;
 }
 
 export class FirmwareService implements IFirmwareRepository {
-  private readonly GITHUB_API_URL =
-    'https://api.github.com/repos/BlueBuzzah/BlueBuzzah-Firmware/releases';
+  // Un-indexed zips only need hashing into the index once per session
+  private cacheMigrated = false;
+
+  /**
+   * List releases for a channel (defaults to the saved setting). Cached
+   * versions GitHub no longer lists are included by the backend.
+   */
+  async fetchReleases(channel?: ReleaseChannel): Promise<FirmwareRelease[]> {
+    const listing = await this.fetchReleaseListing(channel);
+    return listing.releases;
+  }
 
-  async fetchReleases(): Promise<FirmwareRelease[]> {
+  /**
+   * Like fetchReleases, but also reports whether the list came from the
+   * offline copy and when it was last confirmed with GitHub.
+   */
+  async fetchReleaseListing(channel?: ReleaseChannel): Promise<ReleaseListing> {
     try {
       // Verify and clean stale cache entries before loading
       await this.verifyAndCleanCache();
 
-      const controller = new AbortController();
-      const timeoutId = setTimeout(() => controller.abort(), 15_000);
-
-      let response: Response;
-      try {
-        response = await fetch(this.GITHUB_API_URL, { signal: controller.signal });
-      } catch (error) {
-        if (error instanceof DOMException && error.name === 'AbortError') {
-          throw new Error('Request timed out while fetching firmware releases. Check your internet connection and try again.');
-        }
-        throw error;
-      } finally {
-        clearTimeout(timeoutId);
-      }
-
-      if (response.status === 403 || response.status === 429) {
-        const resetHeader = response.headers.get('X-RateLimit-Reset');
-        if (resetHeader) {
-          const resetTime = new Date(parseInt(resetHeader, 10) * 1000);
-          const waitMinutes = Math.max(1, Math.ceil((resetTime.getTime() - Date.now()) / 60_000));
-          throw new Error(`GitHub API rate limit exceeded. Try again in ${waitMinutes} minute${waitMinutes === 1 ? '' : 's'}.`);
-        }
-        throw new Error('GitHub API rate limit exceeded. Try again later.');
-      }
-
-      if (!response.ok) {
-        throw new Error(`GitHub API error: ${response.statusText}`);
-      }
-
-      const releases: GitHubRelease[] = await response.json();
-
-      // Get cache index to mark cached releases
-      const cacheIndex = await this.getCacheIndex();
+      // Backend handles GitHub pagination, rate limits, channels and cache annotation
+      const listing = await invoke<ReleaseListingInfo>('list_firmware_releases', {
+        channel,
+      });
 
-      // Map GitHub releases and mark cached ones
-      const githubVersions = new Set<string>();
-      const firmwareReleases = releases.map((release) => {
+      // Map releases and mark cached ones
+      const firmwareReleases = listing.releases.map((release) => {
         const transformed = this.transformRelease(release);
-        githubVersions.add(transformed.version);
-        const cachedMetadata = cacheIndex[transformed.version];
+        const cachedMetadata = release.cached_metadata;
 
         if (cachedMetadata) {
           return {
@@ -79,52 +119,32 @@ export class FirmwareService implements IFirmwareRepository {
         return transformed;
       });
 
-      // Add cached-only releases (not in GitHub response)
-      for (const [version, cachedMetadata] of Object.entries(cacheIndex)) {
-        if (!githubVersions.has(version)) {
-          // Create release from cached metadata
-          const cachedRelease: FirmwareRelease = {
-            version: cachedMetadata.version,
-            tagName: cachedMetadata.tag_name,
-            releaseNotes: cachedMetadata.release_notes,
-            publishedAt: cachedMetadata.published_at
-              ? new Date(cachedMetadata.published_at)
-              : new Date(cachedMetadata.downloaded_at),
-            downloadUrl: '', // No URL for cached-only
-            assets: [
-              {
-                name: `${version}.zip`,
-                downloadUrl: '',
-                size: cachedMetadata.file_size,
-              },
-            ],
-            isCached: true,
-            cachedMetadata,
-            sha256Hash: cachedMetadata.sha256_hash,
-          };
-
-          firmwareReleases.push(cachedRelease);
-        }
-      }
-
       // Sort by published date (newest first)
       firmwareReleases.sort(
         (a, b) => b.publishedAt.getTime() - a.publishedAt.getTime()
       );
 
-      return firmwareReleases;
+      return {
+        releases: firmwareReleases,
+        stale: listing.stale,
+        fetchedAt: new Date(listing.fetched_at),
+      };
     } catch (error) {
       console.error('Failed to fetch releases:', error);
-      throw new Error(
-        `Failed to fetch firmware releases: ${error instanceof Error ? error.message : 'Unknown error'}`
-      );
+      // Backend errors arrive as plain strings
+      const message =
+        error instanceof Error ? error.message : typeof error === 'string' ? error : 'Unknown error';
+      throw new Error(`Failed to fetch firmware releases: ${message}`);
     }
   }
 
-  async downloadFirmware(release: FirmwareRelease): Promise<FirmwareBundle> {
+  async downloadFirmware(
+    release: FirmwareRelease,
+    onProgress?: (progress: DownloadProgress) => void
+  ): Promise<FirmwareBundle> {
     try {
-      // Check if firmware is already cached
-      const cachedPath = await this.getCachedFirmware(release.version);
+      // Check if firmware is already cached (the DFU flow needs the DFU package)
+      const cachedPath = await this.getCachedFirmware(release.version, 'dfu_package');
 
       if (cachedPath) {
         return {
@@ -142,20 +162,41 @@ export class FirmwareService implements IFirmwareRepository {
         throw new Error('No firmware zip file found in release assets');
       }
 
-      // Download firmware using Tauri command with metadata
-      const localPath = await invoke<string>('download_firmware', {
+      // Published checksum (if any) lets the backend reject corrupted downloads
+      const expectedSha256 = await this.findPublishedSha256(release, firmwareAsset.name);
+
+      // Channel for retry notifications while the download is in flight
+      const progressChannel = new Channel<DownloadProgress>();
+      progressChannel.onmessage = (progress) => {
+        onProgress?.(progress);
+      };
+
+      // Download firmware using Tauri command with metadata.
+      // The version doubles as the download ID for cancelDownload.
+      const outcome = await invoke<DownloadOutcome>('download_firmware', {
         url: firmwareAsset.downloadUrl,
         version: release.version,
         tagName: release.tagName,
         publishedAt: release.publishedAt.toISOString(),
         releaseNotes: release.releaseNotes,
+        expectedSha256,
+        channel: release.isPrerelease ? 'beta' : 'stable',
+        downloadId: release.version,
+        progress: progressChannel,
       });
 
+      if (outcome.status === 'cancelled') {
+        throw new DownloadCancelledError(release.version);
+      }
+
       return {
         version: release.version,
-        localPath,
+        localPath: outcome.path,
       };
     } catch (error) {
+      if (error instanceof DownloadCancelledError) {
+        throw error;
+      }
       console.error('Failed to download firmware:', error);
       throw new Error(
         `Failed to download firmware: ${error instanceof Error ? error.message : 'Unknown error'}`
@@ -163,10 +204,126 @@ export class FirmwareService implements IFirmwareRepository {
     }
   }
 
-  async getCachedFirmware(version: string): Promise<string | null> {
+  /**
+   * Download every zip asset of a release (DFU package and CircuitPython
+   * bundle) as one set. The backend caches all of them or none, so a
+   * failure never leaves mismatched halves. Cancel with cancelDownload.
+   */
+  async downloadReleaseAssets(
+    release: FirmwareRelease,
+    onProgress?: (progress: ReleaseAssetProgress) => void
+  ): Promise<CachedFirmwareMetadata[]> {
+    try {
+      const zipAssets = release.assets.filter((asset) => asset.name.endsWith('.zip'));
+      if (zipAssets.length === 0) {
+        throw new Error('No firmware zip file found in release assets');
+      }
+
+      const assets = await Promise.all(
+        zipAssets.map(async (asset) => ({
+          name: asset.name,
+          downloadUrl: asset.downloadUrl,
+          expectedSha256: await this.findPublishedSha256(release, asset.name),
+        }))
+      );
+
+      const progressChannel = new Channel<ReleaseAssetProgress>();
+      progressChannel.onmessage = (progress) => {
+        onProgress?.(progress);
+      };
+
+      const outcome = await invoke<ReleaseDownloadOutcome>('download_release_assets', {
+        releaseTag: release.tagName,
+        version: release.version,
+        publishedAt: release.publishedAt.toISOString(),
+        releaseNotes: release.releaseNotes,
+        channel: release.isPrerelease ? 'beta' : 'stable',
+        assets,
+        downloadId: release.version,
+        progress: progressChannel,
+      });
+
+      if (outcome.status === 'cancelled') {
+        throw new DownloadCancelledError(release.version);
+      }
+      return outcome.assets;
+    } catch (error) {
+      if (error instanceof DownloadCancelledError) {
+        throw error;
+      }
+      console.error('Failed to download release assets:', error);
+      const message =
+        error instanceof Error ? error.message : typeof error === 'string' ? error : 'Unknown error';
+      throw new Error(`Failed to download release assets: ${message}`);
+    }
+  }
+
+  /**
+   * Cancel an in-flight downloadFirmware call for a version. The partial
+   * file is kept so a later download resumes. Resolves to false if no
+   * download for the version was running.
+   */
+  async cancelDownload(version: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>('cancel_download', { downloadId: version });
+    } catch (error) {
+      console.error('Failed to cancel download:', error);
+      return false;
+    }
+  }
+
+  /**
+   * Look up the published SHA256 for an asset, from a SHA256SUMS release
+   * asset or a "<hash>  <filename>" line in the release notes.
+   */
+  private async findPublishedSha256(
+    release: FirmwareRelease,
+    assetName: string
+  ): Promise<string | undefined> {
+    const fromText = (text: string): string | undefined => {
+      for (const line of text.split('\n')) {
+        const match = line.trim().match(/^([a-fA-F0-9]{64})\s+\*?(\S+)$/);
+        if (match && match[2] === assetName) {
+          return match[1].toLowerCase();
+        }
+      }
+      return undefined;
+    };
+
+    const sumsAsset = release.assets.find((asset) => asset.name === 'SHA256SUMS');
+    if (sumsAsset?.downloadUrl) {
+      try {
+        const response = await fetch(sumsAsset.downloadUrl);
+        if (response.ok) {
+          const hash = fromText(await response.text());
+          if (hash) {
+            return hash;
+          }
+        }
+      } catch (error) {
+        console.warn('Failed to fetch SHA256SUMS:', error);
+      }
+    }
+
+    return fromText(release.releaseNotes ?? '');
+  }
+
+  /**
+   * Resolve a cached version to its zip path. With `kind`, a cached zip of
+   * another asset kind is treated as not cached. The zip is re-hashed first
+   * unless `verify` is false; a corrupt copy is dropped and reported as not
+   * cached so it gets downloaded again.
+   */
+  async getCachedFirmware(
+    version: string,
+    kind?: AssetKind,
+    verify?: boolean
+  ): Promise<string | null> {
     try {
       const result = await invoke<string | null>('get_cached_firmware', {
         version,
+        kind,
+        verify,
       });
       return result;
     } catch (error) {
@@ -175,6 +332,25 @@ export class FirmwareService implements IFirmwareRepository {
     }
   }
 
+  /**
+   * Newest cached version (compared semver-style, so 1.10.0 beats 1.2.0),
+   * optionally limited to an asset kind and channel. Works offline.
+   */
+  async getLatestCachedVersion(
+    kind?: AssetKind,
+    channel?: ReleaseChannel
+  ): Promise<CachedFirmwareMetadata | null> {
+    try {
+      return await invoke<CachedFirmwareMetadata | null>('get_latest_cached_version', {
+        kind,
+        channel,
+      });
+    } catch (error) {
+      console.error('Failed to find latest cached firmware:', error);
+      return null;
+    }
+  }
+
   async getCacheIndex(): Promise<FirmwareCacheIndex> {
     try {
       const result = await invoke<FirmwareCacheIndex>('get_cache_index');
@@ -185,9 +361,59 @@ export class FirmwareService implements IFirmwareRepository {
     }
   }
 
-  async deleteCachedFirmware(version: string): Promise<void> {
+  async getCacheStats(): Promise<CacheStats> {
     try {
-      await invoke('delete_cached_firmware', { version });
+      return await invoke<CacheStats>('get_cache_stats');
+    } catch (error) {
+      console.error('Failed to get cache stats:', error);
+      throw new Error(
+        `Failed to get cache stats: ${error instanceof Error ? error.message : 'Unknown error'}`
+      );
+    }
+  }
+
+  /**
+   * Import a local firmware.zip or .bbfw bundle into the cache. The version
+   * is required for plain zips and defaults to the bundle's own for bundles.
+   * A version already in the cache is only replaced when `overwrite` is set,
+   * and a pinned one never is.
+   */
+  async importFirmwareZip(
+    path: string,
+    version?: string,
+    releaseNotes?: string,
+    overwrite?: boolean
+  ): Promise<CachedFirmwareMetadata> {
+    try {
+      return await invoke<CachedFirmwareMetadata>('import_firmware_zip', {
+        path,
+        version,
+        releaseNotes,
+        overwrite,
+      });
+    } catch (error) {
+      console.error('Failed to import firmware:', error);
+      throw new Error(
+        `Failed to import firmware: ${error instanceof Error ? error.message : 'Unknown error'}`
+      );
+    }
+  }
+
+  async exportCachedFirmware(version: string, destPath: string): Promise<string> {
+    try {
+      return await invoke<string>('export_cached_firmware', { version, destPath });
+    } catch (error) {
+      console.error('Failed to export firmware:', error);
+      throw new Error(
+        `Failed to export firmware: ${error instanceof Error ? error.message : 'Unknown error'}`
+      );
+    }
+  }
+
+  /** Delete a cached version (pinned ones need `force`). Resolves to bytes freed. */
+  async deleteCachedFirmware(version: string, force?: boolean): Promise<number> {
+    try {
+      return await invoke<number>('delete_cached_firmware', { version, force });
     } catch (error) {
       console.error('Failed to delete cached firmware:', error);
       throw new Error(
@@ -196,9 +422,10 @@ export class FirmwareService implements IFirmwareRepository {
     }
   }
 
-  async clearAllCache(): Promise<void> {
+  /** Clear the cache, keeping pinned versions and files the app didn't create. */
+  async clearAllCache(): Promise<CacheClearResult> {
     try {
-      await invoke('clear_all_cache');
+      return await invoke<CacheClearResult>('clear_all_cache');
     } catch (error) {
       console.error('Failed to clear cache:', error);
       throw new Error(
@@ -207,6 +434,40 @@ export class FirmwareService implements IFirmwareRepository {
     }
   }
 
+  /** Remove unpinned versions not downloaded or used in the last `days` days. */
+  async cleanupCacheOlderThan(days: number): Promise<CacheCleanupResult> {
+    try {
+      return await invoke<CacheCleanupResult>('cleanup_cache_older_than', { days });
+    } catch (error) {
+      console.error('Failed to clean up cache:', error);
+      throw new Error(
+        `Failed to clean up cache: ${error instanceof Error ? error.message : 'Unknown error'}`
+      );
+    }
+  }
+
+  async pinCachedFirmware(version: string): Promise<void> {
+    try {
+      await invoke('pin_cached_firmware', { version });
+    } catch (error) {
+      console.error('Failed to pin cached firmware:', error);
+      throw new Error(
+        `Failed to pin cached firmware: ${error instanceof Error ? error.message : 'Unknown error'}`
+      );
+    }
+  }
+
+  async unpinCachedFirmware(version: string): Promise<void> {
+    try {
+      await invoke('unpin_cached_firmware', { version });
+    } catch (error) {
+      console.error('Failed to unpin cached firmware:', error);
+      throw new Error(
+        `Failed to unpin cached firmware: ${error instanceof Error ? error.message : 'Unknown error'}`
+      );
+    }
+  }
+
   async verifyCachedFirmware(version: string): Promise<boolean> {
     try {
       const result = await invoke<boolean>('verify_cached_firmware', {
@@ -219,9 +480,40 @@ export class FirmwareService implements IFirmwareRepository {
     }
   }
 
-  async verifyAndCleanCache(): Promise<string[]> {
+  /**
+   * Hash zips left by older versions into the cache index. Runs in the
+   * background on the backend; `onProgress` reports each zip as it's hashed.
+   */
+  async migrateCache(
+    onProgress?: (progress: CacheMigrationProgress) => void
+  ): Promise<string[]> {
     try {
-      const removedVersions = await invoke<string[]>('verify_and_clean_cache');
+      const progress = new Channel<CacheMigrationProgress>();
+      if (onProgress) {
+        progress.onmessage = onProgress;
+      }
+      const migrated = await invoke<string[]>('migrate_firmware_cache', { progress });
+      this.cacheMigrated = true;
+      return migrated;
+    } catch (error) {
+      console.error('Failed to migrate firmware cache:', error);
+      return [];
+    }
+  }
+
+  /**
+   * Drop stale cache entries. Migration is skipped once it has run this
+   * session unless `options.skipMigration` says otherwise.
+   */
+  async verifyAndCleanCache(options?: CacheMaintenanceOptions): Promise<string[]> {
+    try {
+      const skipMigration = options?.skipMigration ?? this.cacheMigrated;
+      const removedVersions = await invoke<string[]>('verify_and_clean_cache', {
+        options: { skipMigration, collectGarbage: options?.collectGarbage ?? false },
+      });
+      if (!skipMigration) {
+        this.cacheMigrated = true;
+      }
       if (removedVersions.length > 0) {
         console.log(
           `Cleaned ${removedVersions.length} stale cache entries:`,
@@ -235,21 +527,52 @@ export class FirmwareService implements IFirmwareRepository {
     }
   }
 
-  private transformRelease(githubRelease: GitHubRelease): FirmwareRelease {
+  /**
+   * Delete orphaned files from the cache folder (old partial downloads,
+   * stray files and directories). Indexed and migratable zips are kept.
+   */
+  async collectCacheGarbage(): Promise<CacheGarbageReport> {
+    try {
+      return await invoke<CacheGarbageReport>('collect_cache_garbage');
+    } catch (error) {
+      console.error('Failed to collect cache garbage:', error);
+      throw new Error(
+        `Failed to collect cache garbage: ${error instanceof Error ? error.message : 'Unknown error'}`
+      );
+    }
+  }
+
+  /**
+   * Check GitHub is reachable through a proxy configuration (defaults to
+   * the saved one). Resolves with a short status message.
+   */
+  async testProxyConnection(proxy?: ProxySettings): Promise<string> {
+    try {
+      return await invoke<string>('test_proxy_connection', { proxy });
+    } catch (error) {
+      console.error('Proxy connection test failed:', error);
+      const message =
+        error instanceof Error ? error.message : typeof error === 'string' ? error : 'Unknown error';
+      throw new Error(`Proxy connection test failed: ${message}`);
+    }
+  }
+
+  private transformRelease(release: FirmwareReleaseInfo): FirmwareRelease {
     return {
-      version: githubRelease.name || githubRelease.tag_name,
-      tagName: githubRelease.tag_name,
-      releaseNotes: githubRelease.body || 'No release notes available',
-      publishedAt: new Date(githubRelease.published_at),
-      downloadUrl: githubRelease.assets[0]?.browser_download_url || '',
-      assets: githubRelease.assets.map((asset) => ({
+      version: release.version,
+      tagName: release.tag_name,
+      releaseNotes: release.release_notes,
+      publishedAt: release.published_at ? new Date(release.published_at) : new Date(0),
+      downloadUrl: release.assets[0]?.download_url || '',
+      assets: release.assets.map((asset) => ({
         name: asset.name,
-        downloadUrl: asset.browser_download_url,
+        downloadUrl: asset.download_url,
         size: asset.size,
       })),
-      isPrerelease: githubRelease.prerelease,
+      isPrerelease: release.prerelease,
     };
   }
+
 }
 
 // Singleton instance
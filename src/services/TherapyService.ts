@@ -1,10 +1,16 @@
 import { Channel, invoke } from '@tauri-apps/api/core';
 import type {
+  AppliedConfiguration,
+  AvailableProfile,
   Device,
+  DeviceRole,
+  DeviceSettings,
+  PreviewCommand,
   TherapyProfile,
   TherapyConfigProgress,
   TherapyConfigStage,
 } from '@/types';
+import { toCommandError } from '@/lib/error-messages';
 import { useSettingsStore } from '@/stores/settingsStore';
 
 /**
@@ -14,6 +20,9 @@ interface ProfileProgressEvent {
   stage: string;
   percent: number;
   message: string;
+  seq: number;
+  emitted_at_ms: number;
+  operation_id?: number;
 }
 
 /**
@@ -36,6 +45,16 @@ function mapBackendStage(stage: string): TherapyConfigStage {
   }
 }
 
+/**
+ * Device settings are keyed by USB serial number, which some boards lack.
+ */
+function requireSerialNumber(device: Device): string {
+  if (!device.serialNumber) {
+    throw new Error(`${device.label} has no serial number for per-device settings`);
+  }
+  return device.serialNumber;
+}
+
 export interface ITherapyService {
   /**
    * Configure the therapy profile for a device.
@@ -46,6 +65,49 @@ export interface ITherapyService {
     profile: TherapyProfile,
     onProgress?: (progress: TherapyConfigProgress) => void
   ): Promise<void>;
+
+  /**
+   * Change a device's role without reflashing it.
+   */
+  configureRole(
+    device: Device,
+    role: DeviceRole,
+    onProgress?: (progress: TherapyConfigProgress) => void
+  ): Promise<void>;
+
+  /**
+   * Apply the saved advanced settings and a profile to a device. The profile
+   * may be a built-in one or a custom profile from the saved settings.
+   * Resolves with the setting commands that were sent, and those skipped
+   * because the firmware version read earlier predates them.
+   */
+  applyConfiguration(
+    device: Device,
+    profile: string,
+    onProgress?: (progress: TherapyConfigProgress) => void
+  ): Promise<AppliedConfiguration>;
+
+  /**
+   * List the serial commands applyConfiguration would send, in order,
+   * without talking to the device.
+   */
+  previewConfiguration(device: Device, profile: string): Promise<PreviewCommand[]>;
+
+  /**
+   * List the built-in profiles followed by the custom ones from settings.
+   */
+  getAvailableProfiles(): Promise<AvailableProfile[]>;
+
+  /**
+   * Get the settings overrides saved for a device.
+   */
+  getDeviceSettings(device: Device): Promise<DeviceSettings>;
+
+  /**
+   * Save settings overrides for a device, used by applyConfiguration.
+   * Passing {} removes them.
+   */
+  saveDeviceSettings(device: Device, settings: DeviceSettings): Promise<void>;
 }
 
 export class TherapyService implements ITherapyService {
@@ -57,7 +119,13 @@ export class TherapyService implements ITherapyService {
     // Create channel for progress updates from backend
     const progressChannel = new Channel<ProfileProgressEvent>();
 
+    let lastSeq = 0;
     progressChannel.onmessage = (event) => {
+      // Drop events that arrive after a newer one
+      if (event.seq <= lastSeq) {
+        return;
+      }
+      lastSeq = event.seq;
       onProgress?.({
         devicePath: device.path,
         stage: mapBackendStage(event.stage),
@@ -75,6 +143,95 @@ export class TherapyService implements ITherapyService {
       profile: profile,
       advancedSettings: settings,
       progress: progressChannel,
+    }).catch((error) => {
+      throw toCommandError(error);
+    });
+  }
+
+  async configureRole(
+    device: Device,
+    role: DeviceRole,
+    onProgress?: (progress: TherapyConfigProgress) => void
+  ): Promise<void> {
+    const progressChannel = new Channel<ProfileProgressEvent>();
+
+    let lastSeq = 0;
+    progressChannel.onmessage = (event) => {
+      // Drop events that arrive after a newer one
+      if (event.seq <= lastSeq) {
+        return;
+      }
+      lastSeq = event.seq;
+      onProgress?.({
+        devicePath: device.path,
+        stage: mapBackendStage(event.stage),
+        progress: event.percent,
+        message: event.message,
+      });
+    };
+
+    await invoke('set_device_role', {
+      serialPort: device.path,
+      role,
+      progress: progressChannel,
+    }).catch((error) => {
+      throw toCommandError(error);
+    });
+  }
+
+  async applyConfiguration(
+    device: Device,
+    profile: string,
+    onProgress?: (progress: TherapyConfigProgress) => void
+  ): Promise<AppliedConfiguration> {
+    const progressChannel = new Channel<ProfileProgressEvent>();
+
+    let lastSeq = 0;
+    progressChannel.onmessage = (event) => {
+      // Drop events that arrive after a newer one
+      if (event.seq <= lastSeq) {
+        return;
+      }
+      lastSeq = event.seq;
+      onProgress?.({
+        devicePath: device.path,
+        stage: mapBackendStage(event.stage),
+        progress: event.percent,
+        message: event.message,
+      });
+    };
+
+    // Settings come from the backend's saved copy, not the store
+    return invoke<AppliedConfiguration>('apply_device_configuration', {
+      serialPort: device.path,
+      profile,
+      progress: progressChannel,
+    }).catch((error) => {
+      throw toCommandError(error);
+    });
+  }
+
+  async previewConfiguration(device: Device, profile: string): Promise<PreviewCommand[]> {
+    return invoke<PreviewCommand[]>('preview_device_configuration', {
+      serialPortOrSerial: device.path,
+      profile,
+    });
+  }
+
+  async getAvailableProfiles(): Promise<AvailableProfile[]> {
+    return invoke<AvailableProfile[]>('get_available_profiles');
+  }
+
+  async getDeviceSettings(device: Device): Promise<DeviceSettings> {
+    return invoke<DeviceSettings>('get_device_settings', {
+      serialNumber: requireSerialNumber(device),
+    });
+  }
+
+  async saveDeviceSettings(device: Device, settings: DeviceSettings): Promise<void> {
+    await invoke('save_device_settings', {
+      serialNumber: requireSerialNumber(device),
+      settings,
     });
   }
 }
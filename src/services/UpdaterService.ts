@@ -1,6 +1,6 @@
-import { check, Update } from '@tauri-apps/plugin-updater';
+import { Channel, invoke } from '@tauri-apps/api/core';
 import { relaunch } from '@tauri-apps/plugin-process';
-import { AppUpdateInfo, AppUpdateProgress, UpdaterErrorInfo } from '@/types';
+import { AppUpdateInfo, AppUpdateProgress, ReleaseChannel, UpdaterErrorInfo } from '@/types';
 import { extractUpdaterError } from '@/lib/updater-errors';
 
 /**
@@ -17,88 +17,62 @@ export class UpdaterError extends Error {
 }
 
 export interface IUpdaterRepository {
-  checkForUpdate(): Promise<AppUpdateInfo | null>;
+  checkForUpdate(channel?: ReleaseChannel): Promise<AppUpdateInfo | null>;
   downloadAndInstall(
     onProgress?: (progress: AppUpdateProgress) => void
   ): Promise<void>;
+  setUpdateChannel(channel: ReleaseChannel): Promise<void>;
   relaunchApp(): Promise<void>;
 }
 
 export class UpdaterService implements IUpdaterRepository {
-  private currentUpdate: Update | null = null;
-
-  async checkForUpdate(): Promise<AppUpdateInfo | null> {
+  /**
+   * Check for a newer app version. The channel defaults to the one saved in
+   * settings; the backend keeps the update found for downloadAndInstall.
+   */
+  async checkForUpdate(channel?: ReleaseChannel): Promise<AppUpdateInfo | null> {
     try {
-      const update = await check();
-
-      if (!update) {
-        this.currentUpdate = null;
-        return null;
-      }
-
-      this.currentUpdate = update;
-
-      return {
-        version: update.version,
-        currentVersion: update.currentVersion,
-        releaseNotes: update.body || 'No release notes available.',
-        releaseDate: update.date || null,
-      };
+      return await invoke<AppUpdateInfo | null>('check_for_app_update', {
+        channel: channel ?? null,
+      });
     } catch (error) {
       console.error('Failed to check for updates:', error);
       throw new UpdaterError(extractUpdaterError(error, 'check'));
     }
   }
 
+  /**
+   * Download and install the update found by the last check. If a firmware
+   * flash is running, the install waits for it with stage 'waiting'.
+   */
   async downloadAndInstall(
     onProgress?: (progress: AppUpdateProgress) => void
   ): Promise<void> {
-    if (!this.currentUpdate) {
-      throw new Error('No update available to install');
+    const progress = new Channel<AppUpdateProgress>();
+    if (onProgress) {
+      progress.onmessage = onProgress;
     }
 
     try {
-      let downloaded = 0;
-      let total = 0;
-
-      await this.currentUpdate.downloadAndInstall((event) => {
-        switch (event.event) {
-          case 'Started':
-            total = event.data.contentLength ?? 0;
-            onProgress?.({
-              stage: 'downloading',
-              downloaded: 0,
-              total,
-              percent: 0,
-            });
-            break;
-          case 'Progress':
-            downloaded += event.data.chunkLength;
-            const percent =
-              total > 0 ? Math.round((downloaded / total) * 100) : 0;
-            onProgress?.({
-              stage: 'downloading',
-              downloaded,
-              total,
-              percent,
-            });
-            break;
-          case 'Finished':
-            onProgress?.({
-              stage: 'ready',
-              downloaded: total,
-              total,
-              percent: 100,
-            });
-            break;
-        }
-      });
+      await invoke('install_app_update', { progress });
     } catch (error) {
       console.error('Failed to download and install update:', error);
       throw new UpdaterError(extractUpdaterError(error, 'install'));
     }
   }
 
+  /**
+   * Save the release channel the app updates itself from.
+   */
+  async setUpdateChannel(channel: ReleaseChannel): Promise<void> {
+    try {
+      await invoke('set_update_channel', { channel });
+    } catch (error) {
+      console.error('Failed to set update channel:', error);
+      throw error;
+    }
+  }
+
   async relaunchApp(): Promise<void> {
     try {
       await relaunch();
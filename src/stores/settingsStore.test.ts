@@ -1,6 +1,7 @@
 import { describe, it, expect, beforeEach, vi } from 'vitest';
 import { useSettingsStore } from './settingsStore';
 import { invoke } from '@tauri-apps/api/core';
+import type { AdvancedSettings } from '@/types';
 
 describe('settingsStore', () => {
   beforeEach(() => {
@@ -14,6 +15,9 @@ describe('settingsStore', () => {
       isLoaded: false,
       isSyncing: false,
       loadError: null,
+      saveError: null,
+      fieldErrors: [],
+      revision: 0,
     });
     vi.resetAllMocks();
   });
@@ -37,6 +41,7 @@ describe('settingsStore', () => {
         disableLedDuringTherapy: true,
         debugMode: false,
         selectedProfile: 'REGULAR',
+        revision: 4,
       });
 
       await useSettingsStore.getState().loadFromBackend();
@@ -45,6 +50,8 @@ describe('settingsStore', () => {
       expect(state.isLoaded).toBe(true);
       expect(state.loadError).toBeNull();
       expect(state.settings.disableLedDuringTherapy).toBe(true);
+      expect(state.settings).not.toHaveProperty('revision');
+      expect(state.revision).toBe(4);
     });
 
     it('uses localStorage fallback when backend fails', async () => {
@@ -57,6 +64,175 @@ describe('settingsStore', () => {
     });
   });
 
+  describe('applyChange', () => {
+    const noisy: AdvancedSettings = {
+      disableLedDuringTherapy: false,
+      debugMode: false,
+      selectedProfile: 'NOISY',
+    };
+    const gentle: AdvancedSettings = {
+      disableLedDuringTherapy: true,
+      debugMode: false,
+      selectedProfile: 'GENTLE',
+    };
+
+    it('ignores changes older than the settings shown', () => {
+      const { applyChange } = useSettingsStore.getState();
+
+      applyChange({ scope: { scope: 'global' }, settings: gentle, revision: 3 });
+      applyChange({ scope: { scope: 'global' }, settings: noisy, revision: 2 });
+
+      const state = useSettingsStore.getState();
+      expect(state.settings).toEqual(gentle);
+      expect(state.revision).toBe(3);
+    });
+
+    it('keeps the global settings for a device change', () => {
+      const settings = useSettingsStore.getState().settings;
+
+      useSettingsStore.getState().applyChange({
+        scope: { scope: 'device', serial_number: 'ABC123' },
+        settings: gentle,
+        revision: 1,
+      });
+
+      expect(useSettingsStore.getState().settings).toBe(settings);
+      expect(useSettingsStore.getState().revision).toBe(1);
+    });
+  });
+
+  describe('syncToBackend', () => {
+    it('sets saveError when the backend rejects the settings', async () => {
+      vi.mocked(invoke).mockRejectedValueOnce(
+        'Session duration must be between 15 and 480 minutes, got 10'
+      );
+
+      await useSettingsStore.getState().syncToBackend();
+
+      expect(useSettingsStore.getState().saveError).toBe(
+        'Session duration must be between 15 and 480 minutes, got 10'
+      );
+    });
+
+    it('keeps the invalid fields when the backend rejects them', async () => {
+      const errors = [
+        {
+          field: 'vibrationIntensity',
+          message: 'Vibration intensity must be between 0 and 100, got 150',
+        },
+        { field: 'jitterPercent', message: 'Jitter must be between 0.0 and 50.0 percent, got 900' },
+      ];
+      vi.mocked(invoke).mockRejectedValueOnce({ errors });
+
+      await useSettingsStore.getState().syncToBackend();
+
+      const state = useSettingsStore.getState();
+      expect(state.fieldErrors).toEqual(errors);
+      expect(state.saveError).toBe(
+        'Vibration intensity must be between 0 and 100, got 150; ' +
+          'Jitter must be between 0.0 and 50.0 percent, got 900'
+      );
+    });
+
+    it('clears saveError once a save succeeds', async () => {
+      useSettingsStore.setState({
+        saveError: 'Previous error',
+        fieldErrors: [{ field: 'jitterPercent', message: 'Previous error' }],
+      });
+      vi.mocked(invoke).mockResolvedValueOnce(undefined);
+
+      await useSettingsStore.getState().syncToBackend();
+
+      expect(useSettingsStore.getState().saveError).toBeNull();
+      expect(useSettingsStore.getState().fieldErrors).toEqual([]);
+    });
+  });
+
+  describe('resetOnBackend', () => {
+    it('adopts the effective settings after a global reset', async () => {
+      useSettingsStore.setState({
+        settings: { disableLedDuringTherapy: true, debugMode: true, selectedProfile: 'NOISY' },
+      });
+      const previous = useSettingsStore.getState().settings;
+      const effective = { disableLedDuringTherapy: false, debugMode: false, selectedProfile: null };
+      vi.mocked(invoke).mockResolvedValueOnce({
+        effective,
+        previous: { global: previous, devices: {} },
+      });
+
+      const result = await useSettingsStore.getState().resetOnBackend({ scope: 'global' });
+
+      expect(invoke).toHaveBeenCalledWith('reset_advanced_settings', {
+        scope: { scope: 'global' },
+      });
+      expect(useSettingsStore.getState().settings).toEqual(effective);
+      expect(result.previous.global).toEqual(previous);
+    });
+
+    it('keeps the global settings after a device reset', async () => {
+      const settings = { disableLedDuringTherapy: true, debugMode: false, selectedProfile: null };
+      useSettingsStore.setState({ settings });
+      vi.mocked(invoke).mockResolvedValueOnce({
+        effective: settings,
+        previous: { global: null, devices: { ABC123: { debugMode: true } } },
+      });
+
+      await useSettingsStore
+        .getState()
+        .resetOnBackend({ scope: 'device', serial_number: 'ABC123' });
+
+      expect(useSettingsStore.getState().settings).toBe(settings);
+    });
+  });
+
+  describe('resetDfuTiming', () => {
+    it('adopts the settings without timing overrides', async () => {
+      useSettingsStore.setState({
+        settings: {
+          disableLedDuringTherapy: true,
+          debugMode: false,
+          selectedProfile: null,
+          dfuTiming: { bootloaderTimeoutMs: 20000 },
+        },
+      });
+      const settings = { disableLedDuringTherapy: true, debugMode: false, selectedProfile: null };
+      vi.mocked(invoke).mockResolvedValueOnce(settings);
+
+      await useSettingsStore.getState().resetDfuTiming();
+
+      expect(invoke).toHaveBeenCalledWith('reset_dfu_timing');
+      expect(useSettingsStore.getState().settings).toEqual(settings);
+    });
+  });
+
+  describe('importFromFile', () => {
+    it('reloads settings after importing', async () => {
+      const summary = {
+        schema_version: 3,
+        app_version: '1.0.0',
+        changed: ['settings.debugMode'],
+        unknown_keys: [],
+      };
+      vi.mocked(invoke)
+        .mockResolvedValueOnce(summary)
+        .mockResolvedValueOnce({
+          disableLedDuringTherapy: false,
+          debugMode: true,
+          selectedProfile: null,
+        });
+
+      await expect(
+        useSettingsStore.getState().importFromFile('/tmp/settings.json')
+      ).resolves.toEqual(summary);
+
+      expect(invoke).toHaveBeenNthCalledWith(1, 'import_settings', {
+        path: '/tmp/settings.json',
+      });
+      expect(invoke).toHaveBeenNthCalledWith(2, 'get_advanced_settings');
+      expect(useSettingsStore.getState().settings.debugMode).toBe(true);
+    });
+  });
+
   describe('reset', () => {
     it('clears loadError on reset', () => {
       useSettingsStore.setState({ loadError: 'Some error' });
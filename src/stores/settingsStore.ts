@@ -1,7 +1,16 @@
 import { create } from 'zustand';
 import { persist, createJSONStorage } from 'zustand/middleware';
 import { invoke } from '@tauri-apps/api/core';
-import type { AdvancedSettings, TherapyProfile } from '@/types';
+import type {
+  AdvancedSettings,
+  ResetSettingsResult,
+  RevisedSettings,
+  SettingError,
+  SettingsChanged,
+  SettingsImportSummary,
+  SettingsScope,
+  TherapyProfile,
+} from '@/types';
 
 /**
  * Default advanced settings values.
@@ -13,19 +22,41 @@ const defaultSettings: AdvancedSettings = {
   selectedProfile: null,
 };
 
+/**
+ * Per-field errors from a rejected save, or none if the backend rejected it
+ * for another reason.
+ */
+function settingErrors(error: unknown): SettingError[] {
+  if (typeof error === 'object' && error !== null && 'errors' in error) {
+    return (error as { errors: SettingError[] }).errors;
+  }
+  return [];
+}
+
 interface SettingsStore {
   // State
   settings: AdvancedSettings;
   isLoaded: boolean;
   isSyncing: boolean;
   loadError: string | null;
+  /** Why the last save was rejected, e.g. an out-of-range session duration */
+  saveError: string | null;
+  /** Invalid fields from the last rejected save, for highlighting */
+  fieldErrors: SettingError[];
+  /** Backend revision of the settings shown, to drop stale change events */
+  revision: number;
 
   // Actions
   setSettings: (settings: Partial<AdvancedSettings>) => void;
   setSelectedProfile: (profile: TherapyProfile | null) => void;
   loadFromBackend: () => Promise<void>;
+  applyChange: (change: SettingsChanged) => void;
   syncToBackend: () => Promise<void>;
   reset: () => void;
+  resetOnBackend: (scope: SettingsScope) => Promise<ResetSettingsResult>;
+  resetDfuTiming: () => Promise<void>;
+  exportToFile: (path: string, includeSecrets?: boolean) => Promise<void>;
+  importFromFile: (path: string) => Promise<SettingsImportSummary>;
 }
 
 /**
@@ -43,6 +74,9 @@ export const useSettingsStore = create<SettingsStore>()(
       isLoaded: false,
       isSyncing: false,
       loadError: null,
+      saveError: null,
+      fieldErrors: [],
+      revision: 0,
 
       /**
        * Update settings and sync to backend.
@@ -74,8 +108,10 @@ export const useSettingsStore = create<SettingsStore>()(
       loadFromBackend: async () => {
         try {
           set({ loadError: null });
-          const settings = await invoke<AdvancedSettings>('get_advanced_settings');
-          set({ settings, isLoaded: true });
+          const { revision, ...settings } = await invoke<RevisedSettings>(
+            'get_advanced_settings'
+          );
+          set({ settings, revision: revision ?? 0, isLoaded: true });
         } catch (error) {
           const message = error instanceof Error ? error.message : String(error);
           console.error('[SettingsStore] Failed to load settings from backend:', error);
@@ -84,6 +120,20 @@ export const useSettingsStore = create<SettingsStore>()(
         }
       },
 
+      /**
+       * Adopt settings saved elsewhere (another window, a reset or an import),
+       * unless they are older than the ones shown. Device changes only
+       * advance the revision: they don't alter the global settings.
+       */
+      applyChange: (change) => {
+        if (change.revision <= get().revision) return;
+        if (change.scope.scope === 'device') {
+          set({ revision: change.revision });
+        } else {
+          set({ settings: change.settings, revision: change.revision });
+        }
+      },
+
       /**
        * Sync current settings to backend for durable persistence.
        * Called automatically when settings change.
@@ -95,7 +145,16 @@ export const useSettingsStore = create<SettingsStore>()(
         set({ isSyncing: true });
         try {
           await invoke('save_advanced_settings', { settings });
+          set({ saveError: null, fieldErrors: [] });
         } catch (error) {
+          const fieldErrors = settingErrors(error);
+          const message =
+            fieldErrors.length > 0
+              ? fieldErrors.map((e) => e.message).join('; ')
+              : error instanceof Error
+                ? error.message
+                : String(error);
+          set({ saveError: message, fieldErrors });
           console.error('[SettingsStore] Failed to sync settings to backend:', error);
         } finally {
           set({ isSyncing: false });
@@ -106,9 +165,52 @@ export const useSettingsStore = create<SettingsStore>()(
        * Reset settings to defaults and sync to backend.
        */
       reset: () => {
-        set({ settings: defaultSettings, loadError: null });
+        set({ settings: defaultSettings, loadError: null, saveError: null, fieldErrors: [] });
         get().syncToBackend();
       },
+
+      /**
+       * Restore saved defaults on the backend, for the global settings, one
+       * device or everything. Resolves with the replaced settings for undo.
+       */
+      resetOnBackend: async (scope) => {
+        const result = await invoke<ResetSettingsResult>('reset_advanced_settings', { scope });
+        if (scope.scope !== 'device') {
+          set({
+            settings: result.effective,
+            loadError: null,
+            saveError: null,
+            fieldErrors: [],
+          });
+        }
+        return result;
+      },
+
+      /**
+       * Clear the DFU timing overrides on the backend, keeping every other
+       * setting.
+       */
+      resetDfuTiming: async () => {
+        const settings = await invoke<AdvancedSettings>('reset_dfu_timing');
+        set({ settings, saveError: null, fieldErrors: [] });
+      },
+
+      /**
+       * Export all saved settings for use on another machine. The GitHub
+       * token and proxy credentials are left out unless includeSecrets is set.
+       */
+      exportToFile: async (path, includeSecrets = false) => {
+        await invoke('export_settings', { path, includeSecrets });
+      },
+
+      /**
+       * Replace all saved settings with an exported file, then reload them.
+       */
+      importFromFile: async (path) => {
+        const summary = await invoke<SettingsImportSummary>('import_settings', { path });
+        await get().loadFromBackend();
+        return summary;
+      },
     }),
     {
       name: 'bluebuzzah-settings',
@@ -7,8 +7,8 @@ import type {
   UpdateResult,
   DeviceUpdateResult,
   ValidationResult,
-  GitHubRelease,
-  GitHubAsset,
+  FirmwareReleaseInfo,
+  ReleaseAssetInfo,
   CachedFirmwareMetadata,
 } from '@/types';
 
@@ -52,22 +52,28 @@ export const createMockCachedMetadata = (
   ...overrides,
 });
 
-// === GitHub API Factories ===
+// === Release Listing Factories ===
 
-export const createMockGitHubAsset = (overrides?: Partial<GitHubAsset>): GitHubAsset => ({
+export const createMockReleaseAssetInfo = (
+  overrides?: Partial<ReleaseAssetInfo>
+): ReleaseAssetInfo => ({
   name: 'firmware-v1.0.0.zip',
-  browser_download_url: 'https://github.com/test/releases/download/v1.0.0/firmware.zip',
+  download_url: 'https://github.com/test/releases/download/v1.0.0/firmware.zip',
   size: 1024000,
   ...overrides,
 });
 
-export const createMockGitHubRelease = (overrides?: Partial<GitHubRelease>): GitHubRelease => ({
+export const createMockReleaseInfo = (
+  overrides?: Partial<FirmwareReleaseInfo>
+): FirmwareReleaseInfo => ({
+  version: '1.0.0',
   tag_name: 'v1.0.0',
-  name: '1.0.0',
-  body: 'Test release notes',
+  release_notes: 'Test release notes',
   published_at: '2024-01-15T00:00:00Z',
   prerelease: false,
-  assets: [createMockGitHubAsset()],
+  assets: [createMockReleaseAssetInfo()],
+  is_cached: false,
+  cached_metadata: null,
   ...overrides,
 });
 
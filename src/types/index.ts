@@ -27,10 +27,81 @@ export interface CachedFirmwareMetadata {
   file_size: number;
   published_at: string;
   release_notes: string;
+  checksum_verified?: boolean; // true if matched a checksum published with the release
+  locally_imported?: boolean; // true if imported from a local file instead of downloaded
+  pinned?: boolean; // pinned versions survive cache cleanup
+  last_used_at?: string | null; // last time the version was resolved or flashed
+  channel?: ReleaseChannel; // 'beta' for prerelease builds
+  asset_kind?: AssetKind; // what the zip contains, 'unknown' until classified
+}
+
+export type AssetKind = 'dfu_package' | 'circuitpy_bundle' | 'unknown';
+
+export type ReleaseChannel = 'stable' | 'beta';
+
+// 'system' follows the OS proxy configuration; 'none' connects directly
+export type ProxyMode = 'system' | 'none' | 'manual';
+
+export interface ProxySettings {
+  mode: ProxyMode;
+  url?: string | null; // manual mode only, e.g. http://proxy.hospital.local:8080
+  username?: string | null;
+  password?: string | null;
 }
 
 export type FirmwareCacheIndex = Record<string, CachedFirmwareMetadata>;
 
+export interface CachedVersionStats {
+  version: string;
+  downloaded_at: string;
+  zip_exists: boolean;
+  zip_size: number;
+  extracted_size: number;
+}
+
+export interface CacheCleanupResult {
+  removed: string[];
+  bytes_freed: number;
+}
+
+export interface CacheClearResult {
+  versions_removed: string[];
+  bytes_freed: number;
+  skipped_unknown_files: string[]; // files in the cache folder that aren't ours
+  pinned_kept: number;
+  failed: string[]; // files that couldn't be deleted, with the reason
+}
+
+// Progress event while un-indexed zips are hashed into the cache index
+export interface CacheMigrationProgress {
+  file_name: string; // Zip being hashed
+  current: number;   // Position among the zips being migrated (1-based)
+  total: number;     // Number of zips being migrated
+  message: string;   // Human-readable message
+}
+
+export interface CacheMaintenanceOptions {
+  skipMigration?: boolean; // Skip hashing un-indexed zips (already migrated)
+  collectGarbage?: boolean; // Also remove orphaned files from the cache folder
+}
+
+// What a garbage collection pass found in the firmware cache folder
+export interface CacheGarbageReport {
+  indexed: string[];    // Entries backing an index entry
+  migratable: string[]; // Un-indexed zips migration will pick up
+  orphans: string[];    // Partial downloads, stray files and directories
+  removed: string[];    // Orphans old enough to be deleted
+  bytes_freed: number;
+}
+
+export interface CacheStats {
+  version_count: number;
+  total_size: number;
+  oldest_downloaded_at: string | null;
+  versions: CachedVersionStats[];
+  inconsistencies: string[];
+}
+
 export interface Device {
   path: string;           // Serial port path (e.g., "/dev/cu.usbmodem1234" or "COM3")
   label: string;          // Display label for the device
@@ -41,19 +112,253 @@ export interface Device {
   pid?: number;           // USB Product ID
   inBootloader?: boolean; // Whether device is in bootloader mode
   serialNumber?: string;  // Device serial number
+  board?: BoardModel;     // Board model decoded from the PID by the backend
 }
 
+// Boards the backend can identify from their USB PID
+export type BoardModel = 'feather_nrf52840_express' | 'feather_nrf52840_sense';
+
+// Result of is_device_in_bootloader; not_found means nothing is on the port
+export type DeviceMode = 'bootloader' | 'application' | 'not_found';
+
 // DFU progress event from backend
 export interface DfuProgress {
   stage: string;          // Stage name (reading, bootloader, uploading, etc.)
+  message_key: string;    // Stable translation key (e.g., "dfu.stage.uploading")
   sent?: number;          // Bytes sent (for uploading)
   total?: number;         // Total bytes (for uploading)
   percent: number;        // Progress percentage (0-100)
   message: string;        // Human-readable message
+  device_index?: number;  // Device position in flash_both_devices (0 = primary)
+  device_label?: string;  // Device role in flash_both_devices (PRIMARY, SECONDARY)
+  seq: number;            // Position within the operation, starting at 1
+  emitted_at_ms: number;  // Backend creation time (ms since the Unix epoch)
+  operation_id?: number;  // ID of the operation that sent the event
+}
+
+// Result of cancel_dfu_flash
+export interface CancelFlashResult {
+  was_running: boolean;           // A flash was running and has been asked to stop
+  operation_id: number | null;
+  stage_at_cancel: string | null; // e.g. "uploading"
+  not_found: boolean;             // The requested operation ID isn't running
+}
+
+// An operation holding a device's port, from get_active_operations
+export interface ActiveOperation {
+  id: number;                     // Matches operation_id on progress events
+  kind: string;                   // flash, profile, role, command, monitor
+  port: string;
+  started_at: string;             // RFC 3339
+}
+
+// Global dfu://progress event, mirroring an operation's progress channel
+export type ProgressBroadcast = {
+  operation_id: number | null;
+} & (
+  | { kind: 'flash'; event: DfuProgress }
+  | {
+      kind: 'profile';
+      event: { stage: string; percent: number; message: string; seq: number; emitted_at_ms: number };
+    }
+);
+
+// Error rejected by the DFU flash and device configuration commands
+export interface CommandErrorPayload {
+  message_key: string;   // Stable translation key (e.g., "dfu.error.timeout")
+  code: string | null;   // DFU error code (e.g., "DFU-021"), when there is one
+  message: string;       // English fallback
+  existing?: ActiveOperation; // Operation holding the port, for error.port_busy
+}
+
+// Per-device result of flash_both_devices
+export type DeviceFlashOutcome = {
+  device_index: number;
+  device_label: string;
+  port: string;
+} & (
+  | { status: 'success' }
+  | { status: 'failed'; error: string; message_key: string }
+  | { status: 'skipped'; reason: string }
+);
+
+// One firmware installation from the flash history
+export interface FlashRecord {
+  started_at: string;             // RFC 3339
+  duration_ms: number;
+  serial_number: string | null;
+  port: string;
+  firmware_version: string | null; // Set when the zip came from the cache
+  firmware_path: string;
+  app_version: string;
+  role: string | null;
+  profile: string | null;
+  success: boolean;
+  attempts: number;
+  error_code: string | null;      // Support code of the final failure (e.g. "DFU-021")
+  error: string | null;
+}
+
+// A flash a previous run of the app didn't finish (it crashed or was killed)
+export interface InterruptedOperation {
+  id: string;
+  operation: string;              // e.g. "flash"
+  port: string;                   // Port the flash was started on
+  serial_number: string | null;
+  firmware_version: string | null; // Set when the zip came from the cache
+  firmware_path: string;
+  stage: string;                  // Last DFU stage reached
+  started_at: string;             // RFC 3339
+  updated_at: string;             // RFC 3339
+  current_port: string | null;    // Where the device is connected now, if at all
+  in_bootloader: boolean;         // Connected in bootloader mode, ready to reflash
+}
+
+// Criteria for getFlashHistory; unset fields match every record
+export interface FlashHistoryFilter {
+  serial_number?: string;
+  firmware_version?: string;
+  success?: boolean;
+  since?: string;                 // RFC 3339
+  limit?: number;                 // Newest records first
+}
+
+// UF2 flash progress event from backend (copy onto the bootloader drive)
+export interface Uf2Progress {
+  stage: string;          // copying, rebooting, waiting, complete
+  copied: number;         // Bytes copied to the drive
+  total: number;          // Size of the .uf2 file
+  percent: number;        // Progress percentage (0-100)
+  message: string;        // Human-readable message
+}
+
+// Why a device log stream ended
+export type LogStreamEndReason = 'stopped' | 'disconnected' | 'preempted_by_flash';
+
+// Event from start_device_log_stream; 'ended' is always the last one
+export type LogStreamEvent =
+  | { event: 'line'; timestamp: string; text: string } // Non-UTF-8 bytes appear as \xNN
+  | { event: 'ended'; reason: LogStreamEndReason; detail: string | null };
+
+// Global app://second-instance event: the app was launched again and
+// handed its arguments to this instance instead of starting a second copy
+export interface SecondLaunch {
+  args: string[]; // Arguments after the program name
+  cwd: string;    // Working directory of the second launch
+}
+
+// Backend log file level, from quietest to most verbose
+export type LogLevel = 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+// Result of validate_firmware_package
+export interface FirmwareInfo {
+  firmware_size: number;
+  init_size: number;
+  firmware_crc16: number;
+  device_type: number;
+  dfu_version: number;
+  application_version: number | null; // null when the manifest leaves it unset
+  crc_valid: boolean;                 // firmware.bin matches firmware_crc16
+  estimated_duration_ms: number;      // Typical end-to-end flash time
+  compatibility: {                    // Set when a device was passed
+    compatible: boolean;
+    reason: string;
+  } | null;
+}
+
+// Version, role and profile reported by a device in application mode
+export interface DeviceInfo {
+  firmware_version: string | null;
+  role: string | null;            // PRIMARY, SECONDARY
+  profile: string | null;         // REGULAR, NOISY, HYBRID, GENTLE
+  timed_out: string[];            // Queries the device didn't answer (e.g. GET_ROLE)
+  unsupported: string[];          // Queries the firmware rejected
+}
+
+// A saved setting the device isn't running
+export interface SettingMismatch {
+  key: string;                    // e.g. THERAPY_LED_OFF
+  expected: string;               // Value from the saved settings
+  actual: string | null;          // Value the device reported, null if missing
+}
+
+// Settings read back from a device with GET_SETTINGS
+export type DeviceSettingsReadout =
+  | { status: 'supported'; settings: Record<string, string>; mismatches: SettingMismatch[] }
+  | { status: 'unsupported'; reason: string };
+
+// One probe in a device report: value, or why it couldn't be read
+export interface ReportProbe<T> {
+  value: T | null;
+  error: string | null;
+}
+
+// Device health report from backend, for support triage
+export interface DeviceReport {
+  generated_at: string;   // RFC 3339 timestamp
+  app_version: string;
+  os: string;             // macos, windows, linux
+  serial_port: string;
+  device: ReportProbe<{
+    port: string;
+    label: string;
+    vid: number;
+    pid: number;
+    in_bootloader: boolean;
+    serial_number: string | null;
+  }>;
+  mode: ReportProbe<string>;              // application, bootloader
+  firmware_version: ReportProbe<string>;
+  role: ReportProbe<string>;
+  profile: ReportProbe<string>;
+}
+
+// Diagnostic bundle written by exportDiagnostics, for support
+export interface DiagnosticsSummary {
+  path: string;           // Where the zip was written (".zip" added if missing)
+  files: {
+    name: string;         // e.g. "settings.json", "logs/updater.log"
+    description: string;
+    size: number;         // Bytes, before compression
+    truncated: boolean;   // Oldest lines of a log cut to fit the size cap
+  }[];
+  skipped: { name: string; reason: string }[];
+  total_bytes: number;
+}
+
+// Firmware download progress event from backend
+export interface DownloadProgress {
+  stage: string;          // Stage name (retrying)
+  attempt: number;        // Retry number (1-based)
+  max_attempts: number;   // Maximum number of retries
+  message: string;        // Human-readable message
+  rate_limit_bytes_per_sec: number | null; // Configured download cap, if any
 }
 
 export type DeviceRole = 'PRIMARY' | 'SECONDARY';
 
+// Combined progress event from download_release_assets
+export interface ReleaseAssetProgress {
+  stage: string;              // Stage name (downloading, retrying, complete)
+  asset_index: number;        // Asset being downloaded (0-based)
+  asset_count: number;        // Number of assets in the batch
+  asset_name: string;
+  bytes_downloaded: number;   // Bytes of the current asset so far
+  total_bytes: number | null; // Size of the current asset, if known
+  message: string;            // Human-readable message
+  rate_limit_bytes_per_sec: number | null; // Configured download cap, if any
+}
+
+// Result of download_release_assets; a cancelled batch caches nothing
+export type ReleaseDownloadOutcome =
+  | { status: 'completed'; assets: CachedFirmwareMetadata[] }
+  | { status: 'cancelled' };
+
+// Result of download_firmware; cancelled downloads are not cached
+export type DownloadOutcome =
+  | { status: 'completed'; path: string }
+  | { status: 'cancelled' };
+
 export interface FirmwareBundle {
   version: string;
   localPath: string;
@@ -104,6 +409,112 @@ export interface AdvancedSettings {
   debugMode: boolean;
   /** Selected therapy profile, persisted for convenience */
   selectedProfile?: TherapyProfile | null;
+  /** GitHub token for release listing, avoids the anonymous rate limit */
+  githubToken?: string | null;
+  /** Release channel for the firmware list; 'beta' shows prereleases */
+  releaseChannel?: ReleaseChannel;
+  /** Release channel the app updates itself from; 'beta' offers prerelease builds */
+  appUpdateChannel?: ReleaseChannel;
+  /** Proxy for firmware downloads and release queries */
+  proxy?: ProxySettings;
+  /** Download bandwidth cap in bytes per second; 0 or unset is unlimited */
+  maxDownloadBytesPerSec?: number;
+  /** Lets sendDeviceCommand send any command, not only read-only queries */
+  developerMode?: boolean;
+  /** Vibration intensity override (0-100); unset keeps the profile's own */
+  vibrationIntensity?: number | null;
+  /** Dimmer LEDs and reduced BLE advertising for overnight use */
+  lowPowerMode?: boolean;
+  /** Therapy session length in minutes (15-480); unset keeps the firmware's */
+  sessionDurationMinutes?: number | null;
+  /** Jitter override in percent (0-50, one decimal); no effect with REGULAR */
+  jitterPercent?: number | null;
+  /** Named profiles built from setting commands, offered with the built-in ones */
+  customProfiles?: CustomProfile[];
+  /** DFU timeout and retry overrides; unset fields keep the platform defaults */
+  dfuTiming?: DfuTimingSettings;
+  /** Daily therapy windows (at most 4, no overlaps); empty keeps the device's own */
+  therapySchedule?: ScheduleWindow[];
+}
+
+// One daily therapy window, sent as SCHEDULE_ADD:<start>,<end>
+export interface ScheduleWindow {
+  start: string;                  // 24-hour HH:MM, e.g. 09:00
+  end: string;                    // after start, same day, e.g. 11:00
+}
+
+// Overrides for slow USB hubs or busy machines, applied to every flash
+// and profile configuration
+export interface DfuTimingSettings {
+  bootloaderTimeoutMs?: number | null;  // 5000-120000
+  ackTimeoutMs?: number | null;         // 1000-60000
+  maxRetries?: number | null;           // 0-8, per packet and per configuration
+  deadlineSeconds?: number | null;      // 60-3600, when a flash sets no timeout
+}
+
+// A profile defined in settings: commands sent before a built-in base
+// profile, or followed by a reboot when there is no base profile
+export interface CustomProfile {
+  name: string;                   // A-Z, 0-9 and _, e.g. STUDY_A
+  commands: string[];             // e.g. JITTER_PERCENT:18.0, MIRRORED:false
+  baseProfile?: TherapyProfile | null;
+}
+
+// Per-device overrides of AdvancedSettings, keyed by serial number on the
+// backend; unset fields use the global settings
+export type DeviceSettings = Partial<
+  Pick<
+    AdvancedSettings,
+    | 'disableLedDuringTherapy'
+    | 'debugMode'
+    | 'lowPowerMode'
+    | 'vibrationIntensity'
+    | 'sessionDurationMinutes'
+    | 'jitterPercent'
+  >
+>;
+
+// A setting value save_advanced_settings rejected
+export interface SettingError {
+  field: string;                  // JSON name, e.g. jitterPercent
+  message: string;
+}
+
+// Rejection from save_advanced_settings / save_device_settings:
+// per-field errors for invalid values, a plain message otherwise
+export type SaveSettingsError = string | { errors: SettingError[] };
+
+// Which saved settings reset_advanced_settings applies to
+export type SettingsScope =
+  | { scope: 'global' }
+  | { scope: 'device'; serial_number: string }
+  | { scope: 'all' };
+
+// get_advanced_settings result: the global settings and their revision
+export type RevisedSettings = AdvancedSettings & { revision: number };
+
+// Payload of the global settings://changed event, sent after every change
+export interface SettingsChanged {
+  scope: SettingsScope;
+  settings: AdvancedSettings;     // Effective for the scope (device over global)
+  revision: number;               // Ignore changes older than the shown revision
+}
+
+// Result of reset_advanced_settings; save `previous` again to undo
+export interface ResetSettingsResult {
+  effective: AdvancedSettings;
+  previous: {
+    global: AdvancedSettings | null;           // Unset when only a device was reset
+    devices: Record<string, DeviceSettings>; // Removed overrides by serial number
+  };
+}
+
+// Result of import_settings
+export interface SettingsImportSummary {
+  schema_version: number;     // Of the imported file, before migration
+  app_version: string | null; // App version that exported it
+  changed: string[];          // e.g. "settings.debugMode", "devices.ABC123.lowPowerMode"
+  unknown_keys: string[];     // Keys this version ignored
 }
 
 export interface WizardState {
@@ -116,20 +527,35 @@ export interface WizardState {
   logs: string[];
 }
 
-// GitHub API Response Types
-export interface GitHubRelease {
-  tag_name: string;
+// Release listing from backend (list_firmware_releases)
+export interface ReleaseAssetInfo {
   name: string;
-  body: string;
-  published_at: string;
+  download_url: string;
+  size: number;
+}
+
+export interface FirmwareReleaseInfo {
+  version: string;        // Release name, or tag when the name is empty
+  tag_name: string;
+  release_notes: string;
+  published_at: string;   // RFC 3339, empty if unknown
   prerelease: boolean;
-  assets: GitHubAsset[];
+  assets: ReleaseAssetInfo[];
+  is_cached: boolean;
+  cached_metadata: CachedFirmwareMetadata | null;
 }
 
-export interface GitHubAsset {
-  name: string;
-  browser_download_url: string;
-  size: number;
+export interface ReleaseListingInfo {
+  releases: FirmwareReleaseInfo[];
+  stale: boolean;         // true if GitHub was unreachable and a cached copy was used
+  fetched_at: string;     // when the list was last confirmed with GitHub (RFC 3339)
+}
+
+// Release list with freshness info, e.g. "last checked 2 hours ago (offline)"
+export interface ReleaseListing {
+  releases: FirmwareRelease[];
+  stale: boolean;
+  fetchedAt: Date;
 }
 
 // ============================================================================
@@ -138,6 +564,26 @@ export interface GitHubAsset {
 
 export type TherapyProfile = 'REGULAR' | 'NOISY' | 'HYBRID' | 'GENTLE';
 
+// A profile get_available_profiles offers: built-in first, then custom
+export interface AvailableProfile {
+  name: string;
+  built_in: boolean;
+  base_profile: TherapyProfile | null;  // Set for custom profiles with a base
+}
+
+// One serial command preview_device_configuration lists, in send order
+export interface PreviewCommand {
+  command: string;                // e.g. DEBUG:false, SET_PROFILE:NOISY
+  description: string;            // e.g. Debug Mode = false
+}
+
+// Setting commands apply_device_configuration sent, and those left out
+// because the device's firmware (from getDeviceInfo) predates them
+export interface AppliedConfiguration {
+  sent_settings: string[];        // e.g. THERAPY_LED_OFF:true
+  skipped_settings: string[];     // e.g. VIBRATION_INTENSITY:60
+}
+
 export interface TherapyProfileInfo {
   id: TherapyProfile;
   name: string;
@@ -188,6 +634,7 @@ export type AppUpdateStage =
   | 'checking'
   | 'available'
   | 'downloading'
+  | 'waiting' // Downloaded; installs once the running firmware flash ends
   | 'ready'
   | 'error';
 
@@ -196,6 +643,8 @@ export interface AppUpdateInfo {
   currentVersion: string;
   releaseNotes: string;
   releaseDate: string | null;
+  downloadSize: number | null; // Bytes, when GitHub reported it
+  channel: ReleaseChannel;
 }
 
 export interface AppUpdateProgress {